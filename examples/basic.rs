@@ -1,4 +1,4 @@
-use scanner::{Scanner, token_string, EOF};
+use scanner::{Scanner, SourceId, token_string, EOF};
 
 fn main() {
     // Example 1: Basic scanning
@@ -9,7 +9,7 @@ fn main() {
 ";
 
     let mut s = Scanner::init(src.as_bytes());
-    s.position.filename = "example".to_string();
+    s.position.source = SourceId::from("example");
 
     loop {
         let tok = s.scan();
@@ -30,7 +30,7 @@ fn main() {
 	";
 
     let mut s2 = Scanner::init(src2.as_bytes());
-    s2.position.filename = "actual-code".to_string();
+    s2.position.source = SourceId::from("actual-code");
 
     loop {
         let tok = s2.scan();
@@ -0,0 +1,35 @@
+use scanner::{Token, TokenPipeline, LISP_TOKENS};
+
+// Parses forms from a pipeline while it is still scanning, reporting the
+// deepest nesting and the number of top-level forms.
+fn main() {
+    // A large source: many nested forms
+    let mut src = String::new();
+    for i in 0..20_000 {
+        src.push_str(&format!("(def f{} (fn [x] (+ x {} (* x x))))\n", i, i));
+    }
+    src.push_str("(bad 0x)\n");
+
+    // Scanning runs on its own thread, at most 256 tokens ahead of the parser
+    let mut pipeline = TokenPipeline::spawn(src.into_bytes(), 256, |s| s.set_mode(LISP_TOKENS));
+
+    let (mut depth, mut max_depth, mut forms) = (0usize, 0usize, 0usize);
+    for item in &mut pipeline {
+        match item {
+            Ok(t) if t.tok == '(' as Token => {
+                depth += 1;
+                max_depth = max_depth.max(depth);
+            }
+            Ok(t) if t.tok == ')' as Token => {
+                depth -= 1;
+                if depth == 0 {
+                    forms += 1;
+                }
+            }
+            Ok(_) => {}
+            Err(d) => println!("{}: {}", d.span.start, d.message),
+        }
+    }
+    let diagnostics = pipeline.finish();
+    println!("{} forms, nesting depth {}, {} diagnostics", forms, max_depth, diagnostics.len());
+}
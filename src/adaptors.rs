@@ -0,0 +1,227 @@
+// Copyright 2022 Jordi Íñigo Griera. All rights reserved.
+
+//! Token iterators and adaptors to compose token pipelines.
+//!
+//! ```
+//! use scanner::*;
+//!
+//! let mut s = Scanner::init(b"(def a 10) ; comment");
+//! s.set_mode(LISP_TOKENS & !SKIP_COMMENTS);
+//! let idents: Vec<String> = s
+//!     .tokens()
+//!     .spanned()
+//!     .skip_trivia()
+//!     .only(&[IDENT])
+//!     .map_text(|text| text.to_uppercase())
+//!     .map(|t| t.text)
+//!     .collect();
+//! assert_eq!(idents, ["DEF", "A"]);
+//! ```
+
+use alloc::string::String;
+
+use crate::{DEFAULT_BUFFER_SIZE, EOF, Scanner, SpannedToken, Token, is_trivia};
+
+/// Tokens is an iterator over the token kinds of a scanner, up to EOF.
+/// It doesn't materialize token texts; use `spanned()` to get them.
+pub struct Tokens<'s, 'a, const N: usize = DEFAULT_BUFFER_SIZE> {
+    scanner: &'s mut Scanner<'a, N>,
+}
+
+impl<'s, 'a, const N: usize> Tokens<'s, 'a, N> {
+    pub(crate) fn new(scanner: &'s mut Scanner<'a, N>) -> Self {
+        Tokens { scanner }
+    }
+
+    /// Turns the iterator into an iterator over spanned tokens.
+    pub fn spanned(self) -> Spanned<'s, 'a, N> {
+        Spanned { scanner: self.scanner }
+    }
+}
+
+impl<const N: usize> Iterator for Tokens<'_, '_, N> {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Token> {
+        match self.scanner.scan() {
+            EOF => None,
+            tok => Some(tok),
+        }
+    }
+}
+
+/// Spanned is an iterator over the spanned tokens of a scanner, up to EOF.
+/// It is also the iterator of `&mut Scanner`:
+///
+/// ```
+/// use scanner::*;
+///
+/// let mut s = Scanner::init(b"(a \"b\")");
+/// for t in &mut s {
+///     println!("{} {:?} {}", t.span.start, t.tok, t.text);
+/// }
+/// ```
+pub struct Spanned<'s, 'a, const N: usize = DEFAULT_BUFFER_SIZE> {
+    scanner: &'s mut Scanner<'a, N>,
+}
+
+impl<'s, 'a, const N: usize> IntoIterator for &'s mut Scanner<'a, N> {
+    type Item = SpannedToken;
+    type IntoIter = Spanned<'s, 'a, N>;
+
+    fn into_iter(self) -> Spanned<'s, 'a, N> {
+        self.iter()
+    }
+}
+
+impl<const N: usize> Iterator for Spanned<'_, '_, N> {
+    type Item = SpannedToken;
+
+    fn next(&mut self) -> Option<SpannedToken> {
+        let t = self.scanner.scan_spanned();
+        if t.tok == EOF { None } else { Some(t) }
+    }
+}
+
+/// SourceChar is a character of the source with its location.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SourceChar {
+    pub ch: char,
+    /// Byte offset, starting at 0
+    pub offset: usize,
+    /// Line number, starting at 1
+    pub line: usize,
+    /// Column number, starting at 1 (character count per line)
+    pub column: usize,
+}
+
+/// Chars is an iterator over the characters of a scanner, up to EOF, for
+/// building other kinds of scanners on the same UTF-8 decoding and
+/// position tracking. Invalid UTF-8 sequences are returned as U+FFFD and
+/// reported as errors, like when scanning tokens.
+pub struct Chars<'s, 'a, const N: usize = DEFAULT_BUFFER_SIZE> {
+    scanner: &'s mut Scanner<'a, N>,
+}
+
+impl<'s, 'a, const N: usize> Chars<'s, 'a, N> {
+    pub(crate) fn new(scanner: &'s mut Scanner<'a, N>) -> Self {
+        Chars { scanner }
+    }
+}
+
+impl<const N: usize> Iterator for Chars<'_, '_, N> {
+    type Item = SourceChar;
+
+    fn next(&mut self) -> Option<SourceChar> {
+        let ch = self.scanner.peek_char()?;
+        let (offset, line, column) = self.scanner.char_location();
+        self.scanner.next_char();
+        Some(SourceChar { ch, offset, line, column })
+    }
+}
+
+/// Items of token iterators.
+pub trait TokenItem {
+    /// Returns the token kind or Unicode character of the item.
+    fn tok(&self) -> Token;
+}
+
+impl TokenItem for Token {
+    fn tok(&self) -> Token {
+        *self
+    }
+}
+
+impl TokenItem for SpannedToken {
+    fn tok(&self) -> Token {
+        self.tok
+    }
+}
+
+/// Adaptors for iterators over tokens.
+pub trait TokenIteratorExt: Iterator + Sized
+where
+    Self::Item: TokenItem,
+{
+    /// Skips the trivia tokens (comments, white space and prose).
+    fn skip_trivia(self) -> SkipTrivia<Self> {
+        SkipTrivia { iter: self }
+    }
+
+    /// Keeps only the tokens of the given kinds or characters.
+    fn only(self, kinds: &[Token]) -> Only<'_, Self> {
+        Only { iter: self, kinds }
+    }
+
+    /// Replaces the text of every token by `f(text)`.
+    fn map_text<F>(self, f: F) -> MapText<Self, F>
+    where
+        Self: Iterator<Item = SpannedToken>,
+        F: FnMut(&str) -> String,
+    {
+        MapText { iter: self, f }
+    }
+}
+
+impl<I> TokenIteratorExt for I
+where
+    I: Iterator,
+    I::Item: TokenItem,
+{
+}
+
+/// Iterator returned by `TokenIteratorExt::skip_trivia`.
+pub struct SkipTrivia<I> {
+    iter: I,
+}
+
+impl<I> Iterator for SkipTrivia<I>
+where
+    I: Iterator,
+    I::Item: TokenItem,
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        self.iter.by_ref().find(|t| !is_trivia(t.tok()))
+    }
+}
+
+/// Iterator returned by `TokenIteratorExt::only`.
+pub struct Only<'k, I> {
+    iter: I,
+    kinds: &'k [Token],
+}
+
+impl<I> Iterator for Only<'_, I>
+where
+    I: Iterator,
+    I::Item: TokenItem,
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        let kinds = self.kinds;
+        self.iter.by_ref().find(|t| kinds.contains(&t.tok()))
+    }
+}
+
+/// Iterator returned by `TokenIteratorExt::map_text`.
+pub struct MapText<I, F> {
+    iter: I,
+    f: F,
+}
+
+impl<I, F> Iterator for MapText<I, F>
+where
+    I: Iterator<Item = SpannedToken>,
+    F: FnMut(&str) -> String,
+{
+    type Item = SpannedToken;
+
+    fn next(&mut self) -> Option<SpannedToken> {
+        let mut t = self.iter.next()?;
+        t.text = (self.f)(&t.text);
+        Some(t)
+    }
+}
@@ -0,0 +1,158 @@
+// Copyright 2022 Jordi Íñigo Griera. All rights reserved.
+
+//! Asynchronous scanning of input arriving over time, e.g. a REPL over
+//! TCP or a language server reading its standard input.
+//!
+//! An `AsyncScanner` reads from an `AsyncSource` and returns each token
+//! from `async fn scan()` as soon as the input following it is read,
+//! without blocking a thread while waiting for input: a token at the end
+//! of the input read so far, e.g. a raw string left open, is held back,
+//! as it may continue. Positions are relative to the whole input.
+//!
+//! ```
+//! use scanner::*;
+//! # use core::future::Future;
+//! # use core::task::{Context, Poll, Waker};
+//! # fn block_on<F: Future>(f: F) -> F::Output {
+//! #     let mut f = core::pin::pin!(f);
+//! #     loop {
+//! #         if let Poll::Ready(out) = f.as_mut().poll(&mut Context::from_waker(Waker::noop())) {
+//! #             return out;
+//! #         }
+//! #     }
+//! # }
+//!
+//! let mut s = AsyncScanner::new("(print ¬a\nb¬)\n".as_bytes());
+//! let texts = block_on(async {
+//!     let mut texts = Vec::new();
+//!     while let Some(t) = s.scan().await {
+//!         texts.push(t.text);
+//!     }
+//!     texts
+//! });
+//! assert_eq!(texts, ["(", "print", "¬a\nb¬", ")"]);
+//! ```
+//!
+//! The crate doesn't depend on an async runtime: `AsyncSource` mirrors the
+//! `poll_read()` of the runtimes' readers. With the `tokio` feature, every
+//! `tokio::io::AsyncRead + Unpin` reader, e.g. a `TcpStream` or
+//! `tokio::io::stdin()`, is an `AsyncSource`; byte slices and mutable
+//! references to readers are then sources as tokio readers.
+
+use core::future::poll_fn;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+#[cfg(feature = "tokio")]
+use core::task::ready;
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use std::io;
+
+use crate::incremental::Pending;
+use crate::{Diagnostic, Scanner, SpannedToken};
+
+// Size of the reads from the source.
+const CHUNK_SIZE: usize = 4096;
+
+/// AsyncSource is a source of bytes read asynchronously, like the readers
+/// of async runtimes.
+pub trait AsyncSource {
+    /// Attempts to read into buf, returning the number of bytes read, 0 at
+    /// the end of the input, or Pending after scheduling cx to be woken up
+    /// once bytes are available.
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>>;
+}
+
+/// A byte slice is always ready.
+#[cfg(not(feature = "tokio"))]
+impl AsyncSource for &[u8] {
+    fn poll_read(mut self: Pin<&mut Self>, _cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+        let n = buf.len().min(self.len());
+        buf[..n].copy_from_slice(&self[..n]);
+        *self = &self[n..];
+        Poll::Ready(Ok(n))
+    }
+}
+
+#[cfg(not(feature = "tokio"))]
+impl<T: AsyncSource + Unpin + ?Sized> AsyncSource for &mut T {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+        Pin::new(&mut **self).poll_read(cx, buf)
+    }
+}
+
+/// The readers of tokio are sources.
+#[cfg(feature = "tokio")]
+impl<T: tokio::io::AsyncRead + Unpin + ?Sized> AsyncSource for T {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+        let mut buf = tokio::io::ReadBuf::new(buf);
+        ready!(tokio::io::AsyncRead::poll_read(self, cx, &mut buf))?;
+        Poll::Ready(Ok(buf.filled().len()))
+    }
+}
+
+/// AsyncScanner scans tokens from an asynchronous source; see the module
+/// documentation.
+pub struct AsyncScanner<R> {
+    reader: R,
+    chunk: Vec<u8>, // read buffer
+    pending: Pending,
+    error: Option<io::Error>,
+    eof: bool,
+}
+
+impl<R: AsyncSource + Unpin> AsyncScanner<R> {
+    /// Returns an AsyncScanner reading from reader, in `LISP_TOKENS` mode.
+    pub fn new(reader: R) -> Self {
+        AsyncScanner {
+            reader,
+            chunk: alloc::vec![0; CHUNK_SIZE],
+            pending: Pending::new(),
+            error: None,
+            eof: false,
+        }
+    }
+
+    /// Sets the mode of the scanners of the following input.
+    pub fn set_mode(&mut self, mode: u32) {
+        self.pending.mode = mode;
+    }
+
+    /// Sets a function configuring the scanners of the following input;
+    /// see `IterScanner::set_configuration()`.
+    pub fn set_configuration<F>(&mut self, configure: F)
+    where
+        F: Fn(&mut Scanner<'_>) + Send + Sync + 'static,
+    {
+        self.pending.configure = Some(Box::new(configure));
+    }
+
+    /// Returns the diagnostics reported so far.
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.pending.diagnostics
+    }
+
+    /// Returns the read error that ended the input, if any.
+    pub fn io_error(&self) -> Option<&io::Error> {
+        self.error.as_ref()
+    }
+
+    /// Returns the next token, reading more input as needed, or None at
+    /// the end of the input.
+    pub async fn scan(&mut self) -> Option<SpannedToken> {
+        while self.pending.tokens.is_empty() && !self.eof {
+            let read = poll_fn(|cx| Pin::new(&mut self.reader).poll_read(cx, &mut self.chunk)).await;
+            match read {
+                Ok(0) => self.eof = true,
+                Ok(n) => self.pending.bytes.extend_from_slice(&self.chunk[..n]),
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => {
+                    self.error = Some(err);
+                    self.eof = true;
+                }
+            }
+            self.pending.scan(self.eof);
+        }
+        self.pending.tokens.pop_front()
+    }
+}
@@ -0,0 +1,106 @@
+// Copyright 2022 Jordi Íñigo Griera. All rights reserved.
+
+//! Limited backward scanning, for editors: the start of the token at an
+//! offset and the previous token boundary are found by scanning the lines
+//! around the offset instead of the source from its start.
+//!
+//! Scanning restarts at line starts, so a token spanning lines, a raw
+//! string or block comment, is only seen as such from the line it starts
+//! on; from its later lines its text is scanned as code.
+//!
+//! ```
+//! use scanner::*;
+//!
+//! let mut s = Scanner::init(b"(def long-name\n  \"a b\")");
+//! assert_eq!(s.token_start_at(10), Some(5)); // in long-name
+//! assert_eq!(s.token_start_at(14), None); // the line end
+//! assert_eq!(s.token_start_at(20), Some(17)); // in "a b"
+//! // Delete word backward from the end of the string
+//! assert_eq!(s.previous_token_boundary(22), Some(17));
+//! assert_eq!(s.previous_token_boundary(17), Some(5));
+//! ```
+
+use core::ops::Range;
+
+use alloc::vec::Vec;
+
+use crate::{EOF, Scanner, SKIP_COMMENTS};
+
+impl<'a, const N: usize> Scanner<'a, N> {
+    /// Returns the offset of the start of the token containing `offset`,
+    /// None if it is in white space or out of the source. Only the line of
+    /// the offset is scanned.
+    pub fn token_start_at(&mut self, offset: usize) -> Option<usize> {
+        let line_start = self.line_start(offset)?;
+        self.line_tokens(line_start, offset)
+            .into_iter()
+            .find(|t| t.contains(&offset))
+            .map(|t| t.start)
+    }
+
+    /// Returns the offset of the start of the last token starting before
+    /// `offset`, e.g. to delete a word backward: the token containing the
+    /// offset if it doesn't start there, or the token before it. Lines are
+    /// scanned backward until one has such a token; None if there is none.
+    pub fn previous_token_boundary(&mut self, offset: usize) -> Option<usize> {
+        let mut line_start = self.line_start(offset.min(self.base_offset + self.src.len()))?;
+        loop {
+            let tokens = self.line_tokens(line_start, offset);
+            if let Some(t) = tokens.iter().rev().find(|t| t.start < offset) {
+                return Some(t.start);
+            }
+            if line_start == self.base_offset {
+                return None;
+            }
+            line_start = self.line_start(line_start - 1)?;
+        }
+    }
+
+    // Returns the offset of the start of the line containing offset.
+    fn line_start(&self, offset: usize) -> Option<usize> {
+        let i = offset.checked_sub(self.base_offset).filter(|&i| i <= self.src.len())?;
+        let start = self.src[..i].iter().rposition(|&b| b == b'\n').map_or(0, |p| p + 1);
+        Some(self.base_offset + start)
+    }
+
+    // Returns the byte ranges of the tokens starting from start, up to the
+    // one containing or following offset, scanned with the configuration of
+    // the scanner but without its hooks, lints and diagnostics. Comments are
+    // included.
+    fn line_tokens(&mut self, start: usize, offset: usize) -> Vec<Range<usize>> {
+        let mut probe: Scanner<'a, 64> = Scanner::with_buffer(&self.src[start - self.base_offset..]);
+        probe.base_offset = start;
+        probe.src_buf_offset = start;
+        probe.mode = self.mode & !SKIP_COMMENTS;
+        probe.whitespace = self.whitespace;
+        if let Some(ext) = self.ext.as_mut() {
+            let probe_ext = probe.ext_mut();
+            probe_ext.is_ident_rune = ext.is_ident_rune.take();
+            probe_ext.is_whitespace_rune = ext.is_whitespace_rune.take();
+            probe_ext.literate_prefix = ext.literate_prefix.clone();
+            probe_ext.delimiter_pairs = ext.delimiter_pairs.clone();
+            probe_ext.line_comment_prefixes = ext.line_comment_prefixes.clone();
+            probe_ext.dispatch_macros = ext.dispatch_macros.clone();
+        }
+        probe.nested_block_comments = self.nested_block_comments;
+        probe.namespaced_keywords = self.namespaced_keywords;
+        probe.raw_string_delimiters = self.raw_string_delimiters;
+        probe.disabled_policy = self.disabled_policy;
+        let mut tokens = Vec::new();
+        loop {
+            let t = probe.scan_spanned();
+            if t.tok == EOF {
+                break;
+            }
+            tokens.push(t.span.start.offset..t.span.end.offset);
+            if t.span.end.offset > offset {
+                break;
+            }
+        }
+        if let (Some(ext), Some(probe_ext)) = (self.ext.as_mut(), probe.ext.as_mut()) {
+            ext.is_ident_rune = probe_ext.is_ident_rune.take();
+            ext.is_whitespace_rune = probe_ext.is_whitespace_rune.take();
+        }
+        tokens
+    }
+}
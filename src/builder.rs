@@ -0,0 +1,220 @@
+// Copyright 2022 Jordi Íñigo Griera. All rights reserved.
+
+//! Fluent configuration of scanners.
+//!
+//! A `ScannerBuilder` configures a scanner in a single expression, before
+//! it scans anything, instead of a series of setter calls:
+//!
+//! ```
+//! use scanner::*;
+//!
+//! let mut s = Scanner::builder("(def Ñ 1)".as_bytes())
+//!     .filename("x.lisp")
+//!     .mode(MINIMAL_TOKENS)
+//!     .whitespace(LISP_WHITESPACE | (1 << b','))
+//!     .text_normalizer(IDENT, |text| text.to_lowercase())
+//!     .build();
+//! s.scan();
+//! assert_eq!(s.scan(), IDENT);
+//! assert_eq!(s.scan(), IDENT);
+//! assert_eq!(s.token_text(), "ñ");
+//! assert_eq!(s.position.to_string(), "x.lisp:1:6");
+//! ```
+//!
+//! Each method calls the setter of the same name, prefixed by `set_`, or
+//! `add_delimiter_pair()`; see them for the details.
+
+use core::any::Any;
+use alloc::string::String;
+
+use crate::{
+    ChunkedReader, DEFAULT_BUFFER_SIZE, DisabledPolicy, DispatchMacro, EofPolicy, IdentLengthPolicy, Position, Scanner, SourceId, SourceKind,
+    Span, Token,
+};
+
+/// ScannerBuilder configures a scanner; see the module documentation.
+pub struct ScannerBuilder<'a, const N: usize = DEFAULT_BUFFER_SIZE> {
+    scanner: Scanner<'a, N>,
+}
+
+impl<'a, const N: usize> ScannerBuilder<'a, N> {
+    /// Returns a builder of a scanner with an inline buffer of N bytes;
+    /// see `Scanner::with_buffer()`.
+    pub fn with_buffer(src: &'a [u8]) -> Self {
+        ScannerBuilder { scanner: Scanner::with_buffer(src) }
+    }
+
+    /// Returns the configured scanner.
+    pub fn build(self) -> Scanner<'a, N> {
+        self.scanner
+    }
+
+    /// Sets the source of the positions.
+    pub fn source(mut self, source: SourceId) -> Self {
+        self.scanner.position.source = source;
+        self
+    }
+
+    /// Sets the source of the positions to the file at path.
+    pub fn filename(self, path: &str) -> Self {
+        self.source(SourceId::new(SourceKind::Path, path))
+    }
+
+    pub fn mode(mut self, mode: u32) -> Self {
+        self.scanner.set_mode(mode);
+        self
+    }
+
+    pub fn whitespace(mut self, whitespace: u64) -> Self {
+        self.scanner.set_whitespace(whitespace);
+        self
+    }
+
+    pub fn is_ident_rune<F>(mut self, f: F) -> Self
+    where
+        F: Fn(char, usize) -> bool + 'static,
+    {
+        self.scanner.set_is_ident_rune(f);
+        self
+    }
+
+    pub fn is_whitespace_rune<F>(mut self, f: F) -> Self
+    where
+        F: Fn(char) -> bool + 'static,
+    {
+        self.scanner.set_is_whitespace_rune(f);
+        self
+    }
+
+    pub fn text_normalizer<F>(mut self, kind: Token, f: F) -> Self
+    where
+        F: Fn(&str) -> String + 'static,
+    {
+        self.scanner.set_text_normalizer(kind, f);
+        self
+    }
+
+    pub fn chunked_reader(mut self, reader: ChunkedReader) -> Self {
+        self.scanner.set_chunked_reader(reader);
+        self
+    }
+
+    pub fn user_data<T: Any + Send + Sync>(mut self, data: T) -> Self {
+        self.scanner.set_user_data(data);
+        self
+    }
+
+    pub fn token_hook<F>(mut self, f: F) -> Self
+    where
+        F: FnMut(Token, &Span) + 'static,
+    {
+        self.scanner.set_token_hook(f);
+        self
+    }
+
+    pub fn error_handler<F>(mut self, f: F) -> Self
+    where
+        F: FnMut(Position, &str) + 'static,
+    {
+        self.scanner.set_error_handler(f);
+        self
+    }
+
+    pub fn base_position(mut self, pos: &Position) -> Self {
+        self.scanner.set_base_position(pos);
+        self
+    }
+
+    pub fn delimiter_pair(mut self, open: &str, close: &str) -> Self {
+        self.scanner.add_delimiter_pair(open, close);
+        self
+    }
+
+    pub fn line_comment_prefixes(mut self, prefixes: &[&str]) -> Self {
+        self.scanner.set_line_comment_prefixes(prefixes);
+        self
+    }
+
+    pub fn document_delimiter(mut self, delimiter: Option<&str>) -> Self {
+        self.scanner.set_document_delimiter(delimiter);
+        self
+    }
+
+    pub fn reset_lines_per_document(mut self, reset: bool) -> Self {
+        self.scanner.set_reset_lines_per_document(reset);
+        self
+    }
+
+    pub fn nested_block_comments(mut self, nested: bool) -> Self {
+        self.scanner.set_nested_block_comments(nested);
+        self
+    }
+
+    pub fn namespaced_keywords(mut self, namespaced: bool) -> Self {
+        self.scanner.set_namespaced_keywords(namespaced);
+        self
+    }
+
+    pub fn dispatch_macro(mut self, ch: char, dispatch: Option<DispatchMacro>) -> Self {
+        self.scanner.set_dispatch_macro(ch, dispatch);
+        self
+    }
+
+    pub fn raw_string_delimiters(mut self, open: char, close: char) -> Self {
+        self.scanner.set_raw_string_delimiters(open, close);
+        self
+    }
+
+    pub fn literate_prefix(mut self, prefix: Option<&str>) -> Self {
+        self.scanner.set_literate_prefix(prefix);
+        self
+    }
+
+    pub fn disabled_policy(mut self, policy: DisabledPolicy) -> Self {
+        self.scanner.set_disabled_policy(policy);
+        self
+    }
+
+    pub fn eof_policy(mut self, policy: EofPolicy) -> Self {
+        self.scanner.set_eof_policy(policy);
+        self
+    }
+
+    pub fn trivia(mut self, kinds: &[Token]) -> Self {
+        self.scanner.set_trivia(kinds);
+        self
+    }
+
+    pub fn lints(mut self, lints: u32) -> Self {
+        self.scanner.set_lints(lints);
+        self
+    }
+
+    pub fn naming_style<F>(mut self, lint: u32, f: F) -> Self
+    where
+        F: Fn(&str) -> bool + 'static,
+    {
+        self.scanner.set_naming_style(lint, f);
+        self
+    }
+
+    pub fn max_line_length(mut self, max: usize) -> Self {
+        self.scanner.set_max_line_length(max);
+        self
+    }
+
+    pub fn max_nesting_depth(mut self, max: usize) -> Self {
+        self.scanner.set_max_nesting_depth(max);
+        self
+    }
+
+    pub fn max_raw_string_lines(mut self, max: usize) -> Self {
+        self.scanner.set_max_raw_string_lines(max);
+        self
+    }
+
+    pub fn max_ident_length(mut self, max: usize, policy: IdentLengthPolicy) -> Self {
+        self.scanner.set_max_ident_length(max, policy);
+        self
+    }
+}
@@ -0,0 +1,430 @@
+// Copyright 2022 Jordi Íñigo Griera. All rights reserved.
+
+//! Description of the configuration of a scanner.
+//!
+//! `Scanner::capabilities()` describes what the scanner recognizes with
+//! its current configuration: token kinds, comments, delimiters, white
+//! space and limits. Tools display it, and test fixtures compare the
+//! descriptions of two scanners to assert that they were configured alike.
+//! It formats as `key value` lines, in a fixed order:
+//!
+//! ```
+//! use scanner::*;
+//!
+//! let mut s = Scanner::init(b"");
+//! s.set_mode(MINIMAL_TOKENS);
+//! let caps = s.capabilities();
+//! assert_eq!(caps.tokens, [IDENT, INT, FLOAT, STRING]);
+//! assert!(caps.skip_comments);
+//! let text = caps.to_string();
+//! assert!(text.starts_with("tokens Ident Int Float String\ncomments \";\"\nskip_comments true\n"));
+//!
+//! let mut t = Scanner::init(b"(a)");
+//! assert_ne!(t.capabilities(), caps);
+//! t.set_mode(MINIMAL_TOKENS);
+//! assert_eq!(t.capabilities(), caps);
+//! ```
+//!
+//! The description parses back, e.g. from a fixture file:
+//!
+//! ```
+//! use scanner::*;
+//!
+//! let caps = Scanner::init(b"").capabilities();
+//! assert_eq!(caps.to_string().parse(), Ok(caps));
+//! assert_eq!(
+//!     "tokens Ident\nskip".parse::<Capabilities>().unwrap_err().to_string(),
+//!     "line 2: expected comments"
+//! );
+//! ```
+
+use core::fmt;
+use core::str::FromStr;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::extensions::Extensions;
+use crate::literal::unescape_string;
+use crate::render::render_string;
+use crate::{
+    BIG_DECIMAL, BIG_INT, CHAR, COMMENT, DATUM_COMMENT, DEREF, EOF, DOCUMENT_BOUNDARY, DisabledPolicy, DispatchMacro, ERROR, EofPolicy, FLOAT, IDENT, INT, IdentLengthPolicy, KEYWORD,
+    NEED_MORE_INPUT, OTHER, PROSE, QUASIQUOTE, QUOTE, RATIO, RAW_STRING, SCAN_BIG_DECIMALS, SCAN_BIG_INTS, SCAN_BLOCK_COMMENTS, SCAN_CHARS, SCAN_COMMENT_CONTINUATIONS,
+    SCAN_COMMENTS, SCAN_DATUM_COMMENTS, SCAN_FLOATS, SCAN_IDENTS, SCAN_INTS, SCAN_KEYWORDS, SCAN_LISP_SPECIALS, SCAN_QUOTES, SCAN_RATIOS, SCAN_RAW_STRINGS, SCAN_STRINGS,
+    SCAN_WHITESPACE, SKIP_COMMENTS, STRING, Scanner, Token, UNQUOTE, UNQUOTE_SPLICING, WHITESPACE, token_string,
+};
+
+/// Capabilities describes the configuration of a scanner, as returned by
+/// `Scanner::capabilities()`; see the module documentation. Closures, such
+/// as the identifier predicate and the text normalizers, are only reported
+/// as set or not.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Capabilities {
+    /// Token kinds the scanner can return, besides EOF and characters.
+    pub tokens: Vec<Token>,
+    /// Introducers of the comments.
+    pub comments: Vec<String>,
+    /// Whether comments are skipped instead of returned.
+    pub skip_comments: bool,
+    /// Whether block comments nest.
+    pub nested_block_comments: bool,
+    /// Whether a comment ending in `\` continues over the next line.
+    pub comment_continuations: bool,
+    /// Whether keywords may be namespaced, e.g. `:ns/name`.
+    pub namespaced_keywords: bool,
+    /// Opening and closing delimiters of brackets, strings and raw strings.
+    pub delimiters: Vec<(String, String)>,
+    /// Multi-character tokens returned as IDENT, such as `~@`.
+    pub specials: Vec<String>,
+    /// Dispatch macros registered for `#`, in the order they were set.
+    pub dispatch_macros: Vec<(char, DispatchMacro)>,
+    /// White space characters of the bitmask.
+    pub whitespace: Vec<char>,
+    /// Token kinds skipped by `next_significant()`.
+    pub trivia: Vec<Token>,
+    /// How strings, raw strings and comments whose mode bit is off are
+    /// scanned.
+    pub disabled_policy: DisabledPolicy,
+    /// How the end of the input inside a literal is treated.
+    pub eof_policy: EofPolicy,
+    /// Prefix of the code lines in literate mode, None outside it.
+    pub literate_prefix: Option<String>,
+    /// Line separating the documents of the source, if any.
+    pub document_delimiter: Option<String>,
+    /// Whether line numbers restart at each document.
+    pub reset_lines_per_document: bool,
+    /// Lints enabled, as `LINT_*` bits.
+    pub lints: u32,
+    /// Maximum line length checked by `LINT_LINE_LENGTH`.
+    pub max_line_length: usize,
+    /// Maximum nesting depth checked by `LINT_DEEP_NESTING`.
+    pub max_nesting_depth: usize,
+    /// Maximum number of lines of a raw string checked by
+    /// `LINT_SUSPICIOUS_DELIMITERS`.
+    pub max_raw_string_lines: usize,
+    /// Maximum length of identifiers and keywords, `usize::MAX` for none.
+    pub max_ident_length: usize,
+    /// How identifiers longer than `max_ident_length` are scanned.
+    pub ident_length_policy: IdentLengthPolicy,
+    /// Size of the inline buffer.
+    pub buffer_size: usize,
+    /// Whether a custom identifier predicate is set.
+    pub custom_ident_rune: bool,
+    /// Whether a custom white space predicate is set.
+    pub custom_whitespace_rune: bool,
+    /// Token kinds with a text normalizer, in the order they were set.
+    pub normalized: Vec<Token>,
+}
+
+impl Capabilities {
+    pub(crate) fn new<const N: usize>(s: &Scanner<'_, N>) -> Self {
+        let mode = s.mode;
+        let kinds = [
+            (SCAN_IDENTS, IDENT),
+            (SCAN_INTS | SCAN_FLOATS, INT),
+            (SCAN_FLOATS, FLOAT),
+            (SCAN_STRINGS, STRING),
+            (SCAN_KEYWORDS, KEYWORD),
+            (SCAN_RAW_STRINGS, RAW_STRING),
+        ];
+        // A scanner without extensions has the default ones
+        let default = Extensions::new();
+        let ext = s.ext.as_deref().unwrap_or(&default);
+        let mut tokens: Vec<Token> = kinds.iter().filter(|(bit, _)| mode & bit != 0).map(|&(_, tok)| tok).collect();
+        if mode & (SCAN_COMMENTS | SCAN_BLOCK_COMMENTS) != 0 && mode & SKIP_COMMENTS == 0 {
+            tokens.push(COMMENT);
+        }
+        if mode & SCAN_CHARS != 0 {
+            tokens.push(CHAR);
+        }
+        if mode & SCAN_DATUM_COMMENTS != 0 {
+            tokens.push(DATUM_COMMENT);
+        }
+        if mode & SCAN_QUOTES != 0 {
+            tokens.extend([QUOTE, QUASIQUOTE, UNQUOTE, UNQUOTE_SPLICING, DEREF]);
+        }
+        let numbers = mode & (SCAN_INTS | SCAN_FLOATS) != 0;
+        let suffixed = [(SCAN_RATIOS, RATIO), (SCAN_BIG_INTS, BIG_INT), (SCAN_BIG_DECIMALS, BIG_DECIMAL)];
+        tokens.extend(suffixed.iter().filter(|&&(bit, _)| numbers && mode & bit != 0).map(|&(_, tok)| tok));
+        if mode & SCAN_WHITESPACE != 0 {
+            tokens.push(WHITESPACE);
+        }
+        if ext.literate_prefix.is_some() {
+            tokens.push(PROSE);
+        }
+        if s.disabled_policy == DisabledPolicy::Other {
+            tokens.push(OTHER);
+        }
+        if ext.document_delimiter.as_deref().is_some_and(|d| !d.is_empty()) {
+            tokens.push(DOCUMENT_BOUNDARY);
+        }
+        if ext.ident_length_policy == IdentLengthPolicy::Error && ext.max_ident_length != usize::MAX {
+            tokens.push(ERROR);
+        }
+        if s.eof_policy == EofPolicy::NeedMoreInput {
+            tokens.push(NEED_MORE_INPUT);
+        }
+        for (_, dispatch) in &ext.dispatch_macros {
+            let (DispatchMacro::Token(kind) | DispatchMacro::Literal(kind) | DispatchMacro::Prefix(kind)) = *dispatch;
+            if !tokens.contains(&kind) {
+                tokens.push(kind);
+            }
+        }
+
+        let mut comments = if mode & SCAN_COMMENTS != 0 { ext.line_comment_prefixes.iter().map(|p| String::from(p.as_ref())).collect() } else { Vec::new() };
+        if mode & SCAN_BLOCK_COMMENTS != 0 {
+            comments.push(String::from("#|"));
+        }
+
+        let pair = |open: &str, close: &str| (String::from(open), String::from(close));
+        let mut delimiters = alloc::vec![pair("(", ")"), pair("[", "]"), pair("{", "}")];
+        let lisp_specials = mode & SCAN_IDENTS != 0 && mode & SCAN_LISP_SPECIALS != 0;
+        if lisp_specials {
+            delimiters.push(pair("#{", "}"));
+        }
+        if mode & SCAN_IDENTS != 0 {
+            delimiters.extend(ext.delimiter_pairs.iter().cloned());
+        }
+        if mode & SCAN_STRINGS != 0 {
+            delimiters.push(pair("\"", "\""));
+        }
+        if mode & SCAN_RAW_STRINGS != 0 {
+            let (open, close) = s.raw_string_delimiters;
+            delimiters.push(pair(open.encode_utf8(&mut [0; 4]), close.encode_utf8(&mut [0; 4])));
+        }
+
+        let mut specials = Vec::new();
+        if lisp_specials {
+            if mode & SCAN_QUOTES == 0 {
+                specials.push(String::from("~@"));
+            }
+            specials.push(String::from("#{"));
+        }
+
+        Capabilities {
+            tokens,
+            comments,
+            skip_comments: mode & SKIP_COMMENTS != 0,
+            nested_block_comments: s.nested_block_comments,
+            comment_continuations: mode & SCAN_COMMENT_CONTINUATIONS != 0,
+            namespaced_keywords: s.namespaced_keywords,
+            delimiters,
+            specials,
+            dispatch_macros: ext.dispatch_macros.clone(),
+            whitespace: (0..64u8).filter(|&b| s.whitespace & (1 << b) != 0).map(char::from).collect(),
+            trivia: (1..32).filter(|&k| s.trivia & (1 << k) != 0).map(|k: Token| -k).collect(),
+            disabled_policy: s.disabled_policy,
+            eof_policy: s.eof_policy,
+            literate_prefix: ext.literate_prefix.clone(),
+            document_delimiter: ext.document_delimiter.clone(),
+            reset_lines_per_document: ext.reset_lines_per_document,
+            lints: s.lints,
+            max_line_length: ext.max_line_length,
+            max_nesting_depth: ext.max_nesting_depth,
+            max_raw_string_lines: ext.max_raw_string_lines,
+            max_ident_length: ext.max_ident_length,
+            ident_length_policy: ext.ident_length_policy,
+            buffer_size: N,
+            custom_ident_rune: ext.is_ident_rune.is_some(),
+            custom_whitespace_rune: ext.is_whitespace_rune.is_some(),
+            normalized: ext.normalizers.iter().map(|(kind, _)| *kind).collect(),
+        }
+    }
+}
+
+/// Formats the description as `key value` lines, with lists separated by
+/// spaces and texts quoted.
+impl fmt::Display for Capabilities {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let tokens = |toks: &[Token]| toks.iter().map(|&t| token_string(t)).collect::<Vec<_>>().join(" ");
+        let texts = |texts: &mut dyn Iterator<Item = &str>| texts.map(render_string).collect::<Vec<_>>().join(" ");
+        let optional = |value: &Option<String>| value.as_deref().map_or(String::from("none"), render_string);
+        let whitespace: String = self.whitespace.iter().collect();
+        writeln!(f, "tokens {}", tokens(&self.tokens))?;
+        writeln!(f, "comments {}", texts(&mut self.comments.iter().map(String::as_str)))?;
+        writeln!(f, "skip_comments {}", self.skip_comments)?;
+        writeln!(f, "nested_block_comments {}", self.nested_block_comments)?;
+        writeln!(f, "comment_continuations {}", self.comment_continuations)?;
+        writeln!(f, "namespaced_keywords {}", self.namespaced_keywords)?;
+        let mut delimiters = self.delimiters.iter().flat_map(|(open, close)| [open.as_str(), close.as_str()]);
+        writeln!(f, "delimiters {}", texts(&mut delimiters))?;
+        writeln!(f, "specials {}", texts(&mut self.specials.iter().map(String::as_str)))?;
+        let dispatch_macros: Vec<String> = self.dispatch_macros.iter().map(|(ch, dispatch)| format!("{} {:?}", render_string(&format!("#{ch}")), dispatch)).collect();
+        writeln!(f, "dispatch_macros {}", dispatch_macros.join(" "))?;
+        writeln!(f, "whitespace {}", render_string(&whitespace))?;
+        writeln!(f, "trivia {}", tokens(&self.trivia))?;
+        writeln!(f, "disabled_policy {:?}", self.disabled_policy)?;
+        writeln!(f, "eof_policy {:?}", self.eof_policy)?;
+        writeln!(f, "literate_prefix {}", optional(&self.literate_prefix))?;
+        writeln!(f, "document_delimiter {}", optional(&self.document_delimiter))?;
+        writeln!(f, "reset_lines_per_document {}", self.reset_lines_per_document)?;
+        writeln!(f, "lints {:#x}", self.lints)?;
+        writeln!(f, "max_line_length {}", self.max_line_length)?;
+        writeln!(f, "max_nesting_depth {}", self.max_nesting_depth)?;
+        writeln!(f, "max_raw_string_lines {}", self.max_raw_string_lines)?;
+        writeln!(f, "max_ident_length {}", self.max_ident_length)?;
+        writeln!(f, "ident_length_policy {:?}", self.ident_length_policy)?;
+        writeln!(f, "buffer_size {}", self.buffer_size)?;
+        writeln!(f, "custom_ident_rune {}", self.custom_ident_rune)?;
+        writeln!(f, "custom_whitespace_rune {}", self.custom_whitespace_rune)?;
+        writeln!(f, "normalized {}", tokens(&self.normalized))
+    }
+}
+
+/// Parses a description formatted by `Display`, whose lines must all be
+/// present and in their order.
+impl FromStr for Capabilities {
+    type Err = ParseCapabilitiesError;
+
+    fn from_str(text: &str) -> Result<Self, ParseCapabilitiesError> {
+        let mut lines = Lines { lines: text.lines(), line: 0 };
+        let tokens = |value: &str| words(value)?.into_iter().map(parse_token).collect::<Option<Vec<_>>>();
+        let texts = |value: &str| words(value)?.into_iter().map(quoted).collect::<Option<Vec<_>>>();
+        let optional = |value: &str| if value == "none" { Some(None) } else { quoted(value).map(Some) };
+        let number = |value: &str| value.parse().ok();
+        let flag = |value: &str| value.parse().ok();
+        let caps = Capabilities {
+            tokens: lines.value("tokens", tokens)?,
+            comments: lines.value("comments", texts)?,
+            skip_comments: lines.value("skip_comments", flag)?,
+            nested_block_comments: lines.value("nested_block_comments", flag)?,
+            comment_continuations: lines.value("comment_continuations", flag)?,
+            namespaced_keywords: lines.value("namespaced_keywords", flag)?,
+            delimiters: lines.value("delimiters", |value| {
+                let texts = texts(value)?;
+                let pairs = texts.chunks_exact(2);
+                pairs.remainder().is_empty().then(|| pairs.map(|pair| (pair[0].clone(), pair[1].clone())).collect())
+            })?,
+            specials: lines.value("specials", texts)?,
+            dispatch_macros: lines.value("dispatch_macros", |value| {
+                let words = words(value)?;
+                let pairs = words.chunks_exact(2);
+                if !pairs.remainder().is_empty() {
+                    return None;
+                }
+                pairs.map(|pair| Some((single_char(quoted(pair[0])?.strip_prefix('#')?)?, parse_dispatch(pair[1])?))).collect()
+            })?,
+            whitespace: lines.value("whitespace", |value| Some(quoted(value)?.chars().collect()))?,
+            trivia: lines.value("trivia", tokens)?,
+            disabled_policy: lines.value("disabled_policy", |value| match value {
+                "Chars" => Some(DisabledPolicy::Chars),
+                "Skip" => Some(DisabledPolicy::Skip),
+                "Other" => Some(DisabledPolicy::Other),
+                _ => None,
+            })?,
+            eof_policy: lines.value("eof_policy", |value| match value {
+                "Error" => Some(EofPolicy::Error),
+                "NeedMoreInput" => Some(EofPolicy::NeedMoreInput),
+                _ => None,
+            })?,
+            literate_prefix: lines.value("literate_prefix", optional)?,
+            document_delimiter: lines.value("document_delimiter", optional)?,
+            reset_lines_per_document: lines.value("reset_lines_per_document", flag)?,
+            lints: lines.value("lints", |value| u32::from_str_radix(value.strip_prefix("0x")?, 16).ok())?,
+            max_line_length: lines.value("max_line_length", number)?,
+            max_nesting_depth: lines.value("max_nesting_depth", number)?,
+            max_raw_string_lines: lines.value("max_raw_string_lines", number)?,
+            max_ident_length: lines.value("max_ident_length", number)?,
+            ident_length_policy: lines.value("ident_length_policy", |value| match value {
+                "Truncate" => Some(IdentLengthPolicy::Truncate),
+                "Error" => Some(IdentLengthPolicy::Error),
+                _ => None,
+            })?,
+            buffer_size: lines.value("buffer_size", number)?,
+            custom_ident_rune: lines.value("custom_ident_rune", flag)?,
+            custom_whitespace_rune: lines.value("custom_whitespace_rune", flag)?,
+            normalized: lines.value("normalized", tokens)?,
+        };
+        match lines.lines.next() {
+            Some(_) => Err(ParseCapabilitiesError { line: lines.line + 1, key: "end" }),
+            None => Ok(caps),
+        }
+    }
+}
+
+/// ParseCapabilitiesError reports the first line of a description that
+/// isn't the expected `key value` line, counted from 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseCapabilitiesError {
+    pub line: usize,
+    /// Key expected on the line, or `end` past the last one.
+    pub key: &'static str,
+}
+
+impl fmt::Display for ParseCapabilitiesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: expected {}", self.line, self.key)
+    }
+}
+
+impl core::error::Error for ParseCapabilitiesError {}
+
+// Lines of a description being parsed.
+struct Lines<'t> {
+    lines: core::str::Lines<'t>,
+    line: usize, // number of the last line read
+}
+
+impl<'t> Lines<'t> {
+    // Parses the value of the next line, which must have the given key.
+    fn value<T>(&mut self, key: &'static str, parse: impl FnOnce(&'t str) -> Option<T>) -> Result<T, ParseCapabilitiesError> {
+        self.line += 1;
+        let value = self.lines.next().and_then(|line| line.strip_prefix(key)?.strip_prefix(' '));
+        value.and_then(parse).ok_or(ParseCapabilitiesError { line: self.line, key })
+    }
+}
+
+// Splits a value into its words, separated by spaces, or returns None if a
+// quoted word isn't closed. Quoted words may contain spaces and escaped
+// quotes.
+fn words(value: &str) -> Option<Vec<&str>> {
+    let mut words = Vec::new();
+    let mut rest = value.trim_start_matches(' ');
+    while !rest.is_empty() {
+        let len = if let Some(text) = rest.strip_prefix('"') {
+            let mut escaped = false;
+            let end = text.find(|c| {
+                let end = !escaped && c == '"';
+                escaped = !escaped && c == '\\';
+                end
+            })?;
+            end + 2
+        } else {
+            rest.find(' ').unwrap_or(rest.len())
+        };
+        words.push(&rest[..len]);
+        rest = rest[len..].trim_start_matches(' ');
+    }
+    Some(words)
+}
+
+// Returns the value of a quoted word.
+fn quoted(word: &str) -> Option<String> {
+    unescape_string(word.strip_prefix('"')?.strip_suffix('"')?).ok()
+}
+
+// Returns the only character of text.
+fn single_char(text: &str) -> Option<char> {
+    let mut chars = text.chars();
+    chars.next().filter(|_| chars.next().is_none())
+}
+
+// Parses a token kind formatted by `token_string()`.
+fn parse_token(word: &str) -> Option<Token> {
+    if word.starts_with('"') {
+        return single_char(&quoted(word)?).map(|ch| ch as Token);
+    }
+    (DEREF..=EOF).find(|&tok| token_string(tok) == word)
+}
+
+// Parses a dispatch macro formatted by `Debug`.
+fn parse_dispatch(word: &str) -> Option<DispatchMacro> {
+    let (variant, kind) = word.strip_suffix(')')?.split_once('(')?;
+    let kind = kind.parse().ok()?;
+    match variant {
+        "Token" => Some(DispatchMacro::Token(kind)),
+        "Literal" => Some(DispatchMacro::Literal(kind)),
+        "Prefix" => Some(DispatchMacro::Prefix(kind)),
+        _ => None,
+    }
+}
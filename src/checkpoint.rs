@@ -0,0 +1,83 @@
+// Copyright 2022 Jordi Íñigo Griera. All rights reserved.
+
+//! Checkpoints for backtracking parsers.
+//!
+//! A parser trying an alternative takes a checkpoint, scans ahead, and if
+//! the alternative fails, rewinds the scanner to the checkpoint, which
+//! scans the same tokens again. Rewinding drops the diagnostics reported
+//! since the checkpoint, as they are reported again when the tokens are
+//! rescanned; calls to the error handler and the token hook can't be
+//! undone, though, nor can diagnostics taken with `take_diagnostics()`.
+//!
+//! ```
+//! use scanner::*;
+//!
+//! let mut s = Scanner::init(b"(a b) (c)");
+//! s.scan();
+//! let checkpoint = s.checkpoint();
+//! assert_eq!(s.scan(), IDENT);
+//! assert_eq!(s.scan(), IDENT);
+//! s.rewind(checkpoint);
+//! assert_eq!(s.token_text(), "(");
+//! assert_eq!(s.scan(), IDENT);
+//! assert_eq!(s.token_text(), "a");
+//! ```
+//!
+//! The source of a `Scanner` is a byte slice, so a checkpoint is small: it
+//! doesn't keep the bytes read since it was taken, nor the text of the
+//! current token, which are read again from the slice when rewinding. Of
+//! the diagnostics it keeps only their number, and the tokens scanned
+//! ahead are shared with the scanner rather than copied.
+
+use alloc::collections::VecDeque;
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::diagnostics::{LineLint, OpenBracket};
+use crate::summary::Tally;
+use crate::{ChunkedReader, LeadingComment, Position, ScanError, Scanned, Span, Token};
+
+/// ScannerCheckpoint is the scanning state of a scanner, returned by
+/// `Scanner::checkpoint()` to rewind it later; see the module
+/// documentation. It doesn't include the configuration.
+#[derive(Debug, Clone)]
+pub struct ScannerCheckpoint {
+    pub(crate) src_read_pos: usize,
+    pub(crate) src_pos: usize,
+    pub(crate) src_end: usize,
+    pub(crate) src_buf_offset: usize,
+    pub(crate) line: usize,
+    pub(crate) column: usize,
+    pub(crate) last_line_len: usize,
+    pub(crate) last_char_len: usize,
+    pub(crate) tok_buf_len: usize,
+    pub(crate) tok_pos: isize,
+    pub(crate) tok_end: usize,
+    pub(crate) ch: i32,
+    pub(crate) tok: Token,
+    pub(crate) last_significant_line: usize,
+    pub(crate) leading_comment: Option<LeadingComment>,
+    pub(crate) comment_block: Option<Span>,
+    pub(crate) ahead: VecDeque<Rc<Scanned>>,
+    pub(crate) replay: Option<Rc<Scanned>>,
+    pub(crate) ahead_start: Option<Rc<ScannerCheckpoint>>,
+    pub(crate) ahead_scanned: usize,
+    pub(crate) detached: Option<Rc<Scanned>>,
+    pub(crate) error_count: usize,
+    pub(crate) diagnostics_len: usize,
+    pub(crate) first_error: Option<ScanError>,
+    pub(crate) tally: Tally,
+    pub(crate) nesting_depth: usize,
+    pub(crate) open_brackets: Vec<OpenBracket>,
+    pub(crate) line_lint: LineLint,
+    pub(crate) normalized: Option<String>,
+    pub(crate) chunked_reader: Option<ChunkedReader>,
+    pub(crate) chunks: usize, // number of chunks in the replay log
+    pub(crate) bom: bool,
+    #[cfg(feature = "pages")]
+    pub(crate) page: usize,
+    #[cfg(feature = "pages")]
+    pub(crate) page_break: bool,
+    pub(crate) position: Position,
+}
@@ -0,0 +1,88 @@
+// Copyright 2022 Jordi Íñigo Griera. All rights reserved.
+
+//! Delivery of the source in pathological chunks, to stress the handling
+//! of buffer refills.
+//!
+//! The scanner copies its source into its buffer in chunks as large as the
+//! buffer allows. A `ChunkedReader` set with `Scanner::set_chunked_reader()`
+//! ends chunks earlier, at the offsets selected by a `ChunkPattern`, e.g.
+//! inside UTF-8 sequences or inside tokens. The scanned tokens must not
+//! depend on the chunking, which tools embedding the scanner can check on
+//! their own inputs:
+//!
+//! ```
+//! use scanner::*;
+//!
+//! let src = "(def ñ \"abc\")".as_bytes();
+//! let mut s = Scanner::init(src);
+//! let expected: Vec<SpannedToken> = s.tokens().spanned().collect();
+//!
+//! let mut s = Scanner::init(src);
+//! s.set_chunked_reader(ChunkedReader::new(src, ChunkPattern::SplitUtf8));
+//! assert_eq!(s.tokens().spanned().collect::<Vec<_>>(), expected);
+//! ```
+
+use alloc::vec::Vec;
+
+use crate::{EOF, LOSSLESS_TOKENS, Scanner};
+
+/// ChunkPattern selects where a ChunkedReader ends the chunks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChunkPattern {
+    /// Chunks of the given number of bytes (at least 1).
+    Bytes(usize),
+    /// End a chunk after the first byte of every multi-byte UTF-8 sequence.
+    SplitUtf8,
+    /// End a chunk after the first byte of every token, as scanned in
+    /// `LOSSLESS_TOKENS` mode.
+    SplitTokens,
+    /// End chunks at the given source offsets.
+    At(Vec<usize>),
+}
+
+/// ChunkedReader limits the chunks of source a scanner copies into its
+/// buffer; see the module documentation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkedReader {
+    ends: Vec<usize>, // sorted chunk ends
+    next: usize,      // index of the first end after the read position
+}
+
+impl ChunkedReader {
+    /// Returns a reader ending the chunks of src as selected by pattern.
+    pub fn new(src: &[u8], pattern: ChunkPattern) -> Self {
+        let mut ends = match pattern {
+            ChunkPattern::Bytes(n) => (n.max(1)..src.len()).step_by(n.max(1)).collect(),
+            ChunkPattern::SplitUtf8 => src
+                .iter()
+                .enumerate()
+                .filter(|&(_, &b)| b >= 0xC0)
+                .map(|(i, _)| i + 1)
+                .collect(),
+            ChunkPattern::SplitTokens => {
+                let mut s = Scanner::init(src);
+                s.set_mode(LOSSLESS_TOKENS);
+                let mut ends = Vec::new();
+                while s.scan() != EOF {
+                    ends.push(s.position.offset + 1);
+                }
+                ends
+            }
+            ChunkPattern::At(ends) => ends,
+        };
+        ends.sort_unstable();
+        ends.dedup();
+        ChunkedReader { ends, next: 0 }
+    }
+
+    // Returns how many of the n bytes available at offset pos to read.
+    pub(crate) fn limit(&mut self, pos: usize, n: usize) -> usize {
+        while self.next < self.ends.len() && self.ends[self.next] <= pos {
+            self.next += 1;
+        }
+        match self.ends.get(self.next) {
+            Some(&end) => n.min(end - pos),
+            None => n,
+        }
+    }
+}
@@ -0,0 +1,174 @@
+// Copyright 2022 Jordi Íñigo Griera. All rights reserved.
+
+//! Verification of parity with the Go implementation.
+//!
+//! A transcript lists the tokens scanned from a source, one per line, as
+//! `line:column: token text`, where `token` is the token name as returned
+//! by `token_string` (or the quoted character) and `text` is the quoted
+//! token text. This is the output of the following Go program:
+//!
+//! ```go
+//! for tok := s.Scan(); tok != scanner.EOF; tok = s.Scan() {
+//!     fmt.Printf("%d:%d: %s %q\n", s.Line, s.Column, scanner.TokenString(tok), s.TokenText())
+//! }
+//! ```
+//!
+//! Recording a transcript with the Go scanner and comparing it with
+//! `record` keeps forks adjusting the dialect in sync with the upstream
+//! behavior they rely on.
+
+use core::fmt;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::literal::decode_string;
+use crate::render::render_string;
+use crate::{
+    CAPTURE, COMMENT, DOCUMENT_BOUNDARY, EOF, FLOAT, IDENT, INT, KEYWORD, OTHER, PROSE, RAW_STRING, STRING,
+    Scanner, Token, WHITESPACE, token_string,
+};
+
+/// TranscriptEntry is a token in a transcript.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TranscriptEntry {
+    pub line: usize,
+    pub column: usize,
+    pub tok: Token,
+    pub text: String,
+}
+
+impl fmt::Display for TranscriptEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match char::from_u32(self.tok as u32) {
+            Some(ch) if self.tok >= 0 => render_string(&ch.to_string()),
+            _ => token_string(self.tok),
+        };
+        write!(f, "{}:{}: {} {}", self.line, self.column, name, render_string(&self.text))
+    }
+}
+
+/// TranscriptError reports a malformed transcript line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TranscriptError {
+    /// Line number in the transcript, starting at 1.
+    pub line: usize,
+}
+
+impl fmt::Display for TranscriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "transcript line {}: malformed entry", self.line)
+    }
+}
+
+/// Divergence is the first difference between two token streams. An entry
+/// is None where its stream ended before the other.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    /// Index of the diverging token.
+    pub index: usize,
+    pub expected: Option<TranscriptEntry>,
+    pub found: Option<TranscriptEntry>,
+}
+
+/// Scans `src` in the given mode and returns its transcript.
+pub fn record(src: &[u8], mode: u32) -> Vec<TranscriptEntry> {
+    let mut s = Scanner::init(src);
+    s.set_mode(mode);
+    let mut entries = Vec::new();
+    loop {
+        let tok = s.scan();
+        if tok == EOF {
+            return entries;
+        }
+        entries.push(TranscriptEntry {
+            line: s.position.line,
+            column: s.position.column,
+            tok,
+            text: s.token_text(),
+        });
+    }
+}
+
+/// Parses a transcript; blank lines are ignored.
+pub fn parse_transcript(transcript: &str) -> Result<Vec<TranscriptEntry>, TranscriptError> {
+    let mut entries = Vec::new();
+    for (n, line) in (1..).zip(transcript.lines()) {
+        if line.trim().is_empty() {
+            continue;
+        }
+        entries.push(parse_entry(line).ok_or(TranscriptError { line: n })?);
+    }
+    Ok(entries)
+}
+
+fn parse_entry(line: &str) -> Option<TranscriptEntry> {
+    let (pos, rest) = line.split_once(": ")?;
+    let (l, c) = pos.split_once(':')?;
+    let (name, text) = if rest.starts_with('"') {
+        rest.split_at(quoted_len(rest)?)
+    } else {
+        rest.split_at(rest.find(' ')?)
+    };
+    Some(TranscriptEntry {
+        line: l.parse().ok()?,
+        column: c.parse().ok()?,
+        tok: parse_token(name)?,
+        text: decode_string(text.strip_prefix(' ')?)?,
+    })
+}
+
+// Returns the length of the quoted string at the start of s.
+fn quoted_len(s: &str) -> Option<usize> {
+    let mut escaped = false;
+    for (i, ch) in s.char_indices().skip(1) {
+        match ch {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => return Some(i + 1),
+            _ => {}
+        }
+    }
+    None
+}
+
+fn parse_token(name: &str) -> Option<Token> {
+    let tok = match name {
+        "EOF" => EOF,
+        "Ident" => IDENT,
+        "Int" => INT,
+        "Float" => FLOAT,
+        "String" => STRING,
+        "Keyword" => KEYWORD,
+        "RawString" => RAW_STRING,
+        "Comment" => COMMENT,
+        "Prose" => PROSE,
+        "Whitespace" => WHITESPACE,
+        "Capture" => CAPTURE,
+        "Other" => OTHER,
+        "DocumentBoundary" => DOCUMENT_BOUNDARY,
+        _ => {
+            let value = decode_string(name)?;
+            let mut chars = value.chars();
+            let ch = chars.next()?;
+            if chars.next().is_some() {
+                return None;
+            }
+            ch as Token
+        }
+    };
+    Some(tok)
+}
+
+/// Compares two token streams and returns their first divergence, or None
+/// if they are equal.
+pub fn diff(expected: &[TranscriptEntry], found: &[TranscriptEntry]) -> Option<Divergence> {
+    let index = expected.iter().zip(found).take_while(|(e, f)| e == f).count();
+    if index == expected.len() && index == found.len() {
+        return None;
+    }
+    Some(Divergence {
+        index,
+        expected: expected.get(index).cloned(),
+        found: found.get(index).cloned(),
+    })
+}
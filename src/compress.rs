@@ -0,0 +1,331 @@
+// Copyright 2022 Jordi Íñigo Griera. All rights reserved.
+
+//! Decompression of archived sources (`compress` feature).
+//!
+//! `decompress()` decompresses a whole source into a buffer, for the
+//! scanner to borrow; positions refer to the decompressed text:
+//!
+//! ```
+//! use scanner::*;
+//!
+//! // "(a)" compressed with gzip
+//! let gz = [
+//!     0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x03, 0xd3, 0x48, 0xd4, 0x04,
+//!     0x00, 0xc0, 0x0c, 0x4b, 0xf7, 0x03, 0x00, 0x00, 0x00,
+//! ];
+//! let src = decompress(&gz).unwrap();
+//! let mut s = Scanner::init(&src);
+//! assert_eq!(s.scan(), '(' as Token);
+//! assert_eq!(s.scan(), IDENT);
+//!
+//! let mut s = CompressedScanner::new(&gz[..]);
+//! let texts: Vec<String> = s.by_ref().map(|t| t.text).collect();
+//! assert_eq!(texts, ["(", "a", ")"]);
+//! assert_eq!(s.error(), None);
+//! ```
+//!
+//! Large corpora are scanned by a `CompressedScanner` instead, which
+//! decompresses the source a chunk at a time as its tokens are scanned,
+//! so the decompressed text is never held in memory at once. Its
+//! `Decompressor` returns the chunks, and it is a reader too, e.g. for a
+//! `LineScanner`. The output of `decompress()` is
+//! limited to `DEFAULT_MAX_DECOMPRESSED_SIZE` bytes, to resist
+//! decompression bombs; see `decompress_with_limit()`.
+//!
+//! gzip is decoded by `flate2` and zstd by `zstd`, also when several
+//! members or frames are concatenated. zstd frames that need a dictionary,
+//! or a window over 128 MiB, aren't supported.
+
+use core::fmt;
+use alloc::vec::Vec;
+use std::io::{self, BufRead, Read};
+
+use flate2::bufread::MultiGzDecoder;
+
+use crate::{Diagnostic, PushScanner, PushStatus, Scanner, SpannedToken};
+
+/// DecompressError reports a source that can't be decompressed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecompressError {
+    /// The data is neither gzip nor zstd compressed.
+    UnknownFormat,
+    /// The data is truncated, malformed or doesn't match its checksum, or
+    /// it is a zstd frame that isn't supported.
+    Corrupt,
+    /// The decompressed data exceeds the size limit.
+    TooLarge,
+}
+
+impl fmt::Display for DecompressError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let msg = match self {
+            DecompressError::UnknownFormat => "unknown compression format",
+            DecompressError::Corrupt => "corrupt compressed data",
+            DecompressError::TooLarge => "decompressed data exceeds the size limit",
+        };
+        f.write_str(msg)
+    }
+}
+
+impl core::error::Error for DecompressError {}
+
+/// Default limit on the size of the output of `decompress()`: 256 MiB.
+pub const DEFAULT_MAX_DECOMPRESSED_SIZE: usize = 256 << 20;
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+// Size of the chunks decoded at a time.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Reports whether data starts like a gzip or zstd stream.
+pub fn is_compressed(data: &[u8]) -> bool {
+    data.starts_with(GZIP_MAGIC) || is_zstd(data)
+}
+
+// Reports whether data starts like a zstd stream: a frame, or a skippable
+// frame, whose magic numbers are 0x184D2A50 to 0x184D2A5F.
+fn is_zstd(data: &[u8]) -> bool {
+    data.starts_with(ZSTD_MAGIC) || (data.len() >= 4 && data[0] & 0xf0 == 0x50 && data[1..4] == [0x2a, 0x4d, 0x18])
+}
+
+/// Decompresses a gzip or zstd stream, possibly of several members or
+/// frames, of up to `DEFAULT_MAX_DECOMPRESSED_SIZE` bytes.
+pub fn decompress(data: &[u8]) -> Result<Vec<u8>, DecompressError> {
+    decompress_with_limit(data, DEFAULT_MAX_DECOMPRESSED_SIZE)
+}
+
+/// Decompresses a stream like `decompress()`, of up to limit bytes.
+pub fn decompress_with_limit(data: &[u8], limit: usize) -> Result<Vec<u8>, DecompressError> {
+    let mut d = Decompressor::new(data);
+    d.set_limit(limit);
+    let mut out = Vec::new();
+    while let Some(chunk) = d.next_chunk()? {
+        out.extend_from_slice(chunk);
+    }
+    Ok(out)
+}
+
+/// Decompressor decompresses a gzip or zstd stream a chunk at a time,
+/// holding only the chunk and the state of the decoder.
+///
+/// ```
+/// use scanner::*;
+///
+/// let gz = [
+///     0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x03, 0xd3, 0x48, 0xd4, 0x04,
+///     0x00, 0xc0, 0x0c, 0x4b, 0xf7, 0x03, 0x00, 0x00, 0x00,
+/// ];
+/// let mut d = Decompressor::new(&gz[..]);
+/// assert_eq!(d.next_chunk(), Ok(Some(&b"(a)"[..])));
+/// assert_eq!(d.next_chunk(), Ok(None));
+/// ```
+pub struct Decompressor<D> {
+    decoder: Decoder<D>,
+    out: Vec<u8>,    // output decoded and not returned yet, from returned
+    returned: usize, // length of out returned
+    total: usize,    // size of the output decoded so far
+    limit: usize,
+}
+
+// Decoder of the compressed stream, reading the data it owns.
+enum Decoder<D> {
+    Gzip(MultiGzDecoder<Input<D>>),
+    Zstd(zstd::stream::read::Decoder<'static, Input<D>>),
+    Failed(DecompressError), // the error is returned once, then the output ends
+    End,
+}
+
+// Input is the compressed data, read by the decoders.
+struct Input<D> {
+    data: D,
+    pos: usize,
+}
+
+impl<D: AsRef<[u8]>> Read for Input<D> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.fill_buf()?.read(buf)?;
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+impl<D: AsRef<[u8]>> BufRead for Input<D> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        Ok(&self.data.as_ref()[self.pos..])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos += amt;
+    }
+}
+
+impl<D: AsRef<[u8]>> Decompressor<D> {
+    /// Returns a Decompressor of data, without a limit on the size of the
+    /// output.
+    pub fn new(data: D) -> Self {
+        let input = Input { data, pos: 0 };
+        let bytes = input.data.as_ref();
+        let decoder = if bytes.starts_with(GZIP_MAGIC) {
+            Decoder::Gzip(MultiGzDecoder::new(input))
+        } else if is_zstd(bytes) {
+            match zstd::stream::read::Decoder::with_buffer(input) {
+                Ok(decoder) => Decoder::Zstd(decoder),
+                Err(_) => Decoder::Failed(DecompressError::Corrupt),
+            }
+        } else {
+            Decoder::Failed(DecompressError::UnknownFormat)
+        };
+        Decompressor { decoder, out: Vec::new(), returned: 0, total: 0, limit: usize::MAX }
+    }
+
+    /// Sets the limit on the size of the output, over which
+    /// `DecompressError::TooLarge` is returned.
+    pub fn set_limit(&mut self, limit: usize) {
+        self.limit = limit;
+    }
+
+    /// Returns the next chunk of the output, or None at its end. After an
+    /// error, the output ends.
+    pub fn next_chunk(&mut self) -> Result<Option<&[u8]>, DecompressError> {
+        self.fill()?;
+        let start = self.returned;
+        self.returned = self.out.len();
+        Ok(Some(&self.out[start..]).filter(|chunk| !chunk.is_empty()))
+    }
+
+    // Decodes a chunk of output if all of it was returned, unless at its
+    // end.
+    fn fill(&mut self) -> Result<(), DecompressError> {
+        if self.returned < self.out.len() {
+            return Ok(());
+        }
+        self.out.resize(CHUNK_SIZE, 0);
+        self.returned = 0;
+        let n = match self.decode() {
+            Ok(n) => n,
+            Err(err) => {
+                self.decoder = Decoder::End;
+                self.out.clear();
+                return Err(err);
+            }
+        };
+        self.out.truncate(n);
+        self.total += n;
+        if self.total > self.limit {
+            self.decoder = Decoder::End;
+            self.out.clear();
+            return Err(DecompressError::TooLarge);
+        }
+        Ok(())
+    }
+
+    // Decodes up to a chunk of output into out, returning its length.
+    fn decode(&mut self) -> Result<usize, DecompressError> {
+        let mut n = 0;
+        while n < self.out.len() {
+            let read = match &mut self.decoder {
+                Decoder::Gzip(decoder) => decoder.read(&mut self.out[n..]),
+                Decoder::Zstd(decoder) => decoder.read(&mut self.out[n..]),
+                Decoder::Failed(err) => return Err(*err),
+                Decoder::End => break,
+            };
+            match read {
+                Ok(0) => self.decoder = Decoder::End,
+                Ok(len) => n += len,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(_) if n > 0 => {
+                    // The output decoded before the error is returned first
+                    self.decoder = Decoder::Failed(DecompressError::Corrupt);
+                    break;
+                }
+                Err(_) => return Err(DecompressError::Corrupt),
+            }
+        }
+        Ok(n)
+    }
+}
+
+impl<D: AsRef<[u8]>> Read for Decompressor<D> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.fill().map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        let n = buf.len().min(self.out.len() - self.returned);
+        buf[..n].copy_from_slice(&self.out[self.returned..self.returned + n]);
+        self.returned += n;
+        Ok(n)
+    }
+}
+
+/// CompressedScanner scans the tokens of a compressed source, decompressing
+/// it as they are scanned; see the module documentation. Positions refer
+/// to the decompressed text.
+pub struct CompressedScanner<D> {
+    decompressor: Decompressor<D>,
+    scanner: PushScanner,
+    error: Option<DecompressError>,
+}
+
+impl<D: AsRef<[u8]>> CompressedScanner<D> {
+    /// Returns a scanner of the compressed data, in `LISP_TOKENS` mode.
+    pub fn new(data: D) -> Self {
+        CompressedScanner { decompressor: Decompressor::new(data), scanner: PushScanner::new(), error: None }
+    }
+
+    /// Sets the mode of the scanners of the following input.
+    pub fn set_mode(&mut self, mode: u32) {
+        self.scanner.set_mode(mode);
+    }
+
+    /// Sets a function configuring the scanners of the following input;
+    /// see `IterScanner::set_configuration()`.
+    pub fn set_configuration<F>(&mut self, configure: F)
+    where
+        F: Fn(&mut Scanner<'_>) + Send + Sync + 'static,
+    {
+        self.scanner.set_configuration(configure);
+    }
+
+    /// Sets the limit on the size of the decompressed source.
+    pub fn set_limit(&mut self, limit: usize) {
+        self.decompressor.set_limit(limit);
+    }
+
+    /// Returns the diagnostics reported so far.
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        self.scanner.diagnostics()
+    }
+
+    /// Returns the decompression error that ended the source, if any.
+    pub fn error(&self) -> Option<DecompressError> {
+        self.error
+    }
+}
+
+impl CompressedScanner<Vec<u8>> {
+    /// Returns a scanner of the compressed file at path. The compressed
+    /// file is read into memory, and decompressed as it is scanned.
+    pub fn open<P: AsRef<std::path::Path>>(path: P) -> std::io::Result<Self> {
+        Ok(CompressedScanner::new(std::fs::read(path)?))
+    }
+}
+
+impl<D: AsRef<[u8]>> Iterator for CompressedScanner<D> {
+    type Item = SpannedToken;
+
+    fn next(&mut self) -> Option<SpannedToken> {
+        loop {
+            match self.scanner.scan() {
+                PushStatus::Token(t) => return Some(t),
+                PushStatus::End => return None,
+                PushStatus::Incomplete => match self.decompressor.next_chunk() {
+                    Ok(Some(chunk)) => self.scanner.feed(chunk),
+                    Ok(None) => self.scanner.finish(),
+                    Err(err) => {
+                        self.error = Some(err);
+                        self.scanner.finish();
+                    }
+                },
+            }
+        }
+    }
+}
@@ -0,0 +1,294 @@
+// Copyright 2022 Jordi Íñigo Griera. All rights reserved.
+
+//! Diagnostics reported by the scanner.
+//!
+//! Errors found while scanning and the optional lexical lints are collected
+//! as `Diagnostic` values, in source order, and can be inspected with
+//! `Scanner::diagnostics()` or drained with `Scanner::take_diagnostics()`.
+
+use core::any::Any;
+use core::fmt;
+use alloc::string::String;
+use alloc::sync::Arc;
+
+use crate::{Position, Span};
+
+/// Lint bits to enable the optional lexical checks.
+pub const LINT_TRAILING_WHITESPACE: u32 = 1 << 0;
+pub const LINT_MIXED_INDENTATION: u32 = 1 << 1;
+pub const LINT_LINE_LENGTH: u32 = 1 << 2;
+
+/// Lint bits to warn about suspicious but legal tokens.
+pub const LINT_LEGACY_OCTAL: u32 = 1 << 3;
+pub const LINT_ESCAPE_RANGE: u32 = 1 << 4;
+pub const LINT_NON_NFC: u32 = 1 << 5;
+pub const LINT_DEEP_NESTING: u32 = 1 << 6;
+
+/// Lint bits to check the naming style of identifiers, earmuffed
+/// identifiers (`*name*`) and keywords, kebab-case by default (see
+/// `Scanner::set_naming_style()`).
+pub const LINT_IDENT_STYLE: u32 = 1 << 7;
+pub const LINT_EARMUFF_STYLE: u32 = 1 << 8;
+pub const LINT_KEYWORD_STYLE: u32 = 1 << 9;
+
+/// Lint bit to warn about suspicious delimiters that are probable typos:
+/// a doubled opening bracket closed only once, e.g. `((a)`, and raw
+/// strings spanning more lines than `Scanner::set_max_raw_string_lines()`.
+pub const LINT_SUSPICIOUS_DELIMITERS: u32 = 1 << 10;
+
+/// Lint bit to warn about a closing delimiter that doesn't match the
+/// innermost open one, e.g. `(a]`, or that closes nothing. It checks
+/// brackets, `#{` and the pairs added with `Scanner::add_delimiter_pair()`.
+pub const LINT_UNBALANCED_DELIMITERS: u32 = 1 << 11;
+
+// Lints checked on every character and on every token, respectively
+pub(crate) const LINE_LINTS: u32 = LINT_TRAILING_WHITESPACE | LINT_MIXED_INDENTATION | LINT_LINE_LENGTH;
+pub(crate) const TOKEN_LINTS: u32 = LINT_LEGACY_OCTAL
+    | LINT_ESCAPE_RANGE
+    | LINT_NON_NFC
+    | LINT_DEEP_NESTING
+    | LINT_IDENT_STYLE
+    | LINT_EARMUFF_STYLE
+    | LINT_KEYWORD_STYLE
+    | LINT_SUSPICIOUS_DELIMITERS
+    | LINT_UNBALANCED_DELIMITERS;
+
+/// All lexical lints
+pub const ALL_LINTS: u32 = LINE_LINTS | TOKEN_LINTS;
+
+/// Default maximum line length (in characters) used by `LINT_LINE_LENGTH`.
+pub const DEFAULT_MAX_LINE_LENGTH: usize = 100;
+
+/// Default maximum nesting depth of brackets used by `LINT_DEEP_NESTING`.
+pub const DEFAULT_MAX_NESTING_DEPTH: usize = 64;
+
+/// Default maximum number of lines of a raw string used by
+/// `LINT_SUSPICIOUS_DELIMITERS`.
+pub const DEFAULT_MAX_RAW_STRING_LINES: usize = 50;
+
+/// Severity of a diagnostic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// Stable identifier of the kind of a diagnostic.
+///
+/// Codes and messages never change once released, so tools can match on
+/// either. Messages with a placeholder are completed with details of the
+/// offending text.
+///
+/// | Code   | Severity | Message |
+/// |--------|----------|---------|
+/// | `E001` | error    | invalid UTF-8 encoding |
+/// | `E002` | error    | invalid character NUL |
+/// | `E003` | error    | invalid radix point in *literal* |
+/// | `E004` | error    | *literal* has no digits |
+/// | `E005` | error    | '*e*' exponent requires decimal mantissa, '*p*' exponent requires hexadecimal mantissa |
+/// | `E006` | error    | exponent has no digits |
+/// | `E007` | error    | hexadecimal mantissa requires a 'p' exponent |
+/// | `E008` | error    | invalid digit '*d*' in *literal* |
+/// | `E009` | error    | '_' must separate successive digits |
+/// | `E010` | error    | invalid char escape, unknown character name |
+/// | `E011` | error    | literal not terminated, comment not terminated |
+/// | `E012` | error    | *identifier* is *n* characters long (maximum is *max*), for identifiers and keywords |
+/// | `W001` | warning  | trailing whitespace |
+/// | `W002` | warning  | tab after spaces in indentation |
+/// | `W003` | warning  | line is *n* characters long (maximum is *max*) |
+/// | `W004` | warning  | octal literal with leading 0, use the 0o prefix |
+/// | `W005` | warning  | escape sequence *escape* is out of range |
+/// | `W006` | warning  | identifier is not in NFC normal form, stray combining mark *U+XXXX*, the source may not be in NFC normal form |
+/// | `W007` | warning  | nesting depth exceeds *max* |
+/// | `W008` | warning  | *identifier* *name* doesn't follow the naming style, for identifiers, earmuffed identifiers and keywords |
+/// | `W009` | warning  | doubled '*(*' is closed only once, raw string spans *n* lines (maximum is *max*) |
+/// | `W010` | warning  | '*]*' doesn't match '*(*', '*)*' closes nothing |
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum DiagnosticCode {
+    InvalidUtf8,
+    InvalidNul,
+    InvalidRadixPoint,
+    NoDigits,
+    InvalidMantissa,
+    NoExponentDigits,
+    MissingExponent,
+    InvalidDigit,
+    InvalidSeparator,
+    InvalidEscape,
+    Unterminated,
+    IdentTooLong,
+    TrailingWhitespace,
+    MixedIndentation,
+    LineLength,
+    LegacyOctal,
+    EscapeRange,
+    NonNfc,
+    DeepNesting,
+    NamingStyle,
+    SuspiciousDelimiter,
+    UnbalancedDelimiter,
+}
+
+impl DiagnosticCode {
+    /// Returns the code as listed in the table above, e.g. `E011`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DiagnosticCode::InvalidUtf8 => "E001",
+            DiagnosticCode::InvalidNul => "E002",
+            DiagnosticCode::InvalidRadixPoint => "E003",
+            DiagnosticCode::NoDigits => "E004",
+            DiagnosticCode::InvalidMantissa => "E005",
+            DiagnosticCode::NoExponentDigits => "E006",
+            DiagnosticCode::MissingExponent => "E007",
+            DiagnosticCode::InvalidDigit => "E008",
+            DiagnosticCode::InvalidSeparator => "E009",
+            DiagnosticCode::InvalidEscape => "E010",
+            DiagnosticCode::Unterminated => "E011",
+            DiagnosticCode::IdentTooLong => "E012",
+            DiagnosticCode::TrailingWhitespace => "W001",
+            DiagnosticCode::MixedIndentation => "W002",
+            DiagnosticCode::LineLength => "W003",
+            DiagnosticCode::LegacyOctal => "W004",
+            DiagnosticCode::EscapeRange => "W005",
+            DiagnosticCode::NonNfc => "W006",
+            DiagnosticCode::DeepNesting => "W007",
+            DiagnosticCode::NamingStyle => "W008",
+            DiagnosticCode::SuspiciousDelimiter => "W009",
+            DiagnosticCode::UnbalancedDelimiter => "W010",
+        }
+    }
+}
+
+impl fmt::Display for DiagnosticCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Diagnostic is an error or warning found while scanning, with the
+/// span of source text it refers to. The span of an error runs from the
+/// start of the offending token or escape sequence to the point where the
+/// error was detected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: DiagnosticCode,
+    pub message: String,
+    pub span: Span,
+    /// The user data of the scanner that reported the diagnostic.
+    pub user_data: UserData,
+}
+
+impl Diagnostic {
+    /// Reports whether the diagnostic is an error.
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+
+    /// Returns the user data of the scanner that reported the diagnostic,
+    /// if it is of type T.
+    pub fn user_data<T: Any>(&self) -> Option<&T> {
+        self.user_data.get()
+    }
+}
+
+/// UserData is an optional, shared value of any type attached to a scanner
+/// with `Scanner::set_user_data()` and carried by its diagnostics, e.g. to
+/// route them back to the job that scanned the source. Two UserData are
+/// equal if they share the same value.
+#[derive(Clone, Default)]
+pub struct UserData(Option<Arc<dyn Any + Send + Sync>>);
+
+impl UserData {
+    /// Wraps a value as user data.
+    pub fn new<T: Any + Send + Sync>(data: T) -> Self {
+        UserData(Some(Arc::new(data)))
+    }
+
+    /// Returns the value if there is one of type T.
+    pub fn get<T: Any>(&self) -> Option<&T> {
+        self.0.as_deref().and_then(|data| (data as &dyn Any).downcast_ref())
+    }
+
+    /// Reports whether there is no value.
+    pub fn is_none(&self) -> bool {
+        self.0.is_none()
+    }
+}
+
+impl PartialEq for UserData {
+    fn eq(&self, other: &Self) -> bool {
+        match (&self.0, &other.0) {
+            (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+            (a, b) => a.is_none() && b.is_none(),
+        }
+    }
+}
+
+impl Eq for UserData {}
+
+impl fmt::Debug for UserData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(_) => f.write_str("UserData(..)"),
+            None => f.write_str("UserData(None)"),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.severity {
+            Severity::Error => write!(f, "{}: {}", self.span.start, self.message),
+            Severity::Warning => write!(f, "{}: warning: {}", self.span.start, self.message),
+        }
+    }
+}
+
+/// Per-line state of the lexical lints.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct LineLint {
+    pub(crate) chars: usize,
+    pub(crate) in_indent: bool,
+    pub(crate) indent_space: bool,
+    pub(crate) mixed: bool,
+    pub(crate) trailing: Option<(usize, usize)>, // (offset, column) of trailing blanks
+    pub(crate) overflow: Option<(usize, usize)>, // (offset, column) of first char past the limit
+}
+
+/// Reports whether a name is in kebab-case, i.e. it has neither upper case
+/// letters nor underscores, e.g. `read-string` or `*host-language*`. It is
+/// the default naming style of the style lints.
+pub fn is_kebab_case(name: &str) -> bool {
+    !name.chars().any(|ch| ch == '_' || ch.is_uppercase())
+}
+
+/// An open bracket tracked by `LINT_SUSPICIOUS_DELIMITERS`.
+#[derive(Debug, Clone)]
+pub(crate) struct OpenBracket {
+    pub(crate) ch: char,
+    pub(crate) span: Span,
+    // End of the same bracket immediately following this one
+    pub(crate) doubled: Option<Position>,
+}
+
+// Reports whether ch is a combining mark, which doesn't appear after a
+// base character it composes with in NFC normalized text.
+pub(crate) fn is_combining_mark(ch: char) -> bool {
+    matches!(ch,
+        '\u{0300}'..='\u{036F}'
+        | '\u{1AB0}'..='\u{1AFF}'
+        | '\u{1DC0}'..='\u{1DFF}'
+        | '\u{20D0}'..='\u{20FF}'
+        | '\u{FE20}'..='\u{FE2F}')
+}
+
+impl LineLint {
+    pub(crate) fn new() -> Self {
+        LineLint {
+            in_indent: true,
+            ..Default::default()
+        }
+    }
+}
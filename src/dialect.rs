@@ -0,0 +1,109 @@
+// Copyright 2022 Jordi Íñigo Griera. All rights reserved.
+
+//! Declarative dialect definitions.
+
+/// Defines a dialect in one place: a module holding its scanner
+/// configuration, the token-kind constants of its custom tokens, and their
+/// printable names.
+///
+/// ```
+/// use scanner::*;
+///
+/// scanner::dialect! {
+///     /// Extensible Data Notation.
+///     pub mod edn {
+///         mode = LISP_TOKENS & !SCAN_RAW_STRINGS;
+///         whitespace = LISP_WHITESPACE | (1 << b',');
+///         tokens {
+///             /// A `#tag` element.
+///             TAG = -64 => "Tag",
+///         }
+///     }
+/// }
+///
+/// fn main() {
+///     let mut s = edn::scanner(b"[1, 2]");
+///     assert_eq!(s.scan(), '[' as i32);
+///     assert_eq!(s.scan(), INT);
+///     assert_eq!(s.scan(), INT);
+///     assert_eq!(edn::token_string(edn::TAG), "Tag");
+///     assert_eq!(edn::token_string(INT), "Int");
+/// }
+/// ```
+///
+/// The generated module contains:
+///  - `MODE` and `WHITESPACE`, the values for `set_mode` and `set_whitespace`;
+///  - a `Token` constant for each custom token, whose code must not collide
+///    with the crate's token kinds nor with Unicode characters, which the
+///    scanner also returns as tokens (negative values below -63 are safe);
+///  - `token_name(tok)`, the name of a custom token or None;
+///  - `token_string(tok)`, like `scanner::token_string` but aware of the
+///    custom tokens;
+///  - `configure(&mut Scanner)` and `scanner(src)`, to apply the
+///    configuration to a scanner or create a configured one.
+///
+/// Expressions are evaluated in the generated module, which glob-imports
+/// its parent, so names in scope at the definition site can be used.
+#[macro_export]
+macro_rules! dialect {
+    (
+        $(#[$meta:meta])*
+        $vis:vis mod $name:ident {
+            mode = $mode:expr;
+            whitespace = $whitespace:expr;
+            $(tokens {
+                $(
+                    $(#[$tok_meta:meta])*
+                    $tok:ident = $code:expr => $tok_name:expr
+                ),* $(,)?
+            })?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis mod $name {
+            #[allow(unused_imports)]
+            use super::*;
+
+            /// Scanning mode of the dialect.
+            pub const MODE: u32 = $mode;
+
+            /// White space characters of the dialect.
+            pub const WHITESPACE: u64 = $whitespace;
+
+            $($(
+                $(#[$tok_meta])*
+                pub const $tok: $crate::Token = $code;
+            )*)?
+
+            /// Returns the name of a custom token of the dialect.
+            #[allow(unreachable_patterns)]
+            pub fn token_name(tok: $crate::Token) -> ::core::option::Option<&'static str> {
+                match tok {
+                    $($( $tok => ::core::option::Option::Some($tok_name), )*)?
+                    _ => ::core::option::Option::None,
+                }
+            }
+
+            /// Returns a printable string for a token of the dialect or a Unicode character.
+            pub fn token_string(tok: $crate::Token) -> $crate::__private::String {
+                match token_name(tok) {
+                    ::core::option::Option::Some(name) => $crate::__private::String::from(name),
+                    ::core::option::Option::None => $crate::token_string(tok),
+                }
+            }
+
+            /// Applies the dialect configuration to a scanner.
+            pub fn configure(s: &mut $crate::Scanner) {
+                s.set_mode(MODE);
+                s.set_whitespace(WHITESPACE);
+            }
+
+            /// Initializes a Scanner configured for the dialect.
+            pub fn scanner(src: &[u8]) -> $crate::Scanner<'_> {
+                let mut s = $crate::Scanner::init(src);
+                configure(&mut s);
+                s
+            }
+        }
+    };
+}
@@ -0,0 +1,88 @@
+// Copyright 2022 Jordi Íñigo Griera. All rights reserved.
+
+//! Decoding of sources not encoded in UTF-8 (`encoding` feature).
+//!
+//! The scanner reads UTF-8, so a UTF-16 source, as often written by
+//! Windows tools, is decoded into a buffer first; positions refer to the
+//! decoded text:
+//!
+//! ```
+//! use scanner::*;
+//!
+//! // "(a)" in UTF-16LE with a byte order mark
+//! let src = [0xFF, 0xFE, b'(', 0, b'a', 0, b')', 0];
+//! assert_eq!(detect_encoding(&src), (UTF_16LE, 2));
+//! let src = decode(&src);
+//! let mut s = Scanner::init(&src);
+//! assert_eq!(s.scan(), '(' as Token);
+//! assert_eq!(s.scan(), IDENT);
+//! ```
+//!
+//! The encoding is detected from the byte order mark, or else from the
+//! zero bytes of ASCII text encoded in UTF-16.
+//!
+//! Legacy sources, e.g. in Windows-1252 or Shift JIS, can't be told apart
+//! from invalid UTF-8, so their encoding is given to `decode_from()`, e.g.
+//! from a label with `Encoding::for_label()`:
+//!
+//! ```
+//! use scanner::*;
+//!
+//! let encoding = Encoding::for_label(b"cp1252").unwrap();
+//! assert_eq!(&*decode_from(b"\x93caf\xE9\x94", encoding), "“café”".as_bytes());
+//! ```
+//!
+//! The encodings and their labels are those of the WHATWG Encoding
+//! Standard, as implemented by `encoding_rs`, whose `Encoding` is
+//! re-exported; e.g. the label `latin1` is Windows-1252.
+
+use alloc::borrow::Cow;
+use alloc::string::String;
+
+pub use encoding_rs::{Encoding, UTF_8, UTF_16BE, UTF_16LE};
+
+// Number of bytes inspected to detect UTF-16 without a byte order mark.
+const DETECT_LEN: usize = 256;
+
+/// Returns the encoding of data and the length of its byte order mark, 0
+/// if it has none. Data without a byte order mark is UTF-16 if its first
+/// bytes are mostly ASCII characters in UTF-16, and UTF-8 otherwise.
+pub fn detect_encoding(data: &[u8]) -> (&'static Encoding, usize) {
+    if let Some(bom) = Encoding::for_bom(data) {
+        return bom;
+    }
+    // A zero byte is rare in text, but half of the bytes of ASCII in UTF-16
+    let head = &data[..data.len().min(DETECT_LEN) & !1];
+    let (mut even, mut odd) = (0, 0);
+    for pair in head.chunks_exact(2) {
+        even += usize::from(pair[0] == 0);
+        odd += usize::from(pair[1] == 0);
+    }
+    let pairs = head.len() / 2;
+    match (even, odd) {
+        (0, odd) if pairs > 0 && odd * 2 >= pairs => (UTF_16LE, 0),
+        (even, 0) if pairs > 0 && even * 2 >= pairs => (UTF_16BE, 0),
+        _ => (UTF_8, 0),
+    }
+}
+
+/// Returns data decoded into UTF-8 from its detected encoding, borrowed if
+/// it is UTF-8 already. See `decode_from()`.
+pub fn decode(data: &[u8]) -> Cow<'_, [u8]> {
+    decode_from(data, detect_encoding(data).0)
+}
+
+/// Returns data decoded into UTF-8 from encoding, without its byte order
+/// mark, borrowed if it is UTF-8 or ASCII in an ASCII-compatible encoding.
+/// Malformed sequences of other encodings are decoded as U+FFFD; invalid
+/// UTF-8 is left for the scanner to report, as is the UTF-8 byte order
+/// mark, which the scanner discards.
+pub fn decode_from<'a>(data: &'a [u8], encoding: &'static Encoding) -> Cow<'a, [u8]> {
+    if encoding == UTF_8 {
+        return Cow::Borrowed(data);
+    }
+    match encoding.decode_with_bom_removal(data).0 {
+        Cow::Borrowed(text) => Cow::Borrowed(text.as_bytes()),
+        Cow::Owned(text) => Cow::Owned(String::into_bytes(text)),
+    }
+}
@@ -0,0 +1,105 @@
+// Copyright 2022 Jordi Íñigo Griera. All rights reserved.
+
+//! Typed scanning errors.
+
+use core::fmt;
+use alloc::boxed::Box;
+use alloc::string::String;
+
+use crate::{Diagnostic, DiagnosticCode, Position, SpannedToken, Token, token_string};
+
+/// ScanError is an error returned by the Result-based scanning API.
+///
+/// The variants other than `Unexpected` and `IoError` are the errors
+/// reported while scanning a token, as returned by `Scanner::try_scan()`;
+/// their message is the one of the diagnostic (see `DiagnosticCode`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ScanError {
+    /// The next token is not of the expected kind.
+    Unexpected {
+        expected: Token,
+        found: Box<SpannedToken>,
+    },
+    /// An invalid UTF-8 encoding (E001).
+    InvalidUtf8 { position: Position, message: String },
+    /// A NUL character (E002).
+    InvalidNul { position: Position, message: String },
+    /// A number without digits or with a misplaced radix point, exponent
+    /// or digit separator (E003 to E007 and E009).
+    MalformedNumber { position: Position, message: String },
+    /// A digit not valid in the base of the number (E008).
+    InvalidDigit { position: Position, message: String },
+    /// An invalid escape sequence in a string (E010).
+    InvalidEscape { position: Position, message: String },
+    /// A string, raw string or block comment without its closing delimiter
+    /// (E011).
+    UnterminatedString { position: Position, message: String },
+    /// An identifier or keyword longer than the limit (E012).
+    IdentTooLong { position: Position, message: String },
+    /// A read error that ended the input of a reader-backed scanner, at
+    /// the end of the input read, as returned by `LineScanner::try_next()`.
+    IoError { position: Position, message: String },
+}
+
+impl ScanError {
+    /// Returns the position the error refers to.
+    pub fn position(&self) -> &Position {
+        match self {
+            ScanError::Unexpected { found, .. } => &found.span.start,
+            ScanError::InvalidUtf8 { position, .. }
+            | ScanError::InvalidNul { position, .. }
+            | ScanError::MalformedNumber { position, .. }
+            | ScanError::InvalidDigit { position, .. }
+            | ScanError::InvalidEscape { position, .. }
+            | ScanError::UnterminatedString { position, .. }
+            | ScanError::IdentTooLong { position, .. }
+            | ScanError::IoError { position, .. } => position,
+        }
+    }
+
+    // Returns the error of an error diagnostic, None for a warning.
+    pub(crate) fn from_diagnostic(d: &Diagnostic) -> Option<Self> {
+        let position = d.span.start.clone();
+        let message = d.message.clone();
+        Some(match d.code {
+            DiagnosticCode::InvalidUtf8 => ScanError::InvalidUtf8 { position, message },
+            DiagnosticCode::InvalidNul => ScanError::InvalidNul { position, message },
+            DiagnosticCode::InvalidRadixPoint
+            | DiagnosticCode::NoDigits
+            | DiagnosticCode::InvalidMantissa
+            | DiagnosticCode::NoExponentDigits
+            | DiagnosticCode::MissingExponent
+            | DiagnosticCode::InvalidSeparator => ScanError::MalformedNumber { position, message },
+            DiagnosticCode::InvalidDigit => ScanError::InvalidDigit { position, message },
+            DiagnosticCode::InvalidEscape => ScanError::InvalidEscape { position, message },
+            DiagnosticCode::Unterminated => ScanError::UnterminatedString { position, message },
+            DiagnosticCode::IdentTooLong => ScanError::IdentTooLong { position, message },
+            _ => return None,
+        })
+    }
+}
+
+impl fmt::Display for ScanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScanError::Unexpected { expected, found } => write!(
+                f,
+                "{}: expected {}, found {}",
+                found.span.start,
+                token_string(*expected),
+                token_string(found.tok)
+            ),
+            ScanError::InvalidUtf8 { position, message }
+            | ScanError::InvalidNul { position, message }
+            | ScanError::MalformedNumber { position, message }
+            | ScanError::InvalidDigit { position, message }
+            | ScanError::InvalidEscape { position, message }
+            | ScanError::UnterminatedString { position, message }
+            | ScanError::IdentTooLong { position, message }
+            | ScanError::IoError { position, message } => write!(f, "{}: {}", position, message),
+        }
+    }
+}
+
+impl core::error::Error for ScanError {}
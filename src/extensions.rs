@@ -0,0 +1,86 @@
+// Copyright 2022 Jordi Íñigo Griera. All rights reserved.
+
+//! Rarely used configuration and state of a scanner.
+//!
+//! A scanner keeps them out of line, boxed on first use, so that a scanner
+//! with the default configuration stays small and doesn't allocate.
+
+use alloc::borrow::Cow;
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::diagnostics::{LineLint, OpenBracket};
+use crate::summary::Tally;
+use crate::{
+    ChunkedReader, DEFAULT_MAX_LINE_LENGTH, DEFAULT_MAX_NESTING_DEPTH, DEFAULT_MAX_RAW_STRING_LINES, DispatchMacro,
+    ErrorHandler, IdentLengthPolicy, NamingStyle, ReplayLog, ScanError, TextNormalizer, Token, TokenHook, UserData,
+};
+
+// Prefixes of the line comments of a scanner with the default configuration.
+pub(crate) const DEFAULT_LINE_COMMENT_PREFIXES: &[Cow<'static, str>] = &[Cow::Borrowed(";")];
+
+pub(crate) struct Extensions {
+    // Error handling
+    pub(crate) error_handler: Option<ErrorHandler>,
+    pub(crate) first_error: Option<ScanError>, // first error reported by try_scan()
+    pub(crate) tally: Tally,
+
+    // Lexical lints
+    pub(crate) max_line_length: usize,
+    pub(crate) max_nesting_depth: usize,
+    pub(crate) nesting_depth: usize,
+    pub(crate) max_raw_string_lines: usize,
+    pub(crate) open_brackets: Vec<OpenBracket>,
+    pub(crate) max_ident_length: usize,
+    pub(crate) ident_length_policy: IdentLengthPolicy,
+    pub(crate) naming_styles: Vec<(u32, NamingStyle)>,
+    pub(crate) line_lint: LineLint,
+
+    // Configuration
+    pub(crate) is_ident_rune: Option<Box<dyn Fn(char, usize) -> bool>>,
+    pub(crate) is_whitespace_rune: Option<Box<dyn Fn(char) -> bool>>,
+    pub(crate) token_hook: Option<TokenHook>,
+    pub(crate) normalizers: Vec<(Token, TextNormalizer)>,
+    pub(crate) user_data: UserData,
+    pub(crate) recording: Option<ReplayLog>,
+    pub(crate) chunked_reader: Option<ChunkedReader>,
+    pub(crate) literate_prefix: Option<String>,
+    pub(crate) document_delimiter: Option<String>,
+    pub(crate) delimiter_pairs: Vec<(String, String)>,
+    pub(crate) line_comment_prefixes: Vec<Cow<'static, str>>,
+    pub(crate) reset_lines_per_document: bool,
+    pub(crate) dispatch_macros: Vec<(char, DispatchMacro)>,
+}
+
+impl Extensions {
+    pub(crate) fn new() -> Self {
+        Extensions {
+            error_handler: None,
+            first_error: None,
+            tally: Tally::new(),
+            max_line_length: DEFAULT_MAX_LINE_LENGTH,
+            max_nesting_depth: DEFAULT_MAX_NESTING_DEPTH,
+            nesting_depth: 0,
+            max_raw_string_lines: DEFAULT_MAX_RAW_STRING_LINES,
+            open_brackets: Vec::new(),
+            max_ident_length: usize::MAX,
+            ident_length_policy: IdentLengthPolicy::Truncate,
+            naming_styles: Vec::new(),
+            line_lint: LineLint::new(),
+            is_ident_rune: None,
+            is_whitespace_rune: None,
+            token_hook: None,
+            normalizers: Vec::new(),
+            user_data: UserData::default(),
+            recording: None,
+            chunked_reader: None,
+            literate_prefix: None,
+            document_delimiter: None,
+            delimiter_pairs: Vec::new(),
+            line_comment_prefixes: DEFAULT_LINE_COMMENT_PREFIXES.to_vec(),
+            reset_lines_per_document: false,
+            dispatch_macros: Vec::new(),
+        }
+    }
+}
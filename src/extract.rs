@@ -0,0 +1,101 @@
+// Copyright 2022 Jordi Íñigo Griera. All rights reserved.
+
+//! Extraction of literals from a source.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::literal::{decode_raw_string, decode_string};
+use crate::{
+    COMMENT, CommentPlacement, EOF, LISP_TOKENS, RAW_STRING, SKIP_COMMENTS, STRING, Scanner, Span, Token,
+};
+
+/// StringLiteral is a string or raw string literal found in a source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StringLiteral {
+    /// STRING or RAW_STRING.
+    pub tok: Token,
+    /// The literal as written in the source, including delimiters.
+    pub text: String,
+    /// The decoded value, or None if the literal is malformed.
+    pub value: Option<String>,
+    pub span: Span,
+}
+
+/// Scans `src` and returns all its STRING and RAW_STRING literals, with
+/// their decoded values and spans. Comments are skipped.
+pub fn extract_strings(src: &[u8]) -> Vec<StringLiteral> {
+    let mut s = Scanner::init(src);
+    let mut literals = Vec::new();
+    loop {
+        let t = s.scan_spanned();
+        let value = match t.tok {
+            EOF => break,
+            STRING => decode_string(&t.text),
+            RAW_STRING => decode_raw_string(&t.text),
+            _ => continue,
+        };
+        literals.push(StringLiteral {
+            tok: t.tok,
+            text: t.text,
+            value,
+            span: t.span,
+        });
+    }
+    literals
+}
+
+/// CommentBlock is a comment, or a run of full-line comments on consecutive
+/// lines, found in a source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommentBlock {
+    pub placement: CommentPlacement,
+    /// The comment text with the leading semicolons and the first space
+    /// after them removed from each line, lines joined by '\n'.
+    pub text: String,
+    pub span: Span,
+}
+
+/// Scans `src` and returns all its comments. Full-line comments on
+/// consecutive lines are merged into a single block; trailing comments
+/// are always returned as blocks of their own.
+pub fn extract_comments(src: &[u8]) -> Vec<CommentBlock> {
+    let mut s = Scanner::init(src);
+    s.set_mode(LISP_TOKENS & !SKIP_COMMENTS);
+    let mut blocks: Vec<CommentBlock> = Vec::new();
+    let mut mergeable = false;
+    loop {
+        let t = s.scan_spanned();
+        if t.tok == EOF {
+            break;
+        }
+        if t.tok != COMMENT {
+            mergeable = false;
+            continue;
+        }
+        let placement = s.comment_info().map_or(CommentPlacement::FullLine, |info| info.placement);
+        let line = normalize_comment(&t.text);
+        if let Some(last) = blocks.last_mut()
+            && mergeable
+            && placement == CommentPlacement::FullLine
+            && last.span.end.line + 1 == t.span.start.line
+        {
+            last.text.push('\n');
+            last.text.push_str(line);
+            last.span.end = t.span.end;
+            continue;
+        }
+        mergeable = placement == CommentPlacement::FullLine;
+        blocks.push(CommentBlock {
+            placement,
+            text: String::from(line),
+            span: t.span,
+        });
+    }
+    blocks
+}
+
+fn normalize_comment(text: &str) -> &str {
+    let text = text.trim_start_matches(';');
+    text.strip_prefix(' ').unwrap_or(text).trim_end()
+}
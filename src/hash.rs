@@ -0,0 +1,73 @@
+// Copyright 2022 Jordi Íñigo Griera. All rights reserved.
+
+//! Structural hashing of token streams.
+
+use alloc::borrow::Cow;
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+
+use crate::literal::{decode_raw_string, decode_string, normalize_number};
+use crate::{EOF, FLOAT, IDENT, INT, RAW_STRING, STRING, Scanner};
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// Options controlling the normalization applied by `token_stream_hash_with`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HashOptions {
+    /// Replace every identifier by the index of its first occurrence, so
+    /// consistently renamed sources hash the same.
+    pub abstract_identifiers: bool,
+}
+
+/// Returns a structural hash of the tokens of `src`, see `token_stream_hash_with`.
+pub fn token_stream_hash(src: &[u8]) -> u64 {
+    token_stream_hash_with(src, &HashOptions::default())
+}
+
+/// Returns a structural hash of the token kinds and normalized token texts
+/// of `src`. White space and comments don't contribute to the hash.
+///
+/// Normalization rules:
+///  - numbers are lowercased and stripped of `_` separators;
+///  - strings and raw strings hash as their decoded value, so `"a\"b"` and
+///    `¬a"b¬` are equivalent;
+///  - with `abstract_identifiers`, identifiers hash as their first
+///    occurrence index.
+///
+/// The hash is the 64-bit FNV-1a of the normalized stream, which is stable
+/// across platforms and crate versions.
+pub fn token_stream_hash_with(src: &[u8], opts: &HashOptions) -> u64 {
+    let mut s = Scanner::init(src);
+    let mut idents: BTreeMap<String, usize> = BTreeMap::new();
+    let mut h = FNV_OFFSET;
+    loop {
+        let tok = s.scan();
+        if tok == EOF {
+            break;
+        }
+        let text = s.token_str();
+        let (kind, text) = match tok {
+            INT | FLOAT => (tok, Cow::Owned(normalize_number(&text))),
+            STRING => (STRING, decode_string(&text).map_or(text, Cow::Owned)),
+            RAW_STRING => (STRING, decode_raw_string(&text).map_or(text, Cow::Owned)),
+            IDENT if opts.abstract_identifiers => {
+                let n = idents.len();
+                (tok, Cow::Owned(idents.entry(text.into_owned()).or_insert(n).to_string()))
+            }
+            _ => (tok, text),
+        };
+        h = fnv1a(h, &kind.to_le_bytes());
+        h = fnv1a(h, &(text.len() as u64).to_le_bytes());
+        h = fnv1a(h, text.as_bytes());
+    }
+    h
+}
+
+fn fnv1a(mut h: u64, bytes: &[u8]) -> u64 {
+    for &b in bytes {
+        h ^= b as u64;
+        h = h.wrapping_mul(FNV_PRIME);
+    }
+    h
+}
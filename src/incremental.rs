@@ -0,0 +1,401 @@
+// Copyright 2022 Jordi Íñigo Griera. All rights reserved.
+
+//! Scanning of input produced over time.
+//!
+//! A `Scanner` borrows its whole source. The scanners of this module take
+//! their input piecemeal instead, and return each token once the input
+//! following it has been received, as a token at the end of the input
+//! received so far may continue, e.g. an identifier or a raw string.
+//! Positions are relative to the whole input.
+//!
+//! An `IterScanner` pulls the bytes or characters of an iterator, e.g.
+//! generated input, the chunks of a rope or the output of a decoder, a
+//! line at a time, so the iterator may even be endless:
+//!
+//! ```
+//! use scanner::*;
+//!
+//! let chars = "(a\n¬b\nc¬)".chars();
+//! let texts: Vec<String> = IterScanner::new(chars).map(|t| t.text).collect();
+//! assert_eq!(texts, ["(", "a", "¬b\nc¬", ")"]);
+//!
+//! let mut s = IterScanner::new((0..).flat_map(|n: u32| format!("{n}\n").into_bytes()));
+//! let t = s.nth(1000).unwrap();
+//! assert_eq!((t.tok, t.text.as_str(), t.span.start.line), (INT, "1000", 1001));
+//! ```
+//!
+//! A `PushScanner` is fed the input as it arrives, e.g. in network
+//! chunks, and `scan()` never waits for more: it returns
+//! `PushStatus::Incomplete` until more input is fed, or `finish()` marks
+//! the end of the input.
+//!
+//! ```
+//! use scanner::*;
+//!
+//! let mut s = PushScanner::new();
+//! s.feed(b"(ping \"he");
+//! assert!(matches!(s.scan(), PushStatus::Token(t) if t.text == "("));
+//! assert!(matches!(s.scan(), PushStatus::Token(t) if t.text == "ping"));
+//! assert_eq!(s.scan(), PushStatus::Incomplete);
+//! s.feed(b"llo\")");
+//! assert!(matches!(s.scan(), PushStatus::Token(t) if t.text == "\"hello\""));
+//! assert_eq!(s.scan(), PushStatus::Incomplete); // ")" may be followed by more
+//! s.finish();
+//! assert!(matches!(s.scan(), PushStatus::Token(t) if t.text == ")"));
+//! assert_eq!(s.scan(), PushStatus::End);
+//! ```
+
+use alloc::boxed::Box;
+use alloc::collections::VecDeque;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::source::LastToken;
+use crate::{
+    COMMENT, Configure, Diagnostic, EOF, EofPolicy, INT, LISP_TOKENS, NEED_MORE_INPUT, Position, SKIP_COMMENTS, Scanner, Span,
+    SpannedToken, Token, TokenSource,
+};
+
+/// SourceUnit is a unit of the input of an `IterScanner`: a byte of UTF-8
+/// text or a character.
+pub trait SourceUnit {
+    /// Appends the UTF-8 encoding of the unit to out.
+    fn encode(self, out: &mut Vec<u8>);
+}
+
+impl SourceUnit for u8 {
+    fn encode(self, out: &mut Vec<u8>) {
+        out.push(self);
+    }
+}
+
+impl SourceUnit for char {
+    fn encode(self, out: &mut Vec<u8>) {
+        out.extend_from_slice(self.encode_utf8(&mut [0; 4]).as_bytes());
+    }
+}
+
+/// IterScanner scans tokens from an iterator of bytes or characters; see
+/// the module documentation.
+pub struct IterScanner<I> {
+    iter: I,
+    pending: Pending,
+    eof: bool,
+    last: LastToken,
+}
+
+impl<I: Iterator<Item: SourceUnit>> IterScanner<I> {
+    /// Returns an IterScanner pulling its input from iter, in
+    /// `LISP_TOKENS` mode.
+    pub fn new(iter: I) -> Self {
+        IterScanner { iter, pending: Pending::new(), eof: false, last: LastToken::new() }
+    }
+
+    /// Sets the mode of the scanners of the following input.
+    pub fn set_mode(&mut self, mode: u32) {
+        self.pending.mode = mode;
+    }
+
+    /// Sets a function configuring the scanners of the following input,
+    /// e.g. with the comment prefixes or dispatch macros of a dialect. It
+    /// is applied to each scanner after the mode, which it may change too.
+    pub fn set_configuration<F>(&mut self, configure: F)
+    where
+        F: Fn(&mut Scanner<'_>) + Send + Sync + 'static,
+    {
+        self.pending.configure = Some(Box::new(configure));
+    }
+
+    /// Returns the diagnostics reported so far.
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.pending.diagnostics
+    }
+
+    // Pulls lines until the pending input can be scanned, and scans it.
+    fn fill(&mut self) {
+        while self.pending.tokens.is_empty() && !self.eof {
+            loop {
+                let Some(unit) = self.iter.next() else {
+                    self.eof = true;
+                    break;
+                };
+                unit.encode(&mut self.pending.bytes);
+                if self.pending.bytes.last() == Some(&b'\n') {
+                    break;
+                }
+            }
+            self.pending.scan(self.eof);
+        }
+    }
+}
+
+impl<I: Iterator<Item: SourceUnit>> Iterator for IterScanner<I> {
+    type Item = SpannedToken;
+
+    fn next(&mut self) -> Option<SpannedToken> {
+        self.fill();
+        self.pending.tokens.pop_front()
+    }
+}
+
+impl<I: Iterator<Item: SourceUnit>> TokenSource for IterScanner<I> {
+    fn scan(&mut self) -> Token {
+        let t = self.next();
+        self.last.record(t)
+    }
+
+    fn token_text(&self) -> String {
+        self.last.text()
+    }
+
+    fn span(&self) -> Span {
+        self.last.span()
+    }
+
+    fn diagnostics(&self) -> &[Diagnostic] {
+        &self.pending.diagnostics
+    }
+}
+
+/// Result of `PushScanner::scan()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PushStatus {
+    /// A complete token.
+    Token(SpannedToken),
+    /// No complete token is left: more input has to be fed first.
+    Incomplete,
+    /// The input is finished and all its tokens were returned.
+    End,
+}
+
+/// PushScanner scans tokens from input fed to it; see the module
+/// documentation.
+pub struct PushScanner {
+    pending: Pending,
+    fed: bool, // input was fed since the last scan of pending
+    finished: bool,
+    last: LastToken,
+}
+
+impl Default for PushScanner {
+    fn default() -> Self {
+        PushScanner::new()
+    }
+}
+
+impl PushScanner {
+    /// Returns a PushScanner in `LISP_TOKENS` mode.
+    pub fn new() -> Self {
+        PushScanner { pending: Pending::new(), fed: false, finished: false, last: LastToken::new() }
+    }
+
+    /// Sets the mode of the scanners of the following input.
+    pub fn set_mode(&mut self, mode: u32) {
+        self.pending.mode = mode;
+    }
+
+    /// Sets a function configuring the scanners of the following input;
+    /// see `IterScanner::set_configuration()`.
+    pub fn set_configuration<F>(&mut self, configure: F)
+    where
+        F: Fn(&mut Scanner<'_>) + Send + Sync + 'static,
+    {
+        self.pending.configure = Some(Box::new(configure));
+    }
+
+    /// Returns the diagnostics reported so far.
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.pending.diagnostics
+    }
+
+    /// Feeds the next bytes of the input, which may end anywhere, even
+    /// inside a UTF-8 sequence. Bytes fed after `finish()` are ignored.
+    pub fn feed(&mut self, bytes: &[u8]) {
+        if !self.finished {
+            self.pending.bytes.extend_from_slice(bytes);
+            self.fed = true;
+        }
+    }
+
+    /// Marks the end of the input: the tokens left are complete, and a
+    /// literal left open is reported as unterminated.
+    pub fn finish(&mut self) {
+        if !self.finished {
+            self.finished = true;
+            self.fed = true;
+        }
+    }
+
+    /// Reports whether `finish()` was called.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Returns the next complete token, or whether more input is needed.
+    pub fn scan(&mut self) -> PushStatus {
+        if self.pending.tokens.is_empty() && self.fed {
+            self.pending.scan(self.finished);
+            self.fed = false;
+        }
+        match self.pending.tokens.pop_front() {
+            Some(t) => PushStatus::Token(t),
+            None if self.finished => PushStatus::End,
+            None => PushStatus::Incomplete,
+        }
+    }
+}
+
+/// As a TokenSource, a PushScanner returns EOF both at the end of the
+/// input and when more input is needed, which `is_finished()` tells
+/// apart.
+impl TokenSource for PushScanner {
+    fn scan(&mut self) -> Token {
+        let t = match PushScanner::scan(self) {
+            PushStatus::Token(t) => Some(t),
+            PushStatus::Incomplete | PushStatus::End => None,
+        };
+        self.last.record(t)
+    }
+
+    fn token_text(&self) -> String {
+        self.last.text()
+    }
+
+    fn span(&self) -> Span {
+        self.last.span()
+    }
+
+    fn diagnostics(&self) -> &[Diagnostic] {
+        &self.pending.diagnostics
+    }
+}
+
+// Input received but not scanned yet. Only the tokens followed by further
+// input are complete: any other may continue in the input to come.
+pub(crate) struct Pending {
+    pub(crate) mode: u32,
+    pub(crate) configure: Option<Configure>,
+    pub(crate) bytes: Vec<u8>,                 // input received but not scanned yet
+    pub(crate) position: Position,             // position of the start of bytes
+    pub(crate) tokens: VecDeque<SpannedToken>, // tokens scanned but not returned yet
+    pub(crate) diagnostics: Vec<Diagnostic>,
+    pub(crate) open_literal: bool,             // bytes start with a literal left open
+    held: Option<(usize, &'static [&'static [u8]])>, // see literal_ends
+}
+
+impl Pending {
+    pub(crate) fn new() -> Self {
+        Pending {
+            mode: LISP_TOKENS,
+            configure: None,
+            bytes: Vec::new(),
+            position: Position { line: 1, column: 1, ..Default::default() },
+            tokens: VecDeque::new(),
+            diagnostics: Vec::new(),
+            open_literal: false,
+            held: None,
+        }
+    }
+
+    // Scans the complete tokens of the input, or all of them at its end,
+    // keeping back the rest for the next scan.
+    pub(crate) fn scan(&mut self, eof: bool) {
+        // A literal held back is only rescanned once its end may have been
+        // received, so that a long one isn't rescanned for every chunk
+        if let Some((scanned, ends)) = self.held.take()
+            && !eof
+            && !ends.iter().any(|end| contains(&self.bytes[scanned.saturating_sub(end.len() - 1)..], end))
+        {
+            self.held = Some((self.bytes.len(), ends));
+            return;
+        }
+        // A character cut short at the end isn't received yet
+        let len = if eof { self.bytes.len() } else { complete_len(&self.bytes) };
+        if len == 0 {
+            return;
+        }
+        self.open_literal = false;
+        let mut s = Scanner::init(&self.bytes[..len]);
+        s.set_mode(self.mode);
+        if let Some(configure) = &self.configure {
+            configure(&mut s);
+        }
+        // Skipped comments may continue too, so they are scanned as tokens
+        let skip_comments = s.mode & SKIP_COMMENTS != 0;
+        if !eof {
+            s.set_mode(s.mode & !SKIP_COMMENTS);
+        }
+        s.set_base_position(&self.position);
+        s.position.source = self.position.source.clone();
+        if !eof {
+            s.set_eof_policy(EofPolicy::NeedMoreInput);
+        }
+        let end = self.position.offset + len;
+        let mut resume = None;
+        loop {
+            let t = s.scan_spanned();
+            if t.tok == EOF {
+                break;
+            }
+            if !eof && (t.tok == NEED_MORE_INPUT || t.span.end.offset >= end) {
+                let start = t.span.start.offset - self.position.offset;
+                if t.tok == NEED_MORE_INPUT {
+                    self.open_literal = true;
+                    self.held = literal_ends(&self.bytes[start..]).map(|ends| (len - start, ends));
+                }
+                // An integer followed by '/' may still be a ratio
+                let ratio = self.tokens.back().filter(|int| {
+                    t.text.starts_with('/') && int.tok == INT && int.span.end.offset == t.span.start.offset
+                });
+                if ratio.is_some_and(|int| int.span.start.offset >= self.position.offset) {
+                    resume = self.tokens.pop_back().map(|int| int.span.start);
+                } else {
+                    resume = Some(t.span.start);
+                }
+                break;
+            }
+            if t.tok != COMMENT || !skip_comments {
+                self.tokens.push_back(t);
+            }
+        }
+        let position = resume.unwrap_or_else(|| s.pos());
+        let diagnostics = s.diagnostics().iter().filter(|d| d.span.start.offset < position.offset);
+        self.diagnostics.extend(diagnostics.cloned());
+        self.bytes.drain(..position.offset - self.position.offset);
+        self.position = position;
+    }
+}
+
+// Returns the byte sequences of which one has to be received for the
+// literal starting at text to end, or None if unknown.
+fn literal_ends(text: &[u8]) -> Option<&'static [&'static [u8]]> {
+    match text {
+        [b'"', ..] => Some(&[b"\"", b"\n"]),
+        [b'#', b'|', ..] => Some(&[b"|#"]),
+        [0xC2, 0xAC, ..] => Some(&[b"\xC2\xAC"]), // '¬'
+        _ => None,
+    }
+}
+
+// Reports whether bytes contain the sequence seq.
+fn contains(bytes: &[u8], seq: &[u8]) -> bool {
+    bytes.windows(seq.len()).any(|w| w == seq)
+}
+
+// Returns the length of bytes without a UTF-8 sequence cut short at its end.
+fn complete_len(bytes: &[u8]) -> usize {
+    let n = bytes.len();
+    for i in (n.saturating_sub(3)..n).rev() {
+        let b = bytes[i];
+        if b & 0xC0 != 0x80 {
+            let len = match b {
+                0xF0.. => 4,
+                0xE0.. => 3,
+                0xC0.. => 2,
+                _ => 1,
+            };
+            return if i + len > n { i } else { n };
+        }
+    }
+    n
+}
@@ -0,0 +1,247 @@
+// Copyright 2022 Jordi Íñigo Griera. All rights reserved.
+
+//! Interactive scanning of line oriented input, e.g. a REPL on a TTY.
+//!
+//! A `Scanner` reads a complete source, but a REPL has to answer each line
+//! as soon as it is entered. A `LineScanner` reads its input a line at a
+//! time and returns the tokens of each line before reading the next one.
+//! Only a raw string left open at the end of a line makes it read further
+//! lines before returning tokens. Positions are relative to the whole input.
+//!
+//! ```
+//! use scanner::*;
+//!
+//! let input = "(+ 1 2)\n(str ¬a\nb¬)\n".as_bytes();
+//! let mut s = LineScanner::new(input);
+//! let t = s.next().unwrap();
+//! assert_eq!((t.tok, t.span.start.line), ('(' as Token, 1));
+//! assert_eq!(s.lines_read(), 1);
+//!
+//! let t: Vec<SpannedToken> = s.collect();
+//! assert_eq!(t[6].text, "¬a\nb¬");
+//! assert_eq!(t[6].span.start.line, 2);
+//! ```
+//!
+//! `Scanner::stdin()` returns a LineScanner reading the standard input.
+//!
+//! The lines are dropped once scanned, as the input can't be read again.
+//! To quote the source in diagnostics, set how many bytes of the latest
+//! input to retain with `set_retain()` and get them with `slice()`:
+//!
+//! ```
+//! use scanner::*;
+//!
+//! let mut s = LineScanner::new("(a \"b\n(c)\n".as_bytes());
+//! s.set_retain(usize::MAX);
+//! let t: Vec<SpannedToken> = s.by_ref().collect();
+//! let d = &s.diagnostics()[0];
+//! assert_eq!(s.slice(&d.span), Some("\"b"));
+//! assert_eq!(s.slice(&Span { start: t[0].span.start.clone(), end: t[4].span.end.clone() }), Some("(a \"b\n(c"));
+//! ```
+
+use alloc::boxed::Box;
+use alloc::collections::VecDeque;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use std::io::{self, BufRead};
+
+use crate::source::LastToken;
+use crate::{
+    Configure, Diagnostic, DiagnosticCode, EOF, LISP_TOKENS, Position, RAW_STRING, ScanError, Scanner, Span, SpannedToken, Token,
+    TokenSource,
+};
+
+/// LineScanner scans tokens from a line oriented reader; see the module
+/// documentation.
+pub struct LineScanner<R> {
+    reader: R,
+    mode: u32,
+    configure: Option<Configure>,
+    pending: String,                // lines read but not scanned yet
+    position: Position,             // position of the start of pending
+    tokens: VecDeque<SpannedToken>, // tokens scanned but not returned yet
+    diagnostics: Vec<Diagnostic>,
+    lines: usize,
+    retain: usize,         // maximum length of retained
+    retained: String,      // latest input scanned
+    retained_start: usize, // offset of the start of retained
+    error: Option<io::Error>,
+    eof: bool,
+    last: LastToken,
+}
+
+impl<R: BufRead> LineScanner<R> {
+    /// Returns a LineScanner reading from reader, in `LISP_TOKENS` mode.
+    pub fn new(reader: R) -> Self {
+        LineScanner {
+            reader,
+            mode: LISP_TOKENS,
+            configure: None,
+            pending: String::new(),
+            position: Position { line: 1, column: 1, ..Default::default() },
+            tokens: VecDeque::new(),
+            diagnostics: Vec::new(),
+            lines: 0,
+            retain: 0,
+            retained: String::new(),
+            retained_start: 0,
+            error: None,
+            eof: false,
+            last: LastToken::new(),
+        }
+    }
+
+    /// Sets the mode of the scanners of the following lines.
+    pub fn set_mode(&mut self, mode: u32) {
+        self.mode = mode;
+    }
+
+    /// Sets a function configuring the scanners of the following lines,
+    /// e.g. with the comment prefixes or dispatch macros of a dialect. It
+    /// is applied to each scanner after the mode, which it may change too.
+    pub fn set_configuration<F>(&mut self, configure: F)
+    where
+        F: Fn(&mut Scanner<'_>) + Send + Sync + 'static,
+    {
+        self.configure = Some(Box::new(configure));
+    }
+
+    /// Sets how many bytes of the latest input are retained for `slice()`;
+    /// none by default, and all of them with `usize::MAX`.
+    pub fn set_retain(&mut self, max: usize) {
+        self.retain = max;
+        self.trim_retained();
+    }
+
+    /// Returns the retained source text of a span, or None if it wasn't
+    /// retained or doesn't start and end at character boundaries.
+    pub fn slice(&self, span: &Span) -> Option<&str> {
+        let start = span.start.offset.checked_sub(self.retained_start)?;
+        let end = span.end.offset.checked_sub(self.retained_start)?;
+        self.retained.get(start..end)
+    }
+
+    // Drops the start of the retained text beyond the retain limit.
+    fn trim_retained(&mut self) {
+        if self.retained.len() <= self.retain {
+            return;
+        }
+        let mut cut = self.retained.len() - self.retain;
+        while !self.retained.is_char_boundary(cut) {
+            cut += 1;
+        }
+        self.retained.drain(..cut);
+        self.retained_start += cut;
+    }
+
+    /// Returns the number of lines read so far.
+    pub fn lines_read(&self) -> usize {
+        self.lines
+    }
+
+    /// Returns the diagnostics reported so far.
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    /// Returns the read error that ended the input, if any.
+    pub fn io_error(&self) -> Option<&io::Error> {
+        self.error.as_ref()
+    }
+
+    /// Returns the next token like `next()`, or once the tokens read are
+    /// returned, the read error that ended the input as a
+    /// `ScanError::IoError` at the end of the input read.
+    pub fn try_next(&mut self) -> Result<Option<SpannedToken>, ScanError> {
+        if let Some(t) = self.next() {
+            return Ok(Some(t));
+        }
+        match &self.error {
+            Some(err) => Err(ScanError::IoError { position: self.position.clone(), message: err.to_string() }),
+            None => Ok(None),
+        }
+    }
+
+    // Reads lines until the pending text can be scanned, and scans it.
+    fn fill(&mut self) {
+        while self.tokens.is_empty() && !self.eof {
+            match self.reader.read_line(&mut self.pending) {
+                Ok(0) => self.eof = true,
+                Ok(_) => self.lines += 1,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => {
+                    self.error = Some(err);
+                    self.eof = true;
+                }
+            }
+            if self.pending.is_empty() {
+                continue;
+            }
+            let mut s = Scanner::init(self.pending.as_bytes());
+            s.set_mode(self.mode);
+            if let Some(configure) = &self.configure {
+                configure(&mut s);
+            }
+            s.set_base_position(&self.position);
+            let mut tokens = VecDeque::new();
+            loop {
+                let t = s.scan_spanned();
+                if t.tok == EOF {
+                    break;
+                }
+                tokens.push_back(t);
+            }
+            let open = tokens.back().is_some_and(|t| t.tok == RAW_STRING)
+                && s.diagnostics().last().is_some_and(|d| d.code == DiagnosticCode::Unterminated);
+            if open && !self.eof {
+                continue; // the raw string may end on a following line
+            }
+            self.diagnostics.extend_from_slice(s.diagnostics());
+            self.position = s.pos();
+            if self.retain > 0 {
+                self.retained.push_str(&self.pending);
+                self.trim_retained();
+            } else {
+                self.retained_start += self.pending.len();
+            }
+            self.pending.clear();
+            self.tokens = tokens;
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for LineScanner<R> {
+    type Item = SpannedToken;
+
+    fn next(&mut self) -> Option<SpannedToken> {
+        self.fill();
+        self.tokens.pop_front()
+    }
+}
+
+impl<R: BufRead> TokenSource for LineScanner<R> {
+    fn scan(&mut self) -> Token {
+        let t = self.next();
+        self.last.record(t)
+    }
+
+    fn token_text(&self) -> String {
+        self.last.text()
+    }
+
+    fn span(&self) -> Span {
+        self.last.span()
+    }
+
+    fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+}
+
+impl Scanner<'_> {
+    /// Returns a LineScanner reading the standard input, which returns the
+    /// tokens of each line as soon as it is entered.
+    pub fn stdin() -> LineScanner<io::StdinLock<'static>> {
+        LineScanner::new(io::stdin().lock())
+    }
+}
@@ -0,0 +1,249 @@
+// Copyright 2022 Jordi Íñigo Griera. All rights reserved.
+
+//! Typed token kinds.
+//!
+//! Scanning methods return a `Token`, an integer that is either one of the
+//! negative token constants (`EOF`, `IDENT`...) or a Unicode character. A
+//! `TokenKind` is the same value as an enum, which can't hold a bogus
+//! token and can be matched exhaustively:
+//!
+//! ```
+//! use scanner::*;
+//!
+//! let mut s = Scanner::init(b"(a \"b\")");
+//! let mut kinds = Vec::new();
+//! loop {
+//!     match s.scan_kind() {
+//!         TokenKind::Eof => break,
+//!         kind => kinds.push(kind),
+//!     }
+//! }
+//! assert_eq!(kinds, [TokenKind::Char('('), TokenKind::Ident, TokenKind::String, TokenKind::Char(')')]);
+//! assert_eq!(Token::from(TokenKind::Ident), IDENT);
+//! assert_eq!(TokenKind::try_from('(' as Token), Ok(TokenKind::Char('(')));
+//! assert_eq!(TokenKind::try_from(-100), Err(-100));
+//! ```
+//!
+//! Token streams exchanged between processes should encode kinds with
+//! `TokenKind::to_wire()` rather than as `Token` values, whose constants
+//! may change between versions of the crate. Wire codes are stable:
+//!
+//! | Code               | Kind             |
+//! |--------------------|------------------|
+//! | 0                  | Eof              |
+//! | 1                  | Ident            |
+//! | 2                  | Int              |
+//! | 3                  | Float            |
+//! | 4                  | String           |
+//! | 5                  | Keyword          |
+//! | 6                  | RawString        |
+//! | 7                  | Comment          |
+//! | 8                  | Prose            |
+//! | 9                  | Whitespace       |
+//! | 10                 | Capture          |
+//! | 11                 | Other            |
+//! | 12                 | DocumentBoundary |
+//! | 13                 | Error            |
+//! | 14                 | CharLiteral      |
+//! | 15                 | Ratio            |
+//! | 16                 | BigInt           |
+//! | 17                 | BigDecimal       |
+//! | 18                 | NeedMoreInput    |
+//! | 19                 | DatumComment     |
+//! | 20                 | Quote            |
+//! | 21                 | Quasiquote       |
+//! | 22                 | Unquote          |
+//! | 23                 | UnquoteSplicing  |
+//! | 24                 | Deref            |
+//! | 0x100 + code point | Char             |
+//!
+//! New kinds will take the free codes below 0x100.
+
+use core::fmt;
+
+use crate::{
+    BIG_DECIMAL, BIG_INT, CAPTURE, CHAR, COMMENT, DATUM_COMMENT, DEREF, DOCUMENT_BOUNDARY, EOF, ERROR, FLOAT, IDENT, INT, KEYWORD, NEED_MORE_INPUT, OTHER, PROSE,
+    QUASIQUOTE, QUOTE, RATIO, RAW_STRING, STRING, Token, UNQUOTE, UNQUOTE_SPLICING, WHITESPACE, token_string,
+};
+
+/// TokenKind is the kind of a token, or the character of a single
+/// character token; see the `Token` constant of each kind.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TokenKind {
+    Eof,
+    Ident,
+    Int,
+    Float,
+    String,
+    Keyword,
+    RawString,
+    Comment,
+    Prose,
+    Whitespace,
+    Capture,
+    Other,
+    DocumentBoundary,
+    Error,
+    CharLiteral,
+    Ratio,
+    BigInt,
+    BigDecimal,
+    NeedMoreInput,
+    DatumComment,
+    Quote,
+    Quasiquote,
+    Unquote,
+    UnquoteSplicing,
+    Deref,
+    Char(char),
+}
+
+impl From<TokenKind> for Token {
+    fn from(kind: TokenKind) -> Token {
+        match kind {
+            TokenKind::Eof => EOF,
+            TokenKind::Ident => IDENT,
+            TokenKind::Int => INT,
+            TokenKind::Float => FLOAT,
+            TokenKind::String => STRING,
+            TokenKind::Keyword => KEYWORD,
+            TokenKind::RawString => RAW_STRING,
+            TokenKind::Comment => COMMENT,
+            TokenKind::Prose => PROSE,
+            TokenKind::Whitespace => WHITESPACE,
+            TokenKind::Capture => CAPTURE,
+            TokenKind::Other => OTHER,
+            TokenKind::DocumentBoundary => DOCUMENT_BOUNDARY,
+            TokenKind::Error => ERROR,
+            TokenKind::CharLiteral => CHAR,
+            TokenKind::Ratio => RATIO,
+            TokenKind::BigInt => BIG_INT,
+            TokenKind::BigDecimal => BIG_DECIMAL,
+            TokenKind::NeedMoreInput => NEED_MORE_INPUT,
+            TokenKind::DatumComment => DATUM_COMMENT,
+            TokenKind::Quote => QUOTE,
+            TokenKind::Quasiquote => QUASIQUOTE,
+            TokenKind::Unquote => UNQUOTE,
+            TokenKind::UnquoteSplicing => UNQUOTE_SPLICING,
+            TokenKind::Deref => DEREF,
+            TokenKind::Char(ch) => ch as Token,
+        }
+    }
+}
+
+/// Wire code of the first character, U+0000; see the module documentation.
+pub const WIRE_CHAR_BASE: u32 = 0x100;
+
+impl TokenKind {
+    /// Returns the stable wire code of the kind; see the module
+    /// documentation.
+    pub fn to_wire(self) -> u32 {
+        match self {
+            TokenKind::Eof => 0,
+            TokenKind::Ident => 1,
+            TokenKind::Int => 2,
+            TokenKind::Float => 3,
+            TokenKind::String => 4,
+            TokenKind::Keyword => 5,
+            TokenKind::RawString => 6,
+            TokenKind::Comment => 7,
+            TokenKind::Prose => 8,
+            TokenKind::Whitespace => 9,
+            TokenKind::Capture => 10,
+            TokenKind::Other => 11,
+            TokenKind::DocumentBoundary => 12,
+            TokenKind::Error => 13,
+            TokenKind::CharLiteral => 14,
+            TokenKind::Ratio => 15,
+            TokenKind::BigInt => 16,
+            TokenKind::BigDecimal => 17,
+            TokenKind::NeedMoreInput => 18,
+            TokenKind::DatumComment => 19,
+            TokenKind::Quote => 20,
+            TokenKind::Quasiquote => 21,
+            TokenKind::Unquote => 22,
+            TokenKind::UnquoteSplicing => 23,
+            TokenKind::Deref => 24,
+            TokenKind::Char(ch) => WIRE_CHAR_BASE + ch as u32,
+        }
+    }
+
+    /// Returns the kind of a wire code, or None if the code is unassigned,
+    /// e.g. a kind added by a later version of the crate.
+    pub fn from_wire(code: u32) -> Option<Self> {
+        Some(match code {
+            0 => TokenKind::Eof,
+            1 => TokenKind::Ident,
+            2 => TokenKind::Int,
+            3 => TokenKind::Float,
+            4 => TokenKind::String,
+            5 => TokenKind::Keyword,
+            6 => TokenKind::RawString,
+            7 => TokenKind::Comment,
+            8 => TokenKind::Prose,
+            9 => TokenKind::Whitespace,
+            10 => TokenKind::Capture,
+            11 => TokenKind::Other,
+            12 => TokenKind::DocumentBoundary,
+            13 => TokenKind::Error,
+            14 => TokenKind::CharLiteral,
+            15 => TokenKind::Ratio,
+            16 => TokenKind::BigInt,
+            17 => TokenKind::BigDecimal,
+            18 => TokenKind::NeedMoreInput,
+            19 => TokenKind::DatumComment,
+            20 => TokenKind::Quote,
+            21 => TokenKind::Quasiquote,
+            22 => TokenKind::Unquote,
+            23 => TokenKind::UnquoteSplicing,
+            24 => TokenKind::Deref,
+            _ if code >= WIRE_CHAR_BASE => TokenKind::Char(char::from_u32(code - WIRE_CHAR_BASE)?),
+            _ => return None,
+        })
+    }
+}
+
+/// Fails with the token itself if it is neither a token constant nor a
+/// Unicode character.
+impl TryFrom<Token> for TokenKind {
+    type Error = Token;
+
+    fn try_from(tok: Token) -> Result<Self, Token> {
+        Ok(match tok {
+            EOF => TokenKind::Eof,
+            IDENT => TokenKind::Ident,
+            INT => TokenKind::Int,
+            FLOAT => TokenKind::Float,
+            STRING => TokenKind::String,
+            KEYWORD => TokenKind::Keyword,
+            RAW_STRING => TokenKind::RawString,
+            COMMENT => TokenKind::Comment,
+            PROSE => TokenKind::Prose,
+            WHITESPACE => TokenKind::Whitespace,
+            CAPTURE => TokenKind::Capture,
+            OTHER => TokenKind::Other,
+            DOCUMENT_BOUNDARY => TokenKind::DocumentBoundary,
+            ERROR => TokenKind::Error,
+            CHAR => TokenKind::CharLiteral,
+            RATIO => TokenKind::Ratio,
+            BIG_INT => TokenKind::BigInt,
+            BIG_DECIMAL => TokenKind::BigDecimal,
+            NEED_MORE_INPUT => TokenKind::NeedMoreInput,
+            DATUM_COMMENT => TokenKind::DatumComment,
+            QUOTE => TokenKind::Quote,
+            QUASIQUOTE => TokenKind::Quasiquote,
+            UNQUOTE => TokenKind::Unquote,
+            UNQUOTE_SPLICING => TokenKind::UnquoteSplicing,
+            DEREF => TokenKind::Deref,
+            _ if tok >= 0 => TokenKind::Char(char::from_u32(tok as u32).ok_or(tok)?),
+            _ => return Err(tok),
+        })
+    }
+}
+
+/// Formats the kind like `token_string()`.
+impl fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&token_string(Token::from(*self)))
+    }
+}
@@ -10,7 +10,8 @@
 //! It takes a byte slice providing the source, which then can be tokenized
 //! through repeated calls to the `scan()` function. For compatibility with
 //! existing tools, the NUL character is not allowed. If the first character
-//! in the source is a UTF-8 encoded byte order mark (BOM), it is discarded.
+//! in the source is a UTF-8 encoded byte order mark (BOM), it is discarded,
+//! unless white space is returned as tokens (see `SCAN_WHITESPACE`).
 //!
 //! By default, a Scanner skips white space and Lisp comments and recognizes all
 //! literals as defined by the Lisp language as specified on the
@@ -21,24 +22,163 @@
 #![no_std]
 
 extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
 
+use core::any::Any;
 use core::fmt;
+use core::ops::Range;
 use core::str;
 use alloc::string::{String, ToString};
 use alloc::vec::Vec;
+use alloc::borrow::Cow;
 use alloc::boxed::Box;
+use alloc::collections::VecDeque;
+use alloc::rc::Rc;
 use alloc::format;
 
-const BUF_LEN: usize = 1024; // at least 4 (utf8 max bytes)
+mod adaptors;
+#[cfg(feature = "async")]
+mod asynchronous;
+mod backward;
+mod builder;
+mod capabilities;
+mod checkpoint;
+mod chunked;
+pub mod compat;
+#[cfg(feature = "compress")]
+mod compress;
+mod diagnostics;
+mod dialect;
+#[cfg(feature = "encoding")]
+mod encoding;
+mod error;
+mod extensions;
+mod extract;
+mod hash;
+mod incremental;
+#[cfg(feature = "std")]
+mod interactive;
+mod kind;
+mod literal;
+mod lossless;
+#[cfg(feature = "mmap")]
+mod mapped;
+mod markdown;
+mod mixed;
+#[cfg(feature = "std")]
+mod pipeline;
+mod render;
+mod repl;
+mod replay;
+mod roundtrip;
+mod search;
+mod source;
+mod source_id;
+mod summary;
+pub mod testkit;
+mod transform;
+mod visitor;
+
+pub use adaptors::{Chars, MapText, Only, SkipTrivia, SourceChar, Spanned, TokenItem, TokenIteratorExt, Tokens};
+#[cfg(feature = "async")]
+pub use asynchronous::{AsyncScanner, AsyncSource};
+pub use builder::ScannerBuilder;
+pub use capabilities::{Capabilities, ParseCapabilitiesError};
+pub use checkpoint::ScannerCheckpoint;
+pub use chunked::{ChunkPattern, ChunkedReader};
+#[cfg(feature = "compress")]
+pub use compress::{
+    CompressedScanner, DEFAULT_MAX_DECOMPRESSED_SIZE, DecompressError, Decompressor, decompress, decompress_with_limit,
+    is_compressed,
+};
+pub use diagnostics::{
+    ALL_LINTS, DEFAULT_MAX_LINE_LENGTH, DEFAULT_MAX_NESTING_DEPTH, Diagnostic, DiagnosticCode, LINT_DEEP_NESTING,
+    LINT_EARMUFF_STYLE, LINT_ESCAPE_RANGE, LINT_IDENT_STYLE, LINT_KEYWORD_STYLE, LINT_LEGACY_OCTAL, LINT_LINE_LENGTH,
+    DEFAULT_MAX_RAW_STRING_LINES, LINT_MIXED_INDENTATION, LINT_NON_NFC, LINT_SUSPICIOUS_DELIMITERS,
+    LINT_TRAILING_WHITESPACE, LINT_UNBALANCED_DELIMITERS, Severity, UserData, is_kebab_case,
+};
+use diagnostics::{LINE_LINTS, LineLint, OpenBracket, TOKEN_LINTS, is_combining_mark};
+#[cfg(feature = "encoding")]
+pub use encoding::{Encoding, UTF_8, UTF_16BE, UTF_16LE, decode, decode_from, detect_encoding};
+pub use error::ScanError;
+pub use extract::{CommentBlock, StringLiteral, extract_comments, extract_strings};
+pub use hash::{HashOptions, token_stream_hash, token_stream_hash_with};
+pub use incremental::{IterScanner, PushScanner, PushStatus, SourceUnit};
+#[cfg(feature = "std")]
+pub use interactive::LineScanner;
+pub use kind::{TokenKind, WIRE_CHAR_BASE};
+pub use literal::{DecodedChars, EscapeError, KeywordParts, TokenValue, unescape_string};
+pub use lossless::{LosslessMismatch, verify_lossless};
+#[cfg(feature = "mmap")]
+pub use mapped::MappedFile;
+pub use markdown::{CodeBlock, code_blocks};
+pub use mixed::{Island, MixedItem, MixedScanner};
+#[cfg(feature = "std")]
+pub use pipeline::TokenPipeline;
+pub use render::{
+    Radix, RenderOptions, render_float, render_int, render_raw_string, render_string, render_string_with,
+};
+pub use repl::{Completeness, Repl, ReplForm, ReplStatus, is_form_complete};
+pub use replay::{ReplayError, ReplayLog};
+pub use roundtrip::{MismatchKind, RoundtripMismatch, validate_roundtrip};
+pub use search::{FindTokens, ReferenceIndex, TokenTable, find_tokens, token_at};
+pub use source::{TokenSource, VecTokenSource};
+pub use source_id::{SourceId, SourceKind, SourceRegistry};
+pub use summary::ScanSummary;
+use summary::{OpenDelimiter, Tally};
+use extensions::{DEFAULT_LINE_COMMENT_PREFIXES, Extensions};
+pub use transform::{NormalizeOptions, normalize};
+pub use visitor::TokenVisitor;
+
+#[doc(hidden)]
+pub mod __private {
+    pub use alloc::string::String;
+}
+
+/// Default size in bytes of the inline source buffer of a scanner.
+pub const DEFAULT_BUFFER_SIZE: usize = 1025;
+
+// Returns the length of the UTF-8 sequence introduced by the leading byte b.
+fn rune_len(b: u8) -> usize {
+    match b {
+        0xC0..=0xDF => 2,
+        0xE0..=0xEF => 3,
+        0xF0..=0xF7 => 4,
+        _ => 1,
+    }
+}
+
+// Reports whether bytes begin with a full UTF-8 encoding of a rune; an
+// invalid encoding is considered a full rune since it decodes as an error
+// of width 1 (utf8.FullRune equivalent).
+fn full_rune(bytes: &[u8]) -> bool {
+    let n = rune_len(bytes[0]);
+    if bytes.len() >= n {
+        return true;
+    }
+    matches!(str::from_utf8(bytes), Err(e) if e.error_len().is_some())
+}
+
+// Decodes the first rune of bytes, looking only at its own encoding and not
+// at the bytes that follow it.
+fn decode_rune(bytes: &[u8]) -> Option<char> {
+    let n = rune_len(*bytes.first()?);
+    bytes.get(..n).and_then(|b| str::from_utf8(b).ok()).and_then(|s| s.chars().next())
+}
 
 /// Position is a value that represents a source position.
 /// A position is valid if line > 0.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct Position {
-    pub filename: String,
+    pub source: SourceId,
     pub offset: usize,
     pub line: usize,
     pub column: usize,
+    /// Page number, starting at 1. Pages are separated by form feeds, which
+    /// belong to the page they end.
+    #[cfg(feature = "pages")]
+    pub page: usize,
 }
 
 impl Position {
@@ -50,23 +190,83 @@ impl Position {
 
 impl fmt::Display for Position {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let s = if self.filename.is_empty() {
-            "<input>".to_string()
-        } else {
-            self.filename.clone()
-        };
-
         if self.is_valid() {
-            write!(f, "{}:{}:{}", s, self.line, self.column)
+            write!(f, "{}:{}:{}", self.source, self.line, self.column)
         } else {
-            write!(f, "{}", s)
+            write!(f, "{}", self.source)
         }
     }
 }
 
-/// Token type
+/// Span is the range of source text between two positions.
+/// The end position is exclusive: it is the position of the
+/// character immediately after the range.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    pub start: Position,
+    pub end: Position,
+}
+
+impl Span {
+    /// Returns the number of lines the span covers.
+    pub fn line_count(&self) -> usize {
+        self.end.line.saturating_sub(self.start.line) + 1
+    }
+}
+
+/// Token type: one of the token constants below or a Unicode character.
+/// See `TokenKind` for the same values as an enum.
 pub type Token = i32;
 
+/// SpannedToken is a scanned token together with its text and span.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpannedToken {
+    pub tok: Token,
+    pub text: String,
+    pub span: Span,
+}
+
+impl SpannedToken {
+    /// Returns the number of lines the token spans, e.g. 3 for a raw
+    /// string containing two newlines.
+    pub fn line_count(&self) -> usize {
+        self.span.line_count()
+    }
+
+    /// Returns an iterator over the decoded characters of a STRING token,
+    /// with their source spans; it is empty for other tokens.
+    ///
+    /// ```
+    /// use scanner::*;
+    ///
+    /// let t = Scanner::init(br#"(f "a\x41")"#).tokens().spanned().nth(2).unwrap();
+    /// let chars: Vec<_> = t.decoded_chars().map(|(span, ch)| (span.start.column, span.end.column, ch)).collect();
+    /// assert_eq!(chars, [(5, 6, Some('a')), (6, 10, Some('A'))]);
+    /// ```
+    pub fn decoded_chars(&self) -> DecodedChars<'_> {
+        let text = if self.tok == STRING { self.text.as_str() } else { "" };
+        DecodedChars::new(text, &self.span.start)
+    }
+}
+
+/// Formats the token compactly and without the filename, for snapshot
+/// tests, e.g. `Ident "foo" @3:7..3:10`.
+impl fmt::Display for SpannedToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (start, end) = (&self.span.start, &self.span.end);
+        write!(
+            f,
+            "{} {} @{}:{}..{}:{}",
+            token_string(self.tok),
+            render::render_string(&self.text),
+            start.line,
+            start.column,
+            end.line,
+            end.column
+        )
+    }
+}
+
 /// The result of Scan is one of these tokens or a Unicode character.
 pub const EOF: Token = -1;
 pub const IDENT: Token = -2;
@@ -77,6 +277,53 @@ pub const KEYWORD: Token = -6;
 pub const RAW_STRING: Token = -7;
 pub const COMMENT: Token = -8;
 const SKIP_COMMENT: Token = -9;
+const LISP_SPECIAL: Token = -10;
+/// Text outside of the code lines in literate mode.
+pub const PROSE: Token = -11;
+/// A run of white space, only returned in `SCAN_WHITESPACE` mode.
+pub const WHITESPACE: Token = -12;
+/// The rest of a line, only returned by `scan_with()` with `capture_line`.
+pub const CAPTURE: Token = -13;
+const COMMENT_CONTINUATION: Token = -14;
+/// A string, raw string or comment not recognized by the mode, only
+/// returned with `DisabledPolicy::Other`.
+pub const OTHER: Token = -15;
+/// A document delimiter line, including its line terminator, only returned
+/// when a delimiter is set with `set_document_delimiter()`.
+pub const DOCUMENT_BOUNDARY: Token = -16;
+/// An identifier or keyword longer than the limit set with
+/// `set_max_ident_length()`, only returned with `IdentLengthPolicy::Error`.
+pub const ERROR: Token = -17;
+const BLOCK_COMMENT: Token = -18;
+/// A character literal, e.g. `#\a`, `#\newline` or `#\u03BB`, only
+/// returned in SCAN_CHARS mode.
+pub const CHAR: Token = -19;
+/// A ratio literal, e.g. `22/7` or `-1/3`, only returned in SCAN_RATIOS
+/// mode.
+pub const RATIO: Token = -20;
+/// An arbitrary-precision integer, an integer with the `N` suffix, e.g.
+/// `123N`, only returned in SCAN_BIG_INTS mode.
+pub const BIG_INT: Token = -21;
+/// An arbitrary-precision decimal, a decimal integer or float with the
+/// `M` suffix, e.g. `3.14M`, only returned in SCAN_BIG_DECIMALS mode.
+pub const BIG_DECIMAL: Token = -22;
+/// A string, raw string, block comment or character literal cut short by
+/// the end of the input, only returned with `EofPolicy::NeedMoreInput`.
+pub const NEED_MORE_INPUT: Token = -23;
+/// A datum comment marker `#_`, commenting out the form following it (see
+/// `skip_form()`), only returned in SCAN_DATUM_COMMENTS mode.
+pub const DATUM_COMMENT: Token = -24;
+/// The quote prefix `'`, only returned in SCAN_QUOTES mode, like the
+/// following prefixes.
+pub const QUOTE: Token = -25;
+/// The quasiquote prefix `` ` ``.
+pub const QUASIQUOTE: Token = -26;
+/// The unquote prefix `~`.
+pub const UNQUOTE: Token = -27;
+/// The unquote-splicing prefix `~@`.
+pub const UNQUOTE_SPLICING: Token = -28;
+/// The deref prefix `@`.
+pub const DEREF: Token = -29;
 
 /// Predefined mode bits to control recognition of tokens.
 pub const SCAN_IDENTS: u32 = 1 << (-IDENT as u32);
@@ -87,13 +334,72 @@ pub const SCAN_KEYWORDS: u32 = 1 << (-KEYWORD as u32);
 pub const SCAN_RAW_STRINGS: u32 = 1 << (-RAW_STRING as u32);
 pub const SCAN_COMMENTS: u32 = 1 << (-COMMENT as u32);
 pub const SKIP_COMMENTS: u32 = 1 << (-SKIP_COMMENT as u32);
+/// Recognize the Lisp reader specials `~@` and `#{` as IDENT tokens.
+pub const SCAN_LISP_SPECIALS: u32 = 1 << (-LISP_SPECIAL as u32);
+/// Return runs of white space (including a leading BOM) as WHITESPACE tokens.
+pub const SCAN_WHITESPACE: u32 = 1 << (-WHITESPACE as u32);
+/// Continue a comment ending in `\` over the next line, as a single comment.
+pub const SCAN_COMMENT_CONTINUATIONS: u32 = 1 << (-COMMENT_CONTINUATION as u32);
+/// Recognize block comments `#| ... |#`, spanning any number of lines and
+/// nested unless disabled with `set_nested_block_comments()`, as COMMENT
+/// tokens, skipped with SKIP_COMMENTS.
+pub const SCAN_BLOCK_COMMENTS: u32 = 1 << (-BLOCK_COMMENT as u32);
+/// Recognize character literals `#\` followed by a character, a character
+/// name or a `x` or `u` hexadecimal code point, as CHAR tokens.
+pub const SCAN_CHARS: u32 = 1 << (-CHAR as u32);
+/// Recognize decimal integers followed by `/` and decimal digits as RATIO
+/// tokens, instead of an INT and an IDENT.
+pub const SCAN_RATIOS: u32 = 1 << (-RATIO as u32);
+/// Recognize integers with the `N` suffix as BIG_INT tokens.
+pub const SCAN_BIG_INTS: u32 = 1 << (-BIG_INT as u32);
+/// Recognize decimal integers and floats with the `M` suffix as
+/// BIG_DECIMAL tokens.
+pub const SCAN_BIG_DECIMALS: u32 = 1 << (-BIG_DECIMAL as u32);
+/// Recognize the datum comment marker `#_` as DATUM_COMMENT tokens.
+pub const SCAN_DATUM_COMMENTS: u32 = 1 << (-DATUM_COMMENT as u32);
+/// Recognize the prefixes `'`, `` ` ``, `~`, `~@` and `@` as QUOTE,
+/// QUASIQUOTE, UNQUOTE, UNQUOTE_SPLICING and DEREF tokens.
+pub const SCAN_QUOTES: u32 = 1 << (-QUOTE as u32);
 
 /// Standard Lisp tokens mode
-pub const LISP_TOKENS: u32 = SCAN_IDENTS | SCAN_FLOATS | SCAN_STRINGS | SCAN_KEYWORDS | SCAN_RAW_STRINGS | SCAN_COMMENTS | SKIP_COMMENTS;
+pub const LISP_TOKENS: u32 = SCAN_IDENTS | SCAN_FLOATS | SCAN_STRINGS | SCAN_KEYWORDS | SCAN_RAW_STRINGS | SCAN_COMMENTS | SKIP_COMMENTS | SCAN_LISP_SPECIALS;
+
+/// Generic tokens mode, without any of the Lisp-specific tokens
+/// (keywords, raw strings, `~@` and `#{`)
+pub const MINIMAL_TOKENS: u32 = SCAN_IDENTS | SCAN_FLOATS | SCAN_STRINGS | SCAN_COMMENTS | SKIP_COMMENTS;
+
+/// Lossless Lisp tokens mode: comments and white space are returned as
+/// tokens, so that the concatenation of all token texts equals the source
+pub const LOSSLESS_TOKENS: u32 = (LISP_TOKENS & !SKIP_COMMENTS) | SCAN_WHITESPACE;
 
 /// Default whitespace characters
 pub const LISP_WHITESPACE: u64 = (1 << b'\t') | (1 << b'\n') | (1 << b'\r') | (1 << b' ');
 
+/// Default whitespace characters plus form feed and vertical tab, for
+/// sources organized into pages
+pub const PAGE_WHITESPACE: u64 = LISP_WHITESPACE | (1 << 0x0C) | (1 << 0x0B);
+
+// Token kinds skipped by default by next_significant()
+const DEFAULT_TRIVIA: u32 = (1 << -COMMENT) | (1 << -PROSE) | (1 << -WHITESPACE);
+
+/// Reports whether a token is trivia, i.e. not significant for parsing.
+pub fn is_trivia(tok: Token) -> bool {
+    tok == COMMENT || tok == PROSE || tok == WHITESPACE
+}
+
+// Returns the mode bit recognizing the construct opened by ch.
+fn disabled_bit(ch: char) -> u32 {
+    match ch {
+        '"' => SCAN_STRINGS,
+        _ => SCAN_RAW_STRINGS,
+    }
+}
+
+// Returns the character of a Unicode character token, None for the other tokens.
+fn token_char(tok: Token) -> Option<char> {
+    if tok < 0 { None } else { char::from_u32(tok as u32) }
+}
+
 /// Returns a printable string for a token or Unicode character.
 pub fn token_string(tok: Token) -> String {
     match tok {
@@ -105,6 +411,23 @@ pub fn token_string(tok: Token) -> String {
         KEYWORD => "Keyword".to_string(),
         RAW_STRING => "RawString".to_string(),
         COMMENT => "Comment".to_string(),
+        PROSE => "Prose".to_string(),
+        WHITESPACE => "Whitespace".to_string(),
+        CAPTURE => "Capture".to_string(),
+        OTHER => "Other".to_string(),
+        DOCUMENT_BOUNDARY => "DocumentBoundary".to_string(),
+        ERROR => "Error".to_string(),
+        CHAR => "CharLiteral".to_string(),
+        RATIO => "Ratio".to_string(),
+        BIG_INT => "BigInt".to_string(),
+        BIG_DECIMAL => "BigDecimal".to_string(),
+        NEED_MORE_INPUT => "NeedMoreInput".to_string(),
+        DATUM_COMMENT => "DatumComment".to_string(),
+        QUOTE => "Quote".to_string(),
+        QUASIQUOTE => "Quasiquote".to_string(),
+        UNQUOTE => "Unquote".to_string(),
+        UNQUOTE_SPLICING => "UnquoteSplicing".to_string(),
+        DEREF => "Deref".to_string(),
         _ => {
             if let Some(ch) = char::from_u32(tok as u32) {
                 format!("{:?}", ch.to_string())
@@ -115,23 +438,150 @@ pub fn token_string(tok: Token) -> String {
     }
 }
 
+/// Placement of a comment relative to the code on its line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommentPlacement {
+    /// The comment is the first token on its line.
+    FullLine,
+    /// The comment follows code on the same line.
+    Trailing,
+}
+
+/// Layout metadata of a comment token, as needed by formatters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CommentInfo {
+    pub placement: CommentPlacement,
+    pub column: usize,
+}
+
+/// The block of full-line comments on consecutive lines immediately
+/// preceding a token, as returned by `Scanner::leading_comment()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LeadingComment {
+    pub span: Span,
+    /// Number of lines between the end of the block and the token.
+    pub blank_lines: usize,
+}
+
+// An escape sequence scanned by scan_escape(): its value, None if it is
+// invalid or out of range, and its length in bytes, backslash included.
+struct Escape {
+    value: Option<char>,
+    len: usize,
+}
+
+// Role of a token in a pair of delimiters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Delimiter {
+    Open,
+    Close,
+}
+
+// A token scanned ahead, with the state of the scanner that depends on it.
+#[derive(Debug, Clone)]
+struct Scanned {
+    token: SpannedToken,
+    last_significant_line: usize,
+    leading_comment: Option<LeadingComment>,
+}
+
+/// Options overriding the configuration of the scanner for a single call
+/// to `scan_with()`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ScanOpts {
+    /// Mode bits to set.
+    pub set: u32,
+    /// Mode bits to clear.
+    pub clear: u32,
+    /// Return the rest of the current line, without the leading blanks and
+    /// the line terminator, as a single CAPTURE token. A blank rest of line
+    /// is not captured and the next token is scanned as usual.
+    pub capture_line: bool,
+}
+
+/// How the scanner treats the strings, raw strings and comments whose mode
+/// bit is off.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DisabledPolicy {
+    /// Return the opening character and scan the rest as further tokens.
+    #[default]
+    Chars,
+    /// Skip the whole construct, like white space.
+    Skip,
+    /// Return the whole construct as a single OTHER token.
+    Other,
+}
+
+/// How the scanner treats the end of the input inside a string, raw
+/// string, block comment or character literal.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EofPolicy {
+    /// Report an unterminated literal error (E011).
+    #[default]
+    Error,
+    /// Return the literal as a NEED_MORE_INPUT token, without reporting an
+    /// error, for input that continues later, e.g. in a network protocol:
+    /// once more input has arrived, scanning restarts at its start.
+    NeedMoreInput,
+}
+
+/// How a dispatch macro, `#` followed by a dispatch character, is scanned;
+/// see `Scanner::set_dispatch_macro()`. Each variant holds the kind of the
+/// token returned, e.g. IDENT or a dialect token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DispatchMacro {
+    /// `#` and the dispatch character alone, e.g. `#(` or `#?`.
+    Token(Token),
+    /// `#` and the literal starting at the dispatch character, e.g. the
+    /// string of the regex `#"[0-9]+"`, whose escapes are checked as usual.
+    /// A dispatch character starting no string, raw string, number or
+    /// identifier is taken alone.
+    Literal(Token),
+    /// `#`, the dispatch character and the literal following it, if any,
+    /// e.g. the identifier of the var quote `#'map`.
+    Prefix(Token),
+}
+
+/// How the scanner returns an identifier or keyword longer than the limit
+/// set with `set_max_ident_length()`. Either way an error is reported.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum IdentLengthPolicy {
+    /// Return the token with its text truncated to the limit.
+    #[default]
+    Truncate,
+    /// Return an ERROR token instead.
+    Error,
+}
+
+type TokenHook = Box<dyn FnMut(Token, &Span)>;
+type ErrorHandler = Box<dyn FnMut(Position, &str)>;
+type TextNormalizer = Box<dyn Fn(&str) -> String>;
+type NamingStyle = Box<dyn Fn(&str) -> bool>;
+type Configure = Box<dyn Fn(&mut Scanner<'_>) + Send + Sync>; // configuration of the scanners of a backend
+
 /// A Scanner implements reading of Unicode characters and tokens from a byte slice.
-pub struct Scanner<'a> {
+///
+/// N is the size in bytes of the inline buffer the source is read through
+/// (see `Scanner::with_buffer()`). Token texts that don't fit in it are
+/// collected on the heap.
+pub struct Scanner<'a, const N: usize = DEFAULT_BUFFER_SIZE> {
     // Input
     src: &'a [u8],
     src_read_pos: usize,
 
     // Source buffer
-    src_buf: [u8; BUF_LEN + 1],
+    src_buf: [u8; N], // the last byte is reserved for the sentinel
     src_pos: usize,
     src_end: usize,
 
     // Source position
     src_buf_offset: usize,
+    base_offset: usize, // offset of src[0]
     line: usize,
     column: usize,
     last_line_len: usize,
     last_char_len: usize,
+    track_positions: bool, // lines and columns are counted
 
     // Token text buffer
     tok_buf: Vec<u8>,
@@ -141,13 +591,48 @@ pub struct Scanner<'a> {
     // One character look-ahead
     ch: i32,
 
+    // Last scanned token
+    tok: Token,
+    last_significant_line: usize,
+    leading_comment: Option<LeadingComment>,
+    comment_block: Option<Span>, // full-line comments since the last significant token
+
+    // Tokens scanned ahead, shared with checkpoints, and the current token
+    // when it was scanned ahead
+    ahead: VecDeque<Rc<Scanned>>,
+    replay: Option<Rc<Scanned>>,
+    // The state before scanning the tokens ahead, and the number of tokens
+    // scanned ahead since, to read the characters after the current token
+    ahead_start: Option<Rc<ScannerCheckpoint>>,
+    ahead_scanned: usize,
+    // The current token, once next_char() has read past it
+    detached: Option<Rc<Scanned>>,
+
     // Error handling
     error_count: usize,
+    diagnostics: Vec<Diagnostic>,
+    summary: bool,
 
     // Configuration
+    pub lints: u32,
     pub mode: u32,
     pub whitespace: u64,
-    is_ident_rune: Option<Box<dyn Fn(char, usize) -> bool>>,
+    normalized: Option<String>, // normalized text of the current token
+    nested_block_comments: bool,
+    namespaced_keywords: bool,
+    raw_string_delimiters: (char, char),
+    trivia: u32,
+    disabled_policy: DisabledPolicy,
+    eof_policy: EofPolicy,
+    need_more_input: bool, // EOF was found inside the current token
+    bom: bool,
+    ext: Option<Box<Extensions>>, // rarely used configuration, boxed on first use
+
+    // Page of the last character read, and whether it is a form feed
+    #[cfg(feature = "pages")]
+    page: usize,
+    #[cfg(feature = "pages")]
+    page_break: bool,
 
     // Token position
     pub position: Position,
@@ -156,30 +641,87 @@ pub struct Scanner<'a> {
 impl<'a> Scanner<'a> {
     /// Initializes a Scanner with a new source and returns it.
     pub fn init(src: &'a [u8]) -> Self {
+        Scanner::with_buffer(src)
+    }
+
+    /// Returns a builder of a Scanner over src; see `ScannerBuilder`.
+    pub fn builder(src: &'a [u8]) -> ScannerBuilder<'a> {
+        ScannerBuilder::with_buffer(src)
+    }
+}
+
+impl<'a, const N: usize> Scanner<'a, N> {
+    /// Initializes a Scanner with a new source and an inline buffer of N
+    /// bytes, and returns it. N must be at least 5, room for the longest
+    /// UTF-8 sequence and a sentinel byte. Small buffers suit embedded
+    /// targets running many scanners; they scan the same tokens, but long
+    /// tokens spill to the heap.
+    ///
+    /// ```
+    /// use scanner::*;
+    ///
+    /// let mut s: Scanner<'_, 16> = Scanner::with_buffer(b"(def greeting \"hello, world\")");
+    /// assert_eq!(s.scan(), '(' as Token);
+    /// assert_eq!(s.scan(), IDENT);
+    /// assert_eq!(s.scan(), IDENT);
+    /// assert_eq!(s.scan(), STRING);
+    /// assert_eq!(s.token_text(), "\"hello, world\"");
+    /// ```
+    pub fn with_buffer(src: &'a [u8]) -> Self {
+        const { assert!(N >= 5, "the scanner buffer must hold at least 5 bytes") };
         let mut scanner = Scanner {
             src,
             src_read_pos: 0,
-            src_buf: [0; BUF_LEN + 1],
+            src_buf: [0; N],
             src_pos: 0,
             src_end: 0,
             src_buf_offset: 0,
+            base_offset: 0,
             line: 1,
             column: 0,
             last_line_len: 0,
             last_char_len: 0,
+            track_positions: true,
             tok_buf: Vec::new(),
             tok_pos: -1,
             tok_end: 0,
             ch: -2,
+            tok: EOF,
+            last_significant_line: 0,
+            leading_comment: None,
+            comment_block: None,
+            ahead: VecDeque::new(),
+            replay: None,
+            ahead_start: None,
+            ahead_scanned: 0,
+            detached: None,
             error_count: 0,
+            diagnostics: Vec::new(),
+            summary: false,
+            lints: 0,
             mode: LISP_TOKENS,
             whitespace: LISP_WHITESPACE,
-            is_ident_rune: None,
+            normalized: None,
+            nested_block_comments: true,
+            namespaced_keywords: false,
+            raw_string_delimiters: ('¬', '¬'),
+            trivia: DEFAULT_TRIVIA,
+            disabled_policy: DisabledPolicy::Chars,
+            eof_policy: EofPolicy::Error,
+            need_more_input: false,
+            bom: false,
+            ext: None,
+            #[cfg(feature = "pages")]
+            page: 1,
+            #[cfg(feature = "pages")]
+            page_break: false,
             position: Position {
-                filename: String::new(),
+                source: SourceId::default(),
                 offset: 0,
                 line: 0,
                 column: 0,
+                #[cfg(feature = "pages")]
+                page: 0,
             },
         };
 
@@ -188,6 +730,11 @@ impl<'a> Scanner<'a> {
         scanner
     }
 
+    // Returns the rarely used configuration, boxed on first use.
+    fn ext_mut(&mut self) -> &mut Extensions {
+        self.ext.get_or_insert_with(|| Box::new(Extensions::new()))
+    }
+
     /// Sets the mode field
     pub fn set_mode(&mut self, mode: u32) {
         self.mode = mode;
@@ -203,7 +750,398 @@ impl<'a> Scanner<'a> {
     where
         F: Fn(char, usize) -> bool + 'static,
     {
-        self.is_ident_rune = Some(Box::new(f));
+        self.ext_mut().is_ident_rune = Some(Box::new(f));
+    }
+
+    /// Sets a predicate for the white space characters beyond the reach
+    /// of the `set_whitespace()` bitmask, i.e. from U+0040 on, such as the
+    /// no-break space U+00A0 or the ideographic space U+3000. Characters
+    /// below U+0040 are still decided by the bitmask alone.
+    ///
+    /// ```
+    /// use scanner::*;
+    ///
+    /// let mut s = Scanner::init("a\u{A0}b\u{3000}c".as_bytes());
+    /// s.set_is_whitespace_rune(char::is_whitespace);
+    /// let texts: Vec<String> = s.tokens().spanned().map(|t| t.text).collect();
+    /// assert_eq!(texts, ["a", "b", "c"]);
+    /// ```
+    pub fn set_is_whitespace_rune<F>(&mut self, f: F)
+    where
+        F: Fn(char) -> bool + 'static,
+    {
+        self.ext_mut().is_whitespace_rune = Some(Box::new(f));
+    }
+
+    /// Sets a transform of the text of the tokens of the given kind, e.g.
+    /// `|text| text.to_lowercase()` for IDENT, replacing the previous one
+    /// for that kind. It is applied once per token, and its result is the
+    /// text returned by `token_text()`, `token_str()` and the spanned
+    /// tokens, while `token_bytes()` and the value methods (such as
+    /// `string_value()`) keep using the source text.
+    ///
+    /// ```
+    /// use scanner::*;
+    ///
+    /// let mut s = Scanner::init(b"(Def 1_000)");
+    /// s.set_text_normalizer(IDENT, |text| text.to_lowercase());
+    /// s.set_text_normalizer(INT, |text| text.replace('_', ""));
+    /// s.scan();
+    /// s.scan();
+    /// assert_eq!(s.token_text(), "def");
+    /// s.scan();
+    /// assert_eq!(s.token_text(), "1000");
+    /// assert_eq!(s.token_bytes(), b"1_000");
+    /// ```
+    pub fn set_text_normalizer<F>(&mut self, kind: Token, f: F)
+    where
+        F: Fn(&str) -> String + 'static,
+    {
+        let normalizers = &mut self.ext_mut().normalizers;
+        normalizers.retain(|(k, _)| *k != kind);
+        normalizers.push((kind, Box::new(f)));
+    }
+
+    /// Sets a reader limiting the chunks of source copied into the buffer,
+    /// to test that the scanned tokens don't depend on the chunking. See
+    /// `ChunkedReader`.
+    pub fn set_chunked_reader(&mut self, reader: ChunkedReader) {
+        self.ext_mut().chunked_reader = Some(reader);
+    }
+
+    /// Starts recording a replay log of the scan, with the source and the
+    /// current configuration. Call it before scanning, once configured.
+    pub fn start_recording(&mut self) {
+        let log = ReplayLog::new(self);
+        self.ext_mut().recording = Some(log);
+    }
+
+    /// Returns the replay log recorded so far, or None if recording wasn't
+    /// started. See `ReplayLog`.
+    pub fn replay_log(&self) -> Option<ReplayLog> {
+        self.ext.as_ref().and_then(|ext| ext.recording.clone())
+    }
+
+    /// Attaches a value to the scanner, carried by the diagnostics it reports
+    /// from now on (see `Diagnostic::user_data()`).
+    pub fn set_user_data<T: Any + Send + Sync>(&mut self, data: T) {
+        self.ext_mut().user_data = UserData::new(data);
+    }
+
+    /// Returns the value attached to the scanner if it is of type T.
+    pub fn user_data<T: Any>(&self) -> Option<&T> {
+        self.ext.as_ref().and_then(|ext| ext.user_data.get())
+    }
+
+    /// Sets a hook called with the kind and span of every token, other than
+    /// EOF, when it is scanned and before it is returned. Tokens scanned
+    /// ahead are reported once, when they are scanned.
+    pub fn set_token_hook<F>(&mut self, f: F)
+    where
+        F: FnMut(Token, &Span) + 'static,
+    {
+        self.ext_mut().token_hook = Some(Box::new(f));
+    }
+
+    /// Sets a handler called with the position and message of every error,
+    /// like the Error field of the Go scanner, instead of recording it as a
+    /// diagnostic. Errors are still counted by `error_count()`, and warnings
+    /// are still recorded.
+    ///
+    /// ```
+    /// use scanner::*;
+    ///
+    /// let mut s = Scanner::init(b"(a \"b\n c)");
+    /// s.set_error_handler(|pos, msg| assert_eq!(format!("{}: {}", pos, msg), "<input>:1:4: literal not terminated"));
+    /// while s.scan() != EOF {}
+    /// assert_eq!(s.error_count(), 1);
+    /// assert!(s.diagnostics().is_empty());
+    /// ```
+    pub fn set_error_handler<F>(&mut self, f: F)
+    where
+        F: FnMut(Position, &str) + 'static,
+    {
+        self.ext_mut().error_handler = Some(Box::new(f));
+    }
+
+    /// Sets the position of the first character of the source, for sources
+    /// that are a fragment of a larger document: reported offsets, lines and
+    /// columns are then relative to the start of that document. It must be
+    /// called before scanning starts.
+    pub fn set_base_position(&mut self, pos: &Position) {
+        self.base_offset = pos.offset;
+        self.src_buf_offset = pos.offset;
+        self.line = pos.line.max(1);
+        self.column = pos.column.saturating_sub(1);
+        #[cfg(feature = "pages")]
+        {
+            self.page = pos.page.max(1);
+        }
+    }
+
+    /// Sets whether lines and columns are counted; they are by default.
+    /// Without them the positions of tokens and diagnostics hold only their
+    /// offset, with line 0 (they aren't valid). Features that depend on
+    /// lines need them: the literate prefix, document delimiters, leading
+    /// comments and the line lints.
+    ///
+    /// ```
+    /// use scanner::*;
+    ///
+    /// let mut s = Scanner::init(b"a\n  b");
+    /// s.set_track_positions(false);
+    /// s.scan();
+    /// s.scan();
+    /// assert_eq!(s.position.offset, 4);
+    /// assert!(!s.position.is_valid());
+    /// ```
+    pub fn set_track_positions(&mut self, track: bool) {
+        self.track_positions = track;
+    }
+
+    /// Adds a pair of opening and closing delimiters, e.g. `<<` and `>>`,
+    /// tracked like brackets: by the nesting depth lint, the unclosed
+    /// delimiters of the summary and `LINT_UNBALANCED_DELIMITERS`. Both are
+    /// returned as IDENT tokens, taking precedence over the tokens starting
+    /// with the same text (the longest registered text matches), when
+    /// identifiers are scanned. Empty delimiters are ignored.
+    ///
+    /// ```
+    /// use scanner::*;
+    ///
+    /// let mut s = Scanner::init(b"(<<a b) >>");
+    /// s.add_delimiter_pair("<<", ">>");
+    /// s.set_lints(LINT_UNBALANCED_DELIMITERS);
+    /// let texts: Vec<String> = s.tokens().spanned().map(|t| t.text).collect();
+    /// assert_eq!(texts, ["(", "<<", "a", "b", ")", ">>"]);
+    /// assert_eq!(s.diagnostics()[0].message, "')' doesn't match '<<'");
+    /// ```
+    pub fn add_delimiter_pair(&mut self, open: &str, close: &str) {
+        if !open.is_empty() && !close.is_empty() {
+            self.ext_mut().delimiter_pairs.push((String::from(open), String::from(close)));
+        }
+    }
+
+    /// Sets the texts introducing line comments, `;` by default, e.g.
+    /// `&["//", "#"]` for configuration files. The longest one matching at
+    /// the start of a token wins, over the other tokens starting with the
+    /// same text; empty prefixes are ignored, and no prefixes disable line
+    /// comments.
+    ///
+    /// ```
+    /// use scanner::*;
+    ///
+    /// let mut s = Scanner::init(b"a // b\n#c\n/d ;e");
+    /// s.set_mode(LISP_TOKENS & !SKIP_COMMENTS);
+    /// s.set_line_comment_prefixes(&["//", "#"]);
+    /// let texts: Vec<String> = s.tokens().spanned().map(|t| t.text).collect();
+    /// assert_eq!(texts, ["a", "// b", "#c", "/d", ";", "e"]);
+    /// ```
+    pub fn set_line_comment_prefixes(&mut self, prefixes: &[&str]) {
+        self.ext_mut().line_comment_prefixes = prefixes.iter().filter(|p| !p.is_empty()).map(|p| Cow::Owned(String::from(*p))).collect();
+    }
+
+    /// Splits the source into independent documents separated by lines
+    /// consisting of `delimiter` (e.g. "---" or "\x0C"), which are returned
+    /// as DOCUMENT_BOUNDARY tokens, including the line terminator. None or
+    /// an empty delimiter disables the splitting.
+    pub fn set_document_delimiter(&mut self, delimiter: Option<&str>) {
+        self.ext_mut().document_delimiter = delimiter.filter(|d| !d.is_empty()).map(String::from);
+    }
+
+    /// Sets whether line numbers restart at 1 after each DOCUMENT_BOUNDARY,
+    /// so that positions are relative to their document. Offsets are always
+    /// relative to the whole source.
+    pub fn set_reset_lines_per_document(&mut self, reset: bool) {
+        self.ext_mut().reset_lines_per_document = reset;
+    }
+
+    /// Sets whether block comments nest, as in Scheme, which is the default:
+    /// `#| a #| b |# c |#` is then a single comment. Otherwise a block
+    /// comment ends at the first `|#`.
+    pub fn set_nested_block_comments(&mut self, nested: bool) {
+        self.nested_block_comments = nested;
+    }
+
+    /// Sets whether keywords may be namespaced, as in Clojure: their name
+    /// may then hold `.` and `/`, and start with a second colon, so that
+    /// `:clojure.core/map` and `::alias/thing` are single KEYWORD tokens,
+    /// split by `keyword_parts()`.
+    ///
+    /// ```
+    /// use scanner::*;
+    ///
+    /// let mut s = Scanner::init(b"(:clojure.core/map ::alias/thing)");
+    /// s.set_namespaced_keywords(true);
+    /// s.scan();
+    /// assert_eq!((s.scan(), s.token_text().as_str()), (KEYWORD, ":clojure.core/map"));
+    /// assert_eq!((s.scan(), s.token_text().as_str()), (KEYWORD, "::alias/thing"));
+    /// ```
+    pub fn set_namespaced_keywords(&mut self, namespaced: bool) {
+        self.namespaced_keywords = namespaced;
+    }
+
+    /// Registers how `#` followed by the character `ch` is scanned, like
+    /// an entry of a Lisp dispatch macro table, or removes the entry with
+    /// None. Registered entries take precedence over the built-in `#\`,
+    /// `#_` and `#{`, in any mode, but not over block comments `#|`.
+    ///
+    /// ```
+    /// use scanner::*;
+    ///
+    /// const REGEX: Token = -64;
+    /// let mut s = Scanner::init(b"#(inc %) #\"a+\" #'map");
+    /// s.set_dispatch_macro('(', Some(DispatchMacro::Token(IDENT)));
+    /// s.set_dispatch_macro('"', Some(DispatchMacro::Literal(REGEX)));
+    /// s.set_dispatch_macro('\'', Some(DispatchMacro::Prefix(IDENT)));
+    /// let tokens: Vec<(Token, String)> = s.tokens().spanned().map(|t| (t.tok, t.text)).collect();
+    /// assert_eq!(tokens[0], (IDENT, String::from("#(")));
+    /// assert_eq!(tokens[4], (REGEX, String::from("#\"a+\"")));
+    /// assert_eq!(tokens[5], (IDENT, String::from("#'map")));
+    /// ```
+    pub fn set_dispatch_macro(&mut self, ch: char, dispatch: Option<DispatchMacro>) {
+        let dispatch_macros = &mut self.ext_mut().dispatch_macros;
+        dispatch_macros.retain(|(c, _)| *c != ch);
+        if let Some(dispatch) = dispatch {
+            dispatch_macros.push((ch, dispatch));
+        }
+    }
+
+    /// Sets the characters opening and closing raw strings, `¬` by default,
+    /// e.g. `«` and `»`. Inside a raw string, the closing character is
+    /// written doubled. The opening character must not be an identifier
+    /// character or start another token.
+    ///
+    /// ```
+    /// use scanner::*;
+    ///
+    /// let mut s = Scanner::init("(a «b »» c»)".as_bytes());
+    /// s.set_raw_string_delimiters('«', '»');
+    /// s.scan();
+    /// s.scan();
+    /// assert_eq!(s.scan(), RAW_STRING);
+    /// assert_eq!(s.raw_string_value().as_deref(), Some("b » c"));
+    /// ```
+    pub fn set_raw_string_delimiters(&mut self, open: char, close: char) {
+        self.raw_string_delimiters = (open, close);
+    }
+
+    /// Enables the literate mode: only the lines starting with `prefix`
+    /// (e.g. "> " or four spaces) are scanned as code, with the prefix
+    /// skipped as white space. Any other non-empty line is returned as a
+    /// single PROSE token, excluding the line terminator. None disables
+    /// the literate mode.
+    pub fn set_literate_prefix(&mut self, prefix: Option<&str>) {
+        self.ext_mut().literate_prefix = prefix.map(String::from);
+    }
+
+    /// Sets how strings, raw strings and comments are scanned when their
+    /// mode bit is off. By default the opening character is returned and
+    /// the rest is scanned as further tokens, as in the Go scanner.
+    pub fn set_disabled_policy(&mut self, policy: DisabledPolicy) {
+        self.disabled_policy = policy;
+    }
+
+    /// Sets how the end of the input inside a string, raw string, block
+    /// comment or character literal is treated: as an error by default, or
+    /// as a NEED_MORE_INPUT token whose position is where scanning has to
+    /// restart once more input is available. Tokens other than literals,
+    /// e.g. an identifier, are returned as usual at the end of the input.
+    ///
+    /// ```
+    /// use scanner::*;
+    ///
+    /// let mut s = Scanner::init(b"(send \"hel");
+    /// s.set_eof_policy(EofPolicy::NeedMoreInput);
+    /// s.scan();
+    /// s.scan();
+    /// assert_eq!(s.scan(), NEED_MORE_INPUT);
+    /// assert_eq!((s.position.offset, s.token_text().as_str()), (6, "\"hel"));
+    /// assert_eq!(s.error_count(), 0);
+    /// ```
+    pub fn set_eof_policy(&mut self, policy: EofPolicy) {
+        self.eof_policy = policy;
+    }
+
+    /// Sets the token kinds skipped by `next_significant()`, e.g.
+    /// `&[COMMENT, WHITESPACE]`. Other tokens and characters are ignored.
+    pub fn set_trivia(&mut self, kinds: &[Token]) {
+        self.trivia = kinds.iter().filter(|&&tok| tok < EOF && tok > -32).fold(0, |mask, &tok| mask | 1 << -tok);
+    }
+
+    /// Sets the lints field
+    pub fn set_lints(&mut self, lints: u32) {
+        self.lints = lints;
+    }
+
+    /// Sets whether the tokens scanned are tallied for `eof_summary()`;
+    /// it is off by default.
+    pub fn set_summary(&mut self, summary: bool) {
+        self.summary = summary;
+    }
+
+    /// Sets the maximum number of lines of a raw string checked by
+    /// `LINT_SUSPICIOUS_DELIMITERS`
+    pub fn set_max_raw_string_lines(&mut self, max: usize) {
+        self.ext_mut().max_raw_string_lines = max;
+    }
+
+    /// Sets the predicate checked by one of the naming style lints,
+    /// `LINT_IDENT_STYLE`, `LINT_EARMUFF_STYLE` or `LINT_KEYWORD_STYLE`,
+    /// instead of `is_kebab_case()`. It is called with the token text,
+    /// including the earmuffs or the colons.
+    ///
+    /// ```
+    /// use scanner::*;
+    ///
+    /// let mut s = Scanner::init(b"(def *Host* :camelCase my_var)");
+    /// s.set_lints(LINT_IDENT_STYLE | LINT_EARMUFF_STYLE | LINT_KEYWORD_STYLE);
+    /// s.set_naming_style(LINT_KEYWORD_STYLE, |_| true);
+    /// while s.scan() != EOF {}
+    /// let messages: Vec<&str> = s.diagnostics().iter().map(|d| d.message.as_str()).collect();
+    /// assert_eq!(messages, [
+    ///     "earmuffed identifier *Host* doesn't follow the naming style",
+    ///     "identifier my_var doesn't follow the naming style",
+    /// ]);
+    /// ```
+    pub fn set_naming_style<F>(&mut self, lint: u32, f: F)
+    where
+        F: Fn(&str) -> bool + 'static,
+    {
+        let naming_styles = &mut self.ext_mut().naming_styles;
+        naming_styles.retain(|(l, _)| *l != lint);
+        naming_styles.push((lint, Box::new(f)));
+    }
+
+    /// Sets the maximum line length checked by `LINT_LINE_LENGTH`
+    pub fn set_max_line_length(&mut self, max: usize) {
+        self.ext_mut().max_line_length = max;
+    }
+
+    /// Sets the maximum nesting depth of brackets checked by `LINT_DEEP_NESTING`
+    pub fn set_max_nesting_depth(&mut self, max: usize) {
+        self.ext_mut().max_nesting_depth = max;
+    }
+
+    /// Sets the maximum length in characters of identifiers and keywords,
+    /// colons included, and how longer ones are returned. It protects
+    /// consumers interning symbols from adversarial input; there is no
+    /// limit by default.
+    ///
+    /// ```
+    /// use scanner::*;
+    ///
+    /// let mut s = Scanner::init(b"(abcdef :ghijkl)");
+    /// s.set_max_ident_length(4, IdentLengthPolicy::Truncate);
+    /// s.scan();
+    /// assert_eq!((s.scan(), s.token_text()), (IDENT, "abcd".to_string()));
+    /// assert_eq!((s.scan(), s.token_text()), (KEYWORD, ":ghi".to_string()));
+    /// assert_eq!(s.diagnostics()[0].message, "identifier is 6 characters long (maximum is 4)");
+    /// ```
+    pub fn set_max_ident_length(&mut self, max: usize, policy: IdentLengthPolicy) {
+        let ext = self.ext_mut();
+        ext.max_ident_length = max;
+        ext.ident_length_policy = policy;
     }
 
     /// Gets the error count
@@ -211,11 +1149,393 @@ impl<'a> Scanner<'a> {
         self.error_count
     }
 
-    fn error(&mut self, _msg: &str) {
-        self.tok_end = self.src_pos.saturating_sub(self.last_char_len);
+    /// Returns the diagnostics reported so far
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    /// Returns the diagnostics reported so far and clears them
+    pub fn take_diagnostics(&mut self) -> Vec<Diagnostic> {
+        core::mem::take(&mut self.diagnostics)
+    }
+
+    // Returns the last n error diagnostics, in the order they were found:
+    // errors are appended as found, unlike warnings.
+    pub(crate) fn last_errors(&self, n: usize) -> Vec<&Diagnostic> {
+        let mut errors: Vec<&Diagnostic> =
+            self.diagnostics.iter().rev().filter(|d| d.severity == Severity::Error).take(n).collect();
+        errors.reverse();
+        errors
+    }
+
+    // Reports an error in the current token, or at the current position if
+    // no token is being scanned.
+    #[cold]
+    fn error(&mut self, code: DiagnosticCode, msg: &str) {
+        let start = if self.tok_pos >= 0 { self.position.clone() } else { self.pos() };
+        self.error_at(start, code, msg);
+    }
+
+    // Reports an error in the construct starting at start to the error
+    // handler, or records it as a diagnostic; the library never prints.
+    fn error_at(&mut self, start: Position, code: DiagnosticCode, msg: &str) {
+        self.error_span(start, self.pos(), code, msg);
+    }
+
+    // Reports an error like error_at(), with the given end of its span.
+    #[cold]
+    fn error_span(&mut self, start: Position, end: Position, code: DiagnosticCode, msg: &str) {
+        self.tok_end = self.last_char_pos();
         self.error_count += 1;
-        // In no_std environment, we can't use eprintln
-        // The error is tracked in error_count
+        let summary = self.summary;
+        let ext = self.ext_mut();
+        if code == DiagnosticCode::Unterminated && summary {
+            ext.tally.unterminated.push(Span { start: start.clone(), end: end.clone() });
+        }
+        let d = Diagnostic {
+            severity: Severity::Error,
+            code,
+            message: msg.to_string(),
+            span: Span { start, end },
+            user_data: ext.user_data.clone(),
+        };
+        if ext.first_error.is_none() {
+            ext.first_error = ScanError::from_diagnostic(&d);
+        }
+        match ext.error_handler.as_mut() {
+            Some(handler) => handler(d.span.start, msg),
+            None => self.diagnostics.push(d),
+        }
+    }
+
+    // Reports a warning, keeping the diagnostics in source order: token
+    // lints are checked after the lookahead character has been read.
+    fn warning(&mut self, start: Position, end: Position, code: DiagnosticCode, msg: String) {
+        let at = self.diagnostics.partition_point(|d| d.span.start.offset <= start.offset);
+        let user_data = self.ext.as_ref().map_or_else(UserData::default, |ext| ext.user_data.clone());
+        self.diagnostics.insert(at, Diagnostic {
+            severity: Severity::Warning,
+            code,
+            message: msg,
+            span: Span { start, end },
+            user_data,
+        });
+    }
+
+    fn position_at(&self, offset: usize, line: usize, column: usize) -> Position {
+        Position {
+            source: self.position.source.clone(),
+            offset,
+            line,
+            column,
+            #[cfg(feature = "pages")]
+            page: self.page,
+        }
+    }
+
+    // Updates the line lints state with the character just read.
+    #[cold]
+    fn lint_char(&mut self, ch: char, width: usize) {
+        debug_assert!(self.src_pos >= width);
+        let offset = self.src_buf_offset + self.src_pos.saturating_sub(width);
+        let column = self.column;
+        match ch {
+            '\n' => {
+                self.lint_line_end(offset, column);
+                return;
+            }
+            '\r' | '\u{FEFF}' => return,
+            _ => {}
+        }
+        let ext = self.ext_mut();
+        let line = &mut ext.line_lint;
+        let mut mixed = false;
+        if ch == ' ' || ch == '\t' {
+            if line.in_indent {
+                if ch == ' ' {
+                    line.indent_space = true;
+                } else if line.indent_space && !line.mixed {
+                    line.mixed = true;
+                    mixed = true;
+                }
+            }
+            if line.trailing.is_none() {
+                line.trailing = Some((offset, column));
+            }
+        } else {
+            line.in_indent = false;
+            line.trailing = None;
+        }
+        line.chars += 1;
+        if line.chars == ext.max_line_length + 1 {
+            line.overflow = Some((offset, column));
+        }
+        if mixed && (self.lints & LINT_MIXED_INDENTATION) != 0 {
+            let start = self.position_at(offset, self.line, column);
+            let end = self.position_at(offset + width, self.line, column + 1);
+            self.warning(start, end, DiagnosticCode::MixedIndentation, "tab after spaces in indentation".to_string());
+        }
+    }
+
+    // Reports the line lints of the current line, which ends at the given
+    // offset and column (the newline character or EOF).
+    #[cold]
+    fn lint_line_end(&mut self, offset: usize, column: usize) {
+        let ext = self.ext_mut();
+        let line = core::mem::replace(&mut ext.line_lint, LineLint::new());
+        let max_line_length = ext.max_line_length;
+        if (self.lints & LINT_TRAILING_WHITESPACE) != 0
+            && let Some((start_offset, start_column)) = line.trailing
+        {
+            let start = self.position_at(start_offset, self.line, start_column);
+            let end = self.position_at(offset, self.line, column);
+            self.warning(start, end, DiagnosticCode::TrailingWhitespace, "trailing whitespace".to_string());
+        }
+        if (self.lints & LINT_LINE_LENGTH) != 0
+            && let Some((start_offset, start_column)) = line.overflow
+        {
+            let start = self.position_at(start_offset, self.line, start_column);
+            let end = self.position_at(offset, self.line, column);
+            let msg = format!("line is {} characters long (maximum is {})", line.chars, max_line_length);
+            self.warning(start, end, DiagnosticCode::LineLength, msg);
+        }
+    }
+
+    // Reports the token lints of the token just scanned.
+    #[cold]
+    fn lint_token(&mut self, tok: Token) {
+        let text = self.raw_token_str();
+        let start = self.position.clone();
+        let end = self.pos();
+        match tok {
+            INT if (self.lints & LINT_LEGACY_OCTAL) != 0 && literal::is_legacy_octal(&text) => {
+                self.warning(start, end, DiagnosticCode::LegacyOctal, "octal literal with leading 0, use the 0o prefix".to_string());
+            }
+            IDENT if (self.lints & LINT_NON_NFC) != 0 && text.chars().any(is_combining_mark) => {
+                self.warning(start, end, DiagnosticCode::NonNfc, "identifier is not in NFC normal form".to_string());
+            }
+            _ if (self.lints & LINT_NON_NFC) != 0 && char::from_u32(tok as u32).is_some_and(is_combining_mark) => {
+                let msg = format!("stray combining mark U+{:04X}, the source may not be in NFC normal form", tok);
+                self.warning(start, end, DiagnosticCode::NonNfc, msg);
+            }
+            _ => {}
+        }
+
+        let style = match tok {
+            IDENT if text.len() > 2 && text.starts_with('*') && text.ends_with('*') => {
+                Some((LINT_EARMUFF_STYLE, "earmuffed identifier"))
+            }
+            IDENT => Some((LINT_IDENT_STYLE, "identifier")),
+            KEYWORD => Some((LINT_KEYWORD_STYLE, "keyword")),
+            _ => None,
+        };
+        if let Some((lint, what)) = style
+            && (self.lints & lint) != 0
+        {
+            let naming_styles = self.ext.as_ref().map_or(&[][..], |ext| &ext.naming_styles);
+            let ok = match naming_styles.iter().find(|(l, _)| *l == lint) {
+                Some((_, f)) => f(&text),
+                None => is_kebab_case(&text),
+            };
+            if !ok {
+                let msg = format!("{} {} doesn't follow the naming style", what, text);
+                self.warning(self.position.clone(), self.pos(), DiagnosticCode::NamingStyle, msg);
+            }
+        }
+
+        if (self.lints & LINT_SUSPICIOUS_DELIMITERS) != 0 {
+            self.lint_delimiters(tok);
+        }
+
+        if (self.lints & LINT_DEEP_NESTING) != 0 {
+            match self.delimiter_role(tok) {
+                Some(Delimiter::Open) => {
+                    let ext = self.ext_mut();
+                    ext.nesting_depth += 1;
+                    if ext.nesting_depth == ext.max_nesting_depth + 1 {
+                        let msg = format!("nesting depth exceeds {}", ext.max_nesting_depth);
+                        self.warning(self.position.clone(), self.pos(), DiagnosticCode::DeepNesting, msg);
+                    }
+                }
+                Some(Delimiter::Close) => {
+                    let ext = self.ext_mut();
+                    ext.nesting_depth = ext.nesting_depth.saturating_sub(1);
+                }
+                None => {}
+            }
+        }
+
+        if (self.lints & LINT_UNBALANCED_DELIMITERS) != 0 && self.delimiter_role(tok) == Some(Delimiter::Close) {
+            let msg = match self.open_delimiters().last() {
+                None => Some(format!("'{}' closes nothing", text)),
+                Some(open) if self.open_closer(open) != Some(&text) => {
+                    Some(format!("'{}' doesn't match '{}'", text, self.open_text(open)))
+                }
+                Some(_) => None,
+            };
+            if let Some(msg) = msg {
+                self.warning(self.position.clone(), self.pos(), DiagnosticCode::UnbalancedDelimiter, msg);
+            }
+        }
+    }
+
+    // Returns the closing delimiter of the pair opened by a token, given
+    // its kind and text, or None if it doesn't open one.
+    fn closer(&self, tok: Token, text: &str) -> Option<&str> {
+        match tok {
+            _ if tok == '(' as Token => Some(")"),
+            _ if tok == '[' as Token => Some("]"),
+            _ if tok == '{' as Token => Some("}"),
+            IDENT if text == "#{" => Some("}"),
+            IDENT => self.delimiter_pairs().iter().find(|(open, _)| open == text).map(|(_, close)| close.as_str()),
+            _ => None,
+        }
+    }
+
+    // Returns the pairs of delimiters added with add_delimiter_pair().
+    fn delimiter_pairs(&self) -> &[(String, String)] {
+        self.ext.as_ref().map_or(&[], |ext| &ext.delimiter_pairs)
+    }
+
+    // Returns the brackets and delimiters left open, outermost first.
+    fn open_delimiters(&self) -> &[OpenDelimiter] {
+        self.ext.as_ref().map_or(&[], |ext| &ext.tally.open)
+    }
+
+    // Returns the closing delimiter of an open bracket or delimiter.
+    fn open_closer(&self, open: &OpenDelimiter) -> Option<&str> {
+        match open.pair {
+            Some(i) => self.delimiter_pairs().get(i).map(|(_, close)| close.as_str()),
+            None => self.closer(open.tok, "#{"),
+        }
+    }
+
+    // Returns the text of an open bracket or delimiter.
+    fn open_text(&self, open: &OpenDelimiter) -> String {
+        match open.pair {
+            Some(i) => self.delimiter_pairs().get(i).map_or_else(String::new, |(text, _)| text.clone()),
+            None if open.tok == IDENT => String::from("#{"),
+            None => char::from_u32(open.tok as u32).map_or_else(String::new, String::from),
+        }
+    }
+
+    // Returns whether the current token opens or closes a pair of
+    // delimiters. A delimiter that both opens and closes pairs closes the
+    // innermost open one if it can.
+    fn delimiter_role(&self, tok: Token) -> Option<Delimiter> {
+        if tok == ')' as Token || tok == ']' as Token || tok == '}' as Token {
+            return Some(Delimiter::Close);
+        }
+        if tok != IDENT && tok != '(' as Token && tok != '[' as Token && tok != '{' as Token {
+            return None;
+        }
+        let text = if tok == IDENT { self.raw_token_str() } else { Cow::Borrowed("") };
+        let closes = tok == IDENT && self.delimiter_pairs().iter().any(|(_, close)| *close == text);
+        let innermost = self.open_delimiters().last().and_then(|t| self.open_closer(t));
+        match self.closer(tok, &text) {
+            Some(_) if closes && innermost == Some(&text) => Some(Delimiter::Close),
+            Some(_) => Some(Delimiter::Open),
+            None if closes => Some(Delimiter::Close),
+            None => None,
+        }
+    }
+
+    // Reports whether text starts at the current character.
+    fn at_text(&self, text: &str) -> bool {
+        let offset = self.char_location().0.saturating_sub(self.base_offset);
+        self.src.get(offset..).is_some_and(|rest| rest.starts_with(text.as_bytes()))
+    }
+
+    // Returns the number of characters of the longest of texts starting at
+    // the current character ch, if any.
+    fn match_longest<'t>(&self, ch: char, texts: impl Iterator<Item = &'t str>) -> Option<usize> {
+        texts
+            .filter(|text| text.starts_with(ch) && self.at_text(text))
+            .max_by_key(|text| text.len())
+            .map(|text| text.chars().count())
+    }
+
+    fn match_line_comment(&self, ch: char) -> Option<usize> {
+        match &self.ext {
+            Some(ext) => self.match_longest(ch, ext.line_comment_prefixes.iter().map(|p| p.as_ref())),
+            None => (ch == ';').then_some(1),
+        }
+    }
+
+    // Returns the prefixes of the line comments, `;` by default.
+    fn line_comment_prefixes(&self) -> &[Cow<'static, str>] {
+        self.ext.as_ref().map_or(DEFAULT_LINE_COMMENT_PREFIXES, |ext| &ext.line_comment_prefixes)
+    }
+
+    // Returns the dispatch macros set with set_dispatch_macro().
+    fn dispatch_macros(&self) -> &[(char, DispatchMacro)] {
+        self.ext.as_ref().map_or(&[], |ext| &ext.dispatch_macros)
+    }
+
+    // Tracks the brackets and checks the raw strings for
+    // LINT_SUSPICIOUS_DELIMITERS.
+    fn lint_delimiters(&mut self, tok: Token) {
+        match char::from_u32(tok as u32) {
+            Some(ch @ ('(' | '[' | '{')) => {
+                let span = self.token_span();
+                let open_brackets = &mut self.ext_mut().open_brackets;
+                if let Some(last) = open_brackets.last_mut()
+                    && last.ch == ch
+                    && last.doubled.is_none()
+                    && last.span.end.offset == span.start.offset
+                {
+                    last.doubled = Some(span.end.clone());
+                }
+                open_brackets.push(OpenBracket { ch, span, doubled: None });
+            }
+            Some(')' | ']' | '}') => {
+                self.ext_mut().open_brackets.pop();
+            }
+            _ if tok == DOCUMENT_BOUNDARY => self.lint_open_brackets(),
+            _ if tok == RAW_STRING => {
+                let lines = self.token_line_count();
+                let max_raw_string_lines = self.ext.as_ref().map_or(DEFAULT_MAX_RAW_STRING_LINES, |ext| ext.max_raw_string_lines);
+                if lines > max_raw_string_lines {
+                    let start = self.position.clone();
+                    let open = self.raw_string_delimiters.0;
+                    let end = self.position_at(start.offset + open.len_utf8(), start.line, start.column + 1);
+                    let msg = format!("raw string spans {} lines (maximum is {})", lines, max_raw_string_lines);
+                    self.warning(start, end, DiagnosticCode::SuspiciousDelimiter, msg);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Reports the doubled brackets closed only once, at the end of the
+    // document.
+    #[cold]
+    fn lint_open_brackets(&mut self) {
+        let Some(ext) = self.ext.as_mut() else {
+            return;
+        };
+        for b in core::mem::take(&mut ext.open_brackets) {
+            if let Some(end) = b.doubled {
+                let msg = format!("doubled '{}' is closed only once", b.ch);
+                self.warning(b.span.start, end, DiagnosticCode::SuspiciousDelimiter, msg);
+            }
+        }
+    }
+
+    // Reports whether ch is a white space character; a BOM is white space
+    // only as the very first character of the source.
+    fn is_whitespace(&self, ch: char) -> bool {
+        let ch_u32 = ch as u32;
+        if ch_u32 < 64 {
+            return (self.whitespace & (1 << ch_u32)) != 0;
+        }
+        if let Some(f) = self.ext.as_ref().and_then(|ext| ext.is_whitespace_rune.as_ref())
+            && ch != '\u{FFFF}'
+            && f(ch)
+        {
+            return true;
+        }
+        ch == '\u{FEFF}' && self.line == 1 && self.column == 1 && self.last_char_len == 3
     }
 
     fn char_to_token(&self, ch: char) -> Token {
@@ -243,7 +1563,7 @@ impl<'a> Scanner<'a> {
     }
 
     fn is_ident_rune_check(&self, ch: char, i: usize) -> bool {
-        if let Some(ref f) = self.is_ident_rune {
+        if let Some(f) = self.ext.as_ref().and_then(|ext| ext.is_ident_rune.as_ref()) {
             ch as i32 != EOF && f(ch, i)
         } else {
             self.is_ident_rune_default(ch, i)
@@ -259,19 +1579,36 @@ impl<'a> Scanner<'a> {
         } else {
             // Uncommon case: not ASCII or not enough bytes
             loop {
-                let remaining = self.src_end - self.src_pos;
+                debug_assert!(self.src_pos <= self.src_end);
+                let remaining = self.src_end.saturating_sub(self.src_pos);
                 if remaining >= 4 {
                     break;
                 }
 
                 // Check if we have a complete UTF-8 sequence
-                if remaining > 0 {
-                    let bytes = &self.src_buf[self.src_pos..self.src_end];
-                    if let Ok(s) = str::from_utf8(bytes) {
-                        if !s.is_empty() {
-                            break;
-                        }
+                if remaining > 0 && full_rune(&self.src_buf[self.src_pos..self.src_end]) {
+                    break;
+                }
+
+                // Read more bytes from source slice
+                let i = remaining;
+                let bytes_to_read = N - 1 - i;
+                let available = self.src.len().saturating_sub(self.src_read_pos);
+                let mut n = if available < bytes_to_read { available } else { bytes_to_read };
+                if let Some(reader) = self.ext.as_mut().and_then(|ext| ext.chunked_reader.as_mut()) {
+                    n = reader.limit(self.src_read_pos, n);
+                }
+
+                // At EOF the token text stays in the buffer
+                if n == 0 && i == 0 {
+                    if (self.lints & LINE_LINTS) != 0 && self.ext.as_ref().is_some_and(|ext| ext.line_lint.chars > 0) {
+                        self.lint_line_end(self.src_buf_offset + self.src_pos, self.column + 1);
+                    }
+                    if self.last_char_len > 0 && self.track_positions {
+                        self.column += 1;
                     }
+                    self.last_char_len = 0;
+                    return '\u{FFFF}'; // EOF marker
                 }
 
                 // Save token text if any
@@ -284,27 +1621,16 @@ impl<'a> Scanner<'a> {
                 self.src_buf.copy_within(self.src_pos..self.src_end, 0);
                 self.src_buf_offset += self.src_pos;
 
-                // Read more bytes from source slice
-                let i = self.src_end - self.src_pos;
-                let bytes_to_read = BUF_LEN - i;
-                let available = self.src.len() - self.src_read_pos;
-                let n = if available < bytes_to_read { available } else { bytes_to_read };
-
                 if n == 0 {
                     self.src_pos = 0;
                     self.src_end = i;
                     self.src_buf[self.src_end] = 128;
-
-                    if self.src_end == 0 {
-                        if self.last_char_len > 0 {
-                            self.column += 1;
-                        }
-                        self.last_char_len = 0;
-                        return '\u{FFFF}'; // EOF marker
-                    }
                     break;
                 } else {
                     self.src_buf[i..i+n].copy_from_slice(&self.src[self.src_read_pos..self.src_read_pos+n]);
+                    if let Some(log) = self.ext.as_mut().and_then(|ext| ext.recording.as_mut()) {
+                        log.chunks.push(self.src_read_pos..self.src_read_pos + n);
+                    }
                     self.src_read_pos += n;
                     self.src_pos = 0;
                     self.src_end = i + n;
@@ -315,24 +1641,17 @@ impl<'a> Scanner<'a> {
             // Decode UTF-8
             ch = self.src_buf[self.src_pos] as u32;
             if ch >= 128 {
-                let bytes = &self.src_buf[self.src_pos..self.src_end];
-                if let Ok(s) = str::from_utf8(bytes) {
-                    if let Some(decoded_ch) = s.chars().next() {
-                        ch = decoded_ch as u32;
-                        width = decoded_ch.len_utf8();
-                    } else {
-                        self.src_pos += 1;
-                        self.last_char_len = 1;
-                        self.column += 1;
-                        self.error("invalid UTF-8 encoding");
-                        return '\u{FFFD}'; // Replacement character
-                    }
+                if let Some(decoded_ch) = decode_rune(&self.src_buf[self.src_pos..self.src_end]) {
+                    ch = decoded_ch as u32;
+                    width = decoded_ch.len_utf8();
                 } else {
                     self.src_pos += 1;
                     self.last_char_len = 1;
-                    self.column += 1;
-                    self.error("invalid UTF-8 encoding");
-                    return '\u{FFFD}';
+                    if self.track_positions {
+                        self.column += 1;
+                    }
+                    self.error(DiagnosticCode::InvalidUtf8, "invalid UTF-8 encoding");
+                    return '\u{FFFD}'; // Replacement character
                 }
             }
         }
@@ -340,27 +1659,49 @@ impl<'a> Scanner<'a> {
         // Advance
         self.src_pos += width;
         self.last_char_len = width;
-        self.column += 1;
+        if self.track_positions {
+            self.column += 1;
+        }
 
         let result = char::from_u32(ch).unwrap_or('\u{FFFD}');
 
+        if (self.lints & LINE_LINTS) != 0 {
+            self.lint_char(result, width);
+        }
+
         // Special situations
         if result == '\0' {
-            self.error("invalid character NUL");
-        } else if result == '\n' {
+            self.error(DiagnosticCode::InvalidNul, "invalid character NUL");
+        } else if result == '\n' && self.track_positions {
             self.line += 1;
             self.last_line_len = self.column;
             self.column = 0;
         }
+        #[cfg(feature = "pages")]
+        {
+            if self.page_break {
+                self.page += 1;
+            }
+            self.page_break = result == '\x0C';
+        }
 
         result
     }
 
     /// Reads and returns the next Unicode character.
+    /// The current token stays available, as if no character was read.
+    /// Tokens scanned ahead (e.g. by `eat()`) are dropped and the character
+    /// following the current token is read; the tokens are scanned again,
+    /// as after `rewind()`.
     pub fn next_char(&mut self) -> Token {
+        self.drop_ahead();
+        if let Some(t) = self.replay.take() {
+            self.detached = Some(t);
+        } else if self.detached.is_none() && self.tok_pos >= 0 {
+            self.detached = Some(self.current_scanned());
+        }
         self.tok_pos = -1;
-        self.position.line = 0;
-        let ch = self.peek();
+        let ch = self.peek_next();
         if ch != EOF {
             let next_char = self.next();
             if next_char == '\u{FFFF}' {
@@ -373,14 +1714,40 @@ impl<'a> Scanner<'a> {
     }
 
     /// Returns the next Unicode character without advancing the scanner.
+    /// Like `next_char()`, it drops the tokens scanned ahead.
     pub fn peek(&mut self) -> Token {
+        self.drop_ahead();
+        self.peek_next()
+    }
+
+    // Drops the tokens scanned ahead, rewinding to the state before they
+    // were scanned and scanning again the ones consumed since.
+    fn drop_ahead(&mut self) {
+        if self.ahead.is_empty() {
+            return;
+        }
+        let Some(start) = self.ahead_start.take() else {
+            return;
+        };
+        let consumed = self.ahead_scanned - self.ahead.len();
+        self.rewind(Rc::unwrap_or_clone(start));
+        for _ in 0..consumed {
+            self.scan();
+        }
+    }
+
+    // Returns the character following the last one read, which follows
+    // the tokens scanned ahead, if any.
+    fn peek_next(&mut self) -> Token {
         if self.ch == -2 {
             let next_char = self.next();
             if next_char == '\u{FFFF}' {
                 self.ch = EOF;
             } else {
                 self.ch = next_char as i32;
-                if self.ch == 0xFEFF {
+                // The BOM is kept as white space in SCAN_WHITESPACE mode
+                if self.ch == 0xFEFF && (self.mode & SCAN_WHITESPACE) == 0 {
+                    self.bom = true;
                     let bom_next = self.next();
                     if bom_next == '\u{FFFF}' {
                         self.ch = EOF;
@@ -393,6 +1760,19 @@ impl<'a> Scanner<'a> {
         self.ch
     }
 
+    /// Reads and returns the next Unicode character like `next_char()`, or
+    /// None at EOF. A leading BOM is skipped by both, unless white space is
+    /// returned as tokens (see `SCAN_WHITESPACE`).
+    pub fn read_char(&mut self) -> Option<char> {
+        token_char(self.next_char())
+    }
+
+    /// Returns the next Unicode character without advancing the scanner
+    /// like `peek()`, or None at EOF.
+    pub fn peek_char(&mut self) -> Option<char> {
+        token_char(self.peek())
+    }
+
     fn scan_identifier(&mut self) -> char {
         let mut ch = self.next();
         let mut i = 1;
@@ -403,6 +1783,21 @@ impl<'a> Scanner<'a> {
         ch
     }
 
+    // Scans a namespaced keyword after its colon: an optional second colon
+    // and a name whose parts are separated by '.' or '/'.
+    fn scan_namespaced_keyword(&mut self) -> char {
+        let mut ch = self.next();
+        if ch == ':' {
+            ch = self.next();
+        }
+        let mut i = 1;
+        while ch == '.' || ch == '/' || self.is_ident_rune_check(ch, i) {
+            ch = self.next();
+            i += 1;
+        }
+        ch
+    }
+
     fn lower(ch: char) -> char {
         if ch.is_ascii_uppercase() {
             ch.to_ascii_lowercase()
@@ -426,7 +1821,7 @@ impl<'a> Scanner<'a> {
             let max = char::from_u32('0' as u32 + base).unwrap();
             while Self::is_decimal(ch) || ch == '_' {
                 let ds = if ch == '_' { 2 } else { 1 };
-                if ch >= max && invalid.is_none() {
+                if ch != '_' && ch >= max && invalid.is_none() {
                     *invalid = Some(ch);
                 }
                 digsep |= ds;
@@ -495,7 +1890,7 @@ impl<'a> Scanner<'a> {
         if seen_dot {
             tok = FLOAT;
             if prefix == 'o' || prefix == 'b' {
-                self.error(&format!("invalid radix point in {}", Self::litname(prefix)));
+                self.error(DiagnosticCode::InvalidRadixPoint, &format!("invalid radix point in {}", Self::litname(prefix)));
             }
             let (new_ch, ds) = self.digits(ch, base, &mut invalid);
             ch = new_ch;
@@ -506,7 +1901,7 @@ impl<'a> Scanner<'a> {
             if negative {
                 tok = '-' as i32;
             } else {
-                self.error(&format!("{} has no digits", Self::litname(prefix)));
+                self.error(DiagnosticCode::NoDigits, &format!("{} has no digits", Self::litname(prefix)));
             }
         }
 
@@ -514,9 +1909,9 @@ impl<'a> Scanner<'a> {
         let e = Self::lower(ch);
         if (e == 'e' || e == 'p') && (self.mode & SCAN_FLOATS) != 0 {
             if e == 'e' && prefix != '\0' && prefix != '0' {
-                self.error(&format!("'{}' exponent requires decimal mantissa", ch));
+                self.error(DiagnosticCode::InvalidMantissa, &format!("'{}' exponent requires decimal mantissa", ch));
             } else if e == 'p' && prefix != 'x' {
-                self.error(&format!("'{}' exponent requires hexadecimal mantissa", ch));
+                self.error(DiagnosticCode::InvalidMantissa, &format!("'{}' exponent requires hexadecimal mantissa", ch));
             }
 
             ch = self.next();
@@ -531,26 +1926,69 @@ impl<'a> Scanner<'a> {
             digsep |= ds;
 
             if (ds & 1) == 0 {
-                self.error("exponent has no digits");
+                self.error(DiagnosticCode::NoExponentDigits, "exponent has no digits");
             }
         } else if prefix == 'x' && tok == FLOAT {
-            self.error("hexadecimal mantissa requires a 'p' exponent");
+            self.error(DiagnosticCode::MissingExponent, "hexadecimal mantissa requires a 'p' exponent");
         }
 
-        if tok == INT && invalid.is_some() {
-            self.error(&format!("invalid digit '{}' in {}", invalid.unwrap(), Self::litname(prefix)));
+        if tok == INT && let Some(invalid) = invalid {
+            self.error(DiagnosticCode::InvalidDigit, &format!("invalid digit '{}' in {}", invalid, Self::litname(prefix)));
         }
 
         if (digsep & 2) != 0 {
-            self.tok_end = self.src_pos - self.last_char_len;
-            if let Some(_) = Self::invalid_sep(&self.token_text()) {
-                self.error("'_' must separate successive digits");
+            self.tok_end = self.last_char_pos();
+            if Self::invalid_sep(&self.raw_token_str()).is_some() {
+                self.error(DiagnosticCode::InvalidSeparator, "'_' must separate successive digits");
+            }
+        }
+
+        // Arbitrary-precision suffixes
+        if tok == INT && ch == 'N' && (self.mode & SCAN_BIG_INTS) != 0 {
+            ch = self.next();
+            tok = BIG_INT;
+        } else if (tok == INT || tok == FLOAT)
+            && ch == 'M'
+            && (self.mode & SCAN_BIG_DECIMALS) != 0
+            && (prefix == '\0' || tok == FLOAT || self.is_decimal_int())
+        {
+            ch = self.next();
+            tok = BIG_DECIMAL;
+        }
+
+        if tok == INT
+            && ch == '/'
+            && (self.mode & SCAN_RATIOS) != 0
+            && invalid.is_none()
+            && self.is_decimal_int()
+            && self.src.get(self.char_location().0 - self.base_offset + 1).is_some_and(u8::is_ascii_digit)
+        {
+            ch = self.next();
+            let (new_ch, ds) = self.digits(ch, 10, &mut invalid);
+            ch = new_ch;
+            tok = RATIO;
+            if (ds & 2) != 0 {
+                self.tok_end = self.last_char_pos();
+                let text = self.raw_token_str();
+                let denominator = &text[text.find('/').unwrap_or(0) + 1..];
+                if Self::invalid_sep(denominator).is_some() {
+                    self.error(DiagnosticCode::InvalidSeparator, "'_' must separate successive digits");
+                }
             }
         }
 
         (tok, ch)
     }
 
+    // Reports whether the INT being scanned, up to the current character,
+    // is decimal: it has no prefix, or is a lone 0.
+    fn is_decimal_int(&self) -> bool {
+        let start = self.position.offset - self.base_offset;
+        let end = self.char_location().0 - self.base_offset;
+        let digits = self.src[start..end].strip_prefix(b"-").unwrap_or(&self.src[start..end]);
+        digits == b"0" || !digits.starts_with(b"0")
+    }
+
     fn litname(prefix: char) -> String {
         match prefix {
             'x' => "hexadecimal literal".to_string(),
@@ -613,89 +2051,155 @@ impl<'a> Scanner<'a> {
         }
     }
 
-    fn scan_digits(&mut self, mut ch: char, base: u32, mut n: usize) -> char {
+    // Scans the n digits in the given base of a numeric escape starting at
+    // start, ch being the first one, and returns the next character and the
+    // value of the digits, or None if there are fewer than n; the error then
+    // spans the escape up to the first non-digit.
+    fn scan_digits(&mut self, mut ch: char, base: u32, mut n: usize, start: &Position) -> (char, Option<u32>) {
+        let mut value = 0u32;
         while n > 0 && Self::digit_val(ch) < base {
+            value = value.wrapping_mul(base) + Self::digit_val(ch);
             ch = self.next();
             n -= 1;
         }
         if n > 0 {
-            self.error("invalid char escape");
+            if !self.need_more_input(ch) {
+                self.error_span(start.clone(), self.pos(), DiagnosticCode::InvalidEscape, "invalid char escape");
+            }
+            return (ch, None);
         }
-        ch
+        (ch, Some(value))
     }
 
-    fn scan_escape(&mut self, quote: char) -> char {
-        let mut ch = self.next();
+    // Scans the escape sequence whose backslash was just read and returns
+    // the next character and the escape. An invalid escape is reported with
+    // a span covering its text, the offending character included unless it
+    // ends the line; escapes whose value is out of range are accepted, and
+    // reported by LINT_ESCAPE_RANGE.
+    fn scan_escape(&mut self, quote: char) -> (char, Escape) {
+        let start = self.pos(); // position of the backslash
+        let ch = self.next();
+        let simple = match ch {
+            'a' => Some('\x07'),
+            'b' => Some('\x08'),
+            'f' => Some('\x0C'),
+            'n' => Some('\n'),
+            'r' => Some('\r'),
+            't' => Some('\t'),
+            'v' => Some('\x0B'),
+            '\\' => Some('\\'),
+            c if c == quote => Some(quote),
+            _ => None,
+        };
 
-        match ch {
-            'a' | 'b' | 'f' | 'n' | 'r' | 't' | 'v' | '\\' => {
-                if ch == quote {
-                    ch = self.next();
-                } else {
-                    ch = self.next();
-                }
-            }
-            '0'..='7' => {
-                ch = self.scan_digits(ch, 8, 3);
-            }
-            'x' => {
-                let next_ch = self.next();
-                ch = self.scan_digits(next_ch, 16, 2);
-            }
-            'u' => {
-                let next_ch = self.next();
-                ch = self.scan_digits(next_ch, 16, 4);
-            }
-            'U' => {
-                let next_ch = self.next();
-                ch = self.scan_digits(next_ch, 16, 8);
-            }
-            c if c == quote => {
-                ch = self.next();
+        // The value of the digits of a numeric escape, if any
+        let (next, digits) = match ch {
+            _ if simple.is_some() => (self.next(), None),
+            '0'..='7' => self.scan_digits(ch, 8, 3, &start),
+            'x' | 'u' | 'U' => {
+                let n = match ch {
+                    'x' => 2,
+                    'u' => 4,
+                    _ => 8,
+                };
+                let first = self.next();
+                self.scan_digits(first, 16, n, &start)
             }
             _ => {
-                self.error("invalid char escape");
+                let end = match ch {
+                    '\n' | '\u{FFFF}' => self.pos(),
+                    _ => {
+                        let (offset, line, column) = self.char_location();
+                        self.position_at(offset + ch.len_utf8(), line, column + 1)
+                    }
+                };
+                if !self.need_more_input(ch) {
+                    self.error_span(start.clone(), end, DiagnosticCode::InvalidEscape, "invalid char escape");
+                }
+                (ch, None)
             }
+        };
+        let (value, out_of_range) = match digits {
+            Some(v) if ch.is_digit(8) && v > 0o377 => (None, true),
+            Some(v) => (char::from_u32(v), char::from_u32(v).is_none()),
+            None => (simple, false),
+        };
+
+        let end = self.pos();
+        let escape = Escape { value, len: end.offset - start.offset };
+        if out_of_range && (self.lints & LINT_ESCAPE_RANGE) != 0 {
+            let offset = start.offset - self.base_offset;
+            let text = String::from_utf8_lossy(&self.src[offset..offset + escape.len]);
+            let msg = format!("escape sequence {} is out of range", text);
+            self.warning(start, end, DiagnosticCode::EscapeRange, msg);
         }
-        ch
+        (next, escape)
     }
 
-    fn scan_string(&mut self, quote: char) -> usize {
+    // Scans a string after its opening quote.
+    fn scan_string(&mut self, quote: char) {
         let mut ch = self.next();
-        let mut n = 0;
 
         while ch != quote {
             if ch == '\n' || ch == '\u{FFFF}' {
-                self.error("literal not terminated");
-                return n;
-            }
-            if ch == '\\' {
-                ch = self.scan_escape(quote);
-            } else {
-                ch = self.next();
+                if !self.need_more_input(ch) {
+                    self.error(DiagnosticCode::Unterminated, "literal not terminated");
+                }
+                return;
             }
-            n += 1;
+            ch = if ch == '\\' { self.scan_escape(quote).0 } else { self.next() };
         }
-        n
     }
 
     fn scan_raw_string(&mut self) -> char {
+        let close = self.raw_string_delimiters.1;
         loop {
             let mut ch = self.next();
-            while ch != '¬' {
+            while ch != close {
                 if ch == '\u{FFFF}' {
-                    self.error("literal not terminated");
-                    return '\0';
+                    if !self.need_more_input(ch) {
+                        self.error(DiagnosticCode::Unterminated, "literal not terminated");
+                    }
+                    return ch;
                 }
                 ch = self.next();
             }
             ch = self.next();
-            if ch != '¬' {
+            if ch != close {
                 return ch;
             }
         }
     }
 
+    // Scans a character literal after its "#\": a character, or a name or
+    // code point when it is alphanumeric and followed by alphanumerics.
+    fn scan_char_literal(&mut self) -> char {
+        let first = self.next();
+        if first == '\u{FFFF}' {
+            if !self.need_more_input(first) {
+                self.error(DiagnosticCode::Unterminated, "literal not terminated");
+            }
+            return first;
+        }
+        let mut ch = self.next();
+        if !first.is_alphanumeric() || !ch.is_alphanumeric() {
+            // A name may follow
+            if first.is_alphanumeric() {
+                self.need_more_input(ch);
+            }
+            return ch;
+        }
+        let mut name = String::from(first);
+        while ch.is_alphanumeric() {
+            name.push(ch);
+            ch = self.next();
+        }
+        if !self.need_more_input(ch) && literal::char_name_value(&name).is_none() {
+            self.error(DiagnosticCode::InvalidEscape, "unknown character name");
+        }
+        ch
+    }
+
     fn scan_comment(&mut self, mut ch: char) -> char {
         if ch != '\n' {
             ch = self.next();
@@ -706,53 +2210,1008 @@ impl<'a> Scanner<'a> {
         ch
     }
 
+    // Scans a comment, continued over the next line when it ends in a
+    // backslash in SCAN_COMMENT_CONTINUATIONS mode.
+    fn scan_line_comment(&mut self, mut ch: char) -> char {
+        if (self.mode & SCAN_COMMENT_CONTINUATIONS) == 0 {
+            return self.scan_comment(ch);
+        }
+        let mut last = ';'; // last character of the line, ignoring CR
+        loop {
+            while ch != '\n' && ch != '\u{FFFF}' {
+                if ch != '\r' {
+                    last = ch;
+                }
+                ch = self.next();
+            }
+            if ch != '\n' || last != '\\' {
+                return ch;
+            }
+            last = '\n';
+            ch = self.next();
+        }
+    }
+
+    // Reports whether ch is the end of the input and the EOF policy asks
+    // for more input instead of an error, and then records it for the
+    // current token.
+    fn need_more_input(&mut self, ch: char) -> bool {
+        let more = ch == '\u{FFFF}' && self.eof_policy == EofPolicy::NeedMoreInput;
+        self.need_more_input |= more;
+        more
+    }
+
+    // Scans a block comment, starting at the current character '#' of its
+    // opening "#|", up to and including its closing "|#", counting the
+    // nested comments if enabled.
+    fn scan_block_comment(&mut self) -> char {
+        self.next(); // '|'
+        let mut ch = self.next();
+        let mut depth = 1;
+        loop {
+            match ch {
+                '\u{FFFF}' => {
+                    if !self.need_more_input(ch) {
+                        self.error_at(self.position.clone(), DiagnosticCode::Unterminated, "comment not terminated");
+                    }
+                    return ch;
+                }
+                '|' => {
+                    ch = self.next();
+                    if ch == '#' {
+                        depth -= 1;
+                        if depth == 0 || !self.nested_block_comments {
+                            return self.next();
+                        }
+                        ch = self.next();
+                    }
+                }
+                '#' if self.nested_block_comments => {
+                    ch = self.next();
+                    if ch == '|' {
+                        depth += 1;
+                        ch = self.next();
+                    }
+                }
+                _ => ch = self.next(),
+            }
+        }
+    }
+
+    // Scans the line comment whose prefix, of len characters, starts at the
+    // current character ch, or returns None if it is skipped. When the mode
+    // doesn't recognize comments, it is scanned as told by the disabled
+    // policy.
+    fn scan_line_comment_token(&mut self, mode: u32, ch: char, len: usize) -> Option<Token> {
+        let skip = if (mode & SCAN_COMMENTS) != 0 {
+            (mode & SKIP_COMMENTS) != 0
+        } else {
+            self.disabled_policy == DisabledPolicy::Skip
+        };
+        if skip {
+            self.tok_pos = -1;
+        }
+        let mut next = ch;
+        for _ in 0..len {
+            next = self.next();
+        }
+        let new_ch = self.scan_line_comment(next);
+        self.ch = self.char_to_token(new_ch);
+        if skip {
+            if (mode & SCAN_COMMENTS) != 0 {
+                let span = Span { start: self.position.clone(), end: self.pos() };
+                self.note_comment(span);
+            }
+            return None;
+        }
+        self.tok_end = self.last_char_pos();
+        Some(if (mode & SCAN_COMMENTS) != 0 { COMMENT } else { OTHER })
+    }
+
+    // Scans a string or raw string, starting at the current character
+    // open, that the mode doesn't recognize, as told by the
+    // disabled policy. Returns None if it is skipped. Unlike the literals,
+    // its contents are not checked.
+    fn scan_disabled(&mut self, open: char) -> Option<Token> {
+        let mut ch = self.next();
+        match open {
+            '"' => {
+                while ch != '"' && ch != '\n' && ch != '\u{FFFF}' {
+                    if ch == '\\' {
+                        ch = self.next();
+                        if ch == '\n' || ch == '\u{FFFF}' {
+                            break;
+                        }
+                    }
+                    ch = self.next();
+                }
+                if ch == '"' {
+                    ch = self.next();
+                }
+            }
+            _ => loop {
+                let close = self.raw_string_delimiters.1;
+                while ch != close && ch != '\u{FFFF}' {
+                    ch = self.next();
+                }
+                if ch == '\u{FFFF}' {
+                    break;
+                }
+                ch = self.next();
+                if ch != close {
+                    break;
+                }
+                ch = self.next();
+            },
+        }
+        self.ch = self.char_to_token(ch);
+        if self.disabled_policy == DisabledPolicy::Skip {
+            self.tok_pos = -1;
+            return None;
+        }
+        Some(OTHER)
+    }
+
     /// Scans and returns the next token or Unicode character.
     pub fn scan(&mut self) -> Token {
-        let mut ch = self.peek();
-        if ch == EOF {
-            return EOF;
+        self.detached = None;
+        if let Some(t) = self.ahead.pop_front() {
+            if self.ahead.is_empty() {
+                self.ahead_start = None;
+            }
+            self.tok = t.token.tok;
+            self.position = t.token.span.start.clone();
+            self.last_significant_line = t.last_significant_line;
+            self.leading_comment = t.leading_comment.clone();
+            self.replay = Some(t);
+            return self.tok;
         }
+        self.replay = None;
+        self.scan_raw()
+    }
 
-        let mut ch_char = char::from_u32(ch as u32).unwrap_or('\u{FFFF}');
-        if ch_char == '\u{FFFF}' {
-            return EOF;
+    /// Scans and returns the next token like `scan()`, or the first error
+    /// reported while scanning it. Either way the token is scanned and
+    /// becomes the current token, so scanning can go on after an error.
+    /// The errors of tokens already scanned ahead (e.g. by `eat()`) were
+    /// reported when they were scanned, and those tokens are returned Ok.
+    ///
+    /// ```
+    /// use scanner::*;
+    ///
+    /// let mut s = Scanner::init(b"(0o9 \"a\\qb\")");
+    /// assert_eq!(s.try_scan(), Ok('(' as Token));
+    /// let err = s.try_scan().unwrap_err();
+    /// assert!(matches!(err, ScanError::InvalidDigit { .. }));
+    /// assert_eq!(err.to_string(), "<input>:1:2: invalid digit '9' in octal literal");
+    /// assert_eq!(s.token_text(), "0o9");
+    /// assert!(matches!(s.try_scan(), Err(ScanError::InvalidEscape { position, .. }) if position.column == 8));
+    /// assert_eq!(s.try_scan(), Ok(')' as Token));
+    /// ```
+    pub fn try_scan(&mut self) -> Result<Token, ScanError> {
+        if let Some(ext) = self.ext.as_mut() {
+            ext.first_error = None;
         }
+        let tok = self.scan();
+        match self.ext.as_mut().and_then(|ext| ext.first_error.take()) {
+            Some(err) => Err(err),
+            None => Ok(tok),
+        }
+    }
 
-        // Reset token text position
-        self.tok_pos = -1;
-        self.position.line = 0;
+    /// Scans and returns the next token like `scan()`, as a `TokenKind`.
+    pub fn scan_kind(&mut self) -> TokenKind {
+        TokenKind::try_from(self.scan()).expect("the scanner returns valid tokens")
+    }
+
+    /// Scans and returns the next token that is not trivia, as configured
+    /// with `set_trivia()` (comments, white space and prose by default).
+    /// Unlike skipping by mode, this lets the same scanner return trivia
+    /// to callers of `scan()` and skip it for callers of this method.
+    pub fn next_significant(&mut self) -> Token {
+        loop {
+            let tok = self.scan();
+            if tok >= 0 || (self.trivia & (1 << -tok)) == 0 {
+                return tok;
+            }
+        }
+    }
 
-        // Skip white space
-        let mut ch_u32 = ch_char as u32;
-        while ch_u32 < 64 && (self.whitespace & (1 << ch_u32)) != 0 {
+    /// Scans and returns the next token like `scan()`, with the given
+    /// options applied to this call only. Tokens already scanned ahead
+    /// (e.g. by `eat()`) are returned unchanged.
+    pub fn scan_with(&mut self, opts: &ScanOpts) -> Token {
+        self.detached = None;
+        if !self.ahead.is_empty() {
+            return self.scan();
+        }
+        self.replay = None;
+        let mode = self.mode;
+        self.mode = (mode | opts.set) & !opts.clear;
+        let tok = if opts.capture_line { self.scan_capture() } else { self.scan_raw() };
+        self.mode = mode;
+        tok
+    }
+
+    // Scans the rest of the current line as a CAPTURE token.
+    fn scan_capture(&mut self) -> Token {
+        let mut ch = self.peek_next();
+        while ch >= 0 && ch != '\n' as i32 && self.is_whitespace(char::from_u32(ch as u32).unwrap_or('\0')) {
             let next = self.next();
-            if next == '\u{FFFF}' {
-                return EOF;
+            ch = self.char_to_token(next);
+        }
+        self.ch = ch;
+        if ch == EOF || ch == '\n' as i32 {
+            return self.scan_raw();
+        }
+
+        self.start_token();
+        let mut next = self.next();
+        while next != '\n' && next != '\u{FFFF}' {
+            next = self.next();
+        }
+        self.ch = self.char_to_token(next);
+        self.tok_end = self.last_char_pos();
+        self.finish_token(CAPTURE)
+    }
+
+    fn scan_raw(&mut self) -> Token {
+        let tok = self.scan_token();
+        self.finish_token(tok)
+    }
+
+    // Records the token just scanned and checks it.
+    fn finish_token(&mut self, mut tok: Token) -> Token {
+        self.normalized = None;
+        if (tok == IDENT || tok == KEYWORD) && self.ext.as_ref().is_some_and(|ext| ext.max_ident_length != usize::MAX) {
+            tok = self.check_ident_length(tok);
+        }
+        self.tok = tok;
+        debug_assert!(tok == EOF || self.token_len() > 0, "empty {} token", token_string(tok));
+        if (self.lints & TOKEN_LINTS) != 0 && tok != EOF {
+            self.lint_token(tok);
+        } else if (self.lints & LINT_SUSPICIOUS_DELIMITERS) != 0 && tok == EOF {
+            self.lint_open_brackets();
+        }
+        if let Some((_, f)) = self.ext.as_ref().and_then(|ext| ext.normalizers.iter().find(|(k, _)| *k == tok)) {
+            let text = match self.normalized.take() {
+                Some(text) => f(&text),
+                None => f(&self.raw_token_str()),
+            };
+            self.normalized = Some(text);
+        }
+        if self.summary || (self.lints & (LINT_UNBALANCED_DELIMITERS | LINT_DEEP_NESTING)) != 0 {
+            self.tally_token(tok);
+        }
+        self.leading_comment = None;
+        if tok == COMMENT {
+            self.note_comment(self.token_span());
+        } else if tok == DOCUMENT_BOUNDARY {
+            self.comment_block = None;
+        } else if !is_trivia(tok) && tok != EOF {
+            self.leading_comment = self.comment_block.take().map(|span| LeadingComment {
+                blank_lines: self.position.line.saturating_sub(span.end.line + 1),
+                span,
+            });
+        }
+        if !is_trivia(tok) && tok != EOF && tok != DOCUMENT_BOUNDARY {
+            self.last_significant_line = self.end_line();
+        }
+        if tok != EOF && self.ext.as_ref().is_some_and(|ext| ext.token_hook.is_some()) {
+            let span = self.token_span();
+            if let Some(hook) = self.ext.as_mut().and_then(|ext| ext.token_hook.as_mut()) {
+                hook(tok, &span);
+            }
+        }
+        tok
+    }
+
+    // Checks the length of the identifier or keyword just scanned, and
+    // returns the token it is returned as.
+    #[cold]
+    fn check_ident_length(&mut self, tok: Token) -> Token {
+        let len = self.token_char_len();
+        let ext = self.ext_mut();
+        let (max, policy) = (ext.max_ident_length, ext.ident_length_policy);
+        if len <= max {
+            return tok;
+        }
+        let what = if tok == IDENT { "identifier" } else { "keyword" };
+        let msg = format!("{} is {} characters long (maximum is {})", what, len, max);
+        self.error(DiagnosticCode::IdentTooLong, &msg);
+        match policy {
+            IdentLengthPolicy::Truncate => {
+                self.normalized = Some(self.raw_token_str().chars().take(max).collect());
+                tok
+            }
+            IdentLengthPolicy::Error => ERROR,
+        }
+    }
+
+    // Adds a comment, skipped or not, to the block of full-line comments
+    // preceding the next significant token. A trailing comment ends it.
+    fn note_comment(&mut self, span: Span) {
+        if self.last_significant_line == span.start.line {
+            self.comment_block = None;
+            return;
+        }
+        match &mut self.comment_block {
+            Some(block) if block.end.line + 1 == span.start.line => block.end = span.end,
+            _ => self.comment_block = Some(span),
+        }
+    }
+
+    // Adds the token just scanned to the tally of the summary, and tracks
+    // the brackets left open for the summary and the delimiter lints.
+    #[cold]
+    fn tally_token(&mut self, tok: Token) {
+        if self.summary {
+            let tally = &mut self.ext_mut().tally;
+            if tok == EOF {
+                tally.eof = true;
+                return;
+            }
+            *tally.tokens.entry(tok).or_insert(0) += 1;
+        } else if tok == EOF {
+            return;
+        }
+        match self.delimiter_role(tok) {
+            Some(Delimiter::Open) => {
+                let pair = if tok == IDENT {
+                    let text = self.raw_token_str();
+                    self.delimiter_pairs().iter().position(|(open, _)| *open == text && text != "#{")
+                } else {
+                    None
+                };
+                let span = self.token_span();
+                self.ext_mut().tally.open.push(OpenDelimiter { tok, pair, span });
+            }
+            Some(Delimiter::Close) => {
+                self.ext_mut().tally.open.pop();
+            }
+            None => {}
+        }
+    }
+
+    /// Returns a description of the configuration of the scanner; see
+    /// `Capabilities`.
+    pub fn capabilities(&self) -> Capabilities {
+        Capabilities::new(self)
+    }
+
+    /// Returns a checkpoint of the scanning state, to rewind the scanner
+    /// to it with `rewind()`; see `ScannerCheckpoint`.
+    pub fn checkpoint(&self) -> ScannerCheckpoint {
+        let ext = self.ext.as_deref();
+        ScannerCheckpoint {
+            src_read_pos: self.src_read_pos,
+            src_pos: self.src_pos,
+            src_end: self.src_end,
+            src_buf_offset: self.src_buf_offset,
+            line: self.line,
+            column: self.column,
+            last_line_len: self.last_line_len,
+            last_char_len: self.last_char_len,
+            tok_buf_len: self.tok_buf.len(),
+            tok_pos: self.tok_pos,
+            tok_end: self.tok_end,
+            ch: self.ch,
+            tok: self.tok,
+            last_significant_line: self.last_significant_line,
+            leading_comment: self.leading_comment.clone(),
+            comment_block: self.comment_block.clone(),
+            ahead: self.ahead.clone(),
+            replay: self.replay.clone(),
+            ahead_start: self.ahead_start.clone(),
+            ahead_scanned: self.ahead_scanned,
+            detached: self.detached.clone(),
+            error_count: self.error_count,
+            diagnostics_len: self.diagnostics.len(),
+            first_error: ext.and_then(|ext| ext.first_error.clone()),
+            tally: ext.map_or_else(Tally::new, |ext| ext.tally.clone()),
+            nesting_depth: ext.map_or(0, |ext| ext.nesting_depth),
+            open_brackets: ext.map_or_else(Vec::new, |ext| ext.open_brackets.clone()),
+            line_lint: ext.map_or_else(LineLint::new, |ext| ext.line_lint.clone()),
+            normalized: self.normalized.clone(),
+            chunked_reader: ext.and_then(|ext| ext.chunked_reader.clone()),
+            chunks: ext.and_then(|ext| ext.recording.as_ref()).map_or(0, |log| log.chunks.len()),
+            bom: self.bom,
+            #[cfg(feature = "pages")]
+            page: self.page,
+            #[cfg(feature = "pages")]
+            page_break: self.page_break,
+            position: self.position.clone(),
+        }
+    }
+
+    /// Rewinds the scanner to a checkpoint taken with `checkpoint()`: the
+    /// current token and the following ones are those at the checkpoint,
+    /// and the diagnostics reported since are dropped. The checkpoint must
+    /// have been taken from this scanner, and the configuration should be
+    /// the same.
+    pub fn rewind(&mut self, checkpoint: ScannerCheckpoint) {
+        // The buffer holds the last bytes read from the source
+        let start = checkpoint.src_read_pos - checkpoint.src_end;
+        self.src_buf[..checkpoint.src_end].copy_from_slice(&self.src[start..checkpoint.src_read_pos]);
+        self.src_buf[checkpoint.src_end] = 128;
+        self.src_read_pos = checkpoint.src_read_pos;
+        self.src_pos = checkpoint.src_pos;
+        self.src_end = checkpoint.src_end;
+        self.src_buf_offset = checkpoint.src_buf_offset;
+        self.line = checkpoint.line;
+        self.column = checkpoint.column;
+        self.last_line_len = checkpoint.last_line_len;
+        self.last_char_len = checkpoint.last_char_len;
+        // The text of a token spanning refills ends at the start of the buffer
+        self.tok_buf.clear();
+        self.tok_buf.extend_from_slice(&self.src[start - checkpoint.tok_buf_len..start]);
+        self.tok_pos = checkpoint.tok_pos;
+        self.tok_end = checkpoint.tok_end;
+        self.ch = checkpoint.ch;
+        self.tok = checkpoint.tok;
+        self.last_significant_line = checkpoint.last_significant_line;
+        self.leading_comment = checkpoint.leading_comment;
+        self.comment_block = checkpoint.comment_block;
+        self.ahead = checkpoint.ahead;
+        self.replay = checkpoint.replay;
+        self.ahead_start = checkpoint.ahead_start;
+        self.ahead_scanned = checkpoint.ahead_scanned;
+        self.detached = checkpoint.detached;
+        self.error_count = checkpoint.error_count;
+        self.diagnostics.truncate(checkpoint.diagnostics_len);
+        self.normalized = checkpoint.normalized;
+        // Without extensions now, there were none at the checkpoint either
+        if let Some(ext) = self.ext.as_mut() {
+            ext.first_error = checkpoint.first_error;
+            ext.tally = checkpoint.tally;
+            ext.nesting_depth = checkpoint.nesting_depth;
+            ext.open_brackets = checkpoint.open_brackets;
+            ext.line_lint = checkpoint.line_lint;
+            ext.chunked_reader = checkpoint.chunked_reader;
+            if let Some(log) = ext.recording.as_mut() {
+                log.chunks.truncate(checkpoint.chunks);
+            }
+        }
+        self.bom = checkpoint.bom;
+        #[cfg(feature = "pages")]
+        {
+            self.page = checkpoint.page;
+            self.page_break = checkpoint.page_break;
+        }
+        self.position = checkpoint.position;
+    }
+
+    /// Returns the summary of the scan once EOF has been returned, or None
+    /// before or if the tally is off; see `set_summary()` and
+    /// `ScanSummary`.
+    pub fn eof_summary(&self) -> Option<ScanSummary> {
+        let tally = &self.ext.as_ref()?.tally;
+        if self.tok != EOF || !tally.eof {
+            return None;
+        }
+        let end = self.pos();
+        Some(ScanSummary {
+            lines: if end.column > 1 { end.line } else { end.line - 1 },
+            bytes: end.offset - self.base_offset,
+            tokens: tally.tokens.clone(),
+            unclosed: tally
+                .open
+                .iter()
+                .map(|t| SpannedToken { tok: t.tok, text: self.open_text(t), span: t.span.clone() })
+                .collect(),
+            unterminated: tally.unterminated.clone(),
+        })
+    }
+
+    // Scans ahead until there are at least n buffered tokens, keeping the
+    // current token as the visible one.
+    fn fill_ahead(&mut self, n: usize) {
+        if self.ahead.len() >= n {
+            return;
+        }
+        if self.ahead.is_empty() {
+            self.ahead_start = Some(Rc::new(self.checkpoint()));
+            self.ahead_scanned = 0;
+        }
+        self.ahead_scanned += n - self.ahead.len();
+        let current = match self.replay.take().or(self.detached.take()) {
+            Some(t) => t,
+            None => self.current_scanned(),
+        };
+        let last_significant_line = self.last_significant_line;
+        let leading_comment = self.leading_comment.take();
+        while self.ahead.len() < n {
+            match self.ahead.back() {
+                Some(t) if t.token.tok == EOF => {
+                    let eof = Rc::clone(t);
+                    self.ahead.push_back(eof);
+                }
+                _ => {
+                    self.scan_raw();
+                    self.ahead.push_back(Rc::new(Scanned {
+                        token: self.current_spanned(),
+                        last_significant_line: self.last_significant_line,
+                        leading_comment: self.leading_comment.take(),
+                    }));
+                }
+            }
+        }
+        self.tok = current.token.tok;
+        self.position = current.token.span.start.clone();
+        self.last_significant_line = last_significant_line;
+        self.leading_comment = leading_comment;
+        self.replay = Some(current);
+    }
+
+    fn current_spanned(&self) -> SpannedToken {
+        SpannedToken {
+            tok: self.tok,
+            text: self.token_text(),
+            span: self.token_span(),
+        }
+    }
+
+    // Returns the current token, to hold once it is no longer in the buffer.
+    fn current_scanned(&self) -> Rc<Scanned> {
+        Rc::new(Scanned {
+            token: self.current_spanned(),
+            last_significant_line: self.last_significant_line,
+            leading_comment: self.leading_comment.clone(),
+        })
+    }
+
+    /// Returns the next token, with its text and span, without consuming
+    /// it: it is returned again by the following `scan()`. The current
+    /// token doesn't change.
+    ///
+    /// ```
+    /// use scanner::*;
+    ///
+    /// let mut s = Scanner::init(b"(a b)");
+    /// assert_eq!(s.scan(), '(' as Token);
+    /// let next = s.peek_token();
+    /// assert_eq!((next.tok, next.text.as_str(), next.span.start.column), (IDENT, "a", 2));
+    /// assert_eq!(s.token_text(), "(");
+    /// assert_eq!(s.scan(), IDENT);
+    /// assert_eq!(s.token_text(), "a");
+    /// ```
+    pub fn peek_token(&mut self) -> &SpannedToken {
+        self.fill_ahead(1);
+        &self.ahead[0].token
+    }
+
+    /// Returns the next `k` tokens, with their texts and spans, without
+    /// consuming them, for LL(k) parsers. They are buffered and returned
+    /// again by the following scans; past the end of the input the EOF
+    /// token is repeated. The current token doesn't change.
+    ///
+    /// ```
+    /// use scanner::*;
+    ///
+    /// let mut s = Scanner::init(b"(a b)");
+    /// let next: Vec<&str> = s.lookahead(3).map(|t| t.text.as_str()).collect();
+    /// assert_eq!(next, ["(", "a", "b"]);
+    /// assert_eq!(s.lookahead(6).last().unwrap().tok, EOF);
+    /// assert_eq!(s.scan(), '(' as Token);
+    /// assert_eq!(s.lookahead(1).next().unwrap().text, "a");
+    /// ```
+    pub fn lookahead(&mut self, k: usize) -> impl Iterator<Item = &SpannedToken> {
+        self.fill_ahead(k);
+        self.ahead.iter().take(k).map(|t| &t.token)
+    }
+
+    /// Consumes and returns the next token if it is of the given kind or
+    /// character. Otherwise the token is not consumed and None is returned.
+    pub fn eat(&mut self, kind: Token) -> Option<SpannedToken> {
+        if self.peek_token().tok != kind {
+            return None;
+        }
+        Some(self.scan_spanned())
+    }
+
+    /// Consumes and returns the next token, which must be of the given kind
+    /// or character. Otherwise the token is not consumed and an error
+    /// describing the token found is returned.
+    pub fn expect(&mut self, kind: Token) -> Result<SpannedToken, ScanError> {
+        match self.eat(kind) {
+            Some(t) => Ok(t),
+            None => Err(ScanError::Unexpected {
+                expected: kind,
+                found: Box::new(self.peek_token().clone()),
+            }),
+        }
+    }
+
+    /// Returns an iterator over the remaining tokens, up to EOF.
+    pub fn tokens(&mut self) -> Tokens<'_, 'a, N> {
+        Tokens::new(self)
+    }
+
+    /// Returns an iterator over the remaining tokens, up to EOF, with their
+    /// texts and spans; the same as `tokens().spanned()`.
+    pub fn iter(&mut self) -> Spanned<'_, 'a, N> {
+        self.tokens().spanned()
+    }
+
+    /// Returns an iterator over the remaining characters, up to EOF, with
+    /// their locations, bypassing tokenization.
+    pub fn chars(&mut self) -> Chars<'_, 'a, N> {
+        Chars::new(self)
+    }
+
+    /// Returns the span of the most recently scanned token; at EOF it is
+    /// the empty span at the end of the source.
+    pub fn token_span(&self) -> Span {
+        match self.held() {
+            Some(t) => t.span.clone(),
+            None => {
+                let start = if self.tok == EOF { self.pos() } else { self.position.clone() };
+                Span { start, end: self.pos() }
             }
-            ch_char = next;
-            ch_u32 = next as u32;
-            ch = next as i32;
         }
+    }
 
-        // Start collecting token text
+    /// Skips the next form, e.g. the one commented out by a DATUM_COMMENT:
+    /// a token, or a delimited form up to its closing delimiter. Trivia
+    /// (see `set_trivia()`) and the forms commented out by datum comments
+    /// before it are skipped too, as well as the form following a prefix
+    /// such as QUOTE. Returns the span of the form, or None if
+    /// the input ends or a closing delimiter comes first, which is then
+    /// not consumed.
+    ///
+    /// ```
+    /// use scanner::*;
+    ///
+    /// let mut s = Scanner::init(b"(a #_ (b [c]) d #_ #_ e f)");
+    /// s.set_mode(LISP_TOKENS | SCAN_DATUM_COMMENTS);
+    /// let mut forms = Vec::new();
+    /// loop {
+    ///     match s.scan() {
+    ///         EOF => break,
+    ///         DATUM_COMMENT => _ = s.skip_form(),
+    ///         _ => forms.push(s.token_text()),
+    ///     }
+    /// }
+    /// assert_eq!(forms, ["(", "a", "d", ")"]);
+    /// ```
+    pub fn skip_form(&mut self) -> Option<Span> {
+        let mut closers: Vec<String> = Vec::new();
+        let mut span: Option<Span> = None;
+        loop {
+            let t = self.peek_token();
+            let (tok, text) = (t.tok, t.text.clone());
+            let closes = tok == ')' as Token
+                || tok == ']' as Token
+                || tok == '}' as Token
+                || (tok == IDENT && closers.last() == Some(&text));
+            if tok == EOF || (closes && closers.is_empty()) {
+                return span;
+            }
+            let t = self.scan_spanned();
+            if tok < 0 && (self.trivia & (1 << -tok)) != 0 {
+                continue;
+            }
+            if tok == DATUM_COMMENT && span.is_none() {
+                self.skip_form();
+                continue;
+            }
+            match &mut span {
+                Some(span) => span.end = t.span.end,
+                None => span = Some(t.span),
+            }
+            if closes {
+                closers.pop();
+            } else if let Some(closer) = self.closer(tok, &text) {
+                closers.push(String::from(closer));
+            }
+            let prefix = matches!(tok, QUOTE | QUASIQUOTE | UNQUOTE | UNQUOTE_SPLICING | DEREF);
+            if closers.is_empty() && !prefix {
+                return span;
+            }
+        }
+    }
+
+    /// Scans and returns the next token with its text and span.
+    pub fn scan_spanned(&mut self) -> SpannedToken {
+        self.scan();
+        match &self.replay {
+            Some(t) => t.token.clone(),
+            None => self.current_spanned(),
+        }
+    }
+
+    /// Returns the placement of the most recently scanned token if it is a
+    /// comment, or None otherwise.
+    pub fn comment_info(&self) -> Option<CommentInfo> {
+        if self.tok != COMMENT {
+            return None;
+        }
+        let placement = if self.last_significant_line == self.position.line {
+            CommentPlacement::Trailing
+        } else {
+            CommentPlacement::FullLine
+        };
+        Some(CommentInfo {
+            placement,
+            column: self.position.column,
+        })
+    }
+
+    /// Returns the block of full-line comments on consecutive lines that
+    /// immediately precedes the most recently scanned token, if it is
+    /// significant, with the number of blank lines in between, e.g. for a
+    /// doc generator to tell whether the comment documents the token.
+    /// Comments are tracked both when skipped and when returned as tokens.
+    ///
+    /// ```
+    /// use scanner::*;
+    ///
+    /// let mut s = Scanner::init(b";; Adds numbers.\n;; Variadic.\n\n(defn add [& xs])");
+    /// s.scan();
+    /// let doc = s.leading_comment().unwrap();
+    /// assert_eq!((doc.span.start.line, doc.span.end.line, doc.blank_lines), (1, 2, 1));
+    /// s.scan();
+    /// assert!(s.leading_comment().is_none());
+    /// ```
+    pub fn leading_comment(&self) -> Option<&LeadingComment> {
+        self.leading_comment.as_ref()
+    }
+
+    // Starts collecting the token text at the current character and sets
+    // the token position.
+    fn start_token(&mut self) {
         self.tok_buf.clear();
-        self.tok_pos = (self.src_pos - self.last_char_len) as isize;
+        self.tok_pos = self.last_char_pos() as isize;
 
-        // Set token position
         self.position.offset = self.src_buf_offset + (self.tok_pos as usize);
-        if self.column > 0 {
+        #[cfg(feature = "pages")]
+        {
+            self.position.page = self.page;
+        }
+        if !self.track_positions {
+            self.position.line = 0;
+            self.position.column = 0;
+        } else if self.column > 0 {
             self.position.line = self.line;
             self.position.column = self.column;
         } else {
-            self.position.line = self.line - 1;
+            self.position.line = self.prev_line();
             self.position.column = self.last_line_len;
         }
+    }
+
+    // Reports whether the current character is the first of its line.
+    fn at_line_start(&self) -> bool {
+        self.column == 1 || (self.bom && self.line == 1 && self.column == 2)
+    }
+
+    // Returns the document delimiter set with set_document_delimiter().
+    fn document_delimiter(&self) -> Option<&str> {
+        self.ext.as_ref().and_then(|ext| ext.document_delimiter.as_deref())
+    }
+
+    // Returns the literate prefix set with set_literate_prefix().
+    fn literate_prefix(&self) -> Option<&str> {
+        self.ext.as_ref().and_then(|ext| ext.literate_prefix.as_deref())
+    }
+
+    // Reports whether the current character starts a document delimiter line.
+    fn at_document_delimiter(&self) -> bool {
+        let Some(delimiter) = self.document_delimiter() else {
+            return false;
+        };
+        if !self.at_line_start() {
+            return false;
+        }
+        let offset = self.char_location().0.saturating_sub(self.base_offset);
+        let rest = self.src.get(offset..).unwrap_or(&[]);
+        match rest.strip_prefix(delimiter.as_bytes()) {
+            Some(end) => end.is_empty() || end.starts_with(b"\n") || end.starts_with(b"\r\n"),
+            None => false,
+        }
+    }
+
+    // Scans the document delimiter line starting at the current character
+    // ch as a DOCUMENT_BOUNDARY token, and starts a new document.
+    fn scan_document_boundary(&mut self, ch: char) -> Token {
+        self.start_token();
+        let line = self.line;
+        let mut next = ch;
+        let len = self.document_delimiter().map_or(0, |d| d.chars().count());
+        for _ in 0..len {
+            next = self.next();
+        }
+        if next == '\r' {
+            next = self.next();
+        }
+        if next == '\n' {
+            next = self.next();
+        }
+        self.ch = self.char_to_token(next);
+        self.tok_end = self.last_char_pos();
+        let Some(ext) = self.ext.as_mut() else {
+            return DOCUMENT_BOUNDARY;
+        };
+        ext.nesting_depth = 0;
+        if ext.reset_lines_per_document {
+            // The line after the delimiter becomes line 1
+            self.line -= line;
+            self.last_significant_line = 0;
+        }
+        DOCUMENT_BOUNDARY
+    }
+
+    // Consumes the literate prefix starting at ch, returning whether the
+    // whole prefix matched and the first character not consumed.
+    fn match_literate_prefix(&mut self, mut ch: char) -> (bool, char) {
+        let Some(prefix) = self.ext.as_mut().and_then(|ext| ext.literate_prefix.take()) else {
+            return (true, ch);
+        };
+        let mut matched = true;
+        for pc in prefix.chars() {
+            if ch != pc {
+                matched = false;
+                break;
+            }
+            ch = self.next();
+        }
+        self.ext_mut().literate_prefix = Some(prefix);
+        (matched, ch)
+    }
+
+    // Buffer index of the last character read, i.e. of the character after
+    // the current token.
+    fn last_char_pos(&self) -> usize {
+        debug_assert!(self.src_pos >= self.last_char_len);
+        self.src_pos.saturating_sub(self.last_char_len)
+    }
+
+    // Line before the current one, where the last character read is when
+    // it is a newline.
+    fn prev_line(&self) -> usize {
+        debug_assert!(self.line > 1);
+        self.line.saturating_sub(1).max(1)
+    }
+
+    // Line of the character immediately after the last scanned token.
+    fn end_line(&self) -> usize {
+        if self.column > 0 || !self.track_positions {
+            self.line
+        } else {
+            self.prev_line()
+        }
+    }
+
+    fn scan_token(&mut self) -> Token {
+        // Skipped constructs are scanned past in a loop, as a long run of
+        // them would overflow the stack by recursion
+        loop {
+            if let Some(tok) = self.scan_token_or_skip() {
+                return tok;
+            }
+        }
+    }
+
+    // Scans the next token, or returns None when it skips a comment or a
+    // disabled literal, which is followed by the next token.
+    fn scan_token_or_skip(&mut self) -> Option<Token> {
+        let mode = self.mode;
+        let mut ch = self.peek_next();
+
+        // Reset token text position
+        self.tok_pos = -1;
+        self.position.line = 0;
+
+        if ch == EOF {
+            return Some(EOF);
+        }
+
+        let mut ch_char = char::from_u32(ch as u32).unwrap_or('\u{FFFF}');
+        if ch_char == '\u{FFFF}' {
+            return Some(EOF);
+        }
+
+        // Skip white space, and prose lines in literate mode
+        loop {
+            if self.document_delimiter().is_some() && self.at_document_delimiter() {
+                return Some(self.scan_document_boundary(ch_char));
+            }
+
+            if self.literate_prefix().is_some() && ch_char != '\n' && self.at_line_start() {
+                self.start_token();
+                let (matched, next) = self.match_literate_prefix(ch_char);
+                if !matched {
+                    let end = if next == '\n' { next } else { self.scan_comment(next) };
+                    self.ch = self.char_to_token(end);
+                    self.tok_end = self.last_char_pos();
+                    return Some(PROSE);
+                }
+                self.tok_pos = -1;
+                self.position.line = 0;
+                if next == '\u{FFFF}' {
+                    return Some(EOF);
+                }
+                ch_char = next;
+                ch = next as i32;
+            }
+
+            if (mode & SCAN_WHITESPACE) != 0 && self.is_whitespace(ch_char) {
+                self.start_token();
+                let mut next = self.next();
+                while self.is_whitespace(next)
+                    && !(self.literate_prefix().is_some() && self.at_line_start())
+                    && !self.at_document_delimiter()
+                {
+                    next = self.next();
+                }
+                self.ch = self.char_to_token(next);
+                self.tok_end = self.last_char_pos();
+                return Some(WHITESPACE);
+            }
+
+            if self.is_whitespace(ch_char) {
+                let next = self.next();
+                if next == '\u{FFFF}' {
+                    return Some(EOF);
+                }
+                ch_char = next;
+                ch = next as i32;
+                continue;
+            }
+            break;
+        }
+
+        self.start_token();
+
+        if (mode & SCAN_BLOCK_COMMENTS) != 0 && ch_char == '#' && self.at_text("#|") {
+            let skip = (mode & SKIP_COMMENTS) != 0;
+            // Even a skipped comment may be returned, for the caller to resume
+            if skip && self.eof_policy == EofPolicy::Error {
+                self.tok_pos = -1;
+            }
+            let new_ch = self.scan_block_comment();
+            self.ch = self.char_to_token(new_ch);
+            if self.need_more_input {
+                self.need_more_input = false;
+                self.tok_end = self.last_char_pos();
+                return Some(NEED_MORE_INPUT);
+            }
+            if skip {
+                let span = Span { start: self.position.clone(), end: self.pos() };
+                self.note_comment(span);
+                return None;
+            }
+            self.tok_end = self.last_char_pos();
+            return Some(COMMENT);
+        }
+
+        if ((mode & SCAN_COMMENTS) != 0 || self.disabled_policy != DisabledPolicy::Chars)
+            && let Some(len) = self.match_line_comment(ch_char)
+        {
+            return self.scan_line_comment_token(mode, ch_char, len);
+        }
+
+        if !self.delimiter_pairs().is_empty()
+            && (mode & SCAN_IDENTS) != 0
+            && let Some(len) = self.match_longest(
+                ch_char,
+                self.delimiter_pairs().iter().flat_map(|(open, close)| [open.as_str(), close.as_str()]),
+            )
+        {
+            let mut next = ch_char;
+            for _ in 0..len {
+                next = self.next();
+            }
+            self.ch = self.char_to_token(next);
+            self.tok_end = self.last_char_pos();
+            return Some(IDENT);
+        }
 
         // Determine token value
         let mut tok = ch;
 
         if self.is_ident_rune_check(ch_char, 0) {
-            if (self.mode & SCAN_IDENTS) != 0 {
+            if (mode & SCAN_IDENTS) != 0 {
                 tok = IDENT;
                 let new_ch = self.scan_identifier();
                 self.ch = self.char_to_token(new_ch);
@@ -761,7 +3220,7 @@ impl<'a> Scanner<'a> {
                 self.ch = self.char_to_token(ch);
             }
         } else if Self::is_decimal(ch_char) {
-            if (self.mode & (SCAN_INTS | SCAN_FLOATS)) != 0 {
+            if (mode & (SCAN_INTS | SCAN_FLOATS)) != 0 {
                 let (new_tok, new_ch) = self.scan_number(ch_char, false, false);
                 tok = new_tok;
                 self.ch = self.char_to_token(new_ch);
@@ -772,20 +3231,20 @@ impl<'a> Scanner<'a> {
         } else if ch_char == '-' {
             let next_ch = self.next();
             if self.is_ident_rune_check(next_ch, 0) {
-                if (self.mode & SCAN_IDENTS) != 0 {
+                if (mode & SCAN_IDENTS) != 0 {
                     tok = IDENT;
                     let new_ch = self.scan_identifier();
                     self.ch = self.char_to_token(new_ch);
                 }
             } else if Self::is_decimal(next_ch) {
-                if (self.mode & (SCAN_INTS | SCAN_FLOATS)) != 0 {
+                if (mode & (SCAN_INTS | SCAN_FLOATS)) != 0 {
                     let (new_tok, new_ch) = self.scan_number(next_ch, false, true);
                     tok = new_tok;
                     self.ch = self.char_to_token(new_ch);
                 }
             } else {
                 // Bare "-" identifier
-                if (self.mode & SCAN_IDENTS) != 0 {
+                if (mode & SCAN_IDENTS) != 0 {
                     tok = IDENT;
                 }
                 self.ch = self.char_to_token(next_ch);
@@ -795,27 +3254,35 @@ impl<'a> Scanner<'a> {
                 '\u{FFFF}' => {
                     // EOF already handled
                 }
+                c if (c == '"' || c == self.raw_string_delimiters.0)
+                    && self.disabled_policy != DisabledPolicy::Chars
+                    && (mode & disabled_bit(ch_char)) == 0 =>
+                {
+                    match self.scan_disabled(ch_char) {
+                        Some(t) => tok = t,
+                        None => return None,
+                    }
+                }
                 '"' => {
-                    if (self.mode & SCAN_STRINGS) != 0 {
+                    if (mode & SCAN_STRINGS) != 0 {
                         self.scan_string('"');
                         tok = STRING;
                     }
                     let ch = self.next();
                     self.ch = self.char_to_token(ch);
                 }
-                ':' => {
-                    if (self.mode & SCAN_KEYWORDS) != 0 {
-                        tok = KEYWORD;
-                        let new_ch = self.scan_identifier();
-                        self.ch = self.char_to_token(new_ch);
+                ':' if (mode & SCAN_KEYWORDS) != 0 => {
+                    tok = KEYWORD;
+                    let new_ch = if self.namespaced_keywords {
+                        self.scan_namespaced_keyword()
                     } else {
-                        let ch = self.next();
-                        self.ch = self.char_to_token(ch);
-                    }
+                        self.scan_identifier()
+                    };
+                    self.ch = self.char_to_token(new_ch);
                 }
                 '.' => {
                     let next_ch = self.next();
-                    if Self::is_decimal(next_ch) && (self.mode & SCAN_FLOATS) != 0 {
+                    if Self::is_decimal(next_ch) && (mode & SCAN_FLOATS) != 0 {
                         let (new_tok, new_ch) = self.scan_number(next_ch, true, false);
                         tok = new_tok;
                         self.ch = self.char_to_token(new_ch);
@@ -823,35 +3290,28 @@ impl<'a> Scanner<'a> {
                         self.ch = self.char_to_token(next_ch);
                     }
                 }
-                ';' => {
-                    let next_ch = self.next();
-                    if (self.mode & SCAN_COMMENTS) != 0 {
-                        if (self.mode & SKIP_COMMENTS) != 0 {
-                            self.tok_pos = -1;
-                            let new_ch = self.scan_comment(next_ch);
-                            self.ch = self.char_to_token(new_ch);
-                            return self.scan(); // redo
-                        }
-                        let new_ch = self.scan_comment(next_ch);
-                        self.ch = self.char_to_token(new_ch);
-                        tok = COMMENT;
-                    } else {
-                        self.ch = self.char_to_token(next_ch);
-                    }
+                c if c == self.raw_string_delimiters.0 && (mode & SCAN_RAW_STRINGS) != 0 => {
+                    let new_ch = self.scan_raw_string();
+                    self.ch = self.char_to_token(new_ch);
+                    tok = RAW_STRING;
                 }
-                '¬' => {
-                    if (self.mode & SCAN_RAW_STRINGS) != 0 {
-                        let new_ch = self.scan_raw_string();
-                        self.ch = self.char_to_token(new_ch);
-                        tok = RAW_STRING;
-                    } else {
-                        let ch = self.next();
-                        self.ch = self.char_to_token(ch);
+                '\'' | '`' | '~' | '@' if (mode & SCAN_QUOTES) != 0 => {
+                    tok = match ch_char {
+                        '\'' => QUOTE,
+                        '`' => QUASIQUOTE,
+                        '~' => UNQUOTE,
+                        _ => DEREF,
+                    };
+                    let mut next_ch = self.next();
+                    if tok == UNQUOTE && next_ch == '@' {
+                        tok = UNQUOTE_SPLICING;
+                        next_ch = self.next();
                     }
+                    self.ch = self.char_to_token(next_ch);
                 }
                 '~' => {
                     let next_ch = self.next();
-                    if (self.mode & SCAN_IDENTS) != 0 {
+                    if (mode & SCAN_IDENTS) != 0 && (mode & SCAN_LISP_SPECIALS) != 0 {
                         if next_ch == '@' {
                             let ch = self.next();
                             self.ch = self.char_to_token(ch);
@@ -865,7 +3325,33 @@ impl<'a> Scanner<'a> {
                 }
                 '#' => {
                     let next_ch = self.next();
-                    if (self.mode & SCAN_IDENTS) != 0 {
+                    let dispatch = self.dispatch_macros().iter().find(|(c, _)| *c == next_ch).map(|&(_, d)| d);
+                    if let Some(dispatch) = dispatch {
+                        let new_ch = match dispatch {
+                            DispatchMacro::Token(kind) => {
+                                tok = kind;
+                                self.next()
+                            }
+                            DispatchMacro::Literal(kind) => {
+                                tok = kind;
+                                self.scan_literal_at(next_ch).unwrap_or_else(|| self.next())
+                            }
+                            DispatchMacro::Prefix(kind) => {
+                                tok = kind;
+                                let ch = self.next();
+                                self.scan_literal_at(ch).unwrap_or(ch)
+                            }
+                        };
+                        self.ch = self.char_to_token(new_ch);
+                    } else if next_ch == '\\' && (mode & SCAN_CHARS) != 0 {
+                        let new_ch = self.scan_char_literal();
+                        self.ch = self.char_to_token(new_ch);
+                        tok = CHAR;
+                    } else if next_ch == '_' && (mode & SCAN_DATUM_COMMENTS) != 0 {
+                        let ch = self.next();
+                        self.ch = self.char_to_token(ch);
+                        tok = DATUM_COMMENT;
+                    } else if (mode & SCAN_IDENTS) != 0 && (mode & SCAN_LISP_SPECIALS) != 0 {
                         if next_ch == '{' {
                             let ch = self.next();
                             self.ch = self.char_to_token(ch);
@@ -884,56 +3370,343 @@ impl<'a> Scanner<'a> {
             }
         }
 
+        if self.need_more_input {
+            self.need_more_input = false;
+            tok = NEED_MORE_INPUT;
+        }
+
         // End of token text
-        self.tok_end = self.src_pos - self.last_char_len;
+        self.tok_end = self.last_char_pos();
 
-        tok
+        Some(tok)
+    }
+
+    // Scans the string, raw string, number or identifier starting at the
+    // character just read, for a dispatch macro, returning the character
+    // following it, or None if no literal starts there.
+    fn scan_literal_at(&mut self, ch: char) -> Option<char> {
+        if ch == '"' {
+            self.scan_string('"');
+            Some(self.next())
+        } else if ch == self.raw_string_delimiters.0 {
+            Some(self.scan_raw_string())
+        } else if Self::is_decimal(ch) {
+            Some(self.scan_number(ch, false, false).1)
+        } else if self.is_ident_rune_check(ch, 0) {
+            Some(self.scan_identifier())
+        } else {
+            None
+        }
     }
 
     /// Returns the position of the character immediately after
     /// the character or token returned by the last call to next or scan.
     pub fn pos(&self) -> Position {
-        let mut pos = Position {
-            filename: self.position.filename.clone(),
-            offset: self.src_buf_offset + self.src_pos - self.last_char_len,
-            line: 0,
-            column: 0,
-        };
+        if let Some(t) = &self.replay {
+            return t.token.span.end.clone();
+        }
+        let (offset, line, column) = self.char_location();
+        Position {
+            source: self.position.source.clone(),
+            offset,
+            line,
+            column,
+            #[cfg(feature = "pages")]
+            page: self.page,
+        }
+    }
 
-        if self.column > 0 {
-            pos.line = self.line;
-            pos.column = self.column;
+    // Returns the offset, line and column of the last character read.
+    pub(crate) fn char_location(&self) -> (usize, usize, usize) {
+        let offset = self.src_buf_offset + self.last_char_pos();
+        if !self.track_positions {
+            (offset, 0, 0)
+        } else if self.column > 0 {
+            (offset, self.line, self.column)
         } else if self.last_line_len > 0 {
-            pos.line = self.line - 1;
-            pos.column = self.last_line_len;
+            (offset, self.prev_line(), self.last_line_len)
         } else {
-            pos.line = 1;
-            pos.column = 1;
+            (offset, self.line, 1)
         }
+    }
 
-        pos
+    /// Returns the name of the most recently scanned token if it is a
+    /// keyword, i.e. its text without the leading colons, or None otherwise.
+    pub fn keyword_name(&self) -> Option<String> {
+        if self.tok != KEYWORD {
+            return None;
+        }
+        Some(self.raw_token_str().trim_start_matches(':').to_string())
     }
 
-    /// Returns the string corresponding to the most recently scanned token.
-    pub fn token_text(&self) -> String {
+    /// Returns the parts of the most recently scanned token if it is a
+    /// keyword, e.g. the namespace `clojure.core` and name `map` of
+    /// `:clojure.core/map`, or None otherwise.
+    ///
+    /// ```
+    /// use scanner::*;
+    ///
+    /// let mut s = Scanner::init(b"::alias/thing");
+    /// s.set_namespaced_keywords(true);
+    /// s.scan();
+    /// let parts = s.keyword_parts().unwrap();
+    /// assert!(parts.auto_resolved);
+    /// assert_eq!((parts.namespace.as_deref(), parts.name.as_str()), (Some("alias"), "thing"));
+    /// ```
+    pub fn keyword_parts(&self) -> Option<KeywordParts> {
+        if self.tok != KEYWORD {
+            return None;
+        }
+        Some(literal::decode_keyword(&self.raw_token_str()))
+    }
+
+    /// Returns the number of lines spanned by the most recently scanned token.
+    pub fn token_line_count(&self) -> usize {
+        self.pos().line.saturating_sub(self.position.line) + 1
+    }
+
+    /// Returns the absolute byte range of the most recently scanned token in
+    /// the source, computed from the buffer bookkeeping rather than from
+    /// `position`, so it stays exact for tokens crossing buffer refills.
+    /// The range is empty if there is no current token.
+    pub fn token_byte_range(&self) -> Range<u64> {
+        if let Some(t) = self.held() {
+            return t.span.start.offset as u64..t.span.end.offset as u64;
+        }
         if self.tok_pos < 0 {
-            return String::new();
+            let offset = self.pos().offset as u64;
+            return offset..offset;
         }
+        let tok_pos = self.tok_pos as usize;
+        debug_assert!(self.src_buf_offset + tok_pos >= self.tok_buf.len());
+        let start = (self.src_buf_offset + tok_pos).saturating_sub(self.tok_buf.len());
+        let end = self.src_buf_offset + self.tok_end.max(tok_pos);
+        start as u64..end as u64
+    }
 
+    /// Returns the length in bytes of the most recently scanned token,
+    /// without materializing its text. It is zero only at EOF.
+    pub fn token_len(&self) -> usize {
+        let range = self.token_byte_range();
+        (range.end - range.start) as usize
+    }
+
+    /// Returns the length in characters of the most recently scanned token,
+    /// without materializing its text. It is zero only at EOF.
+    pub fn token_char_len(&self) -> usize {
+        if self.held().is_some() {
+            return self.raw_token_str().chars().count();
+        }
+        if self.tok_pos < 0 {
+            return 0;
+        }
         let tok_pos = self.tok_pos as usize;
-        let tok_end = if self.tok_end < tok_pos {
-            tok_pos
-        } else {
-            self.tok_end
+        let bytes = &self.src_buf[tok_pos..self.tok_end.max(tok_pos)];
+        // Both parts hold whole characters; invalid sequences are counted as
+        // the U+FFFD they become in the token text.
+        let count = |b: &[u8]| match str::from_utf8(b) {
+            Ok(s) => s.chars().count(),
+            Err(_) => String::from_utf8_lossy(b).chars().count(),
         };
+        count(&self.tok_buf) + count(bytes)
+    }
 
-        if self.tok_buf.is_empty() {
-            String::from_utf8_lossy(&self.src_buf[tok_pos..tok_end]).to_string()
-        } else {
-            let mut result = self.tok_buf.clone();
-            result.extend_from_slice(&self.src_buf[tok_pos..tok_end]);
-            String::from_utf8_lossy(&result).to_string()
+    /// Returns the value of the most recently scanned token if it is a
+    /// literal (an int, float, string, raw string, keyword, character or
+    /// ratio), or None otherwise or if the literal is malformed or its
+    /// value overflows.
+    ///
+    /// ```
+    /// use scanner::*;
+    ///
+    /// let mut s = Scanner::init(br#"(0x_ff 1.5e3 "a\tb" :key)"#);
+    /// let mut values = Vec::new();
+    /// while s.scan() != EOF {
+    ///     values.extend(s.token_value());
+    /// }
+    /// assert_eq!(
+    ///     values,
+    ///     [
+    ///         TokenValue::Int(255),
+    ///         TokenValue::Float(1500.0),
+    ///         TokenValue::String("a\tb".to_string()),
+    ///         TokenValue::Keyword("key".to_string()),
+    ///     ]
+    /// );
+    /// ```
+    pub fn token_value(&self) -> Option<TokenValue> {
+        match self.tok {
+            INT => literal::decode_int(&self.raw_token_str()).map(TokenValue::Int),
+            FLOAT => literal::decode_float(&self.raw_token_str()).map(TokenValue::Float),
+            STRING => self.string_value().map(TokenValue::String),
+            RAW_STRING => self.raw_string_value().map(TokenValue::String),
+            KEYWORD => self.keyword_name().map(TokenValue::Keyword),
+            CHAR => self.char_value().map(TokenValue::Char),
+            RATIO => self.ratio_value().map(|(n, d)| TokenValue::Ratio(n, d)),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of the most recently scanned token if it is a
+    /// string, with its escape sequences decoded, or None otherwise or if
+    /// the string is malformed.
+    pub fn string_value(&self) -> Option<String> {
+        if self.tok != STRING {
+            return None;
         }
+        literal::decode_string(&self.raw_token_str())
+    }
+
+    /// Returns the numerator and denominator of the most recently scanned
+    /// token if it is a ratio, as written, or None otherwise or if they
+    /// overflow.
+    pub fn ratio_value(&self) -> Option<(i128, i128)> {
+        if self.tok != RATIO {
+            return None;
+        }
+        let text = self.raw_token_str();
+        let (numerator, denominator) = text.split_once('/')?;
+        Some((literal::decode_int(numerator)?, literal::decode_int(denominator)?))
+    }
+
+    /// Returns the value of the most recently scanned token if it is a
+    /// character literal, or None otherwise or if its name is unknown.
+    pub fn char_value(&self) -> Option<char> {
+        if self.tok != CHAR {
+            return None;
+        }
+        literal::decode_char(&self.raw_token_str())
+    }
+
+    /// Returns the value of the most recently scanned token if it is a raw
+    /// string, i.e. its content without the `¬` delimiters and with each
+    /// doubled `¬¬` collapsed to a single `¬` (or the delimiters set with
+    /// `set_raw_string_delimiters()`), or None otherwise or if the raw
+    /// string is unterminated.
+    pub fn raw_string_value(&self) -> Option<String> {
+        if self.tok != RAW_STRING {
+            return None;
+        }
+        let (open, close) = self.raw_string_delimiters;
+        literal::decode_raw_string_with(&self.raw_token_str(), open, close)
+    }
+
+    /// Returns up to `n` bytes of the input following the current token,
+    /// for diagnostics such as explaining an unexpected token. Tokens
+    /// scanned ahead (e.g. by `eat()`) are included, as they weren't
+    /// consumed. The text is cut at a character boundary and isn't a
+    /// token: it may end in the middle of one.
+    pub fn peek_text(&self, n: usize) -> Cow<'_, str> {
+        let start = match &self.replay {
+            Some(t) => t.token.span.end.offset,
+            None if self.ch == -2 => self.base_offset,
+            None if self.ch == EOF => return Cow::Borrowed(""),
+            None => self.src_buf_offset + self.last_char_pos(),
+        } - self.base_offset;
+        let mut end = (start + n).min(self.src.len());
+        while end > start && end < self.src.len() && (self.src[end] & 0xC0) == 0x80 {
+            end -= 1;
+        }
+        String::from_utf8_lossy(&self.src[start..end])
+    }
+
+    // Returns the current token when it is no longer in the buffer.
+    fn held(&self) -> Option<&SpannedToken> {
+        self.replay.as_ref().or(self.detached.as_ref()).map(|t| &t.token)
+    }
+
+    /// Returns the string corresponding to the most recently scanned token.
+    /// It is a new String on every call; `token_str()` and
+    /// `token_source_str()` borrow the text instead.
+    ///
+    /// The text is available until the next token is scanned, by `scan()`
+    /// or any of the methods calling it; peeking and reading characters
+    /// with `next_char()` don't change the current token.
+    pub fn token_text(&self) -> String {
+        self.token_str().into_owned()
+    }
+
+    /// Returns the text of the most recently scanned token borrowed from
+    /// the source when possible. The borrow ends the availability window of
+    /// the text described in `token_text()`, as the scanner can't be
+    /// advanced while it is held.
+    ///
+    /// ```compile_fail
+    /// let mut s = scanner::Scanner::init(b"a b");
+    /// s.scan();
+    /// let text = s.token_str();
+    /// s.scan(); // error: the text is still borrowed
+    /// assert_eq!(text, "a");
+    /// ```
+    pub fn token_str(&self) -> Cow<'_, str> {
+        if let Some(t) = self.held() {
+            return Cow::Borrowed(&t.text);
+        }
+        if let Some(text) = &self.normalized {
+            return Cow::Borrowed(text);
+        }
+        self.raw_token_str()
+    }
+
+    /// Returns the source text of a span of this scanner, or None if it is
+    /// out of the source or isn't valid UTF-8. The scanner always holds its
+    /// whole source, so the text of any token, diagnostic or other span
+    /// can be quoted, also after further tokens have been scanned.
+    ///
+    /// ```
+    /// use scanner::*;
+    ///
+    /// let mut s = Scanner::init(b"(def a\n  (f 1))");
+    /// let start = s.scan_spanned().span.start;
+    /// while s.scan() != INT {}
+    /// let span = Span { start, end: s.pos() };
+    /// s.scan();
+    /// assert_eq!(s.slice(&span), Some("(def a\n  (f 1"));
+    /// ```
+    pub fn slice(&self, span: &Span) -> Option<&'a str> {
+        let start = span.start.offset.checked_sub(self.base_offset)?;
+        let end = span.end.offset.checked_sub(self.base_offset)?;
+        str::from_utf8(self.src.get(start..end)?).ok()
+    }
+
+    /// Returns the source bytes of the most recently scanned token, before
+    /// any transform set with `set_text_normalizer()`.
+    pub fn token_bytes(&self) -> &'a [u8] {
+        if self.held().is_none() && self.tok_pos < 0 {
+            return &[];
+        }
+        let range = self.token_byte_range();
+        let start = (range.start as usize).saturating_sub(self.base_offset);
+        let end = (range.end as usize).saturating_sub(self.base_offset);
+        self.src.get(start..end).unwrap_or(&[])
+    }
+
+    /// Returns the source text of the most recently scanned token, before
+    /// any transform set with `set_text_normalizer()`, like
+    /// `token_bytes()`. Unless the token has invalid UTF-8 sequences, it is
+    /// borrowed from the source for as long as the source lives, not just
+    /// until the next token is scanned, so the texts of a whole scan can be
+    /// collected without copying them.
+    ///
+    /// ```
+    /// use scanner::*;
+    ///
+    /// let src = String::from("(def greeting \"hi\")");
+    /// let mut s = Scanner::init(src.as_bytes());
+    /// let mut texts = Vec::new();
+    /// while s.scan() != EOF {
+    ///     texts.push(s.token_source_str());
+    /// }
+    /// assert_eq!(texts, ["(", "def", "greeting", "\"hi\"", ")"]);
+    /// assert!(texts.iter().all(|t| matches!(t, std::borrow::Cow::Borrowed(_))));
+    /// ```
+    pub fn token_source_str(&self) -> Cow<'a, str> {
+        self.raw_token_str()
+    }
+
+    // Returns the source text of the most recently scanned token.
+    fn raw_token_str(&self) -> Cow<'a, str> {
+        String::from_utf8_lossy(self.token_bytes())
     }
 }
 
@@ -944,18 +3717,18 @@ mod tests {
     #[test]
     fn test_position_is_valid() {
         let pos = Position {
-            filename: "test.lisp".to_string(),
-            offset: 0,
+            source: SourceId::from("test.lisp"),
             line: 1,
             column: 1,
+            ..Default::default()
         };
         assert!(pos.is_valid());
 
         let invalid_pos = Position {
-            filename: "test.lisp".to_string(),
-            offset: 0,
+            source: SourceId::from("test.lisp"),
             line: 0,
             column: 0,
+            ..Default::default()
         };
         assert!(!invalid_pos.is_valid());
     }
@@ -0,0 +1,395 @@
+// Copyright 2022 Jordi Íñigo Griera. All rights reserved.
+
+//! Decoding of literal token texts into their values.
+
+use core::fmt;
+use core::num::IntErrorKind;
+use alloc::string::String;
+
+use crate::{Position, Scanner, Span};
+
+/// TokenValue is the value of a literal token, as returned by
+/// `Scanner::token_value()`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenValue {
+    /// The value of an INT, honoring its radix prefix and `_` separators.
+    Int(i128),
+    Float(f64),
+    /// A STRING with its escape sequences decoded, or the content of a
+    /// RAW_STRING.
+    String(String),
+    /// The name of a KEYWORD, without the leading colons.
+    Keyword(String),
+    Char(char),
+    /// The numerator and denominator of a RATIO, as written.
+    Ratio(i128, i128),
+}
+
+impl TokenValue {
+    /// Returns the value of an Int if it fits in an i64.
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            TokenValue::Int(v) => i64::try_from(*v).ok(),
+            _ => None,
+        }
+    }
+}
+
+/// Decodes the text of a STRING token (including its quotes) into its
+/// value, interpreting escape sequences like the scanner does. Returns
+/// None if the text is not a valid string literal or an escape is out of
+/// range.
+pub(crate) fn decode_string(text: &str) -> Option<String> {
+    unescape_string(text.strip_prefix('"')?.strip_suffix('"')?).ok()
+}
+
+/// KeywordParts are the parts of a keyword, as returned by
+/// `Scanner::keyword_parts()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeywordParts {
+    /// Whether the keyword starts with two colons, to be resolved in the
+    /// current namespace, e.g. `::thing` or `::alias/thing`.
+    pub auto_resolved: bool,
+    /// The text before the first `/`, if any and not empty.
+    pub namespace: Option<String>,
+    pub name: String,
+}
+
+/// Decodes the text of a KEYWORD token into its parts.
+pub(crate) fn decode_keyword(text: &str) -> KeywordParts {
+    let rest = text.strip_prefix(':').unwrap_or(text);
+    let (auto_resolved, rest) = match rest.strip_prefix(':') {
+        Some(rest) => (true, rest),
+        None => (false, rest),
+    };
+    let (namespace, name) = match rest.split_once('/') {
+        Some((namespace, name)) if !namespace.is_empty() && !name.is_empty() => (Some(String::from(namespace)), name),
+        _ => (None, rest),
+    };
+    KeywordParts { auto_resolved, namespace, name: String::from(name) }
+}
+
+/// EscapeError reports why the text of a string can't be unescaped. The
+/// offsets are in bytes from the start of the text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EscapeError {
+    /// A backslash followed by a character that starts no escape, e.g. `\q`.
+    InvalidEscape { offset: usize, ch: char },
+    /// A numeric escape with fewer digits than required, e.g. `\x4`, or a
+    /// backslash ending the text.
+    MissingDigits { offset: usize },
+    /// A numeric escape whose value is not a character, e.g. `\400` or
+    /// `\uD800`.
+    OutOfRange { offset: usize, value: u32 },
+    /// A quote or a new line, which must be escaped.
+    Unescaped { offset: usize, ch: char },
+}
+
+impl fmt::Display for EscapeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EscapeError::InvalidEscape { offset, ch } => write!(f, "offset {}: invalid char escape {:?}", offset, ch),
+            EscapeError::MissingDigits { offset } => write!(f, "offset {}: escape sequence without enough digits", offset),
+            EscapeError::OutOfRange { offset, value } => {
+                write!(f, "offset {}: escape sequence value {:#x} is out of range", offset, value)
+            }
+            EscapeError::Unescaped { offset, ch } => write!(f, "offset {}: unescaped {:?}", offset, ch),
+        }
+    }
+}
+
+impl core::error::Error for EscapeError {}
+
+/// Decodes the escape sequences of the text of a string between its
+/// quotes into its value, with the escapes of the scanner: `\a`, `\b`,
+/// `\f`, `\n`, `\r`, `\t`, `\v`, `\\`, `\"`, three octal digits, and
+/// `\x`, `\u` and `\U` followed by 2, 4 and 8 hexadecimal digits.
+///
+/// ```
+/// use scanner::*;
+///
+/// assert_eq!(unescape_string(r"\n\x41\u00e9"), Ok(String::from("\nAé")));
+/// assert_eq!(unescape_string(r"a\qb"), Err(EscapeError::InvalidEscape { offset: 1, ch: 'q' }));
+/// assert_eq!(unescape_string(r"\U00110000"), Err(EscapeError::OutOfRange { offset: 0, value: 0x110000 }));
+/// ```
+pub fn unescape_string(text: &str) -> Result<String, EscapeError> {
+    let mut value = String::with_capacity(text.len());
+    let mut chars = text.char_indices().peekable();
+    while let Some((offset, ch)) = chars.next() {
+        if ch == '"' || ch == '\n' {
+            return Err(EscapeError::Unescaped { offset, ch });
+        }
+        if ch != '\\' {
+            value.push(ch);
+            continue;
+        }
+        let Some((_, ch)) = chars.next() else {
+            return Err(EscapeError::MissingDigits { offset });
+        };
+        let simple = match ch {
+            'a' => Some('\x07'),
+            'b' => Some('\x08'),
+            'f' => Some('\x0C'),
+            'n' => Some('\n'),
+            'r' => Some('\r'),
+            't' => Some('\t'),
+            'v' => Some('\x0B'),
+            '\\' | '"' => Some(ch),
+            _ => None,
+        };
+        if let Some(c) = simple {
+            value.push(c);
+            continue;
+        }
+        let (base, n) = match ch {
+            '0'..='7' => (8, 2),
+            'x' => (16, 2),
+            'u' => (16, 4),
+            'U' => (16, 8),
+            _ => return Err(EscapeError::InvalidEscape { offset, ch }),
+        };
+        let mut digits = ch.to_digit(8).unwrap_or(0);
+        for _ in 0..n {
+            match chars.peek().and_then(|&(_, c)| c.to_digit(base)) {
+                Some(d) => digits = digits.wrapping_mul(base) + d,
+                None => return Err(EscapeError::MissingDigits { offset }),
+            }
+            chars.next();
+        }
+        match char::from_u32(digits) {
+            Some(c) if base == 16 || digits <= 0o377 => value.push(c),
+            _ => return Err(EscapeError::OutOfRange { offset, value: digits }),
+        }
+    }
+    Ok(value)
+}
+
+/// DecodedChars iterates over the characters of a string literal, decoding
+/// its escape sequences, as returned by `SpannedToken::decoded_chars()`.
+/// Each item is a character with the span of the source text producing
+/// it, a single character or a whole escape sequence; the character is
+/// None for an invalid or out of range escape. The iteration ends at the
+/// closing quote.
+pub struct DecodedChars<'a> {
+    scanner: Scanner<'a, 64>,
+    ch: char,
+    done: bool,
+}
+
+impl<'a> DecodedChars<'a> {
+    pub(crate) fn new(text: &'a str, start: &Position) -> Self {
+        let mut scanner: Scanner<'a, 64> = Scanner::with_buffer(text.as_bytes());
+        scanner.set_base_position(start);
+        scanner.position.source = start.source.clone();
+        let done = scanner.next() != '"';
+        let ch = if done { '\u{FFFF}' } else { scanner.next() };
+        DecodedChars { scanner, ch, done }
+    }
+}
+
+impl Iterator for DecodedChars<'_> {
+    type Item = (Span, Option<char>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done || matches!(self.ch, '"' | '\n' | '\u{FFFF}') {
+            self.done = true;
+            return None;
+        }
+        let start = self.scanner.pos();
+        let value = if self.ch == '\\' {
+            let (next, escape) = self.scanner.scan_escape('"');
+            self.ch = next;
+            // An invalid escape character belongs to the escape, as in its
+            // diagnostic
+            if escape.len == 1 && !matches!(next, '\n' | '\u{FFFF}') {
+                self.ch = self.scanner.next();
+            }
+            escape.value
+        } else {
+            let value = self.ch;
+            self.ch = self.scanner.next();
+            Some(value)
+        };
+        Some((Span { start, end: self.scanner.pos() }, value))
+    }
+}
+
+/// Decodes the text of a CHAR token, e.g. `#\space`, into its value.
+pub(crate) fn decode_char(text: &str) -> Option<char> {
+    char_name_value(text.strip_prefix("#\\")?)
+}
+
+/// Returns the character named by the text of a character literal after
+/// its `#\`: a single character, a name like `newline`, or a code point
+/// in hexadecimal after `x` or `u`.
+pub(crate) fn char_name_value(name: &str) -> Option<char> {
+    let mut chars = name.chars();
+    let first = chars.next()?;
+    if chars.as_str().is_empty() {
+        return Some(first);
+    }
+    let value = match name {
+        "alarm" => '\x07',
+        "backspace" => '\x08',
+        "delete" | "rubout" => '\x7F',
+        "escape" | "altmode" => '\x1B',
+        "newline" | "linefeed" => '\n',
+        "null" | "nul" => '\0',
+        "page" => '\x0C',
+        "return" => '\r',
+        "space" => ' ',
+        "tab" => '\t',
+        _ => {
+            let digits = name.strip_prefix(['x', 'u'])?;
+            if digits.len() > 6 {
+                return None;
+            }
+            char::from_u32(u32::from_str_radix(digits, 16).ok()?)?
+        }
+    };
+    Some(value)
+}
+
+/// Reports whether the text of an INT token is an octal literal with a
+/// leading zero instead of the `0o` prefix, e.g. `0755`.
+pub(crate) fn is_legacy_octal(text: &str) -> bool {
+    let digits = text.strip_prefix(['-', '+']).unwrap_or(text).as_bytes();
+    digits.len() > 1 && digits[0] == b'0' && (digits[1].is_ascii_digit() || digits[1] == b'_')
+}
+
+/// Decodes the text of a RAW_STRING token (including its `¬` delimiters)
+/// into its value: doubled `¬¬` stand for a single `¬`.
+pub(crate) fn decode_raw_string(text: &str) -> Option<String> {
+    decode_raw_string_with(text, '¬', '¬')
+}
+
+/// Decodes the text of a RAW_STRING token delimited by open and close:
+/// doubled close characters stand for a single one.
+pub(crate) fn decode_raw_string_with(text: &str, open: char, close: char) -> Option<String> {
+    let mut chars = text.strip_prefix(open)?.chars();
+    let mut value = String::with_capacity(text.len());
+    while let Some(ch) = chars.next() {
+        if ch == close {
+            match chars.next() {
+                Some(c) if c == close => {}
+                // A single close must be the closing delimiter
+                None => return Some(value),
+                Some(_) => return None,
+            }
+        }
+        value.push(ch);
+    }
+    None
+}
+
+/// Normalizes the text of an INT or FLOAT token: letters (prefixes, hex
+/// digits and exponents) are lowercased and `_` separators are removed.
+pub(crate) fn normalize_number(text: &str) -> String {
+    text.chars()
+        .filter(|&c| c != '_')
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+/// Decodes the text of an INT token into its value, honoring the sign,
+/// the `0x`, `0o`, `0b` and legacy `0` octal prefixes and `_` separators.
+pub(crate) fn decode_int(text: &str) -> Option<i128> {
+    let (negative, text) = match text.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, text),
+    };
+    let digits = normalize_number(text);
+    let (radix, digits) = if let Some(d) = digits.strip_prefix("0x") {
+        (16, d)
+    } else if let Some(d) = digits.strip_prefix("0o") {
+        (8, d)
+    } else if let Some(d) = digits.strip_prefix("0b") {
+        (2, d)
+    } else if digits.len() > 1 && digits.starts_with('0') {
+        (8, &digits[1..])
+    } else {
+        (10, digits.as_str())
+    };
+    if digits.is_empty() || digits.starts_with(['+', '-']) {
+        return None;
+    }
+    let magnitude = u128::from_str_radix(digits, radix).ok()?;
+    if negative {
+        0i128.checked_sub_unsigned(magnitude)
+    } else {
+        i128::try_from(magnitude).ok()
+    }
+}
+
+/// Decodes the text of a FLOAT token into its value, including
+/// hexadecimal mantissas with a `p` exponent.
+pub(crate) fn decode_float(text: &str) -> Option<f64> {
+    let (negative, text) = match text.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, text),
+    };
+    let text = normalize_number(text);
+    let value = match text.strip_prefix("0x") {
+        Some(hex) => decode_hex_float(hex)?,
+        None => text.parse::<f64>().ok()?,
+    };
+    Some(if negative { -value } else { value })
+}
+
+fn decode_hex_float(text: &str) -> Option<f64> {
+    let (mantissa, exp) = text.split_once('p')?;
+    let exp = match exp.parse::<i32>() {
+        Ok(exp) => exp,
+        Err(err) if *err.kind() == IntErrorKind::PosOverflow => i32::MAX,
+        Err(err) if *err.kind() == IntErrorKind::NegOverflow => i32::MIN,
+        Err(_) => return None,
+    };
+    let (int_part, frac_part) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    if int_part.is_empty() && frac_part.is_empty() {
+        return None;
+    }
+    let mut value = 0f64;
+    for c in int_part.chars() {
+        value = value * 16.0 + c.to_digit(16)? as f64;
+    }
+    let mut scale = 1.0 / 16.0;
+    for c in frac_part.chars() {
+        value += c.to_digit(16)? as f64 * scale;
+        scale /= 16.0;
+    }
+    Some(scale_by_exp2(value, exp))
+}
+
+// Largest exponent scaling a finite, nonzero f64 to another one: beyond
+// it, every value overflows to infinity or underflows to zero.
+const MAX_EXP2: i32 = 2200;
+
+// Largest step of scale_by_exp2, so that 2^step is a normal f64.
+const EXP2_STEP: i32 = 1000;
+
+// value * 2^exp without depending on std. 2^exp may be out of range even
+// if the product isn't, so value is scaled in steps.
+fn scale_by_exp2(mut value: f64, exp: i32) -> f64 {
+    let mut exp = exp.clamp(-MAX_EXP2, MAX_EXP2);
+    while exp != 0 {
+        let step = exp.clamp(-EXP2_STEP, EXP2_STEP);
+        value *= exp2(step);
+        exp -= step;
+    }
+    value
+}
+
+// 2^exp for |exp| <= EXP2_STEP, by squaring.
+fn exp2(exp: i32) -> f64 {
+    let mut base = if exp < 0 { 0.5 } else { 2.0 };
+    let mut n = exp.unsigned_abs();
+    let mut result = 1f64;
+    while n > 0 {
+        if n & 1 != 0 {
+            result *= base;
+        }
+        base *= base;
+        n >>= 1;
+    }
+    result
+}
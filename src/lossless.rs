@@ -0,0 +1,49 @@
+// Copyright 2022 Jordi Íñigo Griera. All rights reserved.
+
+//! Verification of lossless scanning.
+//!
+//! In `LOSSLESS_TOKENS` mode every byte of the source belongs to exactly one
+//! token, white space and comments included, so that concatenating the
+//! texts of all tokens reproduces the source. Formatters and other tools
+//! rewriting source code rely on this guarantee.
+
+use alloc::string::String;
+
+use crate::{EOF, LOSSLESS_TOKENS, Scanner, Token};
+
+/// LosslessMismatch describes the first token whose text doesn't match the
+/// source at the offset where it was expected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LosslessMismatch {
+    /// Byte offset of the source where the token was expected to start.
+    pub offset: usize,
+    /// The token found, EOF if the scan ended before the end of the source.
+    pub tok: Token,
+    pub text: String,
+}
+
+/// Scans `src` in `LOSSLESS_TOKENS` mode and checks that the token texts,
+/// concatenated, equal `src`. Returns the first token breaking the property,
+/// or None if the scan is lossless.
+pub fn verify_lossless(src: &str) -> Option<LosslessMismatch> {
+    let mut s = Scanner::init(src.as_bytes());
+    s.set_mode(LOSSLESS_TOKENS);
+    let mut offset = 0;
+    loop {
+        let tok = s.scan();
+        let text = s.token_str();
+        let range = s.token_byte_range();
+        let matches = if tok == EOF {
+            offset == src.len()
+        } else {
+            !text.is_empty() && range.start == offset as u64 && src[offset..].starts_with(&*text)
+        };
+        if !matches {
+            return Some(LosslessMismatch { offset, tok, text: text.into_owned() });
+        }
+        if tok == EOF {
+            return None;
+        }
+        offset += text.len();
+    }
+}
@@ -0,0 +1,73 @@
+// Copyright 2022 Jordi Íñigo Griera. All rights reserved.
+
+//! Scanning of memory-mapped files.
+//!
+//! A `MappedFile` maps a file into memory with `memmap2`, so that its
+//! scanners read it in place instead of through `read()` calls into a
+//! copy, which matters for files of hundreds of megabytes. Its scanners
+//! have the path as the source of their positions:
+//!
+//! ```
+//! use scanner::*;
+//!
+//! let path = std::env::temp_dir().join("scanner_mapped_doc.lisp");
+//! std::fs::write(&path, "(def a 1)").unwrap();
+//! let file = MappedFile::open(&path).unwrap();
+//! let mut s = file.scanner();
+//! s.scan();
+//! assert_eq!(s.scan(), IDENT);
+//! assert_eq!(s.position.source.name(), path.to_str().unwrap());
+//! # std::fs::remove_file(&path).unwrap();
+//! ```
+//!
+//! A scanner still reads the mapping through its inline buffer, like any
+//! other source; what the mapping saves is reading the whole file first.
+//!
+//! The file must not be truncated while it is mapped, by this or another
+//! process: reading the pages past its new end raises a bus error (SIGBUS
+//! on Unix), which aborts the process. Other changes to the file may be
+//! seen by the scanners in the middle of a scan. Map only files that no
+//! one else writes while they are scanned, or read them into memory
+//! instead.
+
+use alloc::string::String;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+use memmap2::Mmap;
+
+use crate::{Scanner, SourceId, SourceKind};
+
+/// MappedFile holds the contents of a file mapped into memory; see the
+/// module documentation, also for the hazard of truncating the file while
+/// it is mapped.
+pub struct MappedFile {
+    path: String,
+    map: Mmap,
+}
+
+impl MappedFile {
+    /// Maps the file at path into memory.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        let file = File::open(path)?;
+        // Safety: the mapping is read only and private; a file modified
+        // while mapped is the hazard documented in the module.
+        let map = unsafe { Mmap::map(&file)? };
+        Ok(MappedFile { path: path.to_string_lossy().into_owned(), map })
+    }
+
+    /// Returns the contents of the file.
+    pub fn bytes(&self) -> &[u8] {
+        &self.map
+    }
+
+    /// Returns a scanner of the file, whose positions have its path as
+    /// their source.
+    pub fn scanner(&self) -> Scanner<'_> {
+        let mut s = Scanner::init(self.bytes());
+        s.position.source = SourceId::new(SourceKind::Path, &self.path);
+        s
+    }
+}
@@ -0,0 +1,113 @@
+// Copyright 2022 Jordi Íñigo Griera. All rights reserved.
+
+//! Extraction of fenced code blocks from Markdown documents.
+
+use alloc::vec::Vec;
+
+use crate::{Position, Scanner, SourceId};
+
+/// CodeBlock is the content of a fenced code block of a Markdown document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CodeBlock<'a> {
+    /// The info string of the opening fence, e.g. "lisp" or "lisp no_run".
+    pub info: &'a str,
+    /// The code between the fences, verbatim.
+    pub code: &'a [u8],
+    /// Position of the first byte of the code in the Markdown document.
+    pub start: Position,
+}
+
+impl<'a> CodeBlock<'a> {
+    /// Returns a scanner over the code reporting positions in Markdown
+    /// document coordinates.
+    pub fn scanner(&self) -> Scanner<'a> {
+        let mut s = Scanner::init(self.code);
+        s.position.source = self.start.source.clone();
+        s.set_base_position(&self.start);
+        s
+    }
+}
+
+/// Returns the fenced code blocks (```` ``` ```` or `~~~`) of a Markdown
+/// document whose info string starts with the language id `lang`. An
+/// empty `lang` selects all blocks. A block without closing fence extends
+/// to the end of the document.
+///
+/// Fences may be indented up to three spaces, but the code lines are
+/// returned verbatim, without removing the fence indentation.
+pub fn code_blocks<'a>(markdown: &'a [u8], lang: &str, filename: &str) -> Vec<CodeBlock<'a>> {
+    let mut blocks = Vec::new();
+    // (fence char, fence length, info, code start offset, code start line)
+    let mut open: Option<(u8, usize, &'a str, usize, usize)> = None;
+    let mut offset = 0;
+
+    for (line, raw) in (1..).zip(markdown.split_inclusive(|&b| b == b'\n')) {
+        let next_offset = offset + raw.len();
+        let fence = parse_fence(raw);
+        match (open, fence) {
+            (None, Some((ch, len, info))) => {
+                open = Some((ch, len, info, next_offset, line + 1));
+            }
+            (Some((ch, len, info, start, start_line)), Some((c, l, rest))) if c == ch && l >= len && rest.is_empty() => {
+                push_block(&mut blocks, markdown, lang, filename, info, start..offset, start_line);
+                open = None;
+            }
+            _ => {}
+        }
+        offset = next_offset;
+    }
+    if let Some((_, _, info, start, start_line)) = open {
+        push_block(&mut blocks, markdown, lang, filename, info, start..markdown.len(), start_line);
+    }
+    blocks
+}
+
+fn push_block<'a>(
+    blocks: &mut Vec<CodeBlock<'a>>,
+    markdown: &'a [u8],
+    lang: &str,
+    filename: &str,
+    info: &'a str,
+    range: core::ops::Range<usize>,
+    line: usize,
+) {
+    let tag = info.split_whitespace().next().unwrap_or("");
+    if !lang.is_empty() && tag != lang {
+        return;
+    }
+    blocks.push(CodeBlock {
+        info,
+        code: &markdown[range.clone()],
+        start: Position {
+            source: SourceId::from(filename),
+            offset: range.start,
+            line,
+            column: 1,
+            #[cfg(feature = "pages")]
+            page: 1 + markdown[..range.start].iter().filter(|&&b| b == b'\x0C').count(),
+        },
+    });
+}
+
+// Parses a fence line, returning the fence character, the fence length
+// and the (trimmed) info string.
+fn parse_fence(line: &[u8]) -> Option<(u8, usize, &str)> {
+    let indent = line.iter().take_while(|&&b| b == b' ').count();
+    if indent > 3 {
+        return None;
+    }
+    let line = &line[indent..];
+    let ch = *line.first()?;
+    if ch != b'`' && ch != b'~' {
+        return None;
+    }
+    let len = line.iter().take_while(|&&b| b == ch).count();
+    if len < 3 {
+        return None;
+    }
+    let info = core::str::from_utf8(&line[len..]).ok()?.trim();
+    if ch == b'`' && info.contains('`') {
+        return None;
+    }
+    Some((ch, len, info))
+}
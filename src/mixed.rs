@@ -0,0 +1,172 @@
+// Copyright 2022 Jordi Íñigo Griera. All rights reserved.
+
+//! Scanning of documents that interleave a host language with islands of
+//! a guest language, such as Lisp code inside Markdown code fences or
+//! text templates with Lisp expressions.
+
+use alloc::boxed::Box;
+use alloc::string::String;
+
+use crate::{EOF, Position, Scanner, SourceId, Span, SpannedToken};
+
+/// Island is a pair of delimiters enclosing guest code in the host text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Island<'d> {
+    pub open: &'d str,
+    pub close: &'d str,
+}
+
+/// An item of a mixed document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MixedItem {
+    /// A run of host text, including the island delimiters.
+    Host { text: String, span: Span },
+    /// A token of a guest island.
+    Guest(SpannedToken),
+}
+
+/// MixedScanner splits a document into host text and guest tokens.
+///
+/// Host text is returned verbatim up to and including an island opening
+/// delimiter. The island content is then scanned with a guest scanner, set
+/// up by the configuration function, until a token starts with the closing
+/// delimiter; since the delimiter is only recognized at token starts, it
+/// may appear inside guest strings and comments. All positions are
+/// reported in host coordinates.
+pub struct MixedScanner<'a, 'd> {
+    src: &'a [u8],
+    islands: &'d [Island<'d>],
+    configure: Box<dyn FnMut(&mut Scanner<'a>) + 'a>,
+    source: SourceId,
+
+    // Host position where the host text resumes
+    pos: Position,
+    guest: Option<(Scanner<'a>, &'d str)>,
+}
+
+impl<'a, 'd> MixedScanner<'a, 'd> {
+    /// Creates a MixedScanner over `src` recognizing the given islands.
+    pub fn new<F>(src: &'a [u8], islands: &'d [Island<'d>], configure: F) -> Self
+    where
+        F: FnMut(&mut Scanner<'a>) + 'a,
+    {
+        MixedScanner {
+            src,
+            islands,
+            configure: Box::new(configure),
+            source: SourceId::default(),
+            pos: Position {
+                source: SourceId::default(),
+                offset: 0,
+                line: 1,
+                column: 1,
+                #[cfg(feature = "pages")]
+                page: 1,
+            },
+            guest: None,
+        }
+    }
+
+    /// Sets the source path reported in host and guest positions.
+    pub fn set_filename(&mut self, filename: &str) {
+        self.source = SourceId::from(filename);
+        self.pos.source = self.source.clone();
+    }
+
+    // Returns the position after advancing over `text` from `pos`.
+    fn advance(mut pos: Position, text: &[u8]) -> Position {
+        for ch in String::from_utf8_lossy(text).chars() {
+            if ch == '\n' {
+                pos.line += 1;
+                pos.column = 1;
+            } else {
+                pos.column += 1;
+            }
+            #[cfg(feature = "pages")]
+            if ch == '\x0C' {
+                pos.page += 1;
+            }
+        }
+        pos.offset += text.len();
+        pos
+    }
+
+    fn next_guest(&mut self) -> Option<MixedItem> {
+        let (guest, close) = self.guest.as_mut()?;
+        let t = guest.scan_spanned();
+        if t.tok != EOF && !self.src[t.span.start.offset..].starts_with(close.as_bytes()) {
+            return Some(MixedItem::Guest(t));
+        }
+        // The island ends where the closing delimiter (or EOF) starts.
+        self.guest = None;
+        self.pos = t.span.start;
+        None
+    }
+
+    fn next_host(&mut self) -> Option<MixedItem> {
+        let start = self.pos.offset;
+        if start >= self.src.len() {
+            return None;
+        }
+        let rest = &self.src[start..];
+
+        // Skip the closing delimiter of the previous island, if any, so
+        // that it isn't taken as an opening one.
+        let skip = self
+            .islands
+            .iter()
+            .filter(|i| rest.starts_with(i.close.as_bytes()))
+            .map(|i| i.close.len())
+            .max()
+            .unwrap_or(0);
+
+        let found = self
+            .islands
+            .iter()
+            .filter_map(|i| find(&rest[skip..], i.open.as_bytes()).map(|at| (skip + at, i)))
+            .min_by_key(|(at, _)| *at);
+
+        let end = match found {
+            Some((at, island)) => start + at + island.open.len(),
+            None => self.src.len(),
+        };
+        let text = String::from_utf8_lossy(&self.src[start..end]).into_owned();
+        let span_start = self.pos.clone();
+        self.pos = Self::advance(self.pos.clone(), &self.src[start..end]);
+
+        if let Some((_, island)) = found {
+            let mut guest = Scanner::init(&self.src[end..]);
+            (self.configure)(&mut guest);
+            guest.position.source = self.source.clone();
+            guest.set_base_position(&self.pos);
+            self.guest = Some((guest, island.close));
+        }
+        Some(MixedItem::Host {
+            text,
+            span: Span {
+                start: span_start,
+                end: self.pos.clone(),
+            },
+        })
+    }
+}
+
+impl Iterator for MixedScanner<'_, '_> {
+    type Item = MixedItem;
+
+    fn next(&mut self) -> Option<MixedItem> {
+        if self.guest.is_some()
+            && let Some(item) = self.next_guest()
+        {
+            return Some(item);
+        }
+        self.next_host()
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    if needle.is_empty() {
+        return None;
+    }
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
@@ -0,0 +1,121 @@
+// Copyright 2022 Jordi Íñigo Griera. All rights reserved.
+
+//! Pipelined scanning: a `TokenPipeline` scans a source on its own thread
+//! and sends the tokens over a bounded channel, so that a parser consuming
+//! them overlaps with scanning.
+//!
+//! - Backpressure: the scanning thread blocks when `capacity` items are
+//!   waiting to be received; a capacity of 0 hands over each item directly.
+//! - Errors: each error found while scanning a token is received as an
+//!   `Err` before the token. Warnings are only returned by `finish()`.
+//! - Cancellation: dropping the pipeline, or calling `finish()` before the
+//!   end, stops the scanning thread at the next item it sends.
+//! - Panics in the scanning thread (e.g. in a hook) are resumed by
+//!   `finish()`; the iteration just ends.
+//!
+//! ```
+//! use scanner::*;
+//!
+//! let src = b"(+ 1 0x)".to_vec();
+//! let mut pipeline = TokenPipeline::spawn(src, 16, |s| s.set_lints(ALL_LINTS));
+//! let mut texts = Vec::new();
+//! for item in &mut pipeline {
+//!     match item {
+//!         Ok(t) => texts.push(t.text),
+//!         Err(d) => texts.push(format!("{}", d.code)),
+//!     }
+//! }
+//! assert_eq!(texts, ["(", "+", "1", "E004", "0x", ")"]);
+//! assert_eq!(pipeline.finish().len(), 1);
+//! ```
+
+use std::panic;
+use std::sync::mpsc::{self, Receiver};
+use std::thread::{self, JoinHandle};
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::source::LastToken;
+use crate::{Diagnostic, EOF, Scanner, Span, SpannedToken, Token, TokenSource};
+
+/// TokenPipeline receives the tokens of a source scanned on another
+/// thread; see the module documentation.
+pub struct TokenPipeline {
+    receiver: Option<Receiver<Result<SpannedToken, Diagnostic>>>,
+    thread: JoinHandle<Vec<Diagnostic>>,
+    errors: Vec<Diagnostic>, // errors received by TokenSource::scan()
+    last: LastToken,
+}
+
+impl TokenPipeline {
+    /// Spawns a thread scanning `src` with a scanner set up by `configure`,
+    /// sending its tokens, up to but excluding EOF, over a channel holding
+    /// up to `capacity` items.
+    pub fn spawn<F>(src: Vec<u8>, capacity: usize, configure: F) -> Self
+    where
+        F: FnOnce(&mut Scanner<'_>) + Send + 'static,
+    {
+        let (sender, receiver) = mpsc::sync_channel(capacity);
+        let thread = thread::spawn(move || {
+            let mut s = Scanner::init(&src);
+            configure(&mut s);
+            loop {
+                let errors = s.error_count();
+                let t = s.scan_spanned();
+                for d in s.last_errors(s.error_count() - errors) {
+                    if sender.send(Err(d.clone())).is_err() {
+                        return s.take_diagnostics();
+                    }
+                }
+                if t.tok == EOF || sender.send(Ok(t)).is_err() {
+                    return s.take_diagnostics();
+                }
+            }
+        });
+        TokenPipeline { receiver: Some(receiver), thread, errors: Vec::new(), last: LastToken::new() }
+    }
+
+    /// Stops scanning if it hasn't ended, waits for the scanning thread and
+    /// returns all the diagnostics it reported, errors included.
+    pub fn finish(mut self) -> Vec<Diagnostic> {
+        self.receiver = None;
+        match self.thread.join() {
+            Ok(diagnostics) => diagnostics,
+            Err(payload) => panic::resume_unwind(payload),
+        }
+    }
+}
+
+impl Iterator for TokenPipeline {
+    type Item = Result<SpannedToken, Diagnostic>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.receiver.as_ref()?.recv().ok()
+    }
+}
+
+/// As a TokenSource, a TokenPipeline returns the tokens received and keeps
+/// the errors received for `diagnostics()`.
+impl TokenSource for TokenPipeline {
+    fn scan(&mut self) -> Token {
+        loop {
+            match self.next() {
+                Some(Err(d)) => self.errors.push(d),
+                t => return self.last.record(t.and_then(Result::ok)),
+            }
+        }
+    }
+
+    fn token_text(&self) -> String {
+        self.last.text()
+    }
+
+    fn span(&self) -> Span {
+        self.last.span()
+    }
+
+    fn diagnostics(&self) -> &[Diagnostic] {
+        &self.errors
+    }
+}
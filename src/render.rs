@@ -0,0 +1,119 @@
+// Copyright 2022 Jordi Íñigo Griera. All rights reserved.
+
+//! Rendering of values as literals accepted by the scanner.
+//!
+//! This is the inverse of the literal decoders: every rendered literal
+//! scans back as a single token whose decoded value is the original one.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// Radix of rendered integer literals.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Radix {
+    Binary,
+    Octal,
+    #[default]
+    Decimal,
+    Hexadecimal,
+}
+
+/// Options of the literal renderers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RenderOptions {
+    /// Allow rendering strings as raw strings when that is shorter.
+    pub raw_strings: bool,
+    /// Separate groups of this many integer digits with `_` (0 disables separators).
+    pub digit_group: usize,
+    /// Radix of integer literals.
+    pub radix: Radix,
+}
+
+/// Renders a string value as a STRING literal using the canonical escapes:
+/// the short forms for `"`, `\` and the common control characters, and
+/// `\xNN` for the remaining control characters.
+pub fn render_string(value: &str) -> String {
+    let mut text = String::with_capacity(value.len() + 2);
+    text.push('"');
+    for ch in value.chars() {
+        match ch {
+            '"' => text.push_str("\\\""),
+            '\\' => text.push_str("\\\\"),
+            '\x07' => text.push_str("\\a"),
+            '\x08' => text.push_str("\\b"),
+            '\x0C' => text.push_str("\\f"),
+            '\n' => text.push_str("\\n"),
+            '\r' => text.push_str("\\r"),
+            '\t' => text.push_str("\\t"),
+            '\x0B' => text.push_str("\\v"),
+            c if c.is_control() => text.push_str(&format!("\\x{:02x}", c as u32)),
+            c => text.push(c),
+        }
+    }
+    text.push('"');
+    text
+}
+
+/// Renders a string value as a RAW_STRING literal, doubling the `¬`
+/// characters in the value.
+pub fn render_raw_string(value: &str) -> String {
+    format!("¬{}¬", value.replace('¬', "¬¬"))
+}
+
+/// Renders a string value as a STRING literal or, when `opts.raw_strings`
+/// is set, as a RAW_STRING literal if that is shorter and the value has no
+/// control characters other than tab.
+pub fn render_string_with(value: &str, opts: &RenderOptions) -> String {
+    let quoted = render_string(value);
+    if opts.raw_strings && !value.chars().any(|c| c.is_control() && c != '\t') {
+        let raw = render_raw_string(value);
+        if raw.chars().count() < quoted.chars().count() {
+            return raw;
+        }
+    }
+    quoted
+}
+
+/// Renders an integer as an INT literal in the radix and with the digit
+/// separators selected in `opts`.
+pub fn render_int(value: i128, opts: &RenderOptions) -> String {
+    let magnitude = value.unsigned_abs();
+    let (prefix, digits) = match opts.radix {
+        Radix::Binary => ("0b", format!("{:b}", magnitude)),
+        Radix::Octal => ("0o", format!("{:o}", magnitude)),
+        Radix::Decimal => ("", format!("{}", magnitude)),
+        Radix::Hexadecimal => ("0x", format!("{:x}", magnitude)),
+    };
+    let sign = if value < 0 { "-" } else { "" };
+    format!("{}{}{}", sign, prefix, group_digits(&digits, opts.digit_group))
+}
+
+/// Renders a float as a FLOAT literal, or returns None for NaN and the
+/// infinities, which have no literal form.
+pub fn render_float(value: f64) -> Option<String> {
+    if !value.is_finite() {
+        return None;
+    }
+    // Debug formatting always includes a '.' or an exponent, so the
+    // literal never scans back as an INT.
+    Some(format!("{:?}", value))
+}
+
+fn group_digits(digits: &str, group: usize) -> String {
+    if group == 0 || digits.len() <= group {
+        return String::from(digits);
+    }
+    let bytes = digits.as_bytes();
+    let first = bytes.len() % group;
+    let mut parts: Vec<&str> = Vec::new();
+    if first > 0 {
+        parts.push(&digits[..first]);
+    }
+    let mut i = first;
+    while i < bytes.len() {
+        parts.push(&digits[i..i + group]);
+        i += group;
+    }
+    parts.join("_")
+}
@@ -0,0 +1,248 @@
+// Copyright 2022 Jordi Íñigo Griera. All rights reserved.
+
+//! Accumulation of REPL input into complete forms.
+//!
+//! A REPL reads a line, and either evaluates the input entered so far, if
+//! it is a complete form, or prompts for a continuation line. A `Repl`
+//! makes that decision: lines are fed to it, and it returns the tokens of
+//! the accumulated input once every bracket is closed and no raw string or
+//! block comment is left open. Positions are relative to the whole session, so the third
+//! line entered is reported as e.g. `stdin:3:5`. Only the line fed is
+//! scanned, and a literal left open once its end may have been entered, so
+//! a long input isn't rescanned for every line.
+//!
+//! ```
+//! use scanner::*;
+//!
+//! let mut repl = Repl::new();
+//! assert!(matches!(repl.feed("(def a 1)"), ReplStatus::Complete(_)));
+//! assert_eq!(repl.prompt(), "> ");
+//! assert_eq!(repl.feed("(+ a"), ReplStatus::Continue);
+//! assert_eq!(repl.prompt(), "... ");
+//! let ReplStatus::Complete(form) = repl.feed("   2)") else { panic!() };
+//! assert_eq!(form.number, 2);
+//! assert_eq!(form.tokens[3].span.start.to_string(), "stdin:3:4");
+//! assert_eq!(repl.history(), ["(def a 1)\n", "(+ a\n   2)\n"]);
+//! ```
+//!
+//! `is_form_complete()` makes the same decision for a whole input, e.g. the
+//! buffer of a line editor deciding whether Enter submits it:
+//!
+//! ```
+//! use scanner::*;
+//!
+//! assert_eq!(is_form_complete("(+ 1 2)"), Completeness::Complete);
+//! assert_eq!(is_form_complete("(str \"a b"), Completeness::Incomplete);
+//! assert_eq!(is_form_complete("(+ 1 2]"), Completeness::Invalid);
+//! ```
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::incremental::Pending;
+use crate::{
+    Diagnostic, DiagnosticCode, EOF, EofPolicy, IDENT, NEED_MORE_INPUT, Scanner, SourceId, SourceKind, SpannedToken,
+    Token,
+};
+
+/// A complete input of a REPL session.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplForm {
+    /// Number of the input in the history, starting at 1, or 0 if it has
+    /// no tokens and wasn't recorded.
+    pub number: usize,
+    /// Text of the input, with every line terminated by a newline.
+    pub text: String,
+    pub tokens: Vec<SpannedToken>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// Result of feeding a line to a `Repl`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplStatus {
+    /// The input entered so far is complete.
+    Complete(ReplForm),
+    /// The input is incomplete: a continuation line is needed.
+    Continue,
+}
+
+/// Whether an input is a complete form; see `is_form_complete()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Completeness {
+    /// Every bracket is closed and no literal is left open.
+    Complete,
+    /// A bracket or a literal is left open: more input may complete it.
+    Incomplete,
+    /// A bracket is closed by the wrong bracket or was never opened, or a
+    /// string ends at a newline: no further input can complete it.
+    Invalid,
+}
+
+/// Reports whether src, scanned in `LISP_TOKENS` mode, is a complete form:
+/// whether every string and raw string is closed, and every parenthesis,
+/// bracket and brace is balanced.
+pub fn is_form_complete(src: &str) -> Completeness {
+    let mut s = Scanner::init(src.as_bytes());
+    s.set_eof_policy(EofPolicy::NeedMoreInput);
+    let mut tokens = Vec::new();
+    loop {
+        let t = s.scan_spanned();
+        if t.tok == EOF {
+            break;
+        }
+        tokens.push(t);
+    }
+    completeness(&tokens, s.diagnostics())
+}
+
+/// Repl accumulates the lines of a REPL session into complete inputs; see
+/// the module documentation.
+pub struct Repl {
+    prompts: (String, String),
+    pending: Pending,          // input not scanned yet, from the end of tokens
+    text: String,              // lines of the incomplete input
+    tokens: Vec<SpannedToken>, // tokens of the incomplete input
+    open: Vec<Token>,          // expected closing brackets
+    invalid: bool,             // the input can't be completed
+    history: Vec<String>,
+}
+
+impl Default for Repl {
+    fn default() -> Self {
+        Repl::new()
+    }
+}
+
+impl Repl {
+    /// Returns a Repl in `LISP_TOKENS` mode, whose positions refer to the
+    /// virtual source `stdin`.
+    pub fn new() -> Self {
+        let mut pending = Pending::new();
+        pending.position.source = SourceId::new(SourceKind::Virtual, "stdin");
+        Repl {
+            prompts: (String::from("> "), String::from("... ")),
+            pending,
+            text: String::new(),
+            tokens: Vec::new(),
+            open: Vec::new(),
+            invalid: false,
+            history: Vec::new(),
+        }
+    }
+
+    /// Sets the mode of the scanners of the following lines.
+    pub fn set_mode(&mut self, mode: u32) {
+        self.pending.mode = mode;
+    }
+
+    /// Sets the source of the positions of the following lines.
+    pub fn set_source(&mut self, source: SourceId) {
+        self.pending.position.source = source;
+    }
+
+    /// Sets the prompts returned by `prompt()`: the primary one, for a new
+    /// input, and the one for continuation lines.
+    pub fn set_prompts(&mut self, primary: &str, continuation: &str) {
+        self.prompts = (String::from(primary), String::from(continuation));
+    }
+
+    /// Returns the prompt for the next line.
+    pub fn prompt(&self) -> &str {
+        if self.text.is_empty() { &self.prompts.0 } else { &self.prompts.1 }
+    }
+
+    /// Reports whether an incomplete input is waiting for more lines.
+    pub fn is_pending(&self) -> bool {
+        !self.text.is_empty()
+    }
+
+    /// Returns the complete inputs of the session, oldest first. Inputs
+    /// without tokens, such as blank lines, are not recorded.
+    pub fn history(&self) -> &[String] {
+        &self.history
+    }
+
+    /// Discards the incomplete input, e.g. when the user interrupts it.
+    /// Its lines still count for the positions of the following inputs.
+    pub fn cancel(&mut self) {
+        self.pending.scan(true);
+        self.pending.tokens.clear();
+        self.pending.diagnostics.clear();
+        self.text.clear();
+        self.tokens.clear();
+        self.open.clear();
+        self.invalid = false;
+    }
+
+    /// Feeds a line, with or without its line terminator, and returns the
+    /// accumulated input if it is complete.
+    pub fn feed(&mut self, line: &str) -> ReplStatus {
+        self.text.push_str(line);
+        self.pending.bytes.extend_from_slice(line.as_bytes());
+        if !line.ends_with('\n') {
+            self.text.push('\n');
+            self.pending.bytes.push(b'\n');
+        }
+
+        let diagnostics = self.pending.diagnostics.len();
+        self.scan(false);
+        // Only a literal left open continues past the end of the line
+        if !self.pending.open_literal {
+            self.scan(true);
+        }
+        self.invalid |= self.pending.diagnostics[diagnostics..].iter().any(|d| d.code == DiagnosticCode::Unterminated);
+        if !self.invalid && (self.pending.open_literal || !self.open.is_empty()) {
+            return ReplStatus::Continue;
+        }
+        // A literal left open in an invalid input ends with it
+        self.scan(true);
+        let text = core::mem::take(&mut self.text);
+        let tokens = core::mem::take(&mut self.tokens);
+        let diagnostics = core::mem::take(&mut self.pending.diagnostics);
+        self.open.clear();
+        self.invalid = false;
+        if !tokens.is_empty() {
+            self.history.push(text.clone());
+        }
+        ReplStatus::Complete(ReplForm { number: self.history.len(), text, tokens, diagnostics })
+    }
+
+    // Scans the input fed, or all of it at eof, and adds its tokens to the
+    // incomplete input.
+    fn scan(&mut self, eof: bool) {
+        self.pending.scan(eof);
+        for t in self.pending.tokens.drain(..) {
+            self.invalid |= !nest(&mut self.open, &t);
+            self.tokens.push(t);
+        }
+    }
+}
+
+// Reports whether the tokens of an input are a complete form, given its
+// diagnostics: an input ending in an unterminated literal needs more input.
+fn completeness(tokens: &[SpannedToken], diagnostics: &[Diagnostic]) -> Completeness {
+    if diagnostics.iter().any(|d| d.code == DiagnosticCode::Unterminated) {
+        return Completeness::Invalid;
+    }
+    let mut open = Vec::new(); // expected closing brackets
+    if !tokens.iter().all(|t| nest(&mut open, t)) {
+        return Completeness::Invalid;
+    }
+    let literal = tokens.last().is_some_and(|t| t.tok == NEED_MORE_INPUT);
+    if literal || !open.is_empty() { Completeness::Incomplete } else { Completeness::Complete }
+}
+
+// Updates the expected closing brackets with a token, reporting whether it
+// isn't a closing bracket other than the expected one.
+fn nest(open: &mut Vec<Token>, t: &SpannedToken) -> bool {
+    let closing = match t.tok {
+        IDENT if t.text == "#{" => '}',
+        tok if tok == '(' as Token => ')',
+        tok if tok == '[' as Token => ']',
+        tok if tok == '{' as Token => '}',
+        tok if tok == ')' as Token || tok == ']' as Token || tok == '}' as Token => return open.pop() == Some(tok),
+        _ => return true,
+    };
+    open.push(closing as Token);
+    true
+}
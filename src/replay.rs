@@ -0,0 +1,414 @@
+// Copyright 2022 Jordi Íñigo Griera. All rights reserved.
+
+//! Deterministic replay of scans for bug reports.
+//!
+//! A scanner started with `Scanner::start_recording()` logs its source,
+//! its configuration and the chunks of source copied into its buffer. The
+//! log is saved with `ReplayLog::to_bytes()` and loaded back with
+//! `ReplayLog::from_bytes()`, and `ReplayLog::scanner()` returns a scanner
+//! that scans it identically, chunk boundaries included, also when they
+//! were set by a `ChunkedReader`. Closures (the identifier predicate, the
+//! token hook and the text normalizers) are not recorded and must be set
+//! again on the replaying scanner.
+//!
+//! ```
+//! use scanner::*;
+//!
+//! let mut s = Scanner::init(b"(def a \"abc\")");
+//! s.set_mode(LOSSLESS_TOKENS);
+//! s.start_recording();
+//! while s.scan() != EOF {}
+//! let log = s.replay_log().unwrap();
+//!
+//! let loaded = ReplayLog::from_bytes(&log.to_bytes()).unwrap();
+//! let mut r: Scanner<'_> = loaded.scanner().unwrap();
+//! while r.scan() != EOF {}
+//! assert_eq!(r.replay_log(), Some(log));
+//! ```
+
+use core::fmt;
+use core::ops::Range;
+use alloc::borrow::Cow;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::extensions::Extensions;
+use crate::literal::decode_string;
+use crate::render::render_string;
+use crate::{
+    ChunkPattern, ChunkedReader, DisabledPolicy, DispatchMacro, EOF, EofPolicy, IdentLengthPolicy, SCAN_STRINGS, Scanner, SourceId, SourceKind,
+};
+
+const MAGIC: &str = "scanner-replay 1";
+
+/// ReplayLog holds everything needed to reproduce a scan.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplayLog {
+    /// Source of the positions; its registry index is not recorded.
+    pub source_id: SourceId,
+    pub mode: u32,
+    pub whitespace: u64,
+    pub lints: u32,
+    pub max_line_length: usize,
+    pub max_nesting_depth: usize,
+    pub max_raw_string_lines: usize,
+    pub max_ident_length: usize,
+    pub ident_length_policy: IdentLengthPolicy,
+    pub literate_prefix: Option<String>,
+    pub document_delimiter: Option<String>,
+    pub delimiter_pairs: Vec<(String, String)>,
+    pub line_comment_prefixes: Vec<String>,
+    pub reset_lines_per_document: bool,
+    pub nested_block_comments: bool,
+    pub namespaced_keywords: bool,
+    pub dispatch_macros: Vec<(char, DispatchMacro)>,
+    pub raw_string_delimiters: (char, char),
+    pub disabled_policy: DisabledPolicy,
+    pub eof_policy: EofPolicy,
+    /// Token kinds skipped by `next_significant()`, as mode-like bits.
+    pub trivia: u32,
+    /// Size of the inline buffer of the recorded scanner.
+    pub buffer_size: usize,
+    /// Ranges of the source copied into the buffer, in order.
+    pub chunks: Vec<Range<usize>>,
+    pub source: Vec<u8>,
+}
+
+/// ReplayError reports a replay log that can't be loaded or replayed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplayError {
+    /// The log is malformed at the given line, starting at 1.
+    Malformed { line: usize },
+    /// The log was recorded with a different buffer size.
+    BufferSize { recorded: usize, requested: usize },
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::Malformed { line } => write!(f, "replay log line {}: malformed entry", line),
+            ReplayError::BufferSize { recorded, requested } => write!(
+                f,
+                "replay log recorded with a {} byte buffer, replayed with {}",
+                recorded, requested
+            ),
+        }
+    }
+}
+
+impl core::error::Error for ReplayError {}
+
+impl ReplayLog {
+    pub(crate) fn new<const N: usize>(s: &Scanner<'_, N>) -> Self {
+        let default = Extensions::new();
+        let ext = s.ext.as_deref().unwrap_or(&default);
+        ReplayLog {
+            source_id: s.position.source.clone(),
+            mode: s.mode,
+            whitespace: s.whitespace,
+            lints: s.lints,
+            max_line_length: ext.max_line_length,
+            max_nesting_depth: ext.max_nesting_depth,
+            max_raw_string_lines: ext.max_raw_string_lines,
+            max_ident_length: ext.max_ident_length,
+            ident_length_policy: ext.ident_length_policy,
+            literate_prefix: ext.literate_prefix.clone(),
+            document_delimiter: ext.document_delimiter.clone(),
+            delimiter_pairs: ext.delimiter_pairs.clone(),
+            line_comment_prefixes: ext.line_comment_prefixes.iter().map(|p| String::from(p.as_ref())).collect(),
+            reset_lines_per_document: ext.reset_lines_per_document,
+            nested_block_comments: s.nested_block_comments,
+            namespaced_keywords: s.namespaced_keywords,
+            dispatch_macros: ext.dispatch_macros.clone(),
+            raw_string_delimiters: s.raw_string_delimiters,
+            disabled_policy: s.disabled_policy,
+            eof_policy: s.eof_policy,
+            trivia: s.trivia,
+            buffer_size: N,
+            chunks: Vec::new(),
+            source: s.src.to_vec(),
+        }
+    }
+
+    /// Returns a recording scanner over the logged source, with the logged
+    /// configuration. The buffer size N must be the recorded one.
+    pub fn scanner<const N: usize>(&self) -> Result<Scanner<'_, N>, ReplayError> {
+        if N != self.buffer_size {
+            return Err(ReplayError::BufferSize { recorded: self.buffer_size, requested: N });
+        }
+        let mut s = Scanner::with_buffer(&self.source);
+        s.position.source = self.source_id.clone();
+        s.mode = self.mode;
+        s.whitespace = self.whitespace;
+        s.lints = self.lints;
+        let ext = s.ext_mut();
+        ext.max_line_length = self.max_line_length;
+        ext.max_nesting_depth = self.max_nesting_depth;
+        ext.max_raw_string_lines = self.max_raw_string_lines;
+        ext.max_ident_length = self.max_ident_length;
+        ext.ident_length_policy = self.ident_length_policy;
+        ext.literate_prefix = self.literate_prefix.clone();
+        ext.document_delimiter = self.document_delimiter.clone();
+        ext.delimiter_pairs = self.delimiter_pairs.clone();
+        ext.line_comment_prefixes = self.line_comment_prefixes.iter().map(|p| Cow::Owned(p.clone())).collect();
+        ext.reset_lines_per_document = self.reset_lines_per_document;
+        ext.dispatch_macros = self.dispatch_macros.clone();
+        s.nested_block_comments = self.nested_block_comments;
+        s.namespaced_keywords = self.namespaced_keywords;
+        s.raw_string_delimiters = self.raw_string_delimiters;
+        s.disabled_policy = self.disabled_policy;
+        s.eof_policy = self.eof_policy;
+        s.trivia = self.trivia;
+        let ends = self.chunks.iter().map(|c| c.end).collect();
+        s.set_chunked_reader(ChunkedReader::new(&self.source, ChunkPattern::At(ends)));
+        s.start_recording();
+        Ok(s)
+    }
+
+    /// Encodes the log as a text header followed by the source bytes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let optional = |value: &Option<String>| match value {
+            Some(value) => render_string(value),
+            None => String::from("none"),
+        };
+        let policy = match self.disabled_policy {
+            DisabledPolicy::Chars => "chars",
+            DisabledPolicy::Skip => "skip",
+            DisabledPolicy::Other => "other",
+        };
+        let eof_policy = match self.eof_policy {
+            EofPolicy::Error => "error",
+            EofPolicy::NeedMoreInput => "need_more_input",
+        };
+        let ident_length_policy = match self.ident_length_policy {
+            IdentLengthPolicy::Truncate => "truncate",
+            IdentLengthPolicy::Error => "error",
+        };
+        let source_id = match self.source_id.kind() {
+            Some(SourceKind::Path) => format!("path {}", render_string(self.source_id.name())),
+            Some(SourceKind::Uri) => format!("uri {}", render_string(self.source_id.name())),
+            Some(SourceKind::Virtual) => format!("virtual {}", render_string(self.source_id.name())),
+            None => String::from("none"),
+        };
+        let delimiter_pairs: Vec<String> = self
+            .delimiter_pairs
+            .iter()
+            .map(|(open, close)| format!("{} {}", render_string(open), render_string(close)))
+            .collect();
+        let line_comment_prefixes: Vec<String> = self.line_comment_prefixes.iter().map(|p| render_string(p)).collect();
+        let dispatch_macros: Vec<String> = self
+            .dispatch_macros
+            .iter()
+            .map(|(ch, dispatch)| match dispatch {
+                DispatchMacro::Token(kind) => format!("{}:token:{}", *ch as u32, kind),
+                DispatchMacro::Literal(kind) => format!("{}:literal:{}", *ch as u32, kind),
+                DispatchMacro::Prefix(kind) => format!("{}:prefix:{}", *ch as u32, kind),
+            })
+            .collect();
+        let chunks: Vec<String> = self.chunks.iter().map(|c| format!("{}+{}", c.start, c.len())).collect();
+        let header = format!(
+            "{}\nsource_id {}\nmode {}\nwhitespace {}\nlints {}\nmax_line_length {}\n\
+             max_nesting_depth {}\nmax_raw_string_lines {}\nmax_ident_length {}\nident_length_policy {}\nliterate_prefix {}\ndocument_delimiter {}\n\
+             delimiter_pairs {}\nline_comment_prefixes {}\nreset_lines_per_document {}\n\
+             nested_block_comments {}\nnamespaced_keywords {}\ndispatch_macros {}\nraw_string_delimiters {} {}\n\
+             disabled_policy {}\neof_policy {}\n\
+             trivia {}\nbuffer_size {}\nchunks {}\nsource {}\n",
+            MAGIC,
+            source_id,
+            self.mode,
+            self.whitespace,
+            self.lints,
+            self.max_line_length,
+            self.max_nesting_depth,
+            self.max_raw_string_lines,
+            self.max_ident_length,
+            ident_length_policy,
+            optional(&self.literate_prefix),
+            optional(&self.document_delimiter),
+            delimiter_pairs.join(" "),
+            line_comment_prefixes.join(" "),
+            self.reset_lines_per_document,
+            self.nested_block_comments,
+            self.namespaced_keywords,
+            dispatch_macros.join(" "),
+            render_string(self.raw_string_delimiters.0.encode_utf8(&mut [0; 4])),
+            render_string(self.raw_string_delimiters.1.encode_utf8(&mut [0; 4])),
+            policy,
+            eof_policy,
+            self.trivia,
+            self.buffer_size,
+            chunks.join(" "),
+            self.source.len(),
+        );
+        let mut bytes = header.into_bytes();
+        bytes.extend_from_slice(&self.source);
+        bytes
+    }
+
+    /// Decodes a log encoded by `to_bytes()`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ReplayError> {
+        let mut rest = bytes;
+        let mut n = 0;
+        let mut next_line = |key: &str| -> Result<(usize, String), ReplayError> {
+            n += 1;
+            let malformed = ReplayError::Malformed { line: n };
+            let end = rest.iter().position(|&b| b == b'\n').ok_or(malformed)?;
+            let line = str::from_utf8(&rest[..end]).map_err(|_| malformed)?;
+            rest = &rest[end + 1..];
+            match line.split_once(' ') {
+                Some((k, value)) if k == key => Ok((n, String::from(value))),
+                _ if line == key => Ok((n, String::new())),
+                _ => Err(malformed),
+            }
+        };
+        fn number<T: core::str::FromStr>((line, value): (usize, String)) -> Result<T, ReplayError> {
+            value.parse().map_err(|_| ReplayError::Malformed { line })
+        }
+        fn string((line, value): (usize, String)) -> Result<String, ReplayError> {
+            decode_string(&value).ok_or(ReplayError::Malformed { line })
+        }
+
+        let (line, magic) = next_line("scanner-replay")?;
+        if magic != "1" {
+            return Err(ReplayError::Malformed { line });
+        }
+        let source_id = match next_line("source_id")? {
+            (_, value) if value == "none" => SourceId::default(),
+            (line, value) => {
+                let (kind, name) = value.split_once(' ').ok_or(ReplayError::Malformed { line })?;
+                let kind = match kind {
+                    "path" => SourceKind::Path,
+                    "uri" => SourceKind::Uri,
+                    "virtual" => SourceKind::Virtual,
+                    _ => return Err(ReplayError::Malformed { line }),
+                };
+                SourceId::new(kind, &string((line, String::from(name)))?)
+            }
+        };
+        let mode = number(next_line("mode")?)?;
+        let whitespace = number(next_line("whitespace")?)?;
+        let lints = number(next_line("lints")?)?;
+        let max_line_length = number(next_line("max_line_length")?)?;
+        let max_nesting_depth = number(next_line("max_nesting_depth")?)?;
+        let max_raw_string_lines = number(next_line("max_raw_string_lines")?)?;
+        let max_ident_length = number(next_line("max_ident_length")?)?;
+        let ident_length_policy = match next_line("ident_length_policy")? {
+            (_, value) if value == "truncate" => IdentLengthPolicy::Truncate,
+            (_, value) if value == "error" => IdentLengthPolicy::Error,
+            (line, _) => return Err(ReplayError::Malformed { line }),
+        };
+        let mut optional = |key: &str| match next_line(key)? {
+            (_, value) if value == "none" => Ok(None),
+            entry => string(entry).map(Some),
+        };
+        let literate_prefix = optional("literate_prefix")?;
+        let document_delimiter = optional("document_delimiter")?;
+        let strings = |(line, value): (usize, String)| -> Result<Vec<String>, ReplayError> {
+            let mut s = Scanner::init(value.as_bytes());
+            s.set_mode(SCAN_STRINGS);
+            let mut texts = Vec::new();
+            while s.scan() != EOF {
+                texts.push(string((line, s.token_text()))?);
+            }
+            if s.error_count() > 0 {
+                return Err(ReplayError::Malformed { line });
+            }
+            Ok(texts)
+        };
+        let entry = next_line("delimiter_pairs")?;
+        let line = entry.0;
+        let texts = strings(entry)?;
+        if texts.len() % 2 != 0 {
+            return Err(ReplayError::Malformed { line });
+        }
+        let delimiter_pairs = texts.chunks(2).map(|pair| (pair[0].clone(), pair[1].clone())).collect();
+        let line_comment_prefixes = strings(next_line("line_comment_prefixes")?)?;
+        let reset_lines_per_document = number(next_line("reset_lines_per_document")?)?;
+        let nested_block_comments = number(next_line("nested_block_comments")?)?;
+        let namespaced_keywords = number(next_line("namespaced_keywords")?)?;
+        let (line, value) = next_line("dispatch_macros")?;
+        let mut dispatch_macros = Vec::new();
+        for entry in value.split_whitespace() {
+            let malformed = ReplayError::Malformed { line };
+            let mut parts = entry.split(':');
+            let (Some(ch), Some(variant), Some(kind), None) = (parts.next(), parts.next(), parts.next(), parts.next()) else {
+                return Err(malformed);
+            };
+            let ch = char::from_u32(number((line, String::from(ch)))?).ok_or(malformed)?;
+            let kind = number((line, String::from(kind)))?;
+            let dispatch = match variant {
+                "token" => DispatchMacro::Token(kind),
+                "literal" => DispatchMacro::Literal(kind),
+                "prefix" => DispatchMacro::Prefix(kind),
+                _ => return Err(malformed),
+            };
+            dispatch_macros.push((ch, dispatch));
+        }
+        let entry = next_line("raw_string_delimiters")?;
+        let line = entry.0;
+        let raw_string_delimiters = match strings(entry)?.as_slice() {
+            [open, close] => {
+                let char_of = |text: &str| match text.chars().collect::<Vec<_>>()[..] {
+                    [c] => Ok(c),
+                    _ => Err(ReplayError::Malformed { line }),
+                };
+                (char_of(open)?, char_of(close)?)
+            }
+            _ => return Err(ReplayError::Malformed { line }),
+        };
+        let disabled_policy = match next_line("disabled_policy")? {
+            (_, value) if value == "chars" => DisabledPolicy::Chars,
+            (_, value) if value == "skip" => DisabledPolicy::Skip,
+            (_, value) if value == "other" => DisabledPolicy::Other,
+            (line, _) => return Err(ReplayError::Malformed { line }),
+        };
+        let eof_policy = match next_line("eof_policy")? {
+            (_, value) if value == "error" => EofPolicy::Error,
+            (_, value) if value == "need_more_input" => EofPolicy::NeedMoreInput,
+            (line, _) => return Err(ReplayError::Malformed { line }),
+        };
+        let trivia = number(next_line("trivia")?)?;
+        let buffer_size = number(next_line("buffer_size")?)?;
+        let (line, value) = next_line("chunks")?;
+        let mut chunks = Vec::new();
+        for chunk in value.split_whitespace() {
+            let (start, len) = chunk.split_once('+').ok_or(ReplayError::Malformed { line })?;
+            let start: usize = number((line, String::from(start)))?;
+            let len: usize = number((line, String::from(len)))?;
+            chunks.push(start..start + len);
+        }
+        let (line, len) = next_line("source")?;
+        let len: usize = number((line, len))?;
+        if rest.len() != len {
+            return Err(ReplayError::Malformed { line });
+        }
+
+        Ok(ReplayLog {
+            source_id,
+            mode,
+            whitespace,
+            lints,
+            max_line_length,
+            max_nesting_depth,
+            max_raw_string_lines,
+            max_ident_length,
+            ident_length_policy,
+            literate_prefix,
+            document_delimiter,
+            delimiter_pairs,
+            line_comment_prefixes,
+            reset_lines_per_document,
+            nested_block_comments,
+            namespaced_keywords,
+            dispatch_macros,
+            raw_string_delimiters,
+            disabled_policy,
+            eof_policy,
+            trivia,
+            buffer_size,
+            chunks,
+            source: rest.to_vec(),
+        })
+    }
+}
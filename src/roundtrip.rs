@@ -0,0 +1,100 @@
+// Copyright 2022 Jordi Íñigo Griera. All rights reserved.
+
+//! Round-trip verification of literals.
+
+use alloc::string::String;
+
+use crate::literal::{decode_float, decode_int, decode_raw_string, decode_string};
+use crate::render::{RenderOptions, render_float, render_int, render_raw_string, render_string};
+use crate::{EOF, FLOAT, INT, RAW_STRING, STRING, Scanner, Span, Token};
+
+/// The step of the round trip at which a literal failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MismatchKind {
+    /// The literal could not be decoded into a value.
+    Undecodable,
+    /// The value has no literal form.
+    Unrenderable,
+    /// The rendered literal doesn't scan back as a single token of the same kind.
+    NotSingleToken,
+    /// The rendered literal decodes to a different value.
+    ValueChanged,
+}
+
+/// RoundtripMismatch describes the first literal that failed the round trip.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RoundtripMismatch {
+    pub kind: MismatchKind,
+    pub tok: Token,
+    /// The literal as written in the source.
+    pub text: String,
+    /// The re-rendered literal, if rendering succeeded.
+    pub rendered: Option<String>,
+    pub span: Span,
+}
+
+#[derive(PartialEq)]
+enum Value {
+    Str(String),
+    Int(i128),
+    Float(u64), // bit pattern, so that values compare exactly
+}
+
+fn decode(tok: Token, text: &str) -> Option<Value> {
+    match tok {
+        STRING => decode_string(text).map(Value::Str),
+        RAW_STRING => decode_raw_string(text).map(Value::Str),
+        INT => decode_int(text).map(Value::Int),
+        FLOAT => decode_float(text).map(|f| Value::Float(f.to_bits())),
+        _ => None,
+    }
+}
+
+fn render(tok: Token, value: &Value) -> Option<String> {
+    match (tok, value) {
+        (STRING, Value::Str(s)) => Some(render_string(s)),
+        (RAW_STRING, Value::Str(s)) => Some(render_raw_string(s)),
+        (INT, Value::Int(i)) => Some(render_int(*i, &RenderOptions::default())),
+        (FLOAT, Value::Float(bits)) => render_float(f64::from_bits(*bits)),
+        _ => None,
+    }
+}
+
+/// Scans `src` and, for every string, raw string, integer and float
+/// literal, decodes it, re-renders the value, re-scans the rendered text
+/// and checks that it decodes to the same value. Returns the first
+/// literal for which any step fails, or None if all literals round-trip.
+pub fn validate_roundtrip(src: &[u8]) -> Option<RoundtripMismatch> {
+    let mut s = Scanner::init(src);
+    loop {
+        let t = s.scan_spanned();
+        match t.tok {
+            EOF => return None,
+            STRING | RAW_STRING | INT | FLOAT => {}
+            _ => continue,
+        }
+        let mismatch = |kind, rendered| RoundtripMismatch {
+            kind,
+            tok: t.tok,
+            text: t.text.clone(),
+            rendered,
+            span: t.span.clone(),
+        };
+        let Some(value) = decode(t.tok, &t.text) else {
+            return Some(mismatch(MismatchKind::Undecodable, None));
+        };
+        let Some(rendered) = render(t.tok, &value) else {
+            return Some(mismatch(MismatchKind::Unrenderable, None));
+        };
+
+        let mut rs = Scanner::init(rendered.as_bytes());
+        let tok = rs.scan();
+        let text = rs.token_bytes();
+        if tok != t.tok || rs.scan() != EOF || rs.error_count() != 0 {
+            return Some(mismatch(MismatchKind::NotSingleToken, Some(rendered)));
+        }
+        if decode(tok, &String::from_utf8_lossy(text)) != Some(value) {
+            return Some(mismatch(MismatchKind::ValueChanged, Some(rendered)));
+        }
+    }
+}
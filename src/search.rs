@@ -0,0 +1,178 @@
+// Copyright 2022 Jordi Íñigo Griera. All rights reserved.
+
+//! Syntax-aware search over a source.
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::{EOF, IDENT, Scanner, Span, SpannedToken, Token};
+
+/// FindTokens is the iterator returned by `find_tokens`.
+pub struct FindTokens<'a, P> {
+    scanner: Scanner<'a>,
+    predicate: P,
+}
+
+/// Scans `src` and yields only the tokens for which `predicate(tok, text)`
+/// returns true, together with their spans.
+///
+/// Since matching is done on whole tokens, text inside strings and
+/// comments never matches an identifier or number predicate.
+pub fn find_tokens<P>(src: &[u8], predicate: P) -> FindTokens<'_, P>
+where
+    P: FnMut(Token, &str) -> bool,
+{
+    FindTokens {
+        scanner: Scanner::init(src),
+        predicate,
+    }
+}
+
+impl<'a, P> FindTokens<'a, P> {
+    /// Returns the underlying scanner, e.g. to inspect its diagnostics.
+    pub fn scanner(&self) -> &Scanner<'a> {
+        &self.scanner
+    }
+}
+
+impl<P> Iterator for FindTokens<'_, P>
+where
+    P: FnMut(Token, &str) -> bool,
+{
+    type Item = SpannedToken;
+
+    fn next(&mut self) -> Option<SpannedToken> {
+        loop {
+            let t = self.scanner.scan_spanned();
+            if t.tok == EOF {
+                return None;
+            }
+            if (self.predicate)(t.tok, &t.text) {
+                return Some(t);
+            }
+        }
+    }
+}
+
+/// Returns the token covering the byte `offset` of `src` scanned with the
+/// default configuration, or None if the offset is in white space or a
+/// skipped comment. To query many offsets, build a `TokenTable` once.
+///
+/// ```
+/// use scanner::*;
+///
+/// let t = token_at(b"(def answer 42)", 7).unwrap();
+/// assert_eq!((t.tok, t.text.as_str()), (IDENT, "answer"));
+/// assert!(token_at(b"(def answer 42)", 4).is_none());
+/// ```
+pub fn token_at(src: &[u8], offset: usize) -> Option<SpannedToken> {
+    TokenTable::build(src).token_at(offset).cloned()
+}
+
+/// TokenTable holds the tokens of a source in source order, to find the
+/// token covering an offset by binary search, e.g. for hover or
+/// click-to-select in an editor.
+#[derive(Debug, Clone, Default)]
+pub struct TokenTable {
+    tokens: Vec<SpannedToken>,
+}
+
+impl TokenTable {
+    /// Builds the table of `src` scanned with the default configuration.
+    pub fn build(src: &[u8]) -> Self {
+        Self::from_scanner(&mut Scanner::init(src))
+    }
+
+    /// Builds the table from the remaining tokens of a configured scanner.
+    pub fn from_scanner(s: &mut Scanner) -> Self {
+        Self::from_tokens(s.tokens().spanned())
+    }
+
+    /// Builds the table from a stream of tokens, which must be in source
+    /// order and not overlap.
+    pub fn from_tokens(tokens: impl IntoIterator<Item = SpannedToken>) -> Self {
+        TokenTable { tokens: tokens.into_iter().collect() }
+    }
+
+    /// Returns the token whose span contains the byte `offset`, or None
+    /// if it falls between tokens.
+    pub fn token_at(&self, offset: usize) -> Option<&SpannedToken> {
+        let i = self.tokens.partition_point(|t| t.span.end.offset <= offset);
+        self.tokens.get(i).filter(|t| t.span.start.offset <= offset)
+    }
+
+    /// Returns the tokens in source order.
+    pub fn tokens(&self) -> &[SpannedToken] {
+        &self.tokens
+    }
+
+    /// Returns the number of tokens.
+    pub fn len(&self) -> usize {
+        self.tokens.len()
+    }
+
+    /// Reports whether the table has no tokens.
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
+    }
+}
+
+/// ReferenceIndex maps the text of every identifier in a source to the spans
+/// of all its occurrences, in source order.
+///
+/// It is built in a single scanning pass and is meant for cheap
+/// find-references and rename prechecks that don't need semantic analysis.
+#[derive(Debug, Clone, Default)]
+pub struct ReferenceIndex {
+    refs: BTreeMap<String, Vec<Span>>,
+}
+
+impl ReferenceIndex {
+    /// Builds the index of `src` scanned with the default configuration.
+    pub fn build(src: &[u8]) -> Self {
+        Self::from_scanner(&mut Scanner::init(src))
+    }
+
+    /// Builds the index from the remaining tokens of a configured scanner.
+    pub fn from_scanner(s: &mut Scanner) -> Self {
+        let mut index = ReferenceIndex::default();
+        loop {
+            let t = s.scan_spanned();
+            match t.tok {
+                EOF => break,
+                // "~@" and "#{" are scanned as IDENT but are not identifiers
+                IDENT if t.text != "~@" && t.text != "#{" => {
+                    index.refs.entry(t.text).or_default().push(t.span);
+                }
+                _ => {}
+            }
+        }
+        index
+    }
+
+    /// Returns the spans of all occurrences of `name`.
+    pub fn references(&self, name: &str) -> &[Span] {
+        self.refs.get(name).map_or(&[], |v| v.as_slice())
+    }
+
+    /// Reports whether `name` occurs in the source.
+    pub fn contains(&self, name: &str) -> bool {
+        self.refs.contains_key(name)
+    }
+
+    /// Returns the distinct identifiers, in lexicographic order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.refs.keys().map(|k| k.as_str())
+    }
+
+    /// Returns the number of distinct identifiers.
+    pub fn len(&self) -> usize {
+        self.refs.len()
+    }
+
+    /// Reports whether the index has no identifiers.
+    pub fn is_empty(&self) -> bool {
+        self.refs.is_empty()
+    }
+}
@@ -0,0 +1,237 @@
+// Copyright 2022 Jordi Íñigo Griera. All rights reserved.
+
+//! The token source abstraction.
+//!
+//! Parsers written against `TokenSource` instead of `Scanner` can be fed
+//! by any backend producing tokens, e.g. a scanner over a different kind
+//! of buffer or a fabricated token sequence in tests. Besides `Scanner`
+//! and `VecTokenSource`, the synchronous backends are token sources:
+//! `IterScanner`, `PushScanner` and, with the `std` feature,
+//! `LineScanner` and `TokenPipeline`. The backends returning
+//! whole tokens record the latest one for `token_text()` and `span()`.
+//!
+//! ```
+//! use scanner::*;
+//!
+//! // Returns the maximum nesting depth of parentheses.
+//! fn depth(src: &mut impl TokenSource) -> usize {
+//!     let (mut depth, mut max) = (0usize, 0);
+//!     loop {
+//!         match src.scan() {
+//!             EOF => return max,
+//!             tok if tok == '(' as i32 => depth += 1,
+//!             tok if tok == ')' as i32 => depth = depth.saturating_sub(1),
+//!             _ => {}
+//!         }
+//!         max = max.max(depth);
+//!     }
+//! }
+//!
+//! assert_eq!(depth(&mut Scanner::init(b"(a (b (c)) (d))")), 3);
+//! ```
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::{COMMENT, Diagnostic, EOF, Position, Scanner, SourceId, Span, SpannedToken, Token};
+
+/// TokenSource is a source of tokens, as produced by `Scanner`.
+pub trait TokenSource {
+    /// Scans and returns the next token or Unicode character, EOF at the
+    /// end of the source.
+    fn scan(&mut self) -> Token;
+
+    /// Returns the text of the most recently scanned token.
+    fn token_text(&self) -> String;
+
+    /// Returns the span of the most recently scanned token.
+    fn span(&self) -> Span;
+
+    /// Returns the diagnostics reported so far.
+    fn diagnostics(&self) -> &[Diagnostic];
+
+    /// Scans and returns the next token with its text and span.
+    fn scan_spanned(&mut self) -> SpannedToken {
+        let tok = self.scan();
+        SpannedToken {
+            tok,
+            text: self.token_text(),
+            span: self.span(),
+        }
+    }
+}
+
+impl<const N: usize> TokenSource for Scanner<'_, N> {
+    fn scan(&mut self) -> Token {
+        Scanner::scan(self)
+    }
+
+    fn token_text(&self) -> String {
+        Scanner::token_text(self)
+    }
+
+    fn span(&self) -> Span {
+        self.token_span()
+    }
+
+    fn diagnostics(&self) -> &[Diagnostic] {
+        Scanner::diagnostics(self)
+    }
+
+    fn scan_spanned(&mut self) -> SpannedToken {
+        Scanner::scan_spanned(self)
+    }
+}
+
+// Latest token of a backend returning `SpannedToken`s, recorded to
+// implement TokenSource.
+#[derive(Debug, Clone)]
+pub(crate) struct LastToken {
+    token: Option<SpannedToken>,
+    end: Position, // end of the latest token
+}
+
+impl LastToken {
+    pub(crate) fn new() -> Self {
+        LastToken { token: None, end: Position { line: 1, column: 1, ..Default::default() } }
+    }
+
+    // Records the next token, None at the end of the source, and returns
+    // its kind.
+    pub(crate) fn record(&mut self, token: Option<SpannedToken>) -> Token {
+        if let Some(t) = &token {
+            self.end = t.span.end.clone();
+        }
+        self.token = token;
+        self.token.as_ref().map_or(EOF, |t| t.tok)
+    }
+
+    pub(crate) fn text(&self) -> String {
+        self.token.as_ref().map(|t| t.text.clone()).unwrap_or_default()
+    }
+
+    pub(crate) fn span(&self) -> Span {
+        match &self.token {
+            Some(t) => t.span.clone(),
+            None => Span { start: self.end.clone(), end: self.end.clone() },
+        }
+    }
+}
+
+/// VecTokenSource is a token source replaying a given token sequence, to
+/// test parsers without writing source text for every case. Positions are
+/// fabricated as if the tokens were separated by a space, or by a newline
+/// after comments.
+///
+/// ```
+/// use scanner::*;
+///
+/// let mut src = VecTokenSource::new(vec![(IDENT, "foo"), (INT, "42")]);
+/// assert_eq!(src.scan(), IDENT);
+/// assert_eq!(src.scan(), INT);
+/// assert_eq!(src.span().start.column, 5);
+/// assert_eq!(src.scan(), EOF);
+/// ```
+#[derive(Debug, Clone)]
+pub struct VecTokenSource {
+    tokens: Vec<SpannedToken>,
+    end: Position,
+    next: usize,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl VecTokenSource {
+    /// Creates a token source returning the given tokens and texts.
+    pub fn new<I, T>(tokens: I) -> Self
+    where
+        I: IntoIterator<Item = (Token, T)>,
+        T: Into<String>,
+    {
+        let mut pos = Position {
+            source: SourceId::default(),
+            offset: 0,
+            line: 1,
+            column: 1,
+            #[cfg(feature = "pages")]
+            page: 1,
+        };
+        let tokens = tokens
+            .into_iter()
+            .map(|(tok, text)| {
+                let text = text.into();
+                let start = pos.clone();
+                advance(&mut pos, &text);
+                let span = Span { start, end: pos.clone() };
+                advance(&mut pos, if tok == COMMENT { "\n" } else { " " });
+                SpannedToken { tok, text, span }
+            })
+            .collect();
+        VecTokenSource { tokens, end: pos, next: 0, diagnostics: Vec::new() }
+    }
+
+    /// Sets the source path of all the positions.
+    pub fn set_filename(&mut self, filename: &str) {
+        let source = SourceId::from(filename);
+        for t in &mut self.tokens {
+            t.span.start.source = source.clone();
+            t.span.end.source = source.clone();
+        }
+        self.end.source = source;
+    }
+
+    /// Adds a diagnostic to be returned by `diagnostics()`.
+    pub fn push_diagnostic(&mut self, diagnostic: Diagnostic) {
+        self.diagnostics.push(diagnostic);
+    }
+
+    fn current(&self) -> Option<&SpannedToken> {
+        self.next.checked_sub(1).and_then(|i| self.tokens.get(i))
+    }
+}
+
+fn advance(pos: &mut Position, text: &str) {
+    for ch in text.chars() {
+        pos.offset += ch.len_utf8();
+        if ch == '\n' {
+            pos.line += 1;
+            pos.column = 1;
+        } else {
+            pos.column += 1;
+        }
+    }
+}
+
+impl TokenSource for VecTokenSource {
+    fn scan(&mut self) -> Token {
+        if self.next <= self.tokens.len() {
+            self.next += 1;
+        }
+        self.current().map_or(EOF, |t| t.tok)
+    }
+
+    fn token_text(&self) -> String {
+        self.current().map(|t| t.text.clone()).unwrap_or_default()
+    }
+
+    fn span(&self) -> Span {
+        match self.current() {
+            Some(t) => t.span.clone(),
+            None if self.next == 0 => {
+                let start = Position {
+                    source: self.end.source.clone(),
+                    offset: 0,
+                    line: 1,
+                    column: 1,
+                    #[cfg(feature = "pages")]
+                    page: 1,
+                };
+                Span { start: start.clone(), end: start }
+            }
+            None => Span { start: self.end.clone(), end: self.end.clone() },
+        }
+    }
+
+    fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+}
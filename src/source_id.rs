@@ -0,0 +1,148 @@
+// Copyright 2022 Jordi Íñigo Griera. All rights reserved.
+
+//! Identification of the source of positions.
+//!
+//! Positions refer to their source with a `SourceId`, a shared handle that
+//! is cloned without copying the source name. A `SourceRegistry` interns
+//! the sources of a session, e.g. the files and unsaved buffers of a
+//! language server, and numbers them:
+//!
+//! ```
+//! use scanner::*;
+//!
+//! let mut registry = SourceRegistry::new();
+//! let id = registry.register(SourceKind::Uri, "untitled:Untitled-1");
+//! assert_eq!(registry.register(SourceKind::Uri, "untitled:Untitled-1"), id);
+//!
+//! let mut s = Scanner::init(b"(a b)");
+//! s.position.source = id.clone();
+//! s.scan();
+//! assert_eq!(s.position.to_string(), "untitled:Untitled-1:1:1");
+//! assert_eq!(s.position.source.index(), Some(0));
+//! assert_eq!(registry.get(0), Some(&id));
+//! ```
+
+use core::fmt;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+/// Kind of the name of a source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SourceKind {
+    /// A file system path.
+    Path,
+    /// A URI, e.g. of an unsaved editor buffer.
+    Uri,
+    /// A name for generated or otherwise virtual sources, e.g. `<repl>`.
+    Virtual,
+}
+
+#[derive(Debug, PartialEq, Eq, Hash)]
+struct SourceInfo {
+    kind: SourceKind,
+    name: String,
+    index: Option<usize>,
+}
+
+/// SourceId identifies the source of a position. The default id is the
+/// anonymous source, displayed as `<input>`. Ids are compared by kind,
+/// name and registry index.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct SourceId(Option<Arc<SourceInfo>>);
+
+impl SourceId {
+    /// Returns an unregistered id of a source.
+    pub fn new(kind: SourceKind, name: &str) -> Self {
+        SourceId(Some(Arc::new(SourceInfo { kind, name: String::from(name), index: None })))
+    }
+
+    /// Returns the name of the source, empty for the anonymous source.
+    pub fn name(&self) -> &str {
+        self.0.as_ref().map_or("", |info| &info.name)
+    }
+
+    /// Returns the kind of the source, None for the anonymous source.
+    pub fn kind(&self) -> Option<SourceKind> {
+        self.0.as_ref().map(|info| info.kind)
+    }
+
+    /// Returns the index of the source in its registry, None if it wasn't
+    /// registered.
+    pub fn index(&self) -> Option<usize> {
+        self.0.as_ref().and_then(|info| info.index)
+    }
+
+    /// Reports whether this is the anonymous source.
+    pub fn is_anonymous(&self) -> bool {
+        self.0.is_none()
+    }
+}
+
+/// A source path, or the anonymous source for an empty string.
+impl From<&str> for SourceId {
+    fn from(path: &str) -> Self {
+        if path.is_empty() {
+            return SourceId::default();
+        }
+        SourceId::new(SourceKind::Path, path)
+    }
+}
+
+impl fmt::Display for SourceId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.0 {
+            Some(info) => f.write_str(&info.name),
+            None => f.write_str("<input>"),
+        }
+    }
+}
+
+/// SourceRegistry interns source ids and numbers them in registration
+/// order, starting at 0.
+#[derive(Debug, Clone, Default)]
+pub struct SourceRegistry {
+    sources: Vec<SourceId>,
+    ids: BTreeMap<(SourceKind, String), SourceId>,
+}
+
+impl SourceRegistry {
+    /// Returns an empty registry.
+    pub fn new() -> Self {
+        SourceRegistry::default()
+    }
+
+    /// Returns the id of the source, registering it if needed.
+    pub fn register(&mut self, kind: SourceKind, name: &str) -> SourceId {
+        let key = (kind, String::from(name));
+        if let Some(id) = self.ids.get(&key) {
+            return id.clone();
+        }
+        let index = Some(self.sources.len());
+        let id = SourceId(Some(Arc::new(SourceInfo { kind, name: String::from(name), index })));
+        self.sources.push(id.clone());
+        self.ids.insert(key, id.clone());
+        id
+    }
+
+    /// Returns the id of the source with the given index.
+    pub fn get(&self, index: usize) -> Option<&SourceId> {
+        self.sources.get(index)
+    }
+
+    /// Returns the number of registered sources.
+    pub fn len(&self) -> usize {
+        self.sources.len()
+    }
+
+    /// Reports whether no source is registered.
+    pub fn is_empty(&self) -> bool {
+        self.sources.is_empty()
+    }
+
+    /// Returns an iterator over the registered sources, in index order.
+    pub fn iter(&self) -> impl Iterator<Item = &SourceId> {
+        self.sources.iter()
+    }
+}
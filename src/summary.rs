@@ -0,0 +1,84 @@
+// Copyright 2022 Jordi Íñigo Griera. All rights reserved.
+
+//! End of scan report.
+//!
+//! A scanner enabled with `set_summary(true)` keeps a tally of the tokens
+//! it scans, which it reports once it reaches EOF as a `ScanSummary`, so
+//! that batch validators get the totals of a source and its unterminated
+//! constructs in a single value. The tally is off by default, as it costs
+//! a map update per token.
+//!
+//! ```
+//! use scanner::*;
+//!
+//! let mut s = Scanner::init(b"(def a\n  [1 2 \"b)\n");
+//! s.set_summary(true);
+//! assert!(s.eof_summary().is_none());
+//! while s.scan() != EOF {}
+//! let summary = s.eof_summary().unwrap();
+//! assert_eq!((summary.lines, summary.bytes), (2, 18));
+//! assert_eq!(summary.count(INT), 2);
+//! assert_eq!(summary.token_count(), 7);
+//! let open: Vec<&str> = summary.unclosed.iter().map(|t| t.text.as_str()).collect();
+//! assert_eq!(open, ["(", "["]);
+//! assert_eq!(summary.unterminated[0].start.column, 8);
+//! ```
+
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+use crate::{Span, SpannedToken, Token};
+
+/// Report of a scan, returned by `Scanner::eof_summary()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScanSummary {
+    /// Number of lines of the source; a final line terminator doesn't
+    /// start a new line.
+    pub lines: usize,
+    /// Length of the source in bytes.
+    pub bytes: usize,
+    /// Number of tokens of each kind or character.
+    pub tokens: BTreeMap<Token, usize>,
+    /// Brackets left open, outermost first.
+    pub unclosed: Vec<SpannedToken>,
+    /// Spans of the strings and raw strings left unterminated, in source
+    /// order.
+    pub unterminated: Vec<Span>,
+}
+
+impl ScanSummary {
+    /// Returns the number of tokens of the given kind or character.
+    pub fn count(&self, kind: Token) -> usize {
+        self.tokens.get(&kind).copied().unwrap_or(0)
+    }
+
+    /// Returns the total number of tokens.
+    pub fn token_count(&self) -> usize {
+        self.tokens.values().sum()
+    }
+}
+
+// Tally of the tokens scanned so far. The brackets left open are also
+// tracked for the delimiter lints, with the summary off.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Tally {
+    pub(crate) tokens: BTreeMap<Token, usize>,
+    pub(crate) open: Vec<OpenDelimiter>,
+    pub(crate) unterminated: Vec<Span>,
+    pub(crate) eof: bool,
+}
+
+// A bracket or delimiter left open: its token, the index of its pair if
+// it was added with add_delimiter_pair(), and its span.
+#[derive(Debug, Clone)]
+pub(crate) struct OpenDelimiter {
+    pub(crate) tok: Token,
+    pub(crate) pair: Option<usize>,
+    pub(crate) span: Span,
+}
+
+impl Tally {
+    pub(crate) fn new() -> Self {
+        Tally::default()
+    }
+}
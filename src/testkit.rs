@@ -0,0 +1,521 @@
+// Copyright 2022 Jordi Íñigo Griera. All rights reserved.
+
+//! Conformance suites for scanner configurations.
+//!
+//! A dialect customizes the mode, white space, delimiters, comment prefixes
+//! or identifier predicate of a scanner. The suites of this module scan
+//! edge cases of numbers, strings, comments, positions and buffer refills
+//! with such a configuration, given as the function applying it, and check
+//! the properties every configuration has to keep. The cases are built
+//! from the configuration itself, e.g. its comment prefixes and delimiters,
+//! and the cases of tokens it doesn't scan are skipped, so that dialect
+//! authors can run the suites on their own presets:
+//!
+//! ```
+//! use scanner::*;
+//!
+//! let failures = testkit::run_all(|s: &mut Scanner| s.set_mode(LOSSLESS_TOKENS));
+//! assert!(failures.is_empty(), "{failures:#?}");
+//! ```
+
+use core::fmt;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::literal::{decode_float, decode_int, decode_raw_string_with, decode_string};
+use crate::{
+    ChunkPattern, ChunkedReader, COMMENT, Diagnostic, DiagnosticCode, EofPolicy, FLOAT, INT, NEED_MORE_INPUT, RAW_STRING,
+    SCAN_BLOCK_COMMENTS, SCAN_COMMENTS, SCAN_FLOATS, SCAN_INTS, SCAN_RAW_STRINGS, SCAN_STRINGS, SKIP_COMMENTS, STRING,
+    Scanner, SpannedToken, Token, token_string,
+};
+
+/// Suite selects a group of conformance cases.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Suite {
+    /// Integer and float literals in every radix, with separators and
+    /// exponents, alone and next to white space and delimiters.
+    Numbers,
+    /// String and raw string literals with every escape, and invalid or
+    /// unterminated ones.
+    Strings,
+    /// Line comments with every configured prefix, and block comments.
+    Comments,
+    /// Token positions and texts against the source.
+    Positions,
+    /// Tokens and diagnostics with buffer refills at every offset.
+    ChunkBoundaries,
+}
+
+impl Suite {
+    /// All the suites, in the order `run_all()` runs them.
+    pub const ALL: [Suite; 5] = [Suite::Numbers, Suite::Strings, Suite::Comments, Suite::Positions, Suite::ChunkBoundaries];
+}
+
+/// Formats the suite in snake case, e.g. `chunk_boundaries`.
+impl fmt::Display for Suite {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Suite::Numbers => "numbers",
+            Suite::Strings => "strings",
+            Suite::Comments => "comments",
+            Suite::Positions => "positions",
+            Suite::ChunkBoundaries => "chunk_boundaries",
+        })
+    }
+}
+
+/// Failure describes a conformance case failed by a configuration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Failure {
+    pub suite: Suite,
+    /// The source scanned by the case.
+    pub source: String,
+    /// What was expected and what was found instead.
+    pub message: String,
+}
+
+/// Formats the failure as `suite: "source": message`.
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {:?}: {}", self.suite, self.source, self.message)
+    }
+}
+
+/// Runs a suite with the configuration applied by configure to a new
+/// scanner and returns the failed cases.
+pub fn run<F: Fn(&mut Scanner<'_>)>(suite: Suite, configure: F) -> Vec<Failure> {
+    let mut kit = Kit::new(&configure);
+    kit.run(suite);
+    kit.failures
+}
+
+/// Runs all the suites like `run()` and returns the failed cases.
+pub fn run_all<F: Fn(&mut Scanner<'_>)>(configure: F) -> Vec<Failure> {
+    let mut kit = Kit::new(&configure);
+    for suite in Suite::ALL {
+        kit.run(suite);
+    }
+    kit.failures
+}
+
+// Integer literals and their values, None if out of range
+const INTS: &[(&str, Option<i128>)] = &[
+    ("0", Some(0)),
+    ("7", Some(7)),
+    ("42", Some(42)),
+    ("1234567890", Some(1234567890)),
+    ("1_000_000", Some(1_000_000)),
+    ("0x1F", Some(0x1F)),
+    ("0XAB", Some(0xAB)),
+    ("0x_ff", Some(0xFF)),
+    ("0o17", Some(0o17)),
+    ("0O17", Some(0o17)),
+    ("017", Some(0o17)),
+    ("0b101", Some(0b101)),
+    ("0B11", Some(0b11)),
+    ("170141183460469231731687303715884105727", Some(i128::MAX)),
+    ("9999999999999999999999999999999999999999", None),
+];
+
+const FLOATS: &[(&str, f64)] = &[
+    ("1.5", 1.5),
+    ("0.25", 0.25),
+    ("1.", 1.0),
+    (".5", 0.5),
+    ("1e3", 1e3),
+    ("1E-3", 1e-3),
+    ("1.5e+2", 1.5e2),
+    ("6.02e23", 6.02e23),
+    ("1_000.5", 1000.5),
+    ("0x1p-2", 0.25),
+    ("0x1.8p1", 3.0),
+];
+
+// Malformed numbers and the error reported
+const BAD_NUMBERS: &[(&str, DiagnosticCode)] = &[
+    ("0x", DiagnosticCode::NoDigits),
+    ("0b2", DiagnosticCode::InvalidDigit),
+    ("0o8", DiagnosticCode::InvalidDigit),
+    ("08", DiagnosticCode::InvalidDigit),
+    ("1_", DiagnosticCode::InvalidSeparator),
+    ("1__0", DiagnosticCode::InvalidSeparator),
+];
+
+const BAD_FLOATS: &[(&str, DiagnosticCode)] = &[
+    ("1e", DiagnosticCode::NoExponentDigits),
+    ("0x1p", DiagnosticCode::NoExponentDigits),
+];
+
+// String literals and their values
+const STRINGS: &[(&str, &str)] = &[
+    (r#""""#, ""),
+    (r#""a""#, "a"),
+    (r#""a b""#, "a b"),
+    (r#""a\tb""#, "a\tb"),
+    (r#""\a\b\f\n\r\t\v""#, "\x07\x08\x0C\n\r\t\x0B"),
+    (r#""\\""#, "\\"),
+    (r#""\"""#, "\""),
+    (r#""\x41""#, "A"),
+    (r#""\101""#, "A"),
+    (r#""\u00e9""#, "é"),
+    (r#""\U0001F389""#, "🎉"),
+    ("\"ñ🎉\"", "ñ🎉"),
+    (r#""; #| ¬""#, "; #| ¬"),
+];
+
+const BAD_STRINGS: &[&str] = &[r#""a\qb""#, r#""\x4""#, r#""\u12""#, r#""\z""#];
+
+// The configuration under test, as applied to a probe scanner.
+struct Config {
+    mode: u32,
+    space: Option<char>,
+    delimiters: Option<(String, String)>,
+    line_comment_prefixes: Vec<String>,
+    nested_block_comments: bool,
+    raw_string_delimiters: (char, char),
+    eof_policy: EofPolicy,
+}
+
+// The tokens and diagnostics of a scan.
+#[derive(PartialEq)]
+struct Scan {
+    tokens: Vec<SpannedToken>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Scan {
+    fn errors(&self) -> usize {
+        self.diagnostics.iter().filter(|d| d.is_error()).count()
+    }
+
+    fn has_error(&self, code: DiagnosticCode) -> bool {
+        self.diagnostics.iter().any(|d| d.is_error() && d.code == code)
+    }
+
+    // Returns the token spanning the given byte range.
+    fn token(&self, start: usize, end: usize) -> Option<&SpannedToken> {
+        self.tokens.iter().find(|t| t.span.start.offset == start && t.span.end.offset == end)
+    }
+}
+
+struct Kit<'f> {
+    configure: &'f dyn Fn(&mut Scanner<'_>),
+    config: Config,
+    suite: Suite,
+    failures: Vec<Failure>,
+}
+
+impl<'f> Kit<'f> {
+    fn new(configure: &'f dyn Fn(&mut Scanner<'_>)) -> Self {
+        let mut s = Scanner::init(b"");
+        configure(&mut s);
+        let (open, close) = s.raw_string_delimiters;
+        let punct = |d: &str| {
+            let mut chars = d.chars();
+            matches!((chars.next(), chars.next()), (Some(c), None) if c.is_ascii_punctuation() && c != '"')
+        };
+        let config = Config {
+            mode: s.mode,
+            space: [' ', '\t'].into_iter().find(|&c| s.is_whitespace(c)),
+            delimiters: s.capabilities().delimiters.into_iter().find(|(o, c)| punct(o) && punct(c)),
+            line_comment_prefixes: s.line_comment_prefixes().iter().map(|p| String::from(p.as_ref())).collect(),
+            nested_block_comments: s.nested_block_comments,
+            raw_string_delimiters: (open, close),
+            eof_policy: s.eof_policy,
+        };
+        Kit { configure, config, suite: Suite::Numbers, failures: Vec::new() }
+    }
+
+    fn run(&mut self, suite: Suite) {
+        self.suite = suite;
+        match suite {
+            Suite::Numbers => self.numbers(),
+            Suite::Strings => self.strings(),
+            Suite::Comments => self.comments(),
+            Suite::Positions => self.positions(),
+            Suite::ChunkBoundaries => self.chunk_boundaries(),
+        }
+    }
+
+    fn scan(&self, src: &str, pattern: Option<ChunkPattern>) -> Scan {
+        let mut s = Scanner::init(src.as_bytes());
+        (self.configure)(&mut s);
+        if let Some(pattern) = pattern {
+            s.set_chunked_reader(ChunkedReader::new(src.as_bytes(), pattern));
+        }
+        let tokens = s.tokens().spanned().collect();
+        Scan { tokens, diagnostics: s.diagnostics().to_vec() }
+    }
+
+    fn fail(&mut self, src: &str, message: String) {
+        self.failures.push(Failure { suite: self.suite, source: String::from(src), message });
+    }
+
+    // Returns the sources placing the literal text alone and next to white
+    // space and delimiters, with the offset of the literal in each.
+    fn contexts(&self, text: &str) -> Vec<(String, usize)> {
+        let mut contexts = vec![(String::from(text), 0), (format!("{text}\n"), 0)];
+        if let Some(space) = self.config.space {
+            contexts.push((format!("{space}{text}{space}"), 1));
+        }
+        if let Some((open, close)) = &self.config.delimiters {
+            contexts.push((format!("{open}{text}{close}"), open.len()));
+        }
+        contexts
+    }
+
+    // Checks that the literal text scans as a single token of kind tok
+    // without errors in every context, and returns the contexts passing.
+    fn check_literal(&mut self, text: &str, tok: Token) -> bool {
+        let mut ok = true;
+        for (src, start) in self.contexts(text) {
+            let scan = self.scan(&src, None);
+            let message = match scan.token(start, start + text.len()) {
+                None => Some(format!("expected {} {:?}, found {}", token_string(tok), text, describe(&scan.tokens))),
+                Some(t) if t.tok != tok => Some(format!("expected {} {:?}, found {}", token_string(tok), text, token_string(t.tok))),
+                Some(_) if scan.errors() > 0 => Some(format!("unexpected {}", describe_errors(&scan))),
+                Some(_) => None,
+            };
+            if let Some(message) = message {
+                self.fail(&src, message);
+                ok = false;
+            }
+        }
+        ok
+    }
+
+    // Checks that src reports an error with the code.
+    fn check_error(&mut self, src: &str, code: DiagnosticCode) {
+        let scan = self.scan(src, None);
+        if !scan.has_error(code) {
+            self.fail(src, format!("expected error {}, found {}", code, describe_errors(&scan)));
+        }
+    }
+
+    // Checks that src, ending inside a literal, reports it unterminated, or
+    // with the NeedMoreInput policy returns a NEED_MORE_INPUT token instead.
+    fn check_eof(&mut self, src: &str) {
+        let scan = self.scan(src, None);
+        if self.config.eof_policy == EofPolicy::NeedMoreInput {
+            if scan.tokens.last().map(|t| t.tok) != Some(NEED_MORE_INPUT) || scan.errors() > 0 {
+                self.fail(src, format!("expected a final NeedMoreInput token, found {}", describe(&scan.tokens)));
+            }
+        } else if !scan.has_error(DiagnosticCode::Unterminated) {
+            let message = format!("expected error {}, found {}", DiagnosticCode::Unterminated, describe_errors(&scan));
+            self.fail(src, message);
+        }
+    }
+
+    fn numbers(&mut self) {
+        if self.config.mode & (SCAN_INTS | SCAN_FLOATS) == 0 {
+            return;
+        }
+        for &(text, value) in INTS {
+            if self.check_literal(text, INT) && value.is_some() && decode_int(text) != value {
+                self.fail(text, format!("expected value {}, found {:?}", value.unwrap_or_default(), decode_int(text)));
+            }
+        }
+        for &(text, code) in BAD_NUMBERS {
+            self.check_error(text, code);
+        }
+        if self.config.mode & SCAN_FLOATS == 0 {
+            return;
+        }
+        for &(text, value) in FLOATS {
+            if self.check_literal(text, FLOAT) && decode_float(text) != Some(value) {
+                self.fail(text, format!("expected value {}, found {:?}", value, decode_float(text)));
+            }
+        }
+        for &(text, code) in BAD_FLOATS {
+            self.check_error(text, code);
+        }
+    }
+
+    fn strings(&mut self) {
+        if self.config.mode & SCAN_STRINGS != 0 {
+            for &(text, value) in STRINGS {
+                if self.check_literal(text, STRING) && decode_string(text).as_deref() != Some(value) {
+                    self.fail(text, format!("expected value {:?}, found {:?}", value, decode_string(text)));
+                }
+            }
+            for &text in BAD_STRINGS {
+                self.check_error(text, DiagnosticCode::InvalidEscape);
+            }
+            self.check_eof("\"abc");
+            self.check_eof("\"a\\");
+            self.check_error("\"a\nb\"", DiagnosticCode::Unterminated);
+        }
+        if self.config.mode & SCAN_RAW_STRINGS != 0 {
+            let (open, close) = self.config.raw_string_delimiters;
+            for value in ["", "a", "a\\nb", "a\nb", "\"", "; #|"] {
+                let text = format!("{open}{value}{close}");
+                let decoded = decode_raw_string_with(&text, open, close);
+                if self.check_literal(&text, RAW_STRING) && decoded.as_deref() != Some(value) {
+                    self.fail(&text, format!("expected value {:?}, found {:?}", value, decoded));
+                }
+            }
+            self.check_eof(&format!("{open}abc"));
+        }
+    }
+
+    fn comments(&mut self) {
+        let skip = self.config.mode & SKIP_COMMENTS != 0;
+        let mut cases = Vec::new(); // comment texts, and whether they end the source
+        if self.config.mode & SCAN_COMMENTS != 0 {
+            for prefix in &self.config.line_comment_prefixes {
+                cases.push((format!("{prefix} note"), false));
+                cases.push((format!("{prefix}{prefix} \"x ¬"), false));
+                cases.push((format!("{prefix} end"), true));
+            }
+        }
+        if self.config.mode & SCAN_BLOCK_COMMENTS != 0 {
+            cases.push((String::from("#| note |#"), false));
+            cases.push((String::from("#| a\nb |#"), false));
+            cases.push((String::from("#||#"), false));
+            if self.config.nested_block_comments {
+                cases.push((String::from("#| a #| b |# c |#"), false));
+            }
+            self.check_eof("x #| a");
+        }
+        for (comment, at_end) in cases {
+            let space = self.config.space.map_or(String::new(), String::from);
+            let mut src = format!("x{space}{comment}");
+            let start = src.len() - comment.len();
+            if !at_end {
+                src.push_str("\ny");
+            }
+            let scan = self.scan(&src, None);
+            let end = start + comment.len();
+            let found = scan.token(start, end);
+            if !skip && found.map(|t| t.tok) != Some(COMMENT) {
+                self.fail(&src, format!("expected a Comment token, found {}", describe(&scan.tokens)));
+            } else if skip && scan.tokens.iter().any(|t| t.span.start.offset < end && t.span.end.offset > start) {
+                self.fail(&src, format!("expected the comment skipped, found {}", describe(&scan.tokens)));
+            } else if scan.errors() > 0 {
+                self.fail(&src, format!("unexpected {}", describe_errors(&scan)));
+            } else if !at_end {
+                let lines = 2 + comment.matches('\n').count();
+                match scan.tokens.iter().find(|t| t.span.start.offset == end + 1) {
+                    Some(t) if (t.span.start.line, t.span.start.column) == (lines, 1) => {}
+                    Some(t) => self.fail(&src, format!("expected y at {}:1, found {}", lines, t.span.start)),
+                    None => self.fail(&src, format!("expected a token for y, found {}", describe(&scan.tokens))),
+                }
+            }
+        }
+    }
+
+    // Returns sources mixing lines, multi-byte characters and the literals
+    // and comments of the configuration.
+    fn sources(&self) -> Vec<String> {
+        let (open, close) = self.config.raw_string_delimiters;
+        let mut sources: Vec<String> = [
+            "x\ny\n\nz",
+            "ñ x\n🎉 y",
+            "\tx\t y",
+            "x\r\ny",
+            "(a [b {c}])",
+            "\"a\" \"b\" 1 2.5",
+            "\"ñ\\n\" \"\\u00e9🎉\" z",
+            "\"unterminated\nx",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+        sources.push(format!("{open}a\nb{close} x\n{open}ñ{close}"));
+        for prefix in &self.config.line_comment_prefixes {
+            sources.push(format!("x {prefix} ñ 🎉\n  y {prefix}\n{prefix}"));
+        }
+        if self.config.mode & SCAN_BLOCK_COMMENTS != 0 {
+            sources.push(String::from("x #| ñ\n🎉 |# y\n#| z"));
+        }
+        let numbers: Vec<&str> = INTS.iter().map(|(t, _)| *t).chain(FLOATS.iter().map(|(t, _)| *t)).collect();
+        sources.push(numbers.join("\n"));
+        let strings: Vec<&str> = STRINGS.iter().map(|(t, _)| *t).chain(BAD_STRINGS.iter().copied()).collect();
+        sources.push(strings.join("\n"));
+        sources
+    }
+
+    fn positions(&mut self) {
+        for src in self.sources() {
+            let scan = self.scan(&src, None);
+            let mut last_end = 0;
+            for t in &scan.tokens {
+                let (start, end) = (t.span.start.offset, t.span.end.offset);
+                let message = if start < last_end || end < start || end > src.len() {
+                    Some(format!("{} spans {}..{}, after {}", describe_token(t), start, end, last_end))
+                } else if src.get(start..end) != Some(t.text.as_str()) {
+                    Some(format!("{} doesn't match the source at {}..{}", describe_token(t), start, end))
+                } else if (t.span.start.line, t.span.start.column) != line_column(&src, start) {
+                    let (line, column) = line_column(&src, start);
+                    Some(format!("{} starts at {}, expected {}:{}", describe_token(t), t.span.start, line, column))
+                } else if (t.span.end.line, t.span.end.column) != line_column(&src, end) {
+                    let (line, column) = line_column(&src, end);
+                    Some(format!("{} ends at {}, expected {}:{}", describe_token(t), t.span.end, line, column))
+                } else {
+                    None
+                };
+                if let Some(message) = message {
+                    self.fail(&src, message);
+                    break;
+                }
+                last_end = end;
+            }
+        }
+    }
+
+    fn chunk_boundaries(&mut self) {
+        let patterns = [
+            ChunkPattern::Bytes(1),
+            ChunkPattern::Bytes(2),
+            ChunkPattern::Bytes(3),
+            ChunkPattern::SplitUtf8,
+            ChunkPattern::SplitTokens,
+        ];
+        for src in self.sources() {
+            let expected = self.scan(&src, None);
+            for pattern in &patterns {
+                let scan = self.scan(&src, Some(pattern.clone()));
+                if scan == expected {
+                    continue;
+                }
+                let message = match expected.tokens.iter().zip(&scan.tokens).position(|(a, b)| a != b) {
+                    Some(i) => format!(
+                        "with {:?}, token {} is {}, expected {}",
+                        pattern,
+                        i,
+                        describe_token(&scan.tokens[i]),
+                        describe_token(&expected.tokens[i])
+                    ),
+                    None if scan.tokens.len() != expected.tokens.len() => {
+                        format!("with {:?}, {} tokens, expected {}", pattern, scan.tokens.len(), expected.tokens.len())
+                    }
+                    None => format!("with {:?}, {}, expected {}", pattern, describe_errors(&scan), describe_errors(&expected)),
+                };
+                self.fail(&src, message);
+            }
+        }
+    }
+}
+
+// Returns the line and column of the byte offset in src.
+fn line_column(src: &str, offset: usize) -> (usize, usize) {
+    let before = &src[..offset];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    (1 + before.matches('\n').count(), 1 + before[line_start..].chars().count())
+}
+
+fn describe_token(t: &SpannedToken) -> String {
+    format!("{} {:?} at {}", token_string(t.tok), t.text, t.span.start)
+}
+
+fn describe(tokens: &[SpannedToken]) -> String {
+    let tokens: Vec<String> = tokens.iter().map(|t| format!("{} {:?}", token_string(t.tok), t.text)).collect();
+    format!("[{}]", tokens.join(", "))
+}
+
+fn describe_errors(scan: &Scan) -> String {
+    let errors: Vec<String> = scan.diagnostics.iter().filter(|d| d.is_error()).map(|d| d.code.to_string()).collect();
+    format!("errors [{}]", errors.join(", "))
+}
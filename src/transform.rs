@@ -0,0 +1,72 @@
+// Copyright 2022 Jordi Íñigo Griera. All rights reserved.
+
+//! Source transformers re-emitting a source from its token stream.
+
+use alloc::string::String;
+
+use crate::literal::decode_string;
+use crate::render::render_string;
+use crate::{COMMENT, EOF, FLOAT, INT, LISP_TOKENS, SKIP_COMMENTS, STRING, Scanner};
+
+/// Options of `normalize`. The default options reproduce the source unchanged.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NormalizeOptions {
+    /// Replace every run of white space between tokens by a single space,
+    /// and drop leading and trailing white space. A newline is kept after
+    /// line comments so they don't swallow the following tokens.
+    pub collapse_whitespace: bool,
+    /// Remove comments.
+    pub drop_comments: bool,
+    /// Lowercase the prefixes, hexadecimal digits and exponents of numbers.
+    pub lowercase_numbers: bool,
+    /// Re-render strings with the canonical escapes, e.g. `"\x41\u000a"`
+    /// becomes `"A\n"`. Malformed strings are left untouched.
+    pub normalize_escapes: bool,
+}
+
+/// Re-emits `src` from its token stream applying the normalizations
+/// selected in `opts`. Useful to produce canonical forms for caching and
+/// diffing.
+pub fn normalize(src: &[u8], opts: &NormalizeOptions) -> String {
+    let mut s = Scanner::init(src);
+    s.set_mode(LISP_TOKENS & !SKIP_COMMENTS);
+
+    let mut out = String::with_capacity(src.len());
+    let mut prev_end = 0;
+    let mut after_comment = false;
+    loop {
+        let t = s.scan_spanned();
+        let gap = &src[prev_end.min(t.span.start.offset)..t.span.start.offset];
+        if t.tok == EOF {
+            if !opts.collapse_whitespace {
+                out.push_str(&String::from_utf8_lossy(gap));
+            } else if after_comment {
+                out.push('\n');
+            }
+            break;
+        }
+        prev_end = t.span.end.offset;
+        if t.tok == COMMENT && opts.drop_comments {
+            continue;
+        }
+
+        if !opts.collapse_whitespace {
+            out.push_str(&String::from_utf8_lossy(gap));
+        } else if after_comment {
+            out.push('\n');
+        } else if !gap.is_empty() && !out.is_empty() {
+            out.push(' ');
+        }
+        after_comment = t.tok == COMMENT;
+
+        match t.tok {
+            INT | FLOAT if opts.lowercase_numbers => out.push_str(&t.text.to_ascii_lowercase()),
+            STRING if opts.normalize_escapes => match decode_string(&t.text) {
+                Some(value) => out.push_str(&render_string(&value)),
+                None => out.push_str(&t.text),
+            },
+            _ => out.push_str(&t.text),
+        }
+    }
+    out
+}
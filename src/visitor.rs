@@ -0,0 +1,99 @@
+// Copyright 2022 Jordi Íñigo Griera. All rights reserved.
+
+//! Push-based scanning: `Scanner::drive()` scans the whole source and calls
+//! the method of a `TokenVisitor` matching each token, instead of returning
+//! the tokens to match on.
+//!
+//! ```
+//! use scanner::*;
+//!
+//! #[derive(Default)]
+//! struct Metrics {
+//!     idents: usize,
+//!     numbers: usize,
+//!     lines: usize,
+//! }
+//!
+//! impl TokenVisitor for Metrics {
+//!     fn on_ident(&mut self, _span: &Span, _text: &str) {
+//!         self.idents += 1;
+//!     }
+//!     fn on_number(&mut self, _tok: Token, _span: &Span, _text: &str) {
+//!         self.numbers += 1;
+//!     }
+//!     fn on_eof(&mut self, pos: &Position) {
+//!         self.lines = pos.line;
+//!     }
+//! }
+//!
+//! let mut metrics = Metrics::default();
+//! Scanner::init(b"(+ a 1\n   2.5)").drive(&mut metrics);
+//! assert_eq!((metrics.idents, metrics.numbers, metrics.lines), (2, 2, 2));
+//! ```
+
+use crate::{
+    COMMENT, Diagnostic, EOF, FLOAT, IDENT, INT, KEYWORD, Position, RAW_STRING, STRING, Scanner, Span, Token,
+    token_char,
+};
+
+/// TokenVisitor receives the tokens scanned by `Scanner::drive()`, each
+/// with its span and text, through the method for its kind. All methods
+/// do nothing by default.
+pub trait TokenVisitor {
+    /// An IDENT token.
+    fn on_ident(&mut self, _span: &Span, _text: &str) {}
+
+    /// A KEYWORD token.
+    fn on_keyword(&mut self, _span: &Span, _text: &str) {}
+
+    /// An INT or FLOAT token.
+    fn on_number(&mut self, _tok: Token, _span: &Span, _text: &str) {}
+
+    /// A STRING or RAW_STRING token.
+    fn on_string(&mut self, _tok: Token, _span: &Span, _text: &str) {}
+
+    /// A COMMENT token, only returned if the mode doesn't skip comments.
+    fn on_comment(&mut self, _span: &Span, _text: &str) {}
+
+    /// A Unicode character token, e.g. a bracket.
+    fn on_char(&mut self, _ch: char, _span: &Span) {}
+
+    /// Any other token, e.g. WHITESPACE or PROSE.
+    fn on_other(&mut self, _tok: Token, _span: &Span, _text: &str) {}
+
+    /// An error found while scanning the token just visited, or before it.
+    /// Errors are not reported here when an error handler is set.
+    fn on_error(&mut self, _diagnostic: &Diagnostic) {}
+
+    /// The end of the source, at its position.
+    fn on_eof(&mut self, _pos: &Position) {}
+}
+
+impl<const N: usize> Scanner<'_, N> {
+    /// Scans the rest of the source, calling the method of `visitor` for
+    /// each token and error, up to `on_eof()`; see `TokenVisitor`.
+    pub fn drive<V: TokenVisitor + ?Sized>(&mut self, visitor: &mut V) {
+        loop {
+            let errors = self.error_count();
+            let t = self.scan_spanned();
+            for d in self.last_errors(self.error_count() - errors) {
+                visitor.on_error(d);
+            }
+            match t.tok {
+                EOF => {
+                    visitor.on_eof(&t.span.start);
+                    return;
+                }
+                IDENT => visitor.on_ident(&t.span, &t.text),
+                KEYWORD => visitor.on_keyword(&t.span, &t.text),
+                INT | FLOAT => visitor.on_number(t.tok, &t.span, &t.text),
+                STRING | RAW_STRING => visitor.on_string(t.tok, &t.span, &t.text),
+                COMMENT => visitor.on_comment(&t.span, &t.text),
+                tok => match token_char(tok) {
+                    Some(ch) => visitor.on_char(ch, &t.span),
+                    None => visitor.on_other(tok, &t.span, &t.text),
+                },
+            }
+        }
+    }
+}
@@ -0,0 +1,117 @@
+// Copyright 2022 Jordi Íñigo Griera. All rights reserved.
+
+#[cfg(test)]
+mod tests {
+    use scanner::*;
+
+    const SRC: &str = ";; doc\n(def a \"s\") ; trailing\n(+ a 1)";
+
+    fn scanner() -> Scanner<'static> {
+        let mut s = Scanner::init(SRC.as_bytes());
+        s.set_mode(LISP_TOKENS & !SKIP_COMMENTS);
+        s
+    }
+
+    #[test]
+    fn test_tokens() {
+        let mut s = scanner();
+        let toks: Vec<Token> = s.tokens().collect();
+        assert_eq!(toks.len(), 12);
+        assert_eq!(toks[0], COMMENT);
+        assert_eq!(s.scan(), EOF);
+    }
+
+    #[test]
+    fn test_skip_trivia_and_only() {
+        let mut s = scanner();
+        let toks: Vec<Token> = s.tokens().skip_trivia().collect();
+        assert_eq!(
+            toks,
+            ['(' as i32, IDENT, IDENT, STRING, ')' as i32, '(' as i32, IDENT, IDENT, INT, ')' as i32]
+        );
+
+        let mut s = scanner();
+        let parens = s.tokens().only(&['(' as i32, ')' as i32]).count();
+        assert_eq!(parens, 4);
+    }
+
+    #[test]
+    fn test_spanned_pipeline() {
+        let mut s = scanner();
+        let atoms: Vec<(String, usize)> = s
+            .tokens()
+            .spanned()
+            .skip_trivia()
+            .only(&[IDENT, STRING, INT])
+            .map_text(|text| text.trim_matches('"').to_string())
+            .map(|t| (t.text, t.span.start.line))
+            .collect();
+        assert_eq!(
+            atoms,
+            [
+                ("def".to_string(), 2),
+                ("a".to_string(), 2),
+                ("s".to_string(), 2),
+                ("+".to_string(), 3),
+                ("a".to_string(), 3),
+                ("1".to_string(), 3),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_chars() {
+        let mut s = Scanner::init("a本\n\r\né".as_bytes());
+        let chars: Vec<(char, usize, usize, usize)> = s.chars().map(|c| (c.ch, c.offset, c.line, c.column)).collect();
+        assert_eq!(
+            chars,
+            [('a', 0, 1, 1), ('本', 1, 1, 2), ('\n', 4, 1, 3), ('\r', 5, 2, 1), ('\n', 6, 2, 2), ('é', 7, 3, 1)]
+        );
+        assert_eq!(s.scan(), EOF);
+    }
+
+    #[test]
+    fn test_chars_across_refills() {
+        let src = "añ\n".repeat(1000);
+        let mut s = Scanner::init(src.as_bytes());
+        let (mut line, mut column) = (1, 1);
+        for (c, (offset, ch)) in s.chars().zip(src.char_indices()) {
+            assert_eq!((c.ch, c.offset, c.line, c.column), (ch, offset, line, column));
+            if ch == '\n' {
+                line += 1;
+                column = 1;
+            } else {
+                column += 1;
+            }
+        }
+        assert_eq!(line, 1001);
+    }
+
+    #[test]
+    fn test_chars_after_token() {
+        let mut s = Scanner::init(b"{{name}} rest");
+        assert_eq!(s.scan(), '{' as i32);
+        assert_eq!(s.scan(), '{' as i32);
+        assert_eq!(s.scan(), IDENT);
+        let rest: String = s.chars().map(|c| c.ch).collect();
+        assert_eq!(rest, "}} rest");
+        assert_eq!(s.token_text(), "name");
+    }
+
+    #[test]
+    fn test_iter() {
+        let mut s = Scanner::init(b"(def a\n  \"b\")");
+        let t: Vec<SpannedToken> = s.iter().filter(|t| t.tok != IDENT).collect();
+        assert_eq!(t.len(), 3);
+        assert_eq!((t[1].tok, t[1].text.as_str()), (STRING, "\"b\""));
+        assert_eq!((t[1].span.start.line, t[1].span.start.column), (2, 3));
+
+        let mut s = Scanner::init(b"(def a\n  \"b\")");
+        let mut texts = Vec::new();
+        for t in &mut s {
+            texts.push(t.text);
+        }
+        assert_eq!(texts, ["(", "def", "a", "\"b\"", ")"]);
+        assert_eq!(s.scan(), EOF);
+    }
+}
@@ -0,0 +1,41 @@
+// Copyright 2022 Jordi Íñigo Griera. All rights reserved.
+
+#[cfg(test)]
+mod tests {
+    use scanner::*;
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    // Counts the allocations of the test binary, which runs a single test.
+    struct CountingAlloc;
+
+    static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+    unsafe impl GlobalAlloc for CountingAlloc {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+            unsafe { System.alloc(layout) }
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            unsafe { System.dealloc(ptr, layout) }
+        }
+    }
+
+    #[global_allocator]
+    static GLOBAL: CountingAlloc = CountingAlloc;
+
+    #[test]
+    fn test_small_buffer_scan_does_not_allocate() {
+        let src = b"(a b c 1 2) ; done\n[:k \"s\" 1.5]";
+        let before = ALLOCATIONS.load(Ordering::Relaxed);
+        let mut s: Scanner<'_, 32> = Scanner::with_buffer(src);
+        let mut count = 0;
+        while s.scan() != EOF {
+            count += 1;
+        }
+        let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+        assert_eq!(count, 12);
+        assert_eq!(allocations, 0);
+    }
+}
@@ -0,0 +1,172 @@
+// Copyright 2022 Jordi Íñigo Griera. All rights reserved.
+
+#[cfg(all(test, feature = "async"))]
+mod tests {
+    use scanner::*;
+    use std::future::Future;
+    use std::io;
+    use std::pin::{Pin, pin};
+    use std::task::{Context, Poll, Waker};
+
+    // Source serving a chunk per read, after a pending poll before each,
+    // and then the end of the input, unless it is endless.
+    struct Chunks {
+        chunks: Vec<Result<Vec<u8>, io::ErrorKind>>,
+        ready: bool,
+        endless: bool,
+    }
+
+    impl Chunks {
+        fn new(chunks: &[&[u8]]) -> Self {
+            Chunks { chunks: chunks.iter().rev().map(|c| Ok(c.to_vec())).collect(), ready: false, endless: false }
+        }
+    }
+
+    impl AsyncSource for Chunks {
+        fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+            if !self.ready {
+                self.ready = true;
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+            self.ready = false;
+            match self.chunks.pop() {
+                None if self.endless => Poll::Pending,
+                None => Poll::Ready(Ok(0)),
+                Some(Err(kind)) => Poll::Ready(Err(kind.into())),
+                Some(Ok(chunk)) => {
+                    buf[..chunk.len()].copy_from_slice(&chunk);
+                    Poll::Ready(Ok(chunk.len()))
+                }
+            }
+        }
+    }
+
+    fn block_on<F: Future>(f: F) -> F::Output {
+        let mut f = pin!(f);
+        loop {
+            if let Poll::Ready(out) = f.as_mut().poll(&mut Context::from_waker(Waker::noop())) {
+                return out;
+            }
+        }
+    }
+
+    fn scan_all<R: AsyncSource + Unpin>(s: &mut AsyncScanner<R>) -> Vec<SpannedToken> {
+        block_on(async {
+            let mut tokens = Vec::new();
+            while let Some(t) = s.scan().await {
+                tokens.push(t);
+            }
+            tokens
+        })
+    }
+
+    #[test]
+    fn test_chunked_input() {
+        let src = "(déf a \"str\") ; c\n#| block\ncomment |# ¬raw\n¬ :end\n(b)";
+        let bytes = src.as_bytes();
+        let mut scanner = Scanner::init(bytes);
+        scanner.set_mode(LISP_TOKENS | SCAN_BLOCK_COMMENTS);
+        let expected: Vec<SpannedToken> = scanner.iter().collect();
+        for size in [1, 2, 3, 7, 64] {
+            let chunks: Vec<&[u8]> = bytes.chunks(size).collect();
+            let mut s = AsyncScanner::new(Chunks::new(&chunks));
+            s.set_mode(LISP_TOKENS | SCAN_BLOCK_COMMENTS);
+            assert_eq!(scan_all(&mut s), expected, "chunks of {size} bytes");
+            assert!(s.diagnostics().is_empty());
+        }
+    }
+
+    #[test]
+    fn test_tokens_before_more_input() {
+        // The source never ends: the tokens of the complete line are returned
+        let mut source = Chunks::new(&["(a b)\n(c ¬d\n".as_bytes()]);
+        source.endless = true;
+        let mut s = AsyncScanner::new(source);
+        let mut texts = Vec::new();
+        let mut scan = Box::pin(async {
+            loop {
+                let t = s.scan().await.unwrap();
+                texts.push(t.text);
+            }
+        });
+        let mut cx = Context::from_waker(Waker::noop());
+        for _ in 0..10 {
+            assert!(scan.as_mut().poll(&mut cx).is_pending());
+        }
+        drop(scan);
+        assert_eq!(texts, ["(", "a", "b", ")", "(", "c"]);
+    }
+
+    #[test]
+    fn test_end_of_input() {
+        let mut s = AsyncScanner::new("(a ¬b\nc".as_bytes());
+        let tokens = scan_all(&mut s);
+        assert_eq!((tokens[2].tok, tokens[2].text.as_str()), (RAW_STRING, "¬b\nc"));
+        assert_eq!(tokens[2].span.end.line, 2);
+        assert_eq!(s.diagnostics().len(), 1);
+
+        let mut source = Chunks::new(&[b"(a)\n(b"]);
+        source.chunks.insert(0, Err(io::ErrorKind::ConnectionReset));
+        let mut s = AsyncScanner::new(source);
+        let texts: Vec<String> = scan_all(&mut s).into_iter().map(|t| t.text).collect();
+        assert_eq!(texts, ["(", "a", ")", "(", "b"]);
+        assert_eq!(s.io_error().map(io::Error::kind), Some(io::ErrorKind::ConnectionReset));
+    }
+
+    #[test]
+    fn test_configuration() {
+        let mut s = AsyncScanner::new(Chunks::new(&[b"(a /", b"/ b\n", b"c ; d)"]));
+        s.set_configuration(|s| s.set_line_comment_prefixes(&["//"]));
+        let texts: Vec<String> = scan_all(&mut s).into_iter().map(|t| t.text).collect();
+        assert_eq!(texts, ["(", "a", "c", ";", "d", ")"]);
+    }
+}
+
+#[cfg(all(test, feature = "tokio"))]
+mod tokio_tests {
+    use scanner::*;
+    use tokio::io::AsyncWriteExt;
+
+    #[tokio::test]
+    async fn test_tokio_duplex() {
+        let src = "(déf a \"str\") ; c\n¬raw\n¬ :end\n(b)";
+        let expected: Vec<SpannedToken> = Scanner::init(src.as_bytes()).iter().collect();
+        let (mut writer, reader) = tokio::io::duplex(4);
+        let write = async move {
+            for chunk in src.as_bytes().chunks(3) {
+                writer.write_all(chunk).await.unwrap();
+                tokio::task::yield_now().await;
+            }
+        };
+        let mut s = AsyncScanner::new(reader);
+        let scan = async {
+            let mut tokens = Vec::new();
+            while let Some(t) = s.scan().await {
+                tokens.push(t);
+            }
+            tokens
+        };
+        let ((), tokens) = tokio::join!(write, scan);
+        assert_eq!(tokens, expected);
+        assert!(s.io_error().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_tokio_readers() {
+        // A token is returned once the input following it is read
+        let (mut writer, reader) = tokio::io::duplex(64);
+        let mut s = AsyncScanner::new(reader);
+        writer.write_all(b"(a \"b").await.unwrap();
+        assert_eq!(s.scan().await.map(|t| t.text), Some("(".to_string()));
+        assert_eq!(s.scan().await.map(|t| t.text), Some("a".to_string()));
+        writer.write_all(b"\")").await.unwrap();
+        drop(writer);
+        assert_eq!(s.scan().await.map(|t| t.text), Some("\"b\"".to_string()));
+        assert_eq!(s.scan().await.map(|t| t.text), Some(")".to_string()));
+        assert_eq!(s.scan().await, None);
+
+        let mut s = AsyncScanner::new(tokio::io::BufReader::new(&b"(c)"[..]));
+        assert_eq!(s.scan().await.map(|t| t.tok), Some('(' as Token));
+    }
+}
@@ -0,0 +1,66 @@
+// Copyright 2022 Jordi Íñigo Griera. All rights reserved.
+
+#[cfg(test)]
+mod tests {
+    use scanner::*;
+
+    #[test]
+    fn test_token_start_at() {
+        let src = "(foo 12.5) ; bar baz\n¬raw string¬ :k";
+        let mut s = Scanner::init(src.as_bytes());
+        let starts: Vec<Option<usize>> = (0..=src.len()).map(|i| s.token_start_at(i)).collect();
+        assert_eq!(starts[0], Some(0));
+        assert_eq!(starts[1..4], [Some(1); 3]);
+        assert_eq!(starts[4], None);
+        assert_eq!(starts[5..9], [Some(5); 4]);
+        assert_eq!(starts[9], Some(9));
+        assert_eq!(starts[10], None);
+        // Comments are tokens, even if the scanner skips them
+        assert_eq!(starts[11..20], [Some(11); 9]);
+        assert_eq!(starts[20], None);
+        assert_eq!(starts[21], Some(21));
+        assert_eq!(starts[34], Some(21));
+        assert_eq!(starts[35], None);
+        assert_eq!(starts[src.len() - 1], Some(src.len() - 2));
+        assert_eq!(s.token_start_at(src.len() + 1), None);
+
+        // Scanning is unaffected
+        assert_eq!(s.scan(), '(' as Token);
+        assert_eq!(s.scan(), IDENT);
+        assert_eq!(s.token_text(), "foo");
+    }
+
+    #[test]
+    fn test_previous_token_boundary() {
+        let src = "(a bb\n\n   ccc) ; d";
+        let mut s = Scanner::init(src.as_bytes());
+        assert_eq!(s.previous_token_boundary(0), None);
+        assert_eq!(s.previous_token_boundary(1), Some(0));
+        assert_eq!(s.previous_token_boundary(3), Some(1));
+        assert_eq!(s.previous_token_boundary(5), Some(3));
+        assert_eq!(s.previous_token_boundary(4), Some(3));
+        // Over empty lines
+        assert_eq!(s.previous_token_boundary(10), Some(3));
+        assert_eq!(s.previous_token_boundary(12), Some(10));
+        assert_eq!(s.previous_token_boundary(src.len()), Some(15));
+        assert_eq!(s.previous_token_boundary(src.len() + 10), Some(15));
+    }
+
+    #[test]
+    fn test_backward_configuration() {
+        // The identifier predicate, base position and delimiters are honored
+        let src = "x.y <<z>>";
+        let mut s = Scanner::init(src.as_bytes());
+        s.set_is_ident_rune(|ch, i| ch.is_alphabetic() || (i > 0 && ch == '.'));
+        s.add_delimiter_pair("<<", ">>");
+        s.set_base_position(&Position { offset: 100, line: 5, column: 1, ..Default::default() });
+        assert_eq!(s.token_start_at(102), Some(100));
+        assert_eq!(s.token_start_at(105), Some(104));
+        assert_eq!(s.token_start_at(106), Some(106));
+        assert_eq!(s.token_start_at(107), Some(107));
+        assert_eq!(s.previous_token_boundary(109), Some(107));
+        assert_eq!(s.token_start_at(50), None);
+        assert_eq!(s.scan(), IDENT);
+        assert_eq!(s.token_text(), "x.y");
+    }
+}
@@ -0,0 +1,62 @@
+// Copyright 2022 Jordi Íñigo Griera. All rights reserved.
+
+#[cfg(test)]
+mod tests {
+    use scanner::*;
+
+    #[test]
+    fn test_builder_matches_setters() {
+        let src = b"(a <<b>> ; c\n---\n:d)";
+        let mut built: Scanner<'_, 32> = ScannerBuilder::with_buffer(src)
+            .filename("a.lisp")
+            .mode(LISP_TOKENS & !SKIP_COMMENTS)
+            .whitespace(PAGE_WHITESPACE)
+            .delimiter_pair("<<", ">>")
+            .document_delimiter(Some("---"))
+            .reset_lines_per_document(true)
+            .disabled_policy(DisabledPolicy::Skip)
+            .trivia(&[COMMENT])
+            .lints(ALL_LINTS)
+            .max_line_length(8)
+            .max_nesting_depth(1)
+            .max_raw_string_lines(2)
+            .max_ident_length(10, IdentLengthPolicy::Error)
+            .build();
+
+        let mut set: Scanner<'_, 32> = Scanner::with_buffer(src);
+        set.position.source = SourceId::new(SourceKind::Path, "a.lisp");
+        set.set_mode(LISP_TOKENS & !SKIP_COMMENTS);
+        set.set_whitespace(PAGE_WHITESPACE);
+        set.add_delimiter_pair("<<", ">>");
+        set.set_document_delimiter(Some("---"));
+        set.set_reset_lines_per_document(true);
+        set.set_disabled_policy(DisabledPolicy::Skip);
+        set.set_trivia(&[COMMENT]);
+        set.set_lints(ALL_LINTS);
+        set.set_max_line_length(8);
+        set.set_max_nesting_depth(1);
+        set.set_max_raw_string_lines(2);
+        set.set_max_ident_length(10, IdentLengthPolicy::Error);
+
+        assert_eq!(built.capabilities(), set.capabilities());
+        let built_tokens: Vec<SpannedToken> = built.tokens().spanned().collect();
+        let set_tokens: Vec<SpannedToken> = set.tokens().spanned().collect();
+        assert_eq!(built_tokens, set_tokens);
+        assert_eq!(built.diagnostics(), set.diagnostics());
+        assert_eq!(built_tokens[0].span.start.to_string(), "a.lisp:1:1");
+    }
+
+    #[test]
+    fn test_builder_closures() {
+        let mut s = Scanner::builder(b"(a 0x)")
+            .source(SourceId::new(SourceKind::Virtual, "repl"))
+            .base_position(&Position { line: 3, column: 1, ..Default::default() })
+            .is_ident_rune(|ch, _| ch == 'a')
+            .user_data(7u8)
+            .error_handler(|pos, msg| assert_eq!(format!("{}: {}", pos, msg), "repl:3:4: hexadecimal literal has no digits"))
+            .build();
+        while s.scan() != EOF {}
+        assert_eq!(s.error_count(), 1);
+        assert_eq!(s.user_data::<u8>(), Some(&7));
+    }
+}
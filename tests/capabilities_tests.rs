@@ -0,0 +1,101 @@
+// Copyright 2022 Jordi Íñigo Griera. All rights reserved.
+
+#[cfg(test)]
+mod tests {
+    use scanner::*;
+
+    #[test]
+    fn test_default_capabilities() {
+        let s = Scanner::init(b"");
+        assert_eq!(
+            s.capabilities().to_string(),
+            "tokens Ident Int Float String Keyword RawString\n\
+             comments \";\"\n\
+             skip_comments true\n\
+             nested_block_comments true\n\
+             comment_continuations false\n\
+             namespaced_keywords false\n\
+             delimiters \"(\" \")\" \"[\" \"]\" \"{\" \"}\" \"#{\" \"}\" \"\\\"\" \"\\\"\" \"¬\" \"¬\"\n\
+             specials \"~@\" \"#{\"\n\
+             dispatch_macros \n\
+             whitespace \"\\t\\n\\r \"\n\
+             trivia Comment Prose Whitespace\n\
+             disabled_policy Chars\n\
+             eof_policy Error\n\
+             literate_prefix none\n\
+             document_delimiter none\n\
+             reset_lines_per_document false\n\
+             lints 0x0\n\
+             max_line_length 100\n\
+             max_nesting_depth 64\n\
+             max_raw_string_lines 50\n\
+             max_ident_length 18446744073709551615\n\
+             ident_length_policy Truncate\n\
+             buffer_size 1025\n\
+             custom_ident_rune false\n\
+             custom_whitespace_rune false\n\
+             normalized \n"
+        );
+    }
+
+    #[test]
+    fn test_configured_capabilities() {
+        let mut s: Scanner<'_, 64> = Scanner::with_buffer(b"");
+        s.set_mode(LOSSLESS_TOKENS & !SCAN_RAW_STRINGS);
+        s.set_whitespace(PAGE_WHITESPACE);
+        s.set_disabled_policy(DisabledPolicy::Other);
+        s.set_document_delimiter(Some("---"));
+        s.set_max_ident_length(32, IdentLengthPolicy::Error);
+        s.set_text_normalizer(IDENT, |text| text.to_lowercase());
+        let caps = s.capabilities();
+        assert_eq!(caps.tokens, [IDENT, INT, FLOAT, STRING, KEYWORD, COMMENT, WHITESPACE, OTHER, DOCUMENT_BOUNDARY, ERROR]);
+        assert!(!caps.skip_comments);
+        assert!(!caps.delimiters.contains(&("¬".to_string(), "¬".to_string())));
+        assert_eq!(caps.whitespace, ['\t', '\n', '\x0B', '\x0C', '\r', ' ']);
+        assert_eq!((caps.max_ident_length, caps.buffer_size), (32, 64));
+        assert_eq!(caps.normalized, [IDENT]);
+
+        // scanners configured alike have the same capabilities
+        let mut t: Scanner<'_, 64> = Scanner::with_buffer(b"(a)");
+        assert_ne!(t.capabilities(), caps);
+        t.set_mode(LOSSLESS_TOKENS & !SCAN_RAW_STRINGS);
+        t.set_whitespace(PAGE_WHITESPACE);
+        t.set_disabled_policy(DisabledPolicy::Other);
+        t.set_document_delimiter(Some("---"));
+        t.set_max_ident_length(32, IdentLengthPolicy::Error);
+        t.set_text_normalizer(IDENT, |text| text.to_uppercase());
+        assert_eq!(t.capabilities(), caps);
+        assert_eq!(t.capabilities().to_string(), caps.to_string());
+    }
+
+    #[test]
+    fn test_parse_capabilities() {
+        let mut s: Scanner<'_, 64> = Scanner::with_buffer(b"");
+        s.set_mode(LOSSLESS_TOKENS | SCAN_BLOCK_COMMENTS);
+        s.set_line_comment_prefixes(&["//", "--"]);
+        s.add_delimiter_pair("<<", ">>");
+        s.set_dispatch_macro('x', Some(DispatchMacro::Prefix(IDENT)));
+        s.set_dispatch_macro(' ', Some(DispatchMacro::Token('"' as Token)));
+        s.set_whitespace(PAGE_WHITESPACE);
+        s.set_trivia(&[COMMENT, WHITESPACE, ' ' as Token]);
+        s.set_eof_policy(EofPolicy::NeedMoreInput);
+        s.set_literate_prefix(Some("> \"x\""));
+        s.set_document_delimiter(Some("none"));
+        s.set_lints(LINT_LINE_LENGTH | LINT_DEEP_NESTING);
+        s.set_max_ident_length(32, IdentLengthPolicy::Error);
+        s.set_text_normalizer('(' as Token, |text| text.to_string());
+        let caps = s.capabilities();
+        assert_eq!(caps.to_string().parse(), Ok(caps.clone()));
+        assert_eq!(Scanner::init(b"").capabilities().to_string().parse(), Ok(Scanner::init(b"").capabilities()));
+
+        let text = caps.to_string();
+        let error = |text: &str| text.parse::<Capabilities>().unwrap_err();
+        assert_eq!(error(""), ParseCapabilitiesError { line: 1, key: "tokens" });
+        assert_eq!(error(&text.replace("skip_comments false", "skip_comments no")).line, 3);
+        assert_eq!(error(&text.replace("\nlints", "\nlint")).key, "lints");
+        assert_eq!(error(&text.replace("Prefix(-2)", "Prefix(-2")).key, "dispatch_macros");
+        assert_eq!(error(&text.replace("\"//\"", "\"//")).key, "comments");
+        assert_eq!(error(&format!("{text}extra\n")), ParseCapabilitiesError { line: 27, key: "end" });
+        assert_eq!(error(&text[..text.find("\nnormalized").unwrap()]).to_string(), "line 26: expected normalized");
+    }
+}
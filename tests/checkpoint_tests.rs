@@ -0,0 +1,92 @@
+// Copyright 2022 Jordi Íñigo Griera. All rights reserved.
+
+#[cfg(test)]
+mod tests {
+    use scanner::*;
+
+    const SRC: &str = "(def ñ \"long string\") 0x 'a ¬raw¬ (b)";
+
+    // Remaining spanned tokens and diagnostics of s
+    fn rest<const N: usize>(s: &mut Scanner<'_, N>) -> (Vec<SpannedToken>, Vec<Diagnostic>) {
+        let tokens = s.tokens().spanned().collect();
+        (tokens, s.diagnostics().to_vec())
+    }
+
+    fn check<const N: usize>(pattern: Option<ChunkPattern>) {
+        for skip in 0..14 {
+            let mut s: Scanner<'_, N> = Scanner::with_buffer(SRC.as_bytes());
+            s.set_lints(ALL_LINTS);
+            if let Some(pattern) = pattern.clone() {
+                s.set_chunked_reader(ChunkedReader::new(SRC.as_bytes(), pattern));
+            }
+            for _ in 0..skip {
+                s.scan();
+            }
+            let checkpoint = s.checkpoint();
+            let current = (s.token_text(), s.token_span());
+            let expected = rest(&mut s);
+            assert_eq!(s.scan(), EOF);
+            s.rewind(checkpoint.clone());
+            assert_eq!((s.token_text(), s.token_span()), current, "{} {:?}", skip, pattern);
+            assert_eq!(rest(&mut s), expected, "{} {:?}", skip, pattern);
+        }
+    }
+
+    #[test]
+    fn test_rewind() {
+        check::<5>(None);
+        check::<8>(Some(ChunkPattern::SplitUtf8));
+        check::<16>(Some(ChunkPattern::SplitTokens));
+        check::<1025>(None);
+    }
+
+    #[test]
+    fn test_rewind_lookahead() {
+        let mut s = Scanner::init(b"(a b c)");
+        s.scan();
+        s.peek_token();
+        let checkpoint = s.checkpoint();
+        assert_eq!(s.lookahead(3).count(), 3);
+        s.scan();
+        s.rewind(checkpoint);
+        assert_eq!(s.token_text(), "(");
+        let texts: Vec<String> = s.tokens().spanned().map(|t| t.text).collect();
+        assert_eq!(texts, ["a", "b", "c", ")"]);
+    }
+
+    #[test]
+    fn test_rewind_shared_lookahead() {
+        let mut s = Scanner::init(b"(a b c)");
+        s.scan();
+        assert_eq!(s.lookahead(2).count(), 2);
+        let checkpoint = s.checkpoint();
+        for _ in 0..2 {
+            s.rewind(checkpoint.clone());
+            let texts: Vec<String> = s.tokens().spanned().map(|t| t.text).collect();
+            assert_eq!(texts, ["a", "b", "c", ")"]);
+        }
+    }
+
+    #[test]
+    fn test_rewind_diagnostics() {
+        let mut s = Scanner::init(b"a 0x b \"c");
+        s.set_summary(true);
+        s.scan();
+        let checkpoint = s.checkpoint();
+        while s.scan() != EOF {}
+        assert_eq!((s.error_count(), s.diagnostics().len()), (2, 2));
+        assert!(s.eof_summary().is_some());
+        s.rewind(checkpoint);
+        assert_eq!((s.error_count(), s.diagnostics().len()), (0, 0));
+        assert!(s.eof_summary().is_none());
+        assert_eq!(s.scan(), INT);
+        assert_eq!(s.error_count(), 1);
+
+        // Diagnostics taken since the checkpoint aren't restored
+        let checkpoint = s.checkpoint();
+        assert_eq!(s.take_diagnostics().len(), 1);
+        s.rewind(checkpoint);
+        assert!(s.diagnostics().is_empty());
+        assert_eq!(s.error_count(), 1);
+    }
+}
@@ -0,0 +1,87 @@
+// Copyright 2022 Jordi Íñigo Griera. All rights reserved.
+
+#[cfg(test)]
+mod tests {
+    use scanner::*;
+
+    const SOURCES: &[&str] = &[
+        "(def a 10) ; comment\n",
+        "\u{FEFF}(ñ 世界 🎉 \"ñ世🎉\" ¬ñ¬¬🎉¬ :kéy)",
+        "0x1.fp+3 1_000 0b1010 0o755 .5 5. 1e-3 -42",
+        "\"a \\\"quoted\\\" \\u00e9 \\U0001F389 string\"",
+        "~@x #{y} 'z `w",
+        "\"unterminated\n¬also unterminated",
+        "a \0 b \"\\q\" 0x 1__0",
+    ];
+
+    // Tokens and diagnostics of src, scanned with an optional chunked reader
+    fn scan<const N: usize>(src: &str, pattern: Option<ChunkPattern>) -> (Vec<SpannedToken>, Vec<Diagnostic>) {
+        let mut s: Scanner<'_, N> = Scanner::with_buffer(src.as_bytes());
+        s.set_mode(LOSSLESS_TOKENS);
+        s.set_lints(ALL_LINTS);
+        if let Some(pattern) = pattern {
+            s.set_chunked_reader(ChunkedReader::new(src.as_bytes(), pattern));
+        }
+        let tokens = s.tokens().spanned().collect();
+        (tokens, s.take_diagnostics())
+    }
+
+    fn patterns(src: &str) -> Vec<ChunkPattern> {
+        vec![
+            ChunkPattern::Bytes(1),
+            ChunkPattern::Bytes(2),
+            ChunkPattern::Bytes(3),
+            ChunkPattern::SplitUtf8,
+            ChunkPattern::SplitTokens,
+            ChunkPattern::At((0..src.len()).filter(|i| i % 7 == 3 || i % 11 == 0).collect()),
+        ]
+    }
+
+    #[test]
+    fn test_chunking_doesnt_change_tokens() {
+        for src in SOURCES {
+            let expected = scan::<DEFAULT_BUFFER_SIZE>(src, None);
+            assert_eq!(verify_lossless(src), None, "{:?}", src);
+            for pattern in patterns(src) {
+                let found = scan::<DEFAULT_BUFFER_SIZE>(src, Some(pattern.clone()));
+                assert_eq!(found, expected, "{:?} {:?}", src, pattern);
+                assert_eq!(scan::<5>(src, Some(pattern.clone())), expected, "{:?} {:?}", src, pattern);
+                assert_eq!(scan::<8>(src, Some(pattern.clone())), expected, "{:?} {:?}", src, pattern);
+            }
+        }
+    }
+
+    #[test]
+    fn test_long_tokens() {
+        let f100 = "f".repeat(100);
+        let src = format!(" \t\"{}\"\n¬{}¬ {} ; {}", f100, f100, f100, f100);
+        let expected = scan::<DEFAULT_BUFFER_SIZE>(&src, None);
+        assert_eq!(expected.0[1].text, format!("\"{}\"", f100));
+        for pattern in patterns(&src) {
+            assert_eq!(scan::<16>(&src, Some(pattern.clone())), expected, "{:?}", pattern);
+        }
+    }
+
+    #[test]
+    fn test_chunk_ends() {
+        let src = "a ñ \"b\"";
+        let mut s = Scanner::init(src.as_bytes());
+        s.set_chunked_reader(ChunkedReader::new(src.as_bytes(), ChunkPattern::SplitUtf8));
+        s.start_recording();
+        while s.scan() != EOF {}
+        assert_eq!(s.replay_log().unwrap().chunks, [0..3, 3..src.len()]);
+
+        let mut s = Scanner::init(src.as_bytes());
+        s.set_chunked_reader(ChunkedReader::new(src.as_bytes(), ChunkPattern::SplitTokens));
+        s.start_recording();
+        while s.scan() != EOF {}
+        let ends: Vec<usize> = s.replay_log().unwrap().chunks.iter().map(|c| c.end).collect();
+        assert_eq!(ends, [1, 2, 3, 5, 6, src.len()]);
+
+        // Replaying reproduces the chunks
+        let log = s.replay_log().unwrap();
+        let mut r: Scanner<'_> = log.scanner().unwrap();
+        while r.scan() != EOF {}
+        assert_eq!(r.replay_log(), Some(log));
+    }
+}
@@ -0,0 +1,66 @@
+// Copyright 2022 Jordi Íñigo Griera. All rights reserved.
+
+#[cfg(test)]
+mod tests {
+    use scanner::compat::*;
+    use scanner::*;
+
+    const SRC: &str = "(def a 10) ; ten\n(str \"a\\n\" :k ¬r¬ 1.5)";
+
+    const TRANSCRIPT: &str = r#"1:1: "(" "("
+1:2: Ident "def"
+1:6: Ident "a"
+1:8: Int "10"
+1:10: ")" ")"
+2:1: "(" "("
+2:2: Ident "str"
+2:6: String "\"a\\n\""
+2:12: Keyword ":k"
+2:15: RawString "¬r¬"
+2:19: Float "1.5"
+2:22: ")" ")"
+"#;
+
+    #[test]
+    fn test_transcript_matches() {
+        let expected = parse_transcript(TRANSCRIPT).unwrap();
+        let found = record(SRC.as_bytes(), LISP_TOKENS);
+
+        assert_eq!(expected.len(), 12);
+        assert_eq!(diff(&expected, &found), None);
+    }
+
+    #[test]
+    fn test_display_round_trips() {
+        let found = record(SRC.as_bytes(), LISP_TOKENS & !SKIP_COMMENTS);
+        let transcript: String = found.iter().map(|e| format!("{}\n", e)).collect();
+
+        assert!(transcript.contains("1:12: Comment \"; ten\"\n"));
+        assert_eq!(parse_transcript(&transcript).unwrap(), found);
+    }
+
+    #[test]
+    fn test_divergence() {
+        let expected = parse_transcript(TRANSCRIPT).unwrap();
+        let found = record(SRC.as_bytes(), MINIMAL_TOKENS);
+
+        let d = diff(&expected, &found).unwrap();
+        assert_eq!(d.index, 8);
+        assert_eq!(d.expected.unwrap().tok, KEYWORD);
+        assert_eq!(d.found.unwrap().tok, ':' as i32);
+
+        let d = diff(&expected, &expected[..3]).unwrap();
+        assert_eq!(d.index, 3);
+        assert_eq!(d.found, None);
+    }
+
+    #[test]
+    fn test_malformed_transcript() {
+        let err = parse_transcript("1:1: Ident \"a\"\n\nfoo\n").unwrap_err();
+        assert_eq!(err.line, 3);
+        assert_eq!(err.to_string(), "transcript line 3: malformed entry");
+
+        assert!(parse_transcript("1:1: \"ab\" \"ab\"").is_err());
+        assert!(parse_transcript("1:1: Ident a").is_err());
+    }
+}
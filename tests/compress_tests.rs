@@ -0,0 +1,219 @@
+// Copyright 2022 Jordi Íñigo Griera. All rights reserved.
+
+#[cfg(all(test, feature = "compress"))]
+mod tests {
+    use scanner::*;
+
+    fn unhex(hex: &str) -> Vec<u8> {
+        (0..hex.len()).step_by(2).map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap()).collect()
+    }
+
+    // Generated with Python's gzip module (mtime=0)
+    const STORED: &str = "1f8b08000000000000ff010700f8ff28612022622229c9fd3fc707000000";
+    const FIXED: &str = "1f8b08000000000002ffd34854504a52d20400c9fd3fc707000000";
+    const NAMED: &str = "1f8b08080000000002ff61726368697665642e6c69737000d3a8d00400d8a54b6c03000000";
+    const MULTI: &str = "1f8b08000000000002ffd348d4e402002f8f44a9040000001f8b08000000000002ffd348d2e40200763102ab04000000";
+    const DYNAMIC: &str = "\
+        1f8b08000000000002ffcd523b6e154110cc3945c9d13e10d2f4677ec04d10c143f6434f481bd848c011901019a45ce0\
+        256488f0f92a48a41c815eec9dda23788351cff4767575554f9757071c8eaf311de6c77839bfc2743c607a8119ba8b63\
+        7a129925fd342eb2db6d6ebafbff3d9a1e04c473dcfed8cf97b7bff0fbe7b73f5f3fe3eff72f9fee806f70b1c7cdbbeb\
+        e3fce602cfde5e7dc4f974bd7f7f3e9d4f77afe7d33d850f09690d251add870a5f43435f43879435ced0bcc60536de2b\
+        7cfcdf50064c471be89220896da3af32a7101f5562d172e04910e8a39304057290128498abd0c6ba065362f6a0cb4913\
+        9c5c54e0ce5cc8d0469d1ab20e4c75e43afa694621172d289b5c45ddd435d40d66c8c27e96d0c9c5049d3c2d74491cc2\
+        4298c4092d94118e6f3924251f2b71a57056214655ad2d8a13b94332fdf0f0aa90948759854e7ab0aa34d98355a3ffbe\
+        f8c5d5f0302c91958763c285f2b04c136b1bd412913bd4d3e89b53b84d5659a065935568656db65806226787f6cd2267\
+        d886558e651672ce75591dd6c62619e7cdb14abea8f10f342683e955040000";
+
+    fn source() -> String {
+        let mut src = "(def fib (fn* [n] (if (< n 2) n (+ (fib (- n 1)) (fib (- n 2))))))\n".repeat(3);
+        src.push_str("; ñandú 世界 🎉\n(def s \"a string\" :key ¬raw¬¬string¬)\n");
+        for i in 0..60 {
+            src.push_str(&format!("(def x{} {})\n", i, i * i));
+        }
+        src
+    }
+
+    #[test]
+    fn test_decompress_blocks() {
+        assert_eq!(decompress(&unhex(STORED)).unwrap(), b"(a \"b\")");
+        assert_eq!(decompress(&unhex(FIXED)).unwrap(), b"(a \"b\")");
+        assert_eq!(decompress(&unhex(NAMED)).unwrap(), b"(x)");
+        assert_eq!(decompress(&unhex(MULTI)).unwrap(), b"(a)\n(b)\n");
+        assert_eq!(String::from_utf8(decompress(&unhex(DYNAMIC)).unwrap()).unwrap(), source());
+    }
+
+    #[test]
+    fn test_scan_decompressed() {
+        let gz = unhex(DYNAMIC);
+        assert!(is_compressed(&gz));
+        let src = decompress(&gz).unwrap();
+        let mut s = Scanner::init(&src);
+        s.set_mode(LISP_TOKENS & !SKIP_COMMENTS);
+        let t: Vec<SpannedToken> = s.tokens().spanned().filter(|t| t.tok == COMMENT).collect();
+        assert_eq!(t[0].text, "; ñandú 世界 🎉");
+        assert_eq!((t[0].span.start.offset, t[0].span.start.line), (source().find(';').unwrap(), 4));
+    }
+
+    #[test]
+    fn test_decompress_errors() {
+        assert_eq!(decompress(b"(a)"), Err(DecompressError::UnknownFormat));
+        assert!(!is_compressed(b"(a)"));
+        let zstd = [0x28, 0xb5, 0x2f, 0xfd, 0x00];
+        assert!(is_compressed(&zstd));
+        assert_eq!(decompress(&zstd), Err(DecompressError::Corrupt));
+
+        let gz = unhex(DYNAMIC);
+        assert_eq!(decompress(&gz[..gz.len() - 10]), Err(DecompressError::Corrupt));
+        let mut bad_crc = gz.clone();
+        let n = bad_crc.len();
+        bad_crc[n - 8] ^= 1;
+        assert_eq!(decompress(&bad_crc), Err(DecompressError::Corrupt));
+        // The second member copies from the first one
+        let cross = unhex("1f8b08000000000002034b040043beb7e8010000001f8b08000000000002ff0302002d7307f003000000");
+        assert_eq!(decompress(&cross), Err(DecompressError::Corrupt));
+        assert_eq!(DecompressError::Corrupt.to_string(), "corrupt compressed data");
+    }
+
+    // Generated with the zstd command
+    const ZSTD: &str = "28b52ffd045839000028612022622229aa8c5846";
+    // Two frames, the second without a checksum, around a skippable frame
+    const ZSTD_MULTI: &str =
+        "28b52ffd04582100002861290a37bd977e532a4d180300000078797a28b52ffd00582100002862290a";
+    // ";" followed by 300000 "-"
+    const ZSTD_RLE: &str = "28b52ffd0458540000103b2d0100fbff39c0020200102d0b9f042db03be714";
+
+    #[test]
+    fn test_decompress_zstd() {
+        assert!(is_compressed(&unhex(ZSTD)));
+        assert_eq!(decompress(&unhex(ZSTD)).unwrap(), b"(a \"b\")");
+        assert_eq!(decompress(&unhex(ZSTD_MULTI)).unwrap(), b"(a)\n(b)\n");
+        let src = decompress(&unhex(ZSTD_RLE)).unwrap();
+        assert_eq!(src.len(), 300_001);
+        assert!(src[1..].iter().all(|&b| b == b'-'));
+
+        let zst = CORPUS_ZSTD;
+        assert_eq!(decompress(zst).unwrap(), corpus().as_bytes());
+        let mut d = Decompressor::new(zst);
+        let mut out = Vec::new();
+        while let Some(chunk) = d.next_chunk().unwrap() {
+            out.extend_from_slice(chunk);
+        }
+        assert_eq!(out, corpus().as_bytes());
+        let src = corpus();
+        let mut s = Scanner::init(src.as_bytes());
+        let expected: Vec<SpannedToken> = s.iter().collect();
+        assert_eq!(CompressedScanner::new(zst).collect::<Vec<_>>(), expected);
+        assert_eq!(decompress_with_limit(zst, 1000), Err(DecompressError::TooLarge));
+    }
+
+    #[test]
+    fn test_decompress_zstd_errors() {
+        let zst = CORPUS_ZSTD;
+        assert_eq!(decompress(&zst[..zst.len() - 100]), Err(DecompressError::Corrupt));
+        let mut bad_checksum = zst.to_vec();
+        let n = bad_checksum.len();
+        bad_checksum[n - 1] ^= 1;
+        assert_eq!(decompress(&bad_checksum), Err(DecompressError::Corrupt));
+        let mut bad_data = unhex(ZSTD);
+        bad_data[10] ^= 1;
+        assert_eq!(decompress(&bad_data), Err(DecompressError::Corrupt));
+        // A frame that needs dictionary 1
+        let dictionary = [0x28, 0xb5, 0x2f, 0xfd, 0x21, 0x01, 0x00, 0x01, 0x00, 0x00];
+        assert_eq!(decompress(&dictionary), Err(DecompressError::Corrupt));
+        // A window of 2 GiB
+        let window = [0x28, 0xb5, 0x2f, 0xfd, 0x00, 0xA8, 0x01, 0x00, 0x00];
+        assert_eq!(decompress(&window), Err(DecompressError::Corrupt));
+    }
+
+    const CORPUS: &[u8] = include_bytes!("data/corpus.lisp.gz");
+    const CORPUS_ZSTD: &[u8] = include_bytes!("data/corpus.lisp.zst");
+
+    fn corpus() -> String {
+        (0..3000).map(|i| format!("(def x{i} (+ {} \"s{}\" :k{}))\n", i % 97, i % 13, i % 7)).collect()
+    }
+
+    // A gzip member of "a" followed by copies of 258 bytes at distance 1,
+    // without a valid trailer.
+    fn bomb(copies: usize) -> Vec<u8> {
+        let mut bits: Vec<bool> = vec![true, true, false]; // last block, fixed codes
+        let mut code = |c: u32, n: u32| bits.extend((0..n).rev().map(|i| c >> i & 1 == 1));
+        code(0x30 + 'a' as u32, 8);
+        for _ in 0..copies {
+            code(0xC5, 8); // length 258
+            code(0, 5); // distance 1
+        }
+        code(0, 7); // end of block
+        let mut gz = vec![0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 0xff];
+        gz.extend(bits.chunks(8).map(|byte| byte.iter().rev().fold(0u8, |b, &bit| b << 1 | bit as u8)));
+        gz.extend([0; 8]);
+        gz
+    }
+
+    #[test]
+    fn test_decompress_chunks() {
+        let mut d = Decompressor::new(CORPUS);
+        let mut out = Vec::new();
+        let mut chunks = 0;
+        while let Some(chunk) = d.next_chunk().unwrap() {
+            out.extend_from_slice(chunk);
+            chunks += 1;
+        }
+        assert!(chunks > 1);
+        assert_eq!(String::from_utf8(out).unwrap(), corpus());
+        assert_eq!(decompress(CORPUS).unwrap(), corpus().as_bytes());
+    }
+
+    #[test]
+    fn test_compressed_scanner() {
+        let src = corpus();
+        let mut s = Scanner::init(src.as_bytes());
+        let expected: Vec<SpannedToken> = s.iter().collect();
+        let mut s = CompressedScanner::new(CORPUS);
+        assert_eq!(s.by_ref().collect::<Vec<_>>(), expected);
+        assert_eq!(s.error(), None);
+
+        let mut s = CompressedScanner::new(&CORPUS[..CORPUS.len() - 100]);
+        assert!(s.by_ref().count() < expected.len());
+        assert_eq!(s.error(), Some(DecompressError::Corrupt));
+    }
+
+    #[test]
+    fn test_decompress_limit() {
+        let gz = bomb(40_000); // 10 MB
+        assert!(gz.len() < 70_000);
+        assert_eq!(decompress_with_limit(&gz, 1 << 20), Err(DecompressError::TooLarge));
+        assert_eq!(decompress_with_limit(&gz, 20 << 20), Err(DecompressError::Corrupt));
+        assert_eq!(decompress_with_limit(CORPUS, corpus().len()).map(|out| out.len()), Ok(corpus().len()));
+        assert_eq!(decompress_with_limit(CORPUS, corpus().len() - 1), Err(DecompressError::TooLarge));
+
+        let mut s = CompressedScanner::new(bomb(40_000));
+        s.set_limit(1 << 20);
+        // Only the text decompressed within the limit is scanned
+        assert!(s.by_ref().map(|t| t.text.len()).sum::<usize>() <= 1 << 20);
+        assert_eq!(s.error(), Some(DecompressError::TooLarge));
+        assert_eq!(DecompressError::TooLarge.to_string(), "decompressed data exceeds the size limit");
+    }
+}
+
+#[cfg(all(test, feature = "compress"))]
+mod std_tests {
+    use scanner::*;
+    use std::io::{BufReader, Read};
+
+    #[test]
+    fn test_decompressor_reader() {
+        let gz = include_bytes!("data/corpus.lisp.gz");
+        let mut text = String::new();
+        Decompressor::new(&gz[..]).read_to_string(&mut text).unwrap();
+        assert!(text.starts_with("(def x0 (+ 0 \"s0\" :k0))\n"));
+
+        let lines = LineScanner::new(BufReader::new(Decompressor::new(&gz[..])));
+        assert_eq!(lines.filter(|t| t.tok == IDENT && t.text == "def").count(), 3000);
+
+        let path = std::env::temp_dir().join("scanner_compress_test.lisp.gz");
+        std::fs::write(&path, gz).unwrap();
+        let s = CompressedScanner::open(&path).unwrap();
+        assert_eq!(s.filter(|t| t.tok == KEYWORD).count(), 3000);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
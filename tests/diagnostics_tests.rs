@@ -0,0 +1,407 @@
+// Copyright 2022 Jordi Íñigo Griera. All rights reserved.
+
+#[cfg(test)]
+mod tests {
+    use scanner::*;
+
+    fn scan_all(s: &mut Scanner) {
+        while s.scan() != EOF {}
+    }
+
+    #[test]
+    fn test_lints_disabled_by_default() {
+        let src = "(def a 10)   \n \t(def b 20)\n";
+        let mut s = Scanner::init(src.as_bytes());
+        scan_all(&mut s);
+
+        assert!(s.diagnostics().is_empty());
+    }
+
+    #[test]
+    fn test_trailing_whitespace() {
+        let src = "(def a 10)  \n(def b 20)\n(def c 30)\t";
+        let mut s = Scanner::init(src.as_bytes());
+        s.set_lints(LINT_TRAILING_WHITESPACE);
+        scan_all(&mut s);
+
+        let d = s.diagnostics();
+        assert_eq!(d.len(), 2);
+        assert_eq!(d[0].severity, Severity::Warning);
+        assert_eq!(d[0].message, "trailing whitespace");
+        assert_eq!((d[0].span.start.line, d[0].span.start.column), (1, 11));
+        assert_eq!((d[0].span.end.line, d[0].span.end.column), (1, 13));
+        assert_eq!(d[0].span.start.offset, 10);
+        assert_eq!(d[0].span.end.offset, 12);
+        assert_eq!((d[1].span.start.line, d[1].span.start.column), (3, 11));
+        assert_eq!((d[1].span.end.line, d[1].span.end.column), (3, 12));
+        assert_eq!(s.error_count(), 0);
+    }
+
+    #[test]
+    fn test_trailing_whitespace_crlf() {
+        let src = "a \r\nb\r\n";
+        let mut s = Scanner::init(src.as_bytes());
+        s.set_lints(LINT_TRAILING_WHITESPACE);
+        scan_all(&mut s);
+
+        assert_eq!(s.diagnostics().len(), 1);
+        assert_eq!(s.diagnostics()[0].span.start.column, 2);
+    }
+
+    #[test]
+    fn test_mixed_indentation() {
+        let src = "(def a\n  \t 10)\n\t  (def b 20)\n";
+        let mut s = Scanner::init(src.as_bytes());
+        s.set_lints(LINT_MIXED_INDENTATION);
+        scan_all(&mut s);
+
+        let d = s.diagnostics();
+        assert_eq!(d.len(), 1);
+        assert_eq!(d[0].message, "tab after spaces in indentation");
+        assert_eq!((d[0].span.start.line, d[0].span.start.column), (2, 3));
+    }
+
+    #[test]
+    fn test_line_length() {
+        let src = "(def abc 1)\n(def abcdef 1)\n";
+        let mut s = Scanner::init(src.as_bytes());
+        s.set_lints(LINT_LINE_LENGTH);
+        s.set_max_line_length(12);
+        scan_all(&mut s);
+
+        let d = s.diagnostics();
+        assert_eq!(d.len(), 1);
+        assert_eq!(d[0].message, "line is 14 characters long (maximum is 12)");
+        assert_eq!((d[0].span.start.line, d[0].span.start.column), (2, 13));
+        assert_eq!((d[0].span.end.line, d[0].span.end.column), (2, 15));
+    }
+
+    #[test]
+    fn test_errors_are_diagnostics() {
+        let src = "\"abc\n";
+        let mut s = Scanner::init(src.as_bytes());
+        s.position.source = SourceId::from("test.lisp");
+        scan_all(&mut s);
+
+        assert_eq!(s.error_count(), 1);
+        let d = s.take_diagnostics();
+        assert_eq!(d.len(), 1);
+        assert!(d[0].is_error());
+        assert_eq!(d[0].message, "literal not terminated");
+        assert!(d[0].to_string().starts_with("test.lisp:"));
+        assert!(s.diagnostics().is_empty());
+    }
+
+    #[test]
+    fn test_error_handler() {
+        let errors = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+        let mut s = Scanner::init("\"a\\qb\" 0x \n\"c".as_bytes());
+        s.set_lints(LINT_TRAILING_WHITESPACE);
+        let collected = errors.clone();
+        s.set_error_handler(move |pos, msg| collected.borrow_mut().push(format!("{}:{} {}", pos.line, pos.column, msg)));
+        scan_all(&mut s);
+
+        assert_eq!(
+            *errors.borrow(),
+            ["1:3 invalid char escape", "1:8 hexadecimal literal has no digits", "2:1 literal not terminated"]
+        );
+        assert_eq!(s.error_count(), 3);
+        assert_eq!(s.diagnostics().len(), 1);
+        assert_eq!(s.diagnostics()[0].code, DiagnosticCode::TrailingWhitespace);
+    }
+
+    fn error_spans(src: &str) -> Vec<((usize, usize), (usize, usize))> {
+        let mut s = Scanner::init(src.as_bytes());
+        scan_all(&mut s);
+        s.diagnostics()
+            .iter()
+            .map(|d| ((d.span.start.line, d.span.start.column), (d.span.end.line, d.span.end.column)))
+            .collect()
+    }
+
+    #[test]
+    fn test_error_spans_start_at_token() {
+        // From the opening quote to the end of the line
+        assert_eq!(error_spans("(a \"abc\n)"), [((1, 4), (1, 8))]);
+        assert_eq!(error_spans("  0x )"), [((1, 3), (1, 5))]);
+        assert_eq!(error_spans("a\n  1e+ b"), [((2, 3), (2, 6))]);
+    }
+
+    #[test]
+    fn test_error_spans_start_at_escape() {
+        assert_eq!(error_spans("\"ab\\qc\""), [((1, 4), (1, 6))]);
+        assert_eq!(error_spans("x \"\\x4g\""), [((1, 4), (1, 7))]);
+
+        let mut s = Scanner::init("\"\\q\"".as_bytes());
+        s.position.source = SourceId::from("test.lisp");
+        scan_all(&mut s);
+        assert_eq!(s.diagnostics()[0].to_string(), "test.lisp:1:2: invalid char escape");
+    }
+
+    #[test]
+    fn test_error_outside_token() {
+        assert_eq!(error_spans("a \0 b"), [((1, 3), (1, 3))]);
+    }
+
+    // Message and (line, column) of the span start and end
+    type Warning = (String, (usize, usize), (usize, usize));
+
+    fn warnings(src: &str, lints: u32) -> Vec<Warning> {
+        let mut s = Scanner::init(src.as_bytes());
+        s.set_lints(lints);
+        scan_all(&mut s);
+        s.diagnostics()
+            .iter()
+            .filter(|d| !d.is_error())
+            .map(|d| {
+                let (start, end) = (&d.span.start, &d.span.end);
+                (d.message.clone(), (start.line, start.column), (end.line, end.column))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_legacy_octal() {
+        let w = warnings("(chmod 0755 0o755 0 0.5 -012 0x10)", LINT_LEGACY_OCTAL);
+        let msg = "octal literal with leading 0, use the 0o prefix".to_string();
+        assert_eq!(w, [(msg.clone(), (1, 8), (1, 12)), (msg, (1, 25), (1, 29))]);
+    }
+
+    #[test]
+    fn test_escape_range() {
+        let w = warnings("(\"a\\777b\\377\" \"\\uD800\\U00110000\")", LINT_ESCAPE_RANGE);
+        assert_eq!(w.len(), 3);
+        assert_eq!(w[0], ("escape sequence \\777 is out of range".to_string(), (1, 4), (1, 8)));
+        assert_eq!(w[1].0, "escape sequence \\uD800 is out of range");
+        assert_eq!((w[1].1, w[1].2), ((1, 16), (1, 22)));
+        assert_eq!(w[2].0, "escape sequence \\U00110000 is out of range");
+
+        // The scanner still accepts them
+        let mut s = Scanner::init("\"\\777\"".as_bytes());
+        assert_eq!(s.scan(), STRING);
+        assert_eq!(s.error_count(), 0);
+    }
+
+    #[test]
+    fn test_non_nfc() {
+        let mut s = Scanner::init("(cafe\u{301} café)".as_bytes());
+        s.set_is_ident_rune(|ch, i| ch.is_alphabetic() || (i > 0 && ch == '\u{301}'));
+        s.set_lints(LINT_NON_NFC);
+        scan_all(&mut s);
+        let d = s.diagnostics();
+        assert_eq!(d.len(), 1);
+        assert_eq!(d[0].message, "identifier is not in NFC normal form");
+        assert_eq!((d[0].span.start.column, d[0].span.end.column), (2, 7));
+
+        // With the default identifier rules the mark is a separate token
+        let w = warnings("cafe\u{301}", LINT_NON_NFC);
+        assert_eq!(w.len(), 1);
+        assert_eq!(w[0].0, "stray combining mark U+0301, the source may not be in NFC normal form");
+    }
+
+    #[test]
+    fn test_deep_nesting() {
+        let mut s = Scanner::init("(a [b {c #{d}}] (e (f)))".as_bytes());
+        s.set_lints(LINT_DEEP_NESTING);
+        s.set_max_nesting_depth(3);
+        scan_all(&mut s);
+        let d = s.diagnostics();
+        assert_eq!(d.len(), 1);
+        assert_eq!(d[0].message, "nesting depth exceeds 3");
+        assert_eq!((d[0].span.start.column, d[0].span.end.column), (10, 12));
+
+        assert!(warnings(&"(".repeat(64), LINT_DEEP_NESTING).is_empty());
+        assert_eq!(warnings(&"(".repeat(65), LINT_DEEP_NESTING).len(), 1);
+    }
+
+    #[test]
+    fn test_token_lints_in_source_order() {
+        let w = warnings("(a 07)  \n", LINT_LEGACY_OCTAL | LINT_TRAILING_WHITESPACE);
+        assert_eq!(w.len(), 2);
+        assert_eq!(w[0].1, (1, 4));
+        assert_eq!(w[1].0, "trailing whitespace");
+    }
+
+    #[test]
+    fn test_error_codes_are_stable() {
+        let cases = [
+            ("a \0", "E002", "invalid character NUL"),
+            ("0b1.1", "E003", "invalid radix point in binary literal"),
+            ("0b1e1", "E005", "'e' exponent requires decimal mantissa"),
+            ("0b", "E004", "binary literal has no digits"),
+            ("0x1p", "E006", "exponent has no digits"),
+            ("0x1.8", "E007", "hexadecimal mantissa requires a 'p' exponent"),
+            ("0o8", "E008", "invalid digit '8' in octal literal"),
+            ("1__0", "E009", "'_' must separate successive digits"),
+            ("\"\\q\"", "E010", "invalid char escape"),
+            ("\"abc", "E011", "literal not terminated"),
+            ("abcdefghi", "E012", "identifier is 9 characters long (maximum is 8)"),
+        ];
+        for (src, code, message) in cases {
+            let mut s = Scanner::init(src.as_bytes());
+            s.set_max_ident_length(8, IdentLengthPolicy::Truncate);
+            scan_all(&mut s);
+            let d = &s.diagnostics()[0];
+            assert_eq!((d.code.as_str(), d.message.as_str()), (code, message), "{:?}", src);
+        }
+
+        let mut s = Scanner::init(b"\xff");
+        scan_all(&mut s);
+        assert_eq!(s.diagnostics()[0].code, DiagnosticCode::InvalidUtf8);
+        assert_eq!(s.diagnostics()[0].code.to_string(), "E001");
+    }
+
+    #[test]
+    fn test_try_scan_errors() {
+        let mut s = Scanner::init("a \0 0b 1__0 0o8 ¬x".as_bytes());
+        s.set_error_handler(|_, _| {});
+        assert_eq!(s.try_scan(), Ok(IDENT));
+        assert!(matches!(s.try_scan(), Err(ScanError::InvalidNul { position, .. }) if position.column == 3));
+        let err = s.try_scan().unwrap_err();
+        assert_eq!(err, ScanError::MalformedNumber { position: s.position.clone(), message: "binary literal has no digits".to_string() });
+        assert!(matches!(s.try_scan(), Err(ScanError::MalformedNumber { .. })));
+        assert!(matches!(s.try_scan(), Err(ScanError::InvalidDigit { .. })));
+        assert!(matches!(s.try_scan(), Err(ScanError::UnterminatedString { position, .. }) if position.column == 17));
+        assert_eq!(s.try_scan(), Ok(EOF));
+        assert_eq!(s.error_count(), 5);
+
+        let mut s = Scanner::init(b"\xff abc");
+        s.set_max_ident_length(2, IdentLengthPolicy::Error);
+        assert!(matches!(s.try_scan(), Err(ScanError::InvalidUtf8 { .. })));
+        let err = s.try_scan().unwrap_err();
+        assert_eq!(err.to_string(), "<input>:1:3: identifier is 3 characters long (maximum is 2)");
+        assert_eq!(s.token_text(), "abc");
+
+        // The message is the one of the diagnostic
+        let mut s = Scanner::init(b"a #| b");
+        s.set_mode(LISP_TOKENS | SCAN_BLOCK_COMMENTS);
+        s.set_error_handler(|_, _| {});
+        assert_eq!(s.try_scan(), Ok(IDENT));
+        let err = s.try_scan().unwrap_err();
+        assert!(matches!(err, ScanError::UnterminatedString { .. }));
+        assert_eq!(err.to_string(), "<input>:1:3: comment not terminated");
+    }
+
+    #[test]
+    fn test_warning_codes_are_stable() {
+        let mut s = Scanner::init("(a 07 \"\\777\")  \n\t  X\n".as_bytes());
+        s.set_lints(ALL_LINTS);
+        s.set_max_line_length(10);
+        scan_all(&mut s);
+        let codes: Vec<&str> = s.diagnostics().iter().map(|d| d.code.as_str()).collect();
+        assert_eq!(codes, ["W004", "W005", "W003", "W001", "W008"]);
+    }
+
+    #[test]
+    fn test_suspicious_delimiters() {
+        let src = "(defn f [x]\n  ((g x)\n  [[1] [2]]\n(h ¬a\nb\nc¬)";
+        assert_eq!(
+            warnings(src, LINT_SUSPICIOUS_DELIMITERS),
+            [("doubled '(' is closed only once".to_string(), (2, 3), (2, 5))]
+        );
+        let mut s = Scanner::init(src.as_bytes());
+        s.set_lints(LINT_SUSPICIOUS_DELIMITERS);
+        s.set_max_raw_string_lines(2);
+        scan_all(&mut s);
+        let d = s.diagnostics();
+        assert_eq!(d.len(), 2);
+        assert_eq!(d[1].message, "raw string spans 3 lines (maximum is 2)");
+        assert_eq!((d[1].span.start.column, d[1].span.end.column), (4, 5));
+        assert_eq!(d[1].code.as_str(), "W009");
+
+        // Separated brackets and documents are not doubled
+        assert!(warnings("(defn f [x]\n  ( (g x)", LINT_SUSPICIOUS_DELIMITERS).is_empty());
+        assert_eq!(warnings("((a)\n---\n(b)", LINT_SUSPICIOUS_DELIMITERS).len(), 1);
+    }
+
+    #[test]
+    fn test_unbalanced_delimiters() {
+        assert_eq!(
+            warnings("(a [b) #{c}}\n]", LINT_UNBALANCED_DELIMITERS),
+            [
+                ("')' doesn't match '['".to_string(), (1, 6), (1, 7)),
+                ("'}' doesn't match '('".to_string(), (1, 12), (1, 13)),
+                ("']' closes nothing".to_string(), (2, 1), (2, 2)),
+            ]
+        );
+        assert!(warnings("(a [b] #{c} {:d e})", LINT_UNBALANCED_DELIMITERS).is_empty());
+    }
+
+    #[test]
+    fn test_delimiter_pairs() {
+        let mut s = Scanner::init("(a <<b #|c|#>> <<d) |# <<<e>>".as_bytes());
+        s.add_delimiter_pair("<<", ">>");
+        s.add_delimiter_pair("#|", "|#");
+        s.add_delimiter_pair("", ")");
+        s.set_lints(LINT_UNBALANCED_DELIMITERS | LINT_DEEP_NESTING);
+        s.set_max_nesting_depth(2);
+        s.set_summary(true);
+        let texts: Vec<String> = s.tokens().spanned().map(|t| t.text).collect();
+        assert_eq!(texts, ["(", "a", "<<", "b", "#|", "c", "|#", ">>", "<<", "d", ")", "|#", "<<", "<e>>"]);
+        let messages: Vec<&str> = s.diagnostics().iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            ["nesting depth exceeds 2", "')' doesn't match '<<'", "'|#' doesn't match '('"]
+        );
+        assert_eq!(s.diagnostics()[1].code.as_str(), "W010");
+        let unclosed: Vec<String> = s.eof_summary().unwrap().unclosed.into_iter().map(|t| t.text).collect();
+        assert_eq!(unclosed, ["<<"]);
+
+        // a delimiter closing its own pair
+        let mut s = Scanner::init("|a |b| c|".as_bytes());
+        s.add_delimiter_pair("|", "|");
+        s.set_lints(LINT_UNBALANCED_DELIMITERS);
+        s.set_summary(true);
+        while s.scan() != EOF {}
+        assert!(s.diagnostics().is_empty());
+        assert!(s.eof_summary().unwrap().unclosed.is_empty());
+    }
+
+    #[test]
+    fn test_naming_style() {
+        let src = "(defn read_string [*out* *Err* let* :Key :ns/ok] (->> x))";
+        assert_eq!(
+            warnings(src, LINT_IDENT_STYLE | LINT_EARMUFF_STYLE | LINT_KEYWORD_STYLE),
+            [
+                ("identifier read_string doesn't follow the naming style".to_string(), (1, 7), (1, 18)),
+                ("earmuffed identifier *Err* doesn't follow the naming style".to_string(), (1, 26), (1, 31)),
+                ("keyword :Key doesn't follow the naming style".to_string(), (1, 37), (1, 41)),
+            ]
+        );
+        assert_eq!(warnings(src, LINT_EARMUFF_STYLE).len(), 1);
+
+        // SCREAMING earmuffs for globals, no underscores in the rest
+        let mut s = Scanner::init(src.as_bytes());
+        s.set_lints(LINT_IDENT_STYLE | LINT_EARMUFF_STYLE);
+        s.set_naming_style(LINT_EARMUFF_STYLE, |text| !text.chars().any(char::is_lowercase));
+        s.set_naming_style(LINT_IDENT_STYLE, |text| !text.contains('_'));
+        scan_all(&mut s);
+        let flagged: Vec<&str> = s.diagnostics().iter().map(|d| &src[d.span.start.offset..d.span.end.offset]).collect();
+        assert_eq!(flagged, ["read_string", "*out*", "*Err*"]);
+
+        assert!(is_kebab_case("*host-language*"));
+        assert!(!is_kebab_case("readString"));
+    }
+
+    #[test]
+    fn test_user_data() {
+        #[derive(Debug, PartialEq)]
+        struct Job(usize);
+
+        let mut s = Scanner::init("\"abc".as_bytes());
+        assert!(s.user_data::<Job>().is_none());
+        s.set_user_data(Job(7));
+        assert_eq!(s.user_data::<Job>(), Some(&Job(7)));
+        assert!(s.user_data::<String>().is_none());
+        scan_all(&mut s);
+
+        let d = s.take_diagnostics();
+        assert_eq!(d[0].user_data::<Job>(), Some(&Job(7)));
+        assert!(d[0].user_data::<u32>().is_none());
+
+        let mut s = Scanner::init("\"abc".as_bytes());
+        scan_all(&mut s);
+        assert!(s.diagnostics()[0].user_data.is_none());
+        assert_ne!(s.diagnostics()[0].user_data, d[0].user_data);
+    }
+}
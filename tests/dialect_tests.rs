@@ -0,0 +1,56 @@
+// Copyright 2022 Jordi Íñigo Griera. All rights reserved.
+
+#[cfg(test)]
+mod tests {
+    use scanner::*;
+
+    const COMMA: u64 = 1 << b',';
+
+    scanner::dialect! {
+        mod edn {
+            mode = LISP_TOKENS & !(SCAN_RAW_STRINGS | SCAN_LISP_SPECIALS);
+            whitespace = LISP_WHITESPACE | COMMA;
+            tokens {
+                TAG = -64 => "Tag",
+                DISCARD = -65 => "Discard",
+            }
+        }
+    }
+
+    scanner::dialect! {
+        mod plain {
+            mode = MINIMAL_TOKENS;
+            whitespace = LISP_WHITESPACE;
+        }
+    }
+
+    #[test]
+    fn test_dialect_configuration() {
+        assert_eq!(edn::MODE, LISP_TOKENS & !(SCAN_RAW_STRINGS | SCAN_LISP_SPECIALS));
+        assert_eq!(edn::WHITESPACE, LISP_WHITESPACE | COMMA);
+
+        let mut s = edn::scanner(b"{:a 1, :b ~@c}");
+        let toks: Vec<Token> = core::iter::from_fn(|| Some(s.scan())).take_while(|&t| t != EOF).collect();
+        assert_eq!(
+            toks,
+            ['{' as i32, KEYWORD, INT, KEYWORD, '~' as i32, '@' as i32, IDENT, '}' as i32]
+        );
+
+        let mut s = Scanner::init(b"a, b");
+        plain::configure(&mut s);
+        assert_eq!(s.mode, MINIMAL_TOKENS);
+        assert_eq!(s.scan(), IDENT);
+        assert_eq!(s.scan(), ',' as i32);
+    }
+
+    #[test]
+    fn test_dialect_token_names() {
+        assert_eq!(edn::TAG, -64);
+        assert_eq!(edn::token_name(edn::DISCARD), Some("Discard"));
+        assert_eq!(edn::token_name(IDENT), None);
+        assert_eq!(edn::token_string(edn::TAG), "Tag");
+        assert_eq!(edn::token_string(KEYWORD), "Keyword");
+        assert_eq!(edn::token_string('x' as i32), "\"x\"");
+        assert_eq!(plain::token_name(-64), None);
+    }
+}
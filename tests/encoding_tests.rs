@@ -0,0 +1,79 @@
+// Copyright 2022 Jordi Íñigo Griera. All rights reserved.
+
+#[cfg(all(test, feature = "encoding"))]
+mod tests {
+    use scanner::*;
+
+    fn utf16(src: &str, encoding: &'static Encoding, bom: bool) -> Vec<u8> {
+        let units = bom.then_some(0xFEFF).into_iter().chain(src.encode_utf16());
+        if encoding == UTF_16LE {
+            units.flat_map(u16::to_le_bytes).collect()
+        } else {
+            units.flat_map(u16::to_be_bytes).collect()
+        }
+    }
+
+    fn texts(src: &[u8]) -> Vec<String> {
+        let mut s = Scanner::init(src);
+        s.iter().map(|t| t.text).collect()
+    }
+
+    #[test]
+    fn test_detect_encoding() {
+        let src = "(def π \"𝄞\")\n";
+        for encoding in [UTF_16LE, UTF_16BE] {
+            assert_eq!(detect_encoding(&utf16(src, encoding, true)), (encoding, 2));
+            assert_eq!(detect_encoding(&utf16(src, encoding, false)), (encoding, 0));
+        }
+        assert_eq!(detect_encoding(b"\xEF\xBB\xBF(a)"), (UTF_8, 3));
+        assert_eq!(detect_encoding(src.as_bytes()), (UTF_8, 0));
+        assert_eq!(detect_encoding(b""), (UTF_8, 0));
+        assert_eq!(detect_encoding(b"a"), (UTF_8, 0));
+        // Non-ASCII UTF-16 text has no zero bytes to detect
+        assert_eq!(detect_encoding(&utf16("πσ", UTF_16LE, false)), (UTF_8, 0));
+    }
+
+    #[test]
+    fn test_decode() {
+        let src = "(def π \"𝄞\")\n; done";
+        let expected = texts(src.as_bytes());
+        for encoding in [UTF_16LE, UTF_16BE] {
+            for bom in [true, false] {
+                let data = utf16(src, encoding, bom);
+                let decoded = decode(&data);
+                assert_eq!(&*decoded, src.as_bytes());
+                assert_eq!(texts(&decoded), expected);
+            }
+        }
+        assert!(matches!(decode(src.as_bytes()), std::borrow::Cow::Borrowed(_)));
+        assert!(matches!(decode_from(b"\xFF(", UTF_8), std::borrow::Cow::Borrowed(b"\xFF(")));
+    }
+
+    #[test]
+    fn test_legacy_encodings() {
+        let data = b"(def caf\xE9 \"\x80 \x93x\x94\") ; \x81\xFF";
+        let windows = "(def café \"€ “x”\") ; \u{81}ÿ";
+        let encoding = Encoding::for_label(b" Windows-1252").unwrap();
+        assert_eq!(&*decode_from(data, encoding), windows.as_bytes());
+        assert_eq!(texts(&decode_from(data, encoding)), texts(windows.as_bytes()));
+        // Latin-1 is decoded as Windows-1252, as by browsers
+        assert_eq!(Encoding::for_label(b"latin1"), Some(encoding));
+        assert!(matches!(decode_from(b"(a)", encoding), std::borrow::Cow::Borrowed(b"(a)")));
+
+        let sjis = Encoding::for_label(b"shift_jis").unwrap();
+        assert_eq!(&*decode_from(b"(\x82\xa0 \"\x93\xfa\x96\x7b\")", sjis), "(あ \"日本\")".as_bytes());
+        assert_eq!(Encoding::for_label(b"UTF-16BE"), Some(UTF_16BE));
+        assert_eq!(Encoding::for_label(b"utf8"), Some(UTF_8));
+        assert_eq!(Encoding::for_label(b"no-such-encoding"), None);
+    }
+
+    #[test]
+    fn test_invalid_utf16() {
+        // An unpaired surrogate and a final odd byte
+        let data = [b'a', 0, 0x00, 0xD8, b'b', 0, b'c'];
+        assert_eq!(&*decode_from(&data, UTF_16LE), "a\u{FFFD}b\u{FFFD}".as_bytes());
+        let mut s = Scanner::init(&data);
+        s.iter().count();
+        assert!(s.error_count() > 0);
+    }
+}
@@ -0,0 +1,56 @@
+// Copyright 2022 Jordi Íñigo Griera. All rights reserved.
+
+#[cfg(test)]
+mod tests {
+    use scanner::*;
+
+    #[test]
+    fn test_extract_strings() {
+        let src = "(print \"hello\\n\\x41\\u00e9\") ; \"not this\"\n(def s ¬C:\\path¬¬s¬)";
+        let lits = extract_strings(src.as_bytes());
+
+        assert_eq!(lits.len(), 2);
+        assert_eq!(lits[0].tok, STRING);
+        assert_eq!(lits[0].text, "\"hello\\n\\x41\\u00e9\"");
+        assert_eq!(lits[0].value.as_deref(), Some("hello\nAé"));
+        assert_eq!((lits[0].span.start.line, lits[0].span.start.column), (1, 8));
+        assert_eq!(lits[1].tok, RAW_STRING);
+        assert_eq!(lits[1].value.as_deref(), Some("C:\\path¬s"));
+        assert_eq!((lits[1].span.start.line, lits[1].span.start.column), (2, 8));
+    }
+
+    #[test]
+    fn test_extract_strings_escapes() {
+        let src = r#""\a\b\f\r\t\v\\\"" "\101\x7e" "\U0001F600" "\777" "\q""#;
+        let values: Vec<Option<String>> = extract_strings(src.as_bytes()).into_iter().map(|l| l.value).collect();
+
+        assert_eq!(
+            values,
+            [
+                Some("\x07\x08\x0C\r\t\x0B\\\"".to_string()),
+                Some("A~".to_string()),
+                Some("😀".to_string()),
+                None,
+                None,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_extract_comments() {
+        let src = ";; Copyright 2022\n;;   indented\n;\n(def a 1) ; trailing\n; next block\n\n; after blank\n";
+        let blocks = extract_comments(src.as_bytes());
+
+        assert_eq!(blocks.len(), 4);
+        assert_eq!(blocks[0].placement, CommentPlacement::FullLine);
+        assert_eq!(blocks[0].text, "Copyright 2022\n  indented\n");
+        assert_eq!((blocks[0].span.start.line, blocks[0].span.start.column), (1, 1));
+        assert_eq!((blocks[0].span.end.line, blocks[0].span.end.column), (3, 2));
+        assert_eq!(blocks[1].placement, CommentPlacement::Trailing);
+        assert_eq!(blocks[1].text, "trailing");
+        assert_eq!(blocks[2].text, "next block");
+        assert_eq!(blocks[2].span.start.line, 5);
+        assert_eq!(blocks[3].text, "after blank");
+        assert_eq!(blocks[3].span.start.line, 7);
+    }
+}
@@ -0,0 +1,36 @@
+// Copyright 2022 Jordi Íñigo Griera. All rights reserved.
+
+#[cfg(test)]
+mod tests {
+    use scanner::*;
+
+    #[test]
+    fn test_hash_ignores_trivia() {
+        let a = token_stream_hash(b"(def a 10) ; a comment");
+        let b = token_stream_hash(b";; header\n(def\n  a\t10)");
+        assert_eq!(a, b);
+        assert_ne!(a, token_stream_hash(b"(def a 11)"));
+        assert_ne!(a, token_stream_hash(b"(def a \"10\")"));
+    }
+
+    #[test]
+    fn test_hash_normalizes_literals() {
+        assert_eq!(token_stream_hash(b"0XFF 1_000 1E5"), token_stream_hash(b"0xff 1000 1e5"));
+        assert_eq!(token_stream_hash(b"\"a\\\"b\""), token_stream_hash("¬a\"b¬".as_bytes()));
+        assert_eq!(token_stream_hash(b"\"\\x41\""), token_stream_hash(b"\"A\""));
+    }
+
+    #[test]
+    fn test_hash_abstract_identifiers() {
+        let a = b"(defn f [x y] (+ x y))";
+        let b = b"(defn g [p q] (+ p q))";
+        let c = b"(defn g [p q] (+ q p))";
+        assert_ne!(token_stream_hash(a), token_stream_hash(b));
+
+        let opts = HashOptions {
+            abstract_identifiers: true,
+        };
+        assert_eq!(token_stream_hash_with(a, &opts), token_stream_hash_with(b, &opts));
+        assert_ne!(token_stream_hash_with(a, &opts), token_stream_hash_with(c, &opts));
+    }
+}
@@ -0,0 +1,187 @@
+// Copyright 2022 Jordi Íñigo Griera. All rights reserved.
+
+#[cfg(test)]
+mod tests {
+    use scanner::*;
+
+    #[test]
+    fn test_iter_scanner() {
+        let src = "(déf a \"str\") ; c\n#| block\ncomment |# ¬raw\n¬ :end\n(b)";
+        let mut s = Scanner::init(src.as_bytes());
+        s.set_mode(LISP_TOKENS | SCAN_BLOCK_COMMENTS);
+        let expected: Vec<SpannedToken> = s.iter().collect();
+
+        let mut bytes = IterScanner::new(src.bytes());
+        bytes.set_mode(LISP_TOKENS | SCAN_BLOCK_COMMENTS);
+        assert_eq!(bytes.collect::<Vec<_>>(), expected);
+        let mut chars = IterScanner::new(src.chars());
+        chars.set_mode(LISP_TOKENS | SCAN_BLOCK_COMMENTS);
+        assert_eq!(chars.collect::<Vec<_>>(), expected);
+    }
+
+    #[test]
+    fn test_pulls_lines_lazily() {
+        let pulled = std::cell::Cell::new(0);
+        let lines = ["(a b)\n", "(c ¬d\n", "e¬)\n", "(f)\n"];
+        let chars = lines.iter().flat_map(|line| {
+            pulled.set(pulled.get() + 1);
+            line.chars()
+        });
+        let mut s = IterScanner::new(chars);
+        assert_eq!(s.next().map(|t| t.text), Some("(".to_string()));
+        assert_eq!(pulled.get(), 1);
+        let t = s.nth(5).unwrap();
+        assert_eq!((t.tok, t.text.as_str(), t.span.start.line), (RAW_STRING, "¬d\ne¬", 2));
+        assert_eq!(pulled.get(), 3);
+        assert_eq!(s.count(), 4);
+    }
+
+    #[test]
+    fn test_iter_diagnostics() {
+        let mut s = IterScanner::new(b"(a \"b\n\xFF \xC2\xACc".iter().copied());
+        let toks: Vec<Token> = s.by_ref().map(|t| t.tok).collect();
+        assert_eq!(toks, ['(' as Token, IDENT, STRING, 0xFFFD, RAW_STRING]);
+        let codes: Vec<DiagnosticCode> = s.diagnostics().iter().map(|d| d.code).collect();
+        assert_eq!(codes.len(), 3);
+    }
+
+    // Configuration of a dialect with // comments and #x dispatch macros.
+    fn configure(s: &mut Scanner<'_>) {
+        s.set_line_comment_prefixes(&["//"]);
+        s.set_dispatch_macro('x', Some(DispatchMacro::Token(IDENT)));
+    }
+
+    #[test]
+    fn test_iter_configuration() {
+        let src = "(a #x // b ; c\n(d ; e) // f\n";
+        let mut s = Scanner::init(src.as_bytes());
+        configure(&mut s);
+        let expected: Vec<SpannedToken> = s.iter().collect();
+        let texts: Vec<&str> = expected.iter().map(|t| t.text.as_str()).collect();
+        assert_eq!(texts, ["(", "a", "#x", "(", "d", ";", "e", ")"]);
+
+        let mut s = IterScanner::new(src.chars());
+        s.set_configuration(configure);
+        assert_eq!(s.collect::<Vec<_>>(), expected);
+    }
+
+    // Scans the tokens of the chunks fed one at a time, then finished.
+    fn push_scan(chunks: &[&[u8]], mode: u32) -> (Vec<SpannedToken>, usize) {
+        let mut s = PushScanner::new();
+        s.set_mode(mode);
+        let mut tokens = Vec::new();
+        for chunk in chunks.iter().map(Some).chain([None]) {
+            match chunk {
+                Some(chunk) => s.feed(chunk),
+                None => s.finish(),
+            }
+            loop {
+                match s.scan() {
+                    PushStatus::Token(t) => tokens.push(t),
+                    PushStatus::Incomplete => break,
+                    PushStatus::End => return (tokens, s.diagnostics().len()),
+                }
+            }
+        }
+        unreachable!()
+    }
+
+    #[test]
+    fn test_push_configuration() {
+        // The comment prefix and the dispatch macro are split by every chunking
+        let src = "(a #x // b ; c\n(d ; e) // f\n";
+        let mut s = Scanner::init(src.as_bytes());
+        configure(&mut s);
+        let expected: Vec<SpannedToken> = s.iter().collect();
+        for size in 1..src.len() {
+            let mut s = PushScanner::new();
+            s.set_configuration(configure);
+            let mut tokens = Vec::new();
+            for chunk in src.as_bytes().chunks(size) {
+                s.feed(chunk);
+                while let PushStatus::Token(t) = s.scan() {
+                    tokens.push(t);
+                }
+            }
+            s.finish();
+            while let PushStatus::Token(t) = s.scan() {
+                tokens.push(t);
+            }
+            assert_eq!(tokens, expected, "chunks of {size} bytes");
+        }
+    }
+
+    #[test]
+    fn test_push_scanner() {
+        let src = "(déf a \"str\") ; c\n#| block\ncomment |# ¬raw\n¬ :end 12.5e3\n(b \"open";
+        let mode = LISP_TOKENS | SCAN_BLOCK_COMMENTS;
+        let mut s = Scanner::init(src.as_bytes());
+        s.set_mode(mode);
+        let expected: Vec<SpannedToken> = s.iter().collect();
+        let diagnostics = s.diagnostics().len();
+        assert!(diagnostics > 0);
+
+        // Every split of the input, even inside a character
+        let bytes = src.as_bytes();
+        for i in 0..=bytes.len() {
+            assert_eq!(push_scan(&[&bytes[..i], &bytes[i..]], mode), (expected.clone(), diagnostics), "split at {i}");
+        }
+        let chunks: Vec<&[u8]> = bytes.chunks(1).collect();
+        assert_eq!(push_scan(&chunks, mode), (expected.clone(), diagnostics));
+        let (tokens, _) = push_scan(&chunks, mode & !SKIP_COMMENTS);
+        assert_eq!(tokens.iter().filter(|t| t.tok == COMMENT).count(), 2);
+    }
+
+    #[test]
+    fn test_push_byte_at_a_time() {
+        let src = "(/ 1/3 22/7 -1/2 1/x 4/ 5) ; ratios\n#| a |# ¬b\n¬ \"c\" 0x1F 1.5e3 :k #\\a é";
+        for mode in [LISP_TOKENS | SCAN_RATIOS, LISP_TOKENS | SCAN_RATIOS | SCAN_BLOCK_COMMENTS | SCAN_CHARS] {
+            let mut s = Scanner::init(src.as_bytes());
+            s.set_mode(mode);
+            let expected: Vec<SpannedToken> = s.iter().collect();
+            let diagnostics = s.diagnostics().len();
+            assert!(expected.iter().any(|t| t.tok == RATIO));
+            let chunks: Vec<&[u8]> = src.as_bytes().chunks(1).collect();
+            assert_eq!(push_scan(&chunks, mode), (expected, diagnostics));
+        }
+    }
+
+    #[test]
+    fn test_push_long_literal() {
+        // Held back literals are rescanned only once they may have ended
+        let mut s = PushScanner::new();
+        s.feed(b"(a \xC2\xAC");
+        let chunk = [b'x'; 1024];
+        for _ in 0..4096 {
+            s.feed(&chunk);
+            while let PushStatus::Token(_) = s.scan() {}
+        }
+        s.feed(b"\xC2\xAC) ");
+        let toks: Vec<SpannedToken> = core::iter::from_fn(|| match s.scan() {
+            PushStatus::Token(t) => Some(t),
+            _ => None,
+        })
+        .collect();
+        assert_eq!(toks.len(), 2);
+        assert_eq!((toks[0].tok, toks[0].text.len()), (RAW_STRING, 4 + 4096 * 1024));
+    }
+
+    #[test]
+    fn test_push_incomplete() {
+        let mut s = PushScanner::default();
+        assert_eq!(s.scan(), PushStatus::Incomplete);
+        s.feed(b"(pi");
+        assert!(matches!(s.scan(), PushStatus::Token(t) if t.text == "("));
+        assert_eq!(s.scan(), PushStatus::Incomplete);
+        s.feed(b"ng ; note");
+        assert!(matches!(s.scan(), PushStatus::Token(t) if t.text == "ping"));
+        assert_eq!(s.scan(), PushStatus::Incomplete);
+        s.feed(b"\n)");
+        assert_eq!(s.scan(), PushStatus::Incomplete);
+        s.finish();
+        assert!(!matches!(s.scan(), PushStatus::Incomplete));
+        s.feed(b" ignored");
+        assert_eq!(s.scan(), PushStatus::End);
+        assert!(s.is_finished());
+    }
+}
@@ -0,0 +1,143 @@
+// Copyright 2022 Jordi Íñigo Griera. All rights reserved.
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use scanner::*;
+    use std::io::{self, BufRead, Read};
+
+    // Reader returning a line per read call, recording how many it served.
+    struct Lines<'a> {
+        lines: Vec<&'a str>,
+        served: std::rc::Rc<std::cell::Cell<usize>>,
+    }
+
+    impl Read for Lines<'_> {
+        fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+            unreachable!()
+        }
+    }
+
+    impl BufRead for Lines<'_> {
+        fn fill_buf(&mut self) -> io::Result<&[u8]> {
+            Ok(self.lines.get(self.served.get()).map_or(&[], |l| l.as_bytes()))
+        }
+        fn consume(&mut self, amt: usize) {
+            if amt > 0 {
+                assert_eq!(amt, self.lines[self.served.get()].len());
+                self.served.set(self.served.get() + 1);
+            }
+        }
+    }
+
+    #[test]
+    fn test_tokens_per_line() {
+        let served = std::rc::Rc::new(std::cell::Cell::new(0));
+        let reader = Lines { lines: vec!["(def a 1)\n", "(+ a\n", " 2)\n"], served: served.clone() };
+        let mut s = LineScanner::new(reader);
+        let t: Vec<SpannedToken> = s.by_ref().take(5).collect();
+        assert_eq!(t.last().unwrap().text, ")");
+        assert_eq!(served.get(), 1);
+        let t = s.next().unwrap();
+        assert_eq!((t.tok, t.span.start.line, t.span.start.offset), ('(' as Token, 2, 10));
+        assert_eq!(served.get(), 2);
+        let t: Vec<SpannedToken> = s.collect();
+        assert_eq!(t[2].text, "2");
+        assert_eq!((t[2].span.start.line, t[2].span.start.column), (3, 2));
+        assert_eq!(served.get(), 3);
+    }
+
+    #[test]
+    fn test_literal_spanning_lines() {
+        let mut s = LineScanner::new("(str \"a\" ¬b\n\nc¬)\n(x".as_bytes());
+        let t: Vec<SpannedToken> = s.by_ref().collect();
+        let text: Vec<&str> = t.iter().map(|t| t.text.as_str()).collect();
+        assert_eq!(text, ["(", "str", "\"a\"", "¬b\n\nc¬", ")", "(", "x"]);
+        assert_eq!(t[5].span.start.line, 4);
+        assert_eq!(s.lines_read(), 4);
+        assert!(s.diagnostics().is_empty());
+        assert!(s.io_error().is_none());
+    }
+
+    // Reader failing after its data.
+    struct Failing<'a>(&'a [u8]);
+
+    impl Read for Failing<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.0.is_empty() {
+                return Err(io::Error::new(io::ErrorKind::ConnectionReset, "connection reset"));
+            }
+            self.0.read(buf)
+        }
+    }
+
+    #[test]
+    fn test_try_next() {
+        let mut s = LineScanner::new(io::BufReader::new(Failing(b"(a)\n(b")));
+        let mut texts = Vec::new();
+        let err = loop {
+            match s.try_next() {
+                Ok(Some(t)) => texts.push(t.text),
+                Ok(None) => panic!("no error"),
+                Err(err) => break err,
+            }
+        };
+        assert_eq!(texts, ["(", "a", ")", "(", "b"]);
+        assert_eq!(err.to_string(), "<input>:2:3: connection reset");
+        assert!(matches!(&err, ScanError::IoError { position, .. } if position.offset == 6));
+        assert!(core::error::Error::source(&err).is_none());
+
+        let mut s = LineScanner::new("(a)".as_bytes());
+        assert_eq!(s.by_ref().count(), 3);
+        assert_eq!(s.try_next(), Ok(None));
+    }
+
+    #[test]
+    fn test_unterminated_at_eof() {
+        let mut s = LineScanner::new("(a \"b\n(c)\n".as_bytes());
+        assert_eq!(s.next().map(|t| t.tok), Some('(' as Token));
+        assert_eq!(s.lines_read(), 1);
+        assert_eq!(s.diagnostics()[0].code, DiagnosticCode::Unterminated);
+        let mut s = LineScanner::new("(a ¬b\n(c)\n".as_bytes());
+        s.set_mode(LISP_TOKENS & !SCAN_RAW_STRINGS);
+        assert_eq!(s.next().map(|t| t.tok), Some('(' as Token));
+        assert_eq!(s.lines_read(), 1);
+        let mut s = LineScanner::new("(a ¬b\n(c)\n".as_bytes());
+        assert_eq!(s.by_ref().last().map(|t| t.tok), Some(RAW_STRING));
+        assert_eq!(s.lines_read(), 2);
+        assert_eq!(s.diagnostics()[0].code, DiagnosticCode::Unterminated);
+    }
+
+    #[test]
+    fn test_retain_window() {
+        let mut s = LineScanner::new("(a ¬é\nb¬)\n(cd)\n".as_bytes());
+        let t: Vec<SpannedToken> = s.by_ref().take(3).collect();
+        assert_eq!(s.slice(&t[2].span), None);
+
+        let mut s = LineScanner::new("(a ¬é\nb¬)\n(cd)\n".as_bytes());
+        s.set_retain(16);
+        let t: Vec<SpannedToken> = s.by_ref().collect();
+        assert_eq!(s.slice(&t[2].span), Some("¬é\nb¬"));
+        assert_eq!(s.slice(&t[4].span), Some("("));
+        assert_eq!(s.slice(&t[1].span), None);
+        s.set_retain(4);
+        assert_eq!(s.slice(&t[5].span), Some("cd"));
+        assert_eq!(s.slice(&t[4].span), None);
+    }
+
+    #[test]
+    fn test_configuration() {
+        let configure = |s: &mut Scanner<'_>| {
+            s.set_mode(LISP_TOKENS & !SKIP_COMMENTS);
+            s.set_line_comment_prefixes(&["//"]);
+        };
+        let src = "(a // b ; c\n(d ; e) // f\n";
+        let mut scanner = Scanner::init(src.as_bytes());
+        configure(&mut scanner);
+        let expected: Vec<SpannedToken> = scanner.iter().collect();
+        assert_eq!(expected[2].text, "// b ; c");
+
+        let mut s = LineScanner::new(src.as_bytes());
+        s.set_configuration(configure);
+        assert_eq!(s.collect::<Vec<_>>(), expected);
+    }
+}
@@ -0,0 +1,59 @@
+// Copyright 2022 Jordi Íñigo Griera. All rights reserved.
+
+#[cfg(test)]
+mod tests {
+    use scanner::*;
+
+    #[test]
+    fn test_conversions() {
+        let tokens = [
+            EOF, IDENT, INT, FLOAT, STRING, KEYWORD, RAW_STRING, COMMENT, PROSE, WHITESPACE, CAPTURE, OTHER,
+            DOCUMENT_BOUNDARY, ERROR, 'x' as Token, 'é' as Token, 0,
+        ];
+        for tok in tokens {
+            let kind = TokenKind::try_from(tok).unwrap();
+            assert_eq!(Token::from(kind), tok);
+            assert_eq!(kind.to_string(), token_string(tok));
+        }
+        assert_eq!(TokenKind::try_from(-9), Err(-9));
+        assert_eq!(TokenKind::try_from(0xD800), Err(0xD800));
+        assert_eq!(TokenKind::Char('¬').to_string(), "\"¬\"");
+    }
+
+    #[test]
+    fn test_scan_kind() {
+        let mut s = Scanner::init(b"1 2.0 :k ; c\n");
+        s.set_mode(LOSSLESS_TOKENS);
+        let mut kinds = Vec::new();
+        loop {
+            let kind = s.scan_kind();
+            kinds.push(kind);
+            if kind == TokenKind::Eof {
+                break;
+            }
+        }
+        use TokenKind::*;
+        assert_eq!(kinds, [Int, Whitespace, Float, Whitespace, Keyword, Whitespace, Comment, Whitespace, Eof]);
+    }
+
+    #[test]
+    fn test_wire_codes() {
+        use TokenKind::*;
+        let kinds = [
+            Eof, Ident, Int, Float, String, Keyword, RawString, Comment, Prose, Whitespace, Capture, Other,
+            DocumentBoundary, Error, CharLiteral, Ratio, BigInt, BigDecimal, NeedMoreInput, DatumComment, Quote, Quasiquote,
+            Unquote, UnquoteSplicing, Deref,
+        ];
+        for (code, kind) in kinds.into_iter().enumerate() {
+            assert_eq!(kind.to_wire(), code as u32);
+            assert_eq!(TokenKind::from_wire(code as u32), Some(kind));
+        }
+        assert_eq!(Char('\0').to_wire(), 0x100);
+        assert_eq!(Char('(').to_wire(), 0x128);
+        assert_eq!(TokenKind::from_wire(0x100 + 0x1F389), Some(Char('🎉')));
+        assert_eq!(TokenKind::from_wire(25), None);
+        assert_eq!(TokenKind::from_wire(0xFF), None);
+        assert_eq!(TokenKind::from_wire(0x100 + 0xD800), None);
+        assert_eq!(TokenKind::from_wire(0x100 + 0x110000), None);
+    }
+}
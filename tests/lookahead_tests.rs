@@ -0,0 +1,157 @@
+// Copyright 2022 Jordi Íñigo Griera. All rights reserved.
+
+#[cfg(test)]
+mod tests {
+    use scanner::*;
+
+    #[test]
+    fn test_eat() {
+        let mut s = Scanner::init(b"(def a 10)");
+
+        assert!(s.eat(IDENT).is_none());
+        let t = s.eat('(' as i32).unwrap();
+        assert_eq!((t.tok, t.text.as_str()), ('(' as i32, "("));
+        assert_eq!(s.token_text(), "(");
+
+        assert!(s.eat(INT).is_none());
+        // A failed eat doesn't change the current token
+        assert_eq!(s.token_text(), "(");
+        assert_eq!(s.position.column, 1);
+
+        let t = s.eat(IDENT).unwrap();
+        assert_eq!(t.text, "def");
+        assert_eq!((t.span.start.column, t.span.end.column), (2, 5));
+        assert_eq!(s.position.column, 2);
+        assert_eq!(s.pos().column, 5);
+
+        assert_eq!(s.scan(), IDENT);
+        assert_eq!(s.token_text(), "a");
+        assert_eq!(s.scan(), INT);
+        assert_eq!(s.token_text(), "10");
+        assert!(s.eat(EOF).is_none());
+        assert_eq!(s.scan(), ')' as i32);
+        assert!(s.eat(EOF).is_some());
+        assert_eq!(s.scan(), EOF);
+    }
+
+    #[test]
+    fn test_peek_token() {
+        let mut s = Scanner::init(b"(def a)");
+        let t = s.peek_token().clone();
+        assert_eq!((t.tok, t.text.as_str()), ('(' as i32, "("));
+        assert_eq!(s.peek_token(), &t);
+        assert_eq!(s.token_text(), "");
+        assert_eq!(s.scan_spanned(), t);
+
+        s.scan();
+        assert_eq!(s.peek_token().text, "a");
+        assert_eq!(s.token_text(), "def");
+        assert_eq!(s.position.column, 2);
+        assert_eq!(s.scan(), IDENT);
+        assert_eq!(s.pos().column, 7);
+        assert_eq!(s.scan(), ')' as i32);
+        assert_eq!(s.peek_token().tok, EOF);
+        assert_eq!(s.peek_token().tok, EOF);
+        assert_eq!(s.token_text(), ")");
+        assert_eq!(s.scan(), EOF);
+    }
+
+    #[test]
+    fn test_expect() {
+        let mut s = Scanner::init(b"(def 10)");
+        s.position.source = SourceId::from("x.lisp");
+
+        assert!(s.expect('(' as i32).is_ok());
+        assert_eq!(s.expect(IDENT).unwrap().text, "def");
+
+        let err = s.expect(IDENT).unwrap_err();
+        assert_eq!(err.to_string(), "x.lisp:1:6: expected Ident, found Int");
+        assert_eq!(err.position().column, 6);
+        match &err {
+            ScanError::Unexpected { expected, found } => {
+                assert_eq!(*expected, IDENT);
+                assert_eq!(found.text, "10");
+            }
+            err => panic!("unexpected error {:?}", err),
+        }
+
+        assert_eq!(s.expect(INT).unwrap().text, "10");
+        assert!(s.expect(')' as i32).is_ok());
+        assert_eq!(s.expect(')' as i32).unwrap_err().to_string(), "x.lisp:1:9: expected \")\", found EOF");
+    }
+
+    #[test]
+    fn test_comment_info_with_lookahead() {
+        let mut s = Scanner::init(b"a ; trailing\n; full\nb");
+        s.set_mode(LISP_TOKENS & !SKIP_COMMENTS);
+
+        assert_eq!(s.scan(), IDENT);
+        assert!(s.eat(IDENT).is_none());
+        assert_eq!(s.scan(), COMMENT);
+        assert_eq!(s.comment_info().unwrap().placement, CommentPlacement::Trailing);
+        assert!(s.eat(IDENT).is_none());
+        assert_eq!(s.scan(), COMMENT);
+        assert_eq!(s.comment_info().unwrap().placement, CommentPlacement::FullLine);
+        assert_eq!(s.token_text(), "; full");
+        assert_eq!(s.scan(), IDENT);
+    }
+
+    #[test]
+    fn test_peek_text() {
+        let mut s = Scanner::init("(foo 本 bar)".as_bytes());
+        assert_eq!(s.peek_text(4), "(foo");
+        assert_eq!(s.scan(), '(' as i32);
+        assert_eq!(s.peek_text(100), "foo 本 bar)");
+        assert_eq!(s.scan(), IDENT);
+        // Cut before the multi-byte character
+        assert_eq!(s.peek_text(3), " ");
+        assert_eq!(s.peek_text(4), " 本");
+
+        // The token scanned ahead is still upcoming input
+        assert!(s.eat(INT).is_none());
+        assert_eq!(s.peek_text(5), " 本 ");
+        assert_eq!(s.scan(), IDENT);
+        assert_eq!(s.peek_text(4), " bar");
+
+        while s.scan() != EOF {}
+        assert_eq!(s.peek_text(10), "");
+    }
+
+    #[test]
+    fn test_peek_text_with_base_position() {
+        let mut s = Scanner::init(b"a b c");
+        s.set_base_position(&Position { offset: 50, line: 3, column: 1, ..Default::default() });
+        assert_eq!(s.peek_text(3), "a b");
+        s.scan();
+        assert_eq!(s.peek_text(10), " b c");
+    }
+
+    #[test]
+    fn test_lookahead() {
+        // the texts of the tokens ahead outlive the 8-byte read buffer
+        let src = "(defn señor [x] \"long string\")";
+        let mut s: Scanner<'_, 8> = Scanner::with_buffer(src.as_bytes());
+        let ahead: Vec<(Token, String, usize)> =
+            s.lookahead(5).map(|t| (t.tok, t.text.clone(), t.span.start.column)).collect();
+        assert_eq!(
+            ahead,
+            [
+                ('(' as Token, "(".to_string(), 1),
+                (IDENT, "defn".to_string(), 2),
+                (IDENT, "señor".to_string(), 7),
+                ('[' as Token, "[".to_string(), 13),
+                (IDENT, "x".to_string(), 14),
+            ]
+        );
+        assert_eq!(s.scan(), '(' as Token);
+        assert_eq!(s.scan(), IDENT);
+        assert_eq!(s.token_text(), "defn");
+        let ahead: Vec<String> = s.lookahead(8).map(|t| t.text.clone()).collect();
+        assert_eq!(ahead, ["señor", "[", "x", "]", "\"long string\"", ")", "", ""]);
+        let mut scanned = Vec::new();
+        while s.scan() != EOF {
+            scanned.push(s.token_text());
+        }
+        assert_eq!(scanned, ["señor", "[", "x", "]", "\"long string\"", ")"]);
+    }
+}
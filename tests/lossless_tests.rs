@@ -0,0 +1,100 @@
+// Copyright 2022 Jordi Íñigo Griera. All rights reserved.
+
+#[cfg(test)]
+mod tests {
+    use scanner::*;
+
+    fn lossless_tokens(src: &str) -> Vec<(Token, String)> {
+        let mut s = Scanner::init(src.as_bytes());
+        s.set_mode(LOSSLESS_TOKENS);
+        let mut v = Vec::new();
+        loop {
+            let tok = s.scan();
+            if tok == EOF {
+                return v;
+            }
+            v.push((tok, s.token_text()));
+        }
+    }
+
+    #[test]
+    fn test_whitespace_tokens() {
+        let src = "(def a\r\n  10) ; ten\n";
+        let toks = lossless_tokens(src);
+
+        let expected: Vec<(Token, String)> = vec![
+            ('(' as i32, "(".into()),
+            (IDENT, "def".into()),
+            (WHITESPACE, " ".into()),
+            (IDENT, "a".into()),
+            (WHITESPACE, "\r\n  ".into()),
+            (INT, "10".into()),
+            (')' as i32, ")".into()),
+            (WHITESPACE, " ".into()),
+            (COMMENT, "; ten".into()),
+            (WHITESPACE, "\n".into()),
+        ];
+        assert_eq!(toks, expected);
+        assert!(is_trivia(WHITESPACE));
+        assert_eq!(token_string(WHITESPACE), "Whitespace");
+    }
+
+    #[test]
+    fn test_bom_is_kept() {
+        let src = "\u{FEFF}a \u{FEFF}";
+        let toks = lossless_tokens(src);
+
+        assert_eq!(toks[0], (WHITESPACE, "\u{FEFF}".into()));
+        assert_eq!(toks[1], (IDENT, "a".into()));
+        // Only a leading BOM is white space
+        assert_eq!(toks[3], (0xFEFF, "\u{FEFF}".into()));
+        assert_eq!(verify_lossless(src), None);
+
+        // Outside of lossless mode the BOM is still discarded
+        let mut s = Scanner::init(src.as_bytes());
+        assert_eq!(s.scan(), IDENT);
+        assert_eq!(s.position.offset, 3);
+    }
+
+    #[test]
+    fn test_unterminated_literals() {
+        for src in ["(¬abc", "\"abc\n)", "'\\", "0x", "1e+", ";"] {
+            assert_eq!(verify_lossless(src), None, "{:?}", src);
+        }
+        // An unterminated raw string ends at EOF
+        assert_eq!(lossless_tokens("¬a\nb"), [(RAW_STRING, "¬a\nb".to_string())]);
+    }
+
+    #[test]
+    fn test_verify_lossless_fuzzed() {
+        let palette: Vec<char> = " \t\r\n;()[]{}\"\\¬:~@#'`-+.0123456789abxeEpP_本é\u{FEFF}\u{0}\u{7f}".chars().collect();
+        let mut x: u64 = 0x2545_F491_4F6C_DD1D;
+        let mut next = move || {
+            x ^= x << 13;
+            x ^= x >> 7;
+            x ^= x << 17;
+            x
+        };
+        for _ in 0..5000 {
+            let n = next() % 40;
+            let src: String = (0..n).map(|_| palette[(next() % palette.len() as u64) as usize]).collect();
+            assert_eq!(verify_lossless(&src), None, "{:?}", src);
+        }
+    }
+
+    #[test]
+    fn test_concatenated_texts() {
+        let src = "a\n;; prose\nb\n";
+        let mut s = Scanner::init(src.as_bytes());
+        s.set_mode(LOSSLESS_TOKENS);
+        let mut text = String::new();
+        while s.scan() != EOF {
+            text.push_str(&s.token_text());
+        }
+        assert_eq!(text, src);
+
+        // A long source crossing buffer refills
+        let src = "(x \"y\") ".repeat(500);
+        assert_eq!(verify_lossless(&src), None);
+    }
+}
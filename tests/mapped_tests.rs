@@ -0,0 +1,59 @@
+// Copyright 2022 Jordi Íñigo Griera. All rights reserved.
+
+#[cfg(all(test, feature = "mmap"))]
+mod tests {
+    use scanner::*;
+    use std::path::PathBuf;
+
+    // Writes a temporary file removed when dropped.
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(name: &str, contents: &[u8]) -> Self {
+            let path = std::env::temp_dir().join(name);
+            std::fs::write(&path, contents).unwrap();
+            TempFile(path)
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn test_mapped_scanner() {
+        let src = "(defn f [x]\n  (* x 2)) ; twice\n".repeat(200);
+        let file = TempFile::new("scanner_mapped_tokens.lisp", src.as_bytes());
+        let mapped = MappedFile::open(&file.0).unwrap();
+        assert_eq!(mapped.bytes(), src.as_bytes());
+
+        let expected: Vec<SpannedToken> = Scanner::init(src.as_bytes()).iter().collect();
+        let toks: Vec<SpannedToken> = mapped.scanner().iter().collect();
+        assert_eq!(toks.len(), expected.len());
+        assert_eq!(toks.last().unwrap().span.end.line, 400);
+        assert_eq!(toks[0].span.start.source.kind(), Some(SourceKind::Path));
+        assert_eq!(toks[0].span.start.source.name(), file.0.to_str().unwrap());
+
+        let mut s = mapped.scanner();
+        s.scan();
+        s.scan();
+        assert!(matches!(s.token_source_str(), std::borrow::Cow::Borrowed("defn")));
+    }
+
+    #[test]
+    fn test_empty_and_missing() {
+        let file = TempFile::new("scanner_mapped_empty.lisp", b"");
+        let mapped = MappedFile::open(&file.0).unwrap();
+        assert_eq!(mapped.scanner().scan(), EOF);
+
+        let file = TempFile::new("scanner_mapped_latin1.lisp", b"(caf\xE9)");
+        let mapped = MappedFile::open(&file.0).unwrap();
+        assert_eq!(mapped.scanner().scan(), '(' as Token);
+
+        let missing = std::env::temp_dir().join("scanner_mapped_missing.lisp");
+        let err = MappedFile::open(missing).err().unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+    }
+}
@@ -0,0 +1,62 @@
+// Copyright 2022 Jordi Íñigo Griera. All rights reserved.
+
+#[cfg(test)]
+mod tests {
+    use scanner::*;
+
+    const DOC: &str = "# Title\n\
+                       \n\
+                       ```lisp\n\
+                       (def a 1)\n\
+                       ```\n\
+                       \n\
+                       ```python\n\
+                       a = 1\n\
+                       ```\n\
+                       ~~~~lisp no_run\n\
+                       ```\n\
+                       (loop)\n\
+                       ~~~~\n\
+                       ```lisp\n\
+                       (unclosed)";
+
+    #[test]
+    fn test_code_blocks() {
+        let blocks = code_blocks(DOC.as_bytes(), "lisp", "README.md");
+        assert_eq!(blocks.len(), 3);
+
+        assert_eq!(blocks[0].info, "lisp");
+        assert_eq!(blocks[0].code, b"(def a 1)\n");
+        assert_eq!(blocks[0].start.to_string(), "README.md:4:1");
+        assert_eq!(blocks[0].start.offset, 17);
+
+        assert_eq!(blocks[1].info, "lisp no_run");
+        assert_eq!(blocks[1].code, b"```\n(loop)\n");
+        assert_eq!(blocks[1].start.line, 11);
+
+        assert_eq!(blocks[2].code, b"(unclosed)");
+        assert_eq!(blocks[2].start.line, 15);
+
+        assert_eq!(code_blocks(DOC.as_bytes(), "", "").len(), 4);
+    }
+
+    #[test]
+    fn test_code_block_scanner() {
+        let blocks = code_blocks(DOC.as_bytes(), "lisp", "README.md");
+        let mut s = blocks[0].scanner();
+
+        assert_eq!(s.scan(), '(' as i32);
+        assert_eq!(s.position.to_string(), "README.md:4:1");
+        assert_eq!(s.scan(), IDENT);
+        assert_eq!(s.position.to_string(), "README.md:4:2");
+        assert_eq!(s.position.offset, 18);
+        assert_eq!(&DOC[s.position.offset..s.position.offset + 3], "def");
+
+        let mut s = blocks[1].scanner();
+        assert_eq!(s.scan(), '`' as i32);
+        assert_eq!(s.scan(), '`' as i32);
+        assert_eq!(s.scan(), '`' as i32);
+        assert_eq!(s.scan(), '(' as i32);
+        assert_eq!(s.position.to_string(), "README.md:12:1");
+    }
+}
@@ -0,0 +1,99 @@
+// Copyright 2022 Jordi Íñigo Griera. All rights reserved.
+
+#[cfg(test)]
+mod tests {
+    use scanner::*;
+
+    fn guest_tokens(items: &[MixedItem]) -> Vec<(Token, String, usize, usize, usize)> {
+        items
+            .iter()
+            .filter_map(|item| match item {
+                MixedItem::Guest(t) => Some((
+                    t.tok,
+                    t.text.clone(),
+                    t.span.start.offset,
+                    t.span.start.line,
+                    t.span.start.column,
+                )),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_template_islands() {
+        let src = "Hello {{ (name \"}}\") }}!\nBye {{x}}";
+        let islands = [Island { open: "{{", close: "}}" }];
+        let items: Vec<MixedItem> = MixedScanner::new(src.as_bytes(), &islands, |_| {}).collect();
+
+        let hosts: Vec<&str> = items
+            .iter()
+            .filter_map(|item| match item {
+                MixedItem::Host { text, .. } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(hosts, ["Hello {{", "}}!\nBye {{", "}}"]);
+
+        assert_eq!(
+            guest_tokens(&items),
+            [
+                ('(' as i32, "(".to_string(), 9, 1, 10),
+                (IDENT, "name".to_string(), 10, 1, 11),
+                (STRING, "\"}}\"".to_string(), 15, 1, 16),
+                (')' as i32, ")".to_string(), 19, 1, 20),
+                (IDENT, "x".to_string(), 31, 2, 7),
+            ]
+        );
+        for item in &items {
+            let (text, span) = match item {
+                MixedItem::Host { text, span } => (text.as_str(), span),
+                MixedItem::Guest(t) => (t.text.as_str(), &t.span),
+            };
+            assert_eq!(&src[span.start.offset..span.end.offset], text);
+        }
+    }
+
+    #[test]
+    fn test_markdown_like_islands() {
+        let src = "# Title\n```\n(def a 1) ; ```\n```\ntext\n";
+        let islands = [Island {
+            open: "```\n",
+            close: "```",
+        }];
+        let mut m = MixedScanner::new(src.as_bytes(), &islands, |s| s.set_mode(LISP_TOKENS & !SKIP_COMMENTS));
+        m.set_filename("doc.md");
+        let items: Vec<MixedItem> = m.collect();
+
+        let toks = guest_tokens(&items);
+        assert_eq!(toks.len(), 6);
+        assert_eq!(toks[1], (IDENT, "def".to_string(), 13, 3, 2));
+        assert_eq!(toks[5], (COMMENT, "; ```".to_string(), 22, 3, 11));
+        match &items[0] {
+            MixedItem::Host { span, .. } => assert_eq!(span.start.to_string(), "doc.md:1:1"),
+            _ => panic!("expected host text"),
+        }
+        match items.last().unwrap() {
+            MixedItem::Host { text, span } => {
+                assert_eq!(text, "```\ntext\n");
+                assert_eq!((span.start.line, span.start.column), (4, 1));
+            }
+            _ => panic!("expected host text"),
+        }
+    }
+
+    #[test]
+    fn test_base_position() {
+        let mut s = Scanner::init(b"a\n b");
+        s.set_base_position(&Position {
+            offset: 100,
+            line: 10,
+            column: 5,
+            ..Default::default()
+        });
+        assert_eq!(s.scan(), IDENT);
+        assert_eq!((s.position.offset, s.position.line, s.position.column), (100, 10, 5));
+        assert_eq!(s.scan(), IDENT);
+        assert_eq!((s.position.offset, s.position.line, s.position.column), (103, 11, 2));
+    }
+}
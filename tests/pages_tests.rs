@@ -0,0 +1,53 @@
+// Copyright 2022 Jordi Íñigo Griera. All rights reserved.
+
+#[cfg(all(test, feature = "pages"))]
+mod tests {
+    use scanner::*;
+
+    #[test]
+    fn test_pages() {
+        let src = "a\n\x0C\nb ; c\n\x0C\x0Cd";
+        let mut s = Scanner::init(src.as_bytes());
+        s.set_mode(LOSSLESS_TOKENS);
+        s.set_whitespace(PAGE_WHITESPACE);
+        let pages: Vec<(String, usize, usize)> = s
+            .tokens()
+            .spanned()
+            .map(|t| (t.text, t.span.start.page, t.span.end.page))
+            .collect();
+        assert_eq!(
+            pages,
+            [
+                ("a".to_string(), 1, 1),
+                ("\n\x0C\n".to_string(), 1, 2),
+                ("b".to_string(), 2, 2),
+                (" ".to_string(), 2, 2),
+                ("; c".to_string(), 2, 2),
+                ("\n\x0C\x0C".to_string(), 2, 4),
+                ("d".to_string(), 4, 4),
+            ]
+        );
+        assert_eq!(s.pos().page, 4);
+    }
+
+    #[test]
+    fn test_form_feed_belongs_to_its_page() {
+        let mut s = Scanner::init(b"a\x0Cb");
+        assert_eq!(s.scan(), IDENT);
+        assert_eq!(s.scan(), 0x0C);
+        assert_eq!(s.position.page, 1);
+        assert_eq!(s.scan(), IDENT);
+        assert_eq!(s.position.page, 2);
+    }
+
+    #[test]
+    fn test_base_page() {
+        let mut s = Scanner::init(b"a\x0Cb");
+        s.set_base_position(&Position { line: 3, column: 1, page: 5, ..Default::default() });
+        s.set_whitespace(PAGE_WHITESPACE);
+        s.scan();
+        assert_eq!(s.position.page, 5);
+        s.scan();
+        assert_eq!(s.position.page, 6);
+    }
+}
@@ -0,0 +1,70 @@
+// Copyright 2022 Jordi Íñigo Griera. All rights reserved.
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use scanner::*;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn test_pipeline() {
+        let src = "(a \"b\\q\" 1e) ; c\n:d";
+        let expected: Vec<SpannedToken> = Scanner::init(src.as_bytes()).tokens().spanned().collect();
+        for capacity in [0, 1, 100] {
+            let mut pipeline = TokenPipeline::spawn(src.as_bytes().to_vec(), capacity, |_| {});
+            let items: Vec<_> = (&mut pipeline).collect();
+            let tokens: Vec<SpannedToken> = items.iter().filter_map(|item| item.clone().ok()).collect();
+            assert_eq!(tokens, expected);
+            let codes: Vec<String> = items
+                .iter()
+                .map(|item| match item {
+                    Ok(t) => t.text.clone(),
+                    Err(d) => d.code.to_string(),
+                })
+                .collect();
+            assert_eq!(codes, ["(", "a", "E010", "\"b\\q\"", "E006", "1e", ")", ":d"]);
+            assert_eq!(pipeline.next(), None);
+            assert_eq!(pipeline.finish().len(), 2);
+        }
+
+        // The scanner is configured on its thread
+        let pipeline = TokenPipeline::spawn(b"a ; b".to_vec(), 4, |s| s.set_mode(LISP_TOKENS & !SKIP_COMMENTS));
+        let toks: Vec<Token> = pipeline.map(|item| item.unwrap().tok).collect();
+        assert_eq!(toks, [IDENT, COMMENT]);
+    }
+
+    #[test]
+    fn test_pipeline_backpressure() {
+        let scanned = Arc::new(AtomicUsize::new(0));
+        let counter = scanned.clone();
+        let src = "a ".repeat(1000).into_bytes();
+        let mut pipeline = TokenPipeline::spawn(src, 2, move |s| {
+            s.set_token_hook(move |_, _| {
+                counter.fetch_add(1, Ordering::SeqCst);
+            })
+        });
+        assert_eq!(pipeline.next().unwrap().unwrap().text, "a");
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        // One received, two waiting, and one blocked in send
+        assert!(scanned.load(Ordering::SeqCst) <= 4);
+
+        // Finishing early stops the scanning thread
+        assert!(pipeline.finish().is_empty());
+        assert!(scanned.load(Ordering::SeqCst) <= 4);
+    }
+
+    #[test]
+    #[should_panic(expected = "hook failed")]
+    fn test_pipeline_panic() {
+        let mut pipeline = TokenPipeline::spawn(b"a b".to_vec(), 1, |s| {
+            s.set_token_hook(|_, span| {
+                if span.start.offset == 2 {
+                    panic!("hook failed");
+                }
+            })
+        });
+        assert!(pipeline.next().is_some());
+        assert!(pipeline.next().is_none());
+        pipeline.finish();
+    }
+}
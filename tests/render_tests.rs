@@ -0,0 +1,87 @@
+// Copyright 2022 Jordi Íñigo Griera. All rights reserved.
+
+#[cfg(test)]
+mod tests {
+    use scanner::*;
+
+    fn scan_one(text: &str) -> (Token, String) {
+        let mut s = Scanner::init(text.as_bytes());
+        let tok = s.scan();
+        let tok_text = s.token_text();
+        assert_eq!(s.scan(), EOF, "{:?} is not a single token", text);
+        assert_eq!(s.error_count(), 0, "{:?} has errors", text);
+        (tok, tok_text)
+    }
+
+    #[test]
+    fn test_render_string() {
+        assert_eq!(render_string("a\"b\\c\n\t\x01é"), "\"a\\\"b\\\\c\\n\\t\\x01é\"");
+        let lit = extract_strings(render_string("x\x7f\u{85}y").as_bytes());
+        assert_eq!(lit[0].value.as_deref(), Some("x\x7f\u{85}y"));
+    }
+
+    #[test]
+    fn test_render_string_with_raw() {
+        let opts = RenderOptions {
+            raw_strings: true,
+            ..Default::default()
+        };
+        assert_eq!(render_string_with("plain", &opts), "\"plain\"");
+        assert_eq!(render_string_with("C:\\dir\\file", &opts), "¬C:\\dir\\file¬");
+        assert_eq!(render_string_with("a\\b\n", &opts), "\"a\\\\b\\n\"");
+        assert_eq!(render_string_with("a\\b", &RenderOptions::default()), "\"a\\\\b\"");
+        assert_eq!(render_raw_string("a¬b"), "¬a¬¬b¬");
+
+        let lit = extract_strings(render_string_with("a\\b\\c¬", &opts).as_bytes());
+        assert_eq!(lit[0].tok, RAW_STRING);
+        assert_eq!(lit[0].value.as_deref(), Some("a\\b\\c¬"));
+    }
+
+    #[test]
+    fn test_render_int() {
+        let dec = RenderOptions::default();
+        assert_eq!(render_int(0, &dec), "0");
+        assert_eq!(render_int(-42, &dec), "-42");
+
+        let grouped = RenderOptions {
+            digit_group: 3,
+            ..Default::default()
+        };
+        assert_eq!(render_int(1234567, &grouped), "1_234_567");
+        assert_eq!(render_int(-123, &grouped), "-123");
+
+        let hex = RenderOptions {
+            radix: Radix::Hexadecimal,
+            digit_group: 4,
+            ..Default::default()
+        };
+        assert_eq!(render_int(0xdeadbeef, &hex), "0xdead_beef");
+
+        let bin = RenderOptions {
+            radix: Radix::Binary,
+            ..Default::default()
+        };
+        assert_eq!(render_int(5, &bin), "0b101");
+        let oct = RenderOptions {
+            radix: Radix::Octal,
+            ..Default::default()
+        };
+        assert_eq!(render_int(8, &oct), "0o10");
+
+        for text in ["1_234_567", "0xdead_beef", "0b101", "0o10", "-42"] {
+            assert_eq!(scan_one(text), (INT, text.to_string()));
+        }
+    }
+
+    #[test]
+    fn test_render_float() {
+        for v in [0.0, 1.0, -2.5, 0.1, 1e300, 1e-7, 123456789.125] {
+            let text = render_float(v).unwrap();
+            let (tok, _) = scan_one(&text);
+            assert_eq!(tok, FLOAT, "{:?}", text);
+            assert_eq!(text.parse::<f64>().unwrap(), v);
+        }
+        assert_eq!(render_float(f64::NAN), None);
+        assert_eq!(render_float(f64::INFINITY), None);
+    }
+}
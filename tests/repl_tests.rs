@@ -0,0 +1,117 @@
+// Copyright 2022 Jordi Íñigo Griera. All rights reserved.
+
+#[cfg(test)]
+mod tests {
+    use scanner::*;
+
+    fn complete(status: ReplStatus) -> ReplForm {
+        match status {
+            ReplStatus::Complete(form) => form,
+            ReplStatus::Continue => panic!("input is incomplete"),
+        }
+    }
+
+    #[test]
+    fn test_continuation() {
+        let mut repl = Repl::new();
+        repl.set_prompts("user> ", "  ");
+        assert_eq!(repl.feed("(let [a {:b #{1"), ReplStatus::Continue);
+        assert!(repl.is_pending());
+        assert_eq!(repl.prompt(), "  ");
+        assert_eq!(repl.feed("}}]"), ReplStatus::Continue);
+        let form = complete(repl.feed("  a)\n"));
+        assert_eq!(form.text, "(let [a {:b #{1\n}}]\n  a)\n");
+        assert_eq!(form.tokens.len(), 13);
+        assert!(form.diagnostics.is_empty());
+        assert_eq!(repl.prompt(), "user> ");
+        assert!(!repl.is_pending());
+    }
+
+    #[test]
+    fn test_open_raw_string() {
+        let mut repl = Repl::new();
+        assert_eq!(repl.feed("(str ¬a"), ReplStatus::Continue);
+        assert_eq!(repl.feed(""), ReplStatus::Continue);
+        let form = complete(repl.feed("b¬)"));
+        assert_eq!(form.tokens[2].text, "¬a\n\nb¬");
+
+        // Strings don't span lines: the error ends the input
+        let form = complete(repl.feed("\"a"));
+        assert_eq!(form.diagnostics[0].code, DiagnosticCode::Unterminated);
+        assert_eq!(form.diagnostics[0].span.start.to_string(), "stdin:4:1");
+    }
+
+    #[test]
+    fn test_open_block_comment() {
+        let mut repl = Repl::new();
+        repl.set_mode(LISP_TOKENS | SCAN_BLOCK_COMMENTS);
+        assert_eq!(repl.feed("(a #| note"), ReplStatus::Continue);
+        assert_eq!(repl.feed("  #| nested |#"), ReplStatus::Continue);
+        let form = complete(repl.feed("|# b)"));
+        let texts: Vec<&str> = form.tokens.iter().map(|t| t.text.as_str()).collect();
+        assert_eq!(texts, ["(", "a", "b", ")"]);
+        assert!(form.diagnostics.is_empty());
+
+        assert_eq!(repl.feed("#| open"), ReplStatus::Continue);
+        repl.cancel();
+        let form = complete(repl.feed("c"));
+        assert_eq!(form.tokens[0].span.start.to_string(), "stdin:5:1");
+    }
+
+    #[test]
+    fn test_positions_and_history() {
+        let mut repl = Repl::new();
+        repl.set_source(SourceId::new(SourceKind::Virtual, "<repl>"));
+        let form = complete(repl.feed(""));
+        assert_eq!((form.number, form.tokens.len()), (0, 0));
+        let form = complete(repl.feed("; comment"));
+        assert!(form.tokens.is_empty());
+        assert_eq!(repl.feed("(a"), ReplStatus::Continue);
+        repl.cancel();
+        assert_eq!(repl.prompt(), "> ");
+        let form = complete(repl.feed("x (y)"));
+        assert_eq!(form.number, 1);
+        assert_eq!(form.tokens[2].span.start.to_string(), "<repl>:4:4");
+        assert_eq!(repl.history(), ["x (y)\n"]);
+    }
+
+    #[test]
+    fn test_long_input() {
+        // The lines aren't rescanned as the input grows
+        let mut repl = Repl::new();
+        assert_eq!(repl.feed("(do"), ReplStatus::Continue);
+        for i in 0..50_000 {
+            assert_eq!(repl.feed(&format!("  (f {i})")), ReplStatus::Continue);
+        }
+        assert_eq!(repl.feed("(str ¬a"), ReplStatus::Continue);
+        for _ in 0..50_000 {
+            assert_eq!(repl.feed("b"), ReplStatus::Continue);
+        }
+        let form = complete(repl.feed("c¬))"));
+        assert_eq!(form.tokens.len(), 200_007);
+        assert_eq!(form.tokens[200_004].text.len(), "¬a\nc¬".len() + 50_000 * 2);
+        assert_eq!(form.tokens[200_006].span.start.to_string(), "stdin:100003:4");
+        assert!(form.diagnostics.is_empty());
+    }
+
+    #[test]
+    fn test_is_form_complete() {
+        for src in ["", "  ; comment", "(def a [1 2] {:b #{3}})", "(str ¬a\nb¬)\n", "a b"] {
+            assert_eq!(is_form_complete(src), Completeness::Complete, "{src:?}");
+        }
+        for src in ["(", "(a [b", "#{1", "(str ¬a\nb", "(print \"a", "\"a\\"] {
+            assert_eq!(is_form_complete(src), Completeness::Incomplete, "{src:?}");
+        }
+        for src in [")", "(a]", "(a))", "#{1)", "(\"a\nb\")"] {
+            assert_eq!(is_form_complete(src), Completeness::Invalid, "{src:?}");
+        }
+    }
+
+    #[test]
+    fn test_invalid_input_is_complete() {
+        let mut repl = Repl::new();
+        let form = complete(repl.feed("(a \"b"));
+        assert_eq!(form.diagnostics[0].code, DiagnosticCode::Unterminated);
+        assert!(matches!(repl.feed("(a]"), ReplStatus::Complete(_)));
+    }
+}
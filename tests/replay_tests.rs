@@ -0,0 +1,74 @@
+// Copyright 2022 Jordi Íñigo Griera. All rights reserved.
+
+#[cfg(test)]
+mod tests {
+    use scanner::*;
+
+    fn tokens<const N: usize>(s: &mut Scanner<'_, N>) -> Vec<SpannedToken> {
+        s.tokens().spanned().collect()
+    }
+
+    #[test]
+    fn test_replay_small_buffer() {
+        let src = format!(" \t\"{}\"\n(a ¬ñ¬ ; c\n", "f".repeat(100));
+        let mut s: Scanner<'_, 16> = Scanner::with_buffer(src.as_bytes());
+        s.position.source = SourceId::new(SourceKind::Uri, "untitled:bug \"report\"");
+        s.set_mode(LOSSLESS_TOKENS);
+        s.set_whitespace(LISP_WHITESPACE & !(1 << b'\t'));
+        s.set_lints(LINT_TRAILING_WHITESPACE);
+        s.set_max_line_length(80);
+        s.set_max_ident_length(40, IdentLengthPolicy::Error);
+        s.set_max_raw_string_lines(3);
+        s.set_literate_prefix(Some("> "));
+        s.set_document_delimiter(Some("---"));
+        s.add_delimiter_pair("<<", ">>");
+        s.add_delimiter_pair("#|", "a \"b\"");
+        s.set_line_comment_prefixes(&["//", "#!"]);
+        s.set_reset_lines_per_document(true);
+        s.set_nested_block_comments(false);
+        s.set_namespaced_keywords(true);
+        s.set_dispatch_macro('(', Some(DispatchMacro::Token(IDENT)));
+        s.set_dispatch_macro('"', Some(DispatchMacro::Literal(-64)));
+        s.set_raw_string_delimiters('«', '"');
+        s.set_disabled_policy(DisabledPolicy::Other);
+        s.set_eof_policy(EofPolicy::NeedMoreInput);
+        s.start_recording();
+        let expected = tokens(&mut s);
+        let log = s.replay_log().unwrap();
+        assert_eq!(log.buffer_size, 16);
+        assert!(log.chunks.len() > 5);
+        assert_eq!(log.chunks[0], 0..15);
+
+        let loaded = ReplayLog::from_bytes(&log.to_bytes()).unwrap();
+        assert_eq!(loaded, log);
+        let mut r: Scanner<'_, 16> = loaded.scanner().unwrap();
+        assert_eq!(tokens(&mut r), expected);
+        assert_eq!(r.diagnostics(), s.diagnostics());
+        assert_eq!(r.replay_log(), Some(log));
+    }
+
+    #[test]
+    fn test_replay_errors() {
+        let mut s = Scanner::init(b"a");
+        assert_eq!(s.replay_log(), None);
+        s.start_recording();
+        let log = s.replay_log().unwrap();
+        assert!(log.chunks.is_empty());
+        assert_eq!(
+            log.scanner::<16>().err(),
+            Some(ReplayError::BufferSize { recorded: DEFAULT_BUFFER_SIZE, requested: 16 })
+        );
+
+        let bytes = log.to_bytes();
+        assert_eq!(ReplayLog::from_bytes(&bytes[..bytes.len() - 1]), Err(ReplayError::Malformed { line: 25 }));
+        let text = String::from_utf8(bytes).unwrap().replace("mode ", "mode x");
+        assert_eq!(ReplayLog::from_bytes(text.as_bytes()), Err(ReplayError::Malformed { line: 3 }));
+        assert_eq!(ReplayLog::from_bytes(b"scanner-replay 2\n"), Err(ReplayError::Malformed { line: 1 }));
+        assert_eq!(
+            ReplayError::Malformed { line: 3 }.to_string(),
+            "replay log line 3: malformed entry"
+        );
+        let err: Box<dyn core::error::Error> = Box::new(ReplayError::Malformed { line: 1 });
+        assert_eq!(err.to_string(), "replay log line 1: malformed entry");
+    }
+}
@@ -0,0 +1,44 @@
+// Copyright 2022 Jordi Íñigo Griera. All rights reserved.
+
+#[cfg(test)]
+mod tests {
+    use scanner::*;
+
+    #[test]
+    fn test_roundtrip_ok() {
+        let src = "(def s \"a\\tb\\x41\\101\\u00e9\\U0001F600\" r ¬x¬¬y¬)\n\
+                   (def n [0 42 -7 0x1F 0o17 0b101 0755 1_000 -1_984])\n\
+                   (def f [0. .5 3.14 1e10 1.5e-3 -3.141592 0x1.fp+3 0x1p-2])";
+        assert_eq!(validate_roundtrip(src.as_bytes()), None);
+    }
+
+    #[test]
+    fn test_roundtrip_undecodable() {
+        let src = "(ok \"fine\") (bad \"\\777\")";
+        let m = validate_roundtrip(src.as_bytes()).unwrap();
+        assert_eq!(m.kind, MismatchKind::Undecodable);
+        assert_eq!(m.tok, STRING);
+        assert_eq!(m.text, "\"\\777\"");
+        assert_eq!(m.span.start.column, 18);
+    }
+
+    #[test]
+    fn test_roundtrip_unrenderable() {
+        let m = validate_roundtrip(b"1e400").unwrap();
+        assert_eq!(m.kind, MismatchKind::Unrenderable);
+        assert_eq!(m.tok, FLOAT);
+    }
+
+    #[test]
+    fn test_huge_hex_exponents() {
+        let src = "0x1p2000000000 0x1p-2000000000 0x1p99999999999 0x10p-1078 0x0.0001p1038 0x1p-1074";
+        let mut s = Scanner::init(src.as_bytes());
+        let mut values = Vec::new();
+        while s.scan() != EOF {
+            values.push(s.token_value());
+        }
+        let floats = [f64::INFINITY, 0.0, f64::INFINITY, 5e-324, 2f64.powi(1022), 5e-324];
+        assert_eq!(values, floats.map(|f| Some(TokenValue::Float(f))));
+        assert_eq!(validate_roundtrip(b"0x1p2000000000").unwrap().kind, MismatchKind::Unrenderable);
+    }
+}
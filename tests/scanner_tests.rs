@@ -162,7 +162,7 @@ mod tests {
             TestToken::new(KEYWORD, ":*?"),
             TestToken::new(COMMENT, ";; individual characters"),
             TestToken::new('\x01' as i32, "\x01"),
-            TestToken::new((' ' as i32) - 1, &format!("{}", (' ' as u8 - 1) as char)),
+            TestToken::new((' ' as i32) - 1, &format!("{}", (b' ' - 1) as char)),
             TestToken::new('.' as i32, "."),
             TestToken::new('(' as i32, "("),
             TestToken::new(')' as i32, ")"),
@@ -180,7 +180,7 @@ mod tests {
             TestToken::new(IDENT, "hello-world"),
             TestToken::new(INT, "-9"),
             TestToken::new(INT, "-1984"),
-            // TestToken::new(INT, "-1_984"),
+            TestToken::new(INT, "-1_984"),
             TestToken::new(FLOAT, "-3.141592"),
         ]
     }
@@ -283,6 +283,20 @@ mod tests {
         assert_eq!(s.scan(), EOF);
     }
 
+    #[test]
+    fn test_keyword_name() {
+        let src = ":a :hello-world foo";
+        let mut s = Scanner::init(src.as_bytes());
+
+        assert!(s.keyword_name().is_none());
+        assert_eq!(s.scan(), KEYWORD);
+        assert_eq!(s.keyword_name().as_deref(), Some("a"));
+        assert_eq!(s.scan(), KEYWORD);
+        assert_eq!(s.keyword_name().as_deref(), Some("hello-world"));
+        assert_eq!(s.scan(), IDENT);
+        assert!(s.keyword_name().is_none());
+    }
+
     #[test]
     fn test_strings() {
         let src = r#""hello" "world" "hel\"lo""#;
@@ -314,6 +328,357 @@ mod tests {
         assert_eq!(s.scan(), EOF);
     }
 
+    #[test]
+    fn test_raw_string_delimiters() {
+        let src = "«a«b»»c» ¬d «e\n»";
+        let mut s = Scanner::init(src.as_bytes());
+        s.set_raw_string_delimiters('«', '»');
+        s.set_lints(LINT_SUSPICIOUS_DELIMITERS);
+        s.set_max_raw_string_lines(1);
+        assert_eq!(s.scan(), RAW_STRING);
+        assert_eq!(s.token_text(), "«a«b»»c»");
+        assert_eq!(s.raw_string_value().as_deref(), Some("a«b»c"));
+        assert_eq!(s.scan(), '¬' as Token);
+        assert_eq!(s.scan(), IDENT);
+        assert_eq!(s.scan(), RAW_STRING);
+        assert_eq!(s.raw_string_value().as_deref(), Some("e\n"));
+        assert_eq!(s.scan(), EOF);
+        let d = &s.diagnostics()[0];
+        assert_eq!((d.code, d.span.start.offset, d.span.end.offset), (DiagnosticCode::SuspiciousDelimiter, 18, 20));
+
+        let mut s = Scanner::init("'a' 'b".as_bytes());
+        s.set_raw_string_delimiters('\'', '\'');
+        assert_eq!(s.scan(), RAW_STRING);
+        assert_eq!(s.scan(), RAW_STRING);
+        assert_eq!(s.raw_string_value(), None);
+        assert_eq!(s.diagnostics()[0].code, DiagnosticCode::Unterminated);
+
+        let mut s = Scanner::init("a «b» c".as_bytes());
+        s.set_mode(SCAN_IDENTS);
+        s.set_raw_string_delimiters('«', '»');
+        s.set_disabled_policy(DisabledPolicy::Other);
+        assert_eq!(s.scan(), IDENT);
+        assert_eq!(s.scan(), OTHER);
+        assert_eq!(s.token_text(), "«b»");
+    }
+
+    #[test]
+    fn test_char_literals() {
+        let src = "(#\\a #\\( #\\  #\\newline #\\u03BB #\\x41) #\\λ#\\x #\\bogus #\\";
+        let mut s = Scanner::init(src.as_bytes());
+        s.set_mode(LISP_TOKENS | SCAN_CHARS);
+        let mut chars = Vec::new();
+        loop {
+            match s.scan() {
+                EOF => break,
+                CHAR => chars.push((s.token_text(), s.char_value())),
+                _ => assert_eq!(s.char_value(), None),
+            }
+        }
+        assert_eq!(
+            chars,
+            [
+                ("#\\a".to_string(), Some('a')),
+                ("#\\(".to_string(), Some('(')),
+                ("#\\ ".to_string(), Some(' ')),
+                ("#\\newline".to_string(), Some('\n')),
+                ("#\\u03BB".to_string(), Some('λ')),
+                ("#\\x41".to_string(), Some('A')),
+                ("#\\λ".to_string(), Some('λ')),
+                ("#\\x".to_string(), Some('x')),
+                ("#\\bogus".to_string(), None),
+                ("#\\".to_string(), None),
+            ]
+        );
+        let codes: Vec<_> = s.diagnostics().iter().map(|d| (d.code, d.message.as_str(), d.span.start.column)).collect();
+        assert_eq!(
+            codes,
+            [
+                (DiagnosticCode::InvalidEscape, "unknown character name", 46),
+                (DiagnosticCode::Unterminated, "literal not terminated", 54),
+            ]
+        );
+
+        // Without the mode bit
+        let mut s = Scanner::init(b"#\\a");
+        assert_eq!(s.scan(), '#' as Token);
+        assert_eq!(token_string(CHAR), "CharLiteral");
+    }
+
+    #[test]
+    fn test_ratios() {
+        let src = "22/7 -1/3 0/5 1_000/3 1/x 0x1/2 07/2 1.5/2 1/";
+        let mut s = Scanner::init(src.as_bytes());
+        s.set_mode(LISP_TOKENS | SCAN_RATIOS);
+        let mut toks = Vec::new();
+        loop {
+            match s.scan() {
+                EOF => break,
+                tok => toks.push((token_string(tok), s.token_text(), s.ratio_value())),
+            }
+        }
+        let t = |tok: &str, text: &str, value: Option<(i128, i128)>| (tok.to_string(), text.to_string(), value);
+        assert_eq!(
+            toks,
+            [
+                t("Ratio", "22/7", Some((22, 7))),
+                t("Ratio", "-1/3", Some((-1, 3))),
+                t("Ratio", "0/5", Some((0, 5))),
+                t("Ratio", "1_000/3", Some((1000, 3))),
+                t("Int", "1", None),
+                t("Ident", "/x", None),
+                t("Int", "0x1", None),
+                t("Ident", "/2", None),
+                t("Int", "07", None),
+                t("Ident", "/2", None),
+                t("Float", "1.5", None),
+                t("Ident", "/2", None),
+                t("Int", "1", None),
+                t("Ident", "/", None),
+            ]
+        );
+        assert_eq!(s.error_count(), 0);
+
+        let mut s = Scanner::init(b"1/3");
+        assert_eq!(s.scan(), INT);
+    }
+
+    #[test]
+    fn test_big_number_suffixes() {
+        let src = "123N -4N 0x10N 3.14M 1e3M 0M 7M 0.5M 07M 1.5N 2Mx";
+        let mut s = Scanner::init(src.as_bytes());
+        s.set_mode(LISP_TOKENS | SCAN_BIG_INTS | SCAN_BIG_DECIMALS);
+        let mut toks = Vec::new();
+        loop {
+            match s.scan() {
+                EOF => break,
+                tok => toks.push(format!("{} {}", token_string(tok), s.token_text())),
+            }
+        }
+        assert_eq!(
+            toks,
+            [
+                "BigInt 123N",
+                "BigInt -4N",
+                "BigInt 0x10N",
+                "BigDecimal 3.14M",
+                "BigDecimal 1e3M",
+                "BigDecimal 0M",
+                "BigDecimal 7M",
+                "BigDecimal 0.5M",
+                "Int 07",
+                "Ident M",
+                "Float 1.5",
+                "Ident N",
+                "BigDecimal 2M",
+                "Ident x",
+            ]
+        );
+
+        // Only with the mode bits
+        let mut s = Scanner::init(b"123N 1M");
+        s.set_mode(LISP_TOKENS | SCAN_BIG_DECIMALS);
+        assert_eq!(s.scan(), INT);
+        assert_eq!(s.scan(), IDENT);
+        assert_eq!(s.scan(), BIG_DECIMAL);
+    }
+
+    #[test]
+    fn test_eof_policy() {
+        let need_more = |src: &[u8], mode: u32| {
+            let mut s = Scanner::init(src);
+            s.set_mode(mode);
+            s.set_eof_policy(EofPolicy::NeedMoreInput);
+            let mut tok = s.scan();
+            while tok != EOF && tok != NEED_MORE_INPUT {
+                tok = s.scan();
+            }
+            (tok, s.position.offset, s.token_text(), s.error_count())
+        };
+        assert_eq!(need_more(b"(a \"bc", LISP_TOKENS), (NEED_MORE_INPUT, 3, "\"bc".to_string(), 0));
+        assert_eq!(need_more(b"\"a\\", LISP_TOKENS), (NEED_MORE_INPUT, 0, "\"a\\".to_string(), 0));
+        assert_eq!(need_more(b"\"\\u00", LISP_TOKENS), (NEED_MORE_INPUT, 0, "\"\\u00".to_string(), 0));
+        assert_eq!(need_more(b"x \xc2\xac raw", LISP_TOKENS), (NEED_MORE_INPUT, 2, "¬ raw".to_string(), 0));
+        assert_eq!(need_more(b"a #| b", LISP_TOKENS | SCAN_BLOCK_COMMENTS), (NEED_MORE_INPUT, 2, "#| b".to_string(), 0));
+        let mode = LISP_TOKENS | SCAN_BLOCK_COMMENTS | SCAN_COMMENTS;
+        assert_eq!(need_more(b"a #| b", mode), (NEED_MORE_INPUT, 2, "#| b".to_string(), 0));
+        assert_eq!(need_more(b"#\\", LISP_TOKENS | SCAN_CHARS), (NEED_MORE_INPUT, 0, "#\\".to_string(), 0));
+        assert_eq!(need_more(b"#\\new", LISP_TOKENS | SCAN_CHARS), (NEED_MORE_INPUT, 0, "#\\new".to_string(), 0));
+        assert_eq!(need_more(b"#\\n", LISP_TOKENS | SCAN_CHARS), (NEED_MORE_INPUT, 0, "#\\n".to_string(), 0));
+        assert_eq!(need_more(b"#\\( x", LISP_TOKENS | SCAN_CHARS), (EOF, 4, "".to_string(), 0));
+
+        // Complete tokens at the end are returned as usual
+        assert_eq!(need_more(b"abc 12", LISP_TOKENS), (EOF, 4, "".to_string(), 0));
+
+        // A new line in a string is still an error
+        assert_eq!(need_more(b"\"a\nb", LISP_TOKENS), (EOF, 3, "".to_string(), 1));
+
+        // The default policy reports the error
+        let mut s = Scanner::init(b"\"bc");
+        assert_eq!(s.scan(), STRING);
+        assert_eq!(s.error_count(), 1);
+    }
+
+    #[test]
+    fn test_string_values() {
+        let src = "¬hello¬ ¬hel¬¬lo¬ ¬¬ ¬¬¬¬ ¬a\nb¬ \"a\\tb\" foo ¬unterminated¬¬";
+        let mut s = Scanner::init(src.as_bytes());
+
+        assert_eq!(s.scan(), RAW_STRING);
+        assert_eq!(s.raw_string_value().as_deref(), Some("hello"));
+        assert!(s.string_value().is_none());
+        assert_eq!(s.scan(), RAW_STRING);
+        assert_eq!(s.raw_string_value().as_deref(), Some("hel¬lo"));
+        assert_eq!(s.scan(), RAW_STRING);
+        assert_eq!(s.raw_string_value().as_deref(), Some(""));
+        assert_eq!(s.scan(), RAW_STRING);
+        assert_eq!(s.raw_string_value().as_deref(), Some("¬"));
+        assert_eq!(s.scan(), RAW_STRING);
+        assert_eq!(s.raw_string_value().as_deref(), Some("a\nb"));
+        assert_eq!(s.scan(), STRING);
+        assert_eq!(s.string_value().as_deref(), Some("a\tb"));
+        assert!(s.raw_string_value().is_none());
+        assert_eq!(s.scan(), IDENT);
+        assert!(s.raw_string_value().is_none());
+        assert_eq!(s.scan(), RAW_STRING);
+        assert!(s.raw_string_value().is_none());
+    }
+
+    #[test]
+    fn test_token_values() {
+        let src = "42 -7 0x1F 0o17 0b1_01 1_000 017 1.5 1e-3 0x1p-2 \"a\\n\" ¬b¬ :c #\\x 22/7 e 99999999999999999999999 \"\\q\"";
+        let mut s = Scanner::init(src.as_bytes());
+        s.set_mode(LISP_TOKENS | SCAN_CHARS | SCAN_RATIOS);
+        let mut values = Vec::new();
+        while s.scan() != EOF {
+            values.push(s.token_value());
+        }
+        assert_eq!(
+            values,
+            [
+                Some(TokenValue::Int(42)),
+                Some(TokenValue::Int(-7)),
+                Some(TokenValue::Int(31)),
+                Some(TokenValue::Int(15)),
+                Some(TokenValue::Int(5)),
+                Some(TokenValue::Int(1000)),
+                Some(TokenValue::Int(15)),
+                Some(TokenValue::Float(1.5)),
+                Some(TokenValue::Float(0.001)),
+                Some(TokenValue::Float(0.25)),
+                Some(TokenValue::String("a\n".to_string())),
+                Some(TokenValue::String("b".to_string())),
+                Some(TokenValue::Keyword("c".to_string())),
+                Some(TokenValue::Char('x')),
+                Some(TokenValue::Ratio(22, 7)),
+                None,
+                Some(TokenValue::Int(99999999999999999999999)),
+                None,
+            ]
+        );
+        assert_eq!(TokenValue::Int(-7).as_i64(), Some(-7));
+        assert_eq!(TokenValue::Int(i128::MAX).as_i64(), None);
+        assert_eq!(TokenValue::Float(1.0).as_i64(), None);
+    }
+
+    #[test]
+    fn test_escapes() {
+        // Body of a string, its value, and the span of the error, if any, as
+        // byte offsets in the source (the body starts at offset 1)
+        type Case<'a> = (&'a str, Option<&'a str>, Option<(usize, usize)>);
+        let cases: &[Case] = &[
+            (r#"\a\b\f\n\r\t\v\\\""#, Some("\x07\x08\x0C\n\r\t\x0B\\\""), None),
+            (r"\0\101\377", None, Some((1, 3))),
+            (r"\000\101\377", Some("\0A\u{FF}"), None),
+            (r"\400", None, None),
+            (r"\18", None, Some((1, 3))),
+            (r"\x41\x7e", Some("A~"), None),
+            (r"\x4g", None, Some((1, 4))),
+            (r"\x", None, Some((1, 3))),
+            (r"é€", Some("é€"), None),
+            (r"\u00e", None, Some((1, 6))),
+            (r"\uD800", None, None),
+            (r"\U0001F389", Some("🎉"), None),
+            (r"\U00110000", None, None),
+            (r"\U0001F38", None, Some((1, 10))),
+            (r"a\qb", None, Some((2, 4))),
+            (r"\ñ", None, Some((1, 4))),
+            (r"\'", None, Some((1, 3))),
+        ];
+        for &(body, value, span) in cases {
+            let src = format!("\"{}\" x", body);
+            let mut s = Scanner::init(src.as_bytes());
+            assert_eq!(s.scan(), STRING, "{}", body);
+            assert_eq!(s.token_text(), format!("\"{}\"", body));
+            assert_eq!(s.string_value().as_deref(), value, "{}", body);
+            let spans: Vec<(usize, usize)> = s.diagnostics().iter().map(|d| (d.span.start.offset, d.span.end.offset)).collect();
+            assert_eq!(spans, span.into_iter().collect::<Vec<_>>(), "{}", body);
+            assert_eq!(s.scan(), IDENT, "{}", body);
+        }
+
+        // An escape at the end of the line or source spans the backslash
+        for src in ["\"a\\\nb", "\"a\\"] {
+            let mut s = Scanner::init(src.as_bytes());
+            assert_eq!(s.scan(), STRING);
+            let d = s.diagnostics();
+            assert_eq!(d[0].message, "invalid char escape");
+            assert_eq!((d[0].span.start.offset, d[0].span.end.offset), (2, 3));
+            assert_eq!(d[1].message, "literal not terminated");
+            assert_eq!(s.string_value(), None);
+        }
+    }
+
+    #[test]
+    fn test_unescape_string() {
+        assert_eq!(unescape_string(r"\n\x41é"), Ok("\nAé".to_string()));
+        assert_eq!(unescape_string(r#"\"a\\b\"\000\377"#), Ok("\"a\\b\"\0\u{FF}".to_string()));
+        assert_eq!(unescape_string(""), Ok(String::new()));
+
+        let errors = [
+            (r"ab\qc", EscapeError::InvalidEscape { offset: 2, ch: 'q' }),
+            (r"\'", EscapeError::InvalidEscape { offset: 0, ch: '\'' }),
+            (r"\18", EscapeError::MissingDigits { offset: 0 }),
+            (r"é\x4g", EscapeError::MissingDigits { offset: 2 }),
+            (r"\u00e", EscapeError::MissingDigits { offset: 0 }),
+            (r"a\", EscapeError::MissingDigits { offset: 1 }),
+            (r"\400", EscapeError::OutOfRange { offset: 0, value: 0o400 }),
+            (r"\uD800", EscapeError::OutOfRange { offset: 0, value: 0xD800 }),
+            (r"\U00110000", EscapeError::OutOfRange { offset: 0, value: 0x110000 }),
+            ("a\"", EscapeError::Unescaped { offset: 1, ch: '"' }),
+            ("a\nb", EscapeError::Unescaped { offset: 1, ch: '\n' }),
+        ];
+        for (text, err) in errors {
+            assert_eq!(unescape_string(text), Err(err), "{}", text);
+        }
+        assert_eq!(
+            EscapeError::OutOfRange { offset: 3, value: 0xD800 }.to_string(),
+            "offset 3: escape sequence value 0xd800 is out of range"
+        );
+        let err: Box<dyn core::error::Error> = Box::new(EscapeError::MissingDigits { offset: 0 });
+        assert_eq!(err.to_string(), "offset 0: escape sequence without enough digits");
+    }
+
+    #[test]
+    fn test_decoded_chars() {
+        let src = "x\n  \"本\\n\\q\\u00e9\" y";
+        let toks: Vec<SpannedToken> = Scanner::init(src.as_bytes()).tokens().spanned().collect();
+        let chars: Vec<_> = toks[1]
+            .decoded_chars()
+            .map(|(span, ch)| (span.start.offset, (span.start.line, span.start.column), span.end.column, ch))
+            .collect();
+        assert_eq!(
+            chars,
+            [
+                (5, (2, 4), 5, Some('本')),
+                (8, (2, 5), 7, Some('\n')),
+                (10, (2, 7), 9, None),
+                (12, (2, 9), 15, Some('é')),
+            ]
+        );
+        assert_eq!(toks[0].decoded_chars().count(), 0);
+        assert_eq!(toks[2].decoded_chars().count(), 0);
+    }
+
     #[test]
     fn test_comments() {
         let src = "; This is a comment\n(def a 10) ;; another comment";
@@ -326,6 +691,286 @@ mod tests {
         assert_eq!(s.token_text(), "def");
     }
 
+    #[test]
+    fn test_line_comment_prefixes() {
+        let texts = |src: &str, mode: u32, prefixes: &[&str]| -> Vec<(Token, String)> {
+            let mut s = Scanner::init(src.as_bytes());
+            s.set_mode(mode);
+            s.set_line_comment_prefixes(prefixes);
+            let mut toks = Vec::new();
+            loop {
+                match s.scan() {
+                    EOF => break,
+                    tok => toks.push((tok, s.token_text())),
+                }
+            }
+            toks
+        };
+        let mode = LISP_TOKENS & !SKIP_COMMENTS;
+
+        // The longest prefix wins, also over identifiers starting with it
+        let toks = texts("a #b\n#!c\n//d /e", mode, &["#", "#!", "//"]);
+        assert_eq!(
+            toks,
+            [
+                (IDENT, "a".to_string()),
+                (COMMENT, "#b".to_string()),
+                (COMMENT, "#!c".to_string()),
+                (COMMENT, "//d /e".to_string()),
+            ]
+        );
+
+        // ; is no longer a comment, and no prefixes disable line comments
+        let toks = texts("a ; b", mode, &["#"]);
+        assert_eq!(toks[1], (';' as Token, ";".to_string()));
+        let toks = texts("a ; b", mode, &[]);
+        assert_eq!(toks.len(), 3);
+
+        // Skipped comments and disabled comments
+        let toks = texts("a // b\nc", LISP_TOKENS, &["//"]);
+        assert_eq!(toks, [(IDENT, "a".to_string()), (IDENT, "c".to_string())]);
+        let mut s = Scanner::init(b"a -- b\nc");
+        s.set_mode(SCAN_IDENTS);
+        s.set_line_comment_prefixes(&["--"]);
+        s.set_disabled_policy(DisabledPolicy::Other);
+        assert_eq!(s.scan(), IDENT);
+        assert_eq!(s.scan(), OTHER);
+        assert_eq!(s.token_text(), "-- b");
+        assert_eq!(s.scan(), IDENT);
+    }
+
+    #[test]
+    fn test_block_comments() {
+        let src = "a #| b\n  c ||# d\n#|x|# ; e\n#|f";
+        let mut s = Scanner::init(src.as_bytes());
+        s.set_mode((LISP_TOKENS & !SKIP_COMMENTS) | SCAN_BLOCK_COMMENTS);
+
+        assert_eq!(s.scan(), IDENT);
+        assert_eq!(s.scan(), COMMENT);
+        assert_eq!(s.token_text(), "#| b\n  c ||#");
+        assert_eq!((s.position.line, s.position.column), (1, 3));
+        assert_eq!(s.scan(), IDENT);
+        assert_eq!(s.token_text(), "d");
+        assert_eq!((s.position.line, s.position.column), (2, 9));
+        assert_eq!(s.scan(), COMMENT);
+        assert_eq!(s.token_text(), "#|x|#");
+        assert_eq!(s.scan(), COMMENT);
+        assert_eq!(s.token_text(), "; e");
+        assert_eq!(s.scan(), COMMENT);
+        assert_eq!(s.token_text(), "#|f");
+        assert_eq!(s.scan(), EOF);
+        let d = &s.diagnostics()[0];
+        assert_eq!(d.code, DiagnosticCode::Unterminated);
+        assert_eq!(d.message, "comment not terminated");
+        assert_eq!((d.span.start.line, d.span.start.column), (4, 1));
+
+        // Skipped, and not recognized without the mode bit
+        let mut s = Scanner::init(b"#| a\n|# b #|c|#");
+        s.set_mode(LISP_TOKENS | SCAN_BLOCK_COMMENTS);
+        assert_eq!(s.scan(), IDENT);
+        assert_eq!(s.token_text(), "b");
+        assert_eq!(s.scan(), EOF);
+        let mut s = Scanner::init(b"#|a|#");
+        s.set_mode(LISP_TOKENS);
+        assert_ne!(s.scan(), COMMENT);
+    }
+
+    #[test]
+    fn test_nested_block_comments() {
+        let src = "#| a #| b ||# c |# d #|#|x|#";
+        let mut s = Scanner::init(src.as_bytes());
+        s.set_mode((LISP_TOKENS & !SKIP_COMMENTS) | SCAN_BLOCK_COMMENTS);
+        assert_eq!(s.scan(), COMMENT);
+        assert_eq!(s.token_text(), "#| a #| b ||# c |#");
+        assert_eq!(s.scan(), IDENT);
+        assert_eq!(s.scan(), COMMENT);
+        assert_eq!(s.token_text(), "#|#|x|#");
+        assert_eq!(s.scan(), EOF);
+        assert_eq!(s.diagnostics()[0].code, DiagnosticCode::Unterminated);
+
+        let mut s = Scanner::init(src.as_bytes());
+        s.set_mode((LISP_TOKENS & !SKIP_COMMENTS) | SCAN_BLOCK_COMMENTS);
+        s.set_nested_block_comments(false);
+        assert_eq!(s.scan(), COMMENT);
+        assert_eq!(s.token_text(), "#| a #| b ||#");
+        assert_eq!(s.scan(), IDENT);
+        assert_eq!(s.token_text(), "c");
+    }
+
+    #[test]
+    fn test_datum_comments() {
+        let mut s = Scanner::init(b"#_a #_(b) #a #_");
+        s.set_mode(LISP_TOKENS | SCAN_DATUM_COMMENTS);
+        let toks: Vec<(Token, String)> = s.tokens().spanned().map(|t| (t.tok, t.text)).collect();
+        let datum = |text: &str| (DATUM_COMMENT, text.to_string());
+        assert_eq!(
+            toks,
+            [
+                datum("#_"),
+                (IDENT, "a".to_string()),
+                datum("#_"),
+                ('(' as Token, "(".to_string()),
+                (IDENT, "b".to_string()),
+                (')' as Token, ")".to_string()),
+                ('#' as Token, "#".to_string()),
+                (IDENT, "a".to_string()),
+                datum("#_"),
+            ]
+        );
+
+        // Only with the mode bit
+        let mut s = Scanner::init(b"#_a");
+        assert_eq!(s.scan(), '#' as Token);
+        assert_eq!(s.scan(), IDENT);
+        assert_eq!(s.token_text(), "_a");
+    }
+
+    #[test]
+    fn test_dispatch_macros() {
+        const REGEX: Token = -64;
+        const VAR: Token = -65;
+        let src = r##"#(+ % 1) #"[0-9]+" #'inc #' #? #_x #{y} #|z|# #\a"##;
+        let mut s = Scanner::init(src.as_bytes());
+        s.set_mode(LISP_TOKENS | SCAN_BLOCK_COMMENTS | SCAN_CHARS | SCAN_DATUM_COMMENTS);
+        s.set_dispatch_macro('(', Some(DispatchMacro::Token(IDENT)));
+        s.set_dispatch_macro('"', Some(DispatchMacro::Literal(REGEX)));
+        s.set_dispatch_macro('\'', Some(DispatchMacro::Prefix(VAR)));
+        s.set_dispatch_macro('?', Some(DispatchMacro::Literal(IDENT)));
+        s.set_dispatch_macro('_', Some(DispatchMacro::Token(DATUM_COMMENT)));
+        s.set_dispatch_macro('{', Some(DispatchMacro::Prefix(IDENT)));
+        s.set_dispatch_macro('|', Some(DispatchMacro::Token(IDENT)));
+        s.set_dispatch_macro('{', None);
+        let toks: Vec<(Token, String)> = s.tokens().spanned().map(|t| (t.tok, t.text)).collect();
+        let tok = |tok: Token, text: &str| (tok, text.to_string());
+        assert_eq!(
+            toks,
+            [
+                tok(IDENT, "#("),
+                tok(IDENT, "+"),
+                tok('%' as Token, "%"),
+                tok(INT, "1"),
+                tok(')' as Token, ")"),
+                tok(REGEX, r#"#"[0-9]+""#),
+                tok(VAR, "#'inc"),
+                tok(VAR, "#'"),
+                tok(IDENT, "#?"),
+                tok(DATUM_COMMENT, "#_"),
+                tok(IDENT, "x"),
+                tok(IDENT, "#{"),
+                tok(IDENT, "y"),
+                tok('}' as Token, "}"),
+                tok(CHAR, "#\\a"),
+            ]
+        );
+        assert_eq!(s.error_count(), 0);
+        assert!(s.capabilities().tokens.ends_with(&[REGEX, VAR]));
+    }
+
+    #[test]
+    fn test_skip_form() {
+        let src = "#_ ; note\n {:a [1 #{2}]} x #_ #_ y (z) w #_) v #_ #_ u";
+        let mut s = Scanner::init(src.as_bytes());
+        s.set_mode(LISP_TOKENS | SCAN_DATUM_COMMENTS);
+        let mut kept = Vec::new();
+        let mut skipped = Vec::new();
+        loop {
+            match s.scan() {
+                EOF => break,
+                DATUM_COMMENT => skipped.push(s.skip_form().map(|span| &src[span.start.offset..span.end.offset])),
+                _ => kept.push(s.token_text()),
+            }
+        }
+        assert_eq!(kept, ["x", "w", ")", "v"]);
+        assert_eq!(skipped, [Some("{:a [1 #{2}]}"), Some("(z)"), None, None]);
+
+        // An unclosed form ends with the input
+        let mut s = Scanner::init(b"(a [b");
+        assert_eq!(s.skip_form().map(|span| (span.start.offset, span.end.offset)), Some((0, 5)));
+        assert_eq!(s.scan(), EOF);
+    }
+
+    #[test]
+    fn test_quotes() {
+        let mut s = Scanner::init(b"'a `(b ~c ~@d) @e ~ @");
+        s.set_mode(LISP_TOKENS | SCAN_QUOTES);
+        let toks: Vec<(Token, String)> = s.tokens().spanned().map(|t| (t.tok, t.text)).collect();
+        let tok = |tok: Token, text: &str| (tok, text.to_string());
+        assert_eq!(
+            toks,
+            [
+                tok(QUOTE, "'"),
+                tok(IDENT, "a"),
+                tok(QUASIQUOTE, "`"),
+                tok('(' as Token, "("),
+                tok(IDENT, "b"),
+                tok(UNQUOTE, "~"),
+                tok(IDENT, "c"),
+                tok(UNQUOTE_SPLICING, "~@"),
+                tok(IDENT, "d"),
+                tok(')' as Token, ")"),
+                tok(DEREF, "@"),
+                tok(IDENT, "e"),
+                tok(UNQUOTE, "~"),
+                tok(DEREF, "@"),
+            ]
+        );
+        assert!(!s.capabilities().specials.contains(&"~@".to_string()));
+
+        // Without the mode bit, as characters and a special
+        let mut s = Scanner::init(b"'a ~@b");
+        assert_eq!(s.scan(), '\'' as Token);
+        assert_eq!(s.scan(), IDENT);
+        assert_eq!((s.scan(), s.token_text().as_str()), (IDENT, "~@"));
+
+        // Prefixes belong to the form skipped after them
+        let mut s = Scanner::init(b"#_ '@(a) b #_ ` ");
+        s.set_mode(LISP_TOKENS | SCAN_QUOTES | SCAN_DATUM_COMMENTS);
+        assert_eq!(s.scan(), DATUM_COMMENT);
+        assert_eq!(s.skip_form().map(|span| (span.start.offset, span.end.offset)), Some((3, 8)));
+        assert_eq!((s.scan(), s.token_text().as_str()), (IDENT, "b"));
+        assert_eq!(s.scan(), DATUM_COMMENT);
+        assert_eq!(s.skip_form().map(|span| (span.start.offset, span.end.offset)), Some((14, 15)));
+        assert_eq!(s.scan(), EOF);
+    }
+
+    #[test]
+    fn test_namespaced_keywords() {
+        let src = ":clojure.core/map ::alias/thing ::local :a.b :/ :ns// :plain)";
+        let mut s = Scanner::init(src.as_bytes());
+        s.set_namespaced_keywords(true);
+        let mut keywords = Vec::new();
+        while s.scan() == KEYWORD {
+            let parts = s.keyword_parts().unwrap();
+            keywords.push((s.token_text(), parts.auto_resolved, parts.namespace, parts.name));
+        }
+        let keyword = |text: &str, auto: bool, ns: Option<&str>, name: &str| {
+            (text.to_string(), auto, ns.map(String::from), name.to_string())
+        };
+        assert_eq!(
+            keywords,
+            [
+                keyword(":clojure.core/map", false, Some("clojure.core"), "map"),
+                keyword("::alias/thing", true, Some("alias"), "thing"),
+                keyword("::local", true, None, "local"),
+                keyword(":a.b", false, None, "a.b"),
+                keyword(":/", false, None, "/"),
+                keyword(":ns//", false, Some("ns"), "/"),
+                keyword(":plain", false, None, "plain"),
+            ]
+        );
+        assert_eq!(s.token_text(), ")");
+
+        // Without namespaces, '.' and a second colon end keywords
+        let mut s = Scanner::init(b":a.b ::c");
+        assert_eq!((s.scan(), s.token_text().as_str()), (KEYWORD, ":a"));
+        assert_eq!(s.scan(), '.' as Token);
+        s.scan();
+        assert_eq!((s.scan(), s.token_text().as_str()), (KEYWORD, ":"));
+        assert_eq!(s.keyword_parts().map(|p| p.name), Some(String::new()));
+        assert_eq!(s.scan(), KEYWORD);
+        assert!(s.keyword_parts().is_some_and(|p| !p.auto_resolved));
+    }
+
     #[test]
     fn test_floats() {
         let src = "3.14 0.5 .5 5. 1e10 1.5e-3";
@@ -435,4 +1080,711 @@ mod tests {
         assert_eq!(s.token_text(), "hello");
         assert_eq!(s.scan(), EOF);
     }
+
+    #[test]
+    fn test_comment_info() {
+        let src = ";; header\n(def a 10) ; trailing\n  ; indented\n¬raw\nstring¬ ; after raw\n";
+        let mut s = Scanner::init(src.as_bytes());
+        s.set_mode(LISP_TOKENS & !SKIP_COMMENTS);
+
+        assert_eq!(s.scan(), COMMENT);
+        let info = s.comment_info().unwrap();
+        assert_eq!(info.placement, CommentPlacement::FullLine);
+        assert_eq!(info.column, 1);
+
+        assert_eq!(s.scan(), '(' as i32);
+        assert!(s.comment_info().is_none());
+        assert_eq!(s.scan(), IDENT);
+        assert_eq!(s.scan(), IDENT);
+        assert_eq!(s.scan(), INT);
+        assert_eq!(s.scan(), ')' as i32);
+
+        assert_eq!(s.scan(), COMMENT);
+        let info = s.comment_info().unwrap();
+        assert_eq!(info.placement, CommentPlacement::Trailing);
+        assert_eq!(info.column, 12);
+
+        assert_eq!(s.scan(), COMMENT);
+        let info = s.comment_info().unwrap();
+        assert_eq!(info.placement, CommentPlacement::FullLine);
+        assert_eq!(info.column, 3);
+
+        assert_eq!(s.scan(), RAW_STRING);
+        assert_eq!(s.scan(), COMMENT);
+        assert_eq!(s.comment_info().unwrap().placement, CommentPlacement::Trailing);
+
+        assert_eq!(s.scan(), EOF);
+    }
+
+    #[test]
+    fn test_minimal_tokens() {
+        let src = "(~@a #{b} :c ¬d¬ \"e\" 1.5)";
+        let mut s = Scanner::init(src.as_bytes());
+        s.set_mode(MINIMAL_TOKENS);
+
+        let mut toks = Vec::new();
+        loop {
+            let tok = s.scan();
+            if tok == EOF {
+                break;
+            }
+            toks.push((tok, s.token_text()));
+        }
+        let want: Vec<(Token, String)> = [
+            ('(' as i32, "("),
+            ('~' as i32, "~"),
+            ('@' as i32, "@"),
+            (IDENT, "a"),
+            ('#' as i32, "#"),
+            ('{' as i32, "{"),
+            (IDENT, "b"),
+            ('}' as i32, "}"),
+            (':' as i32, ":"),
+            (IDENT, "c"),
+            ('¬' as i32, "¬"),
+            (IDENT, "d"),
+            ('¬' as i32, "¬"),
+            (STRING, "\"e\""),
+            (FLOAT, "1.5"),
+            (')' as i32, ")"),
+        ]
+        .iter()
+        .map(|&(tok, text)| (tok, text.to_string()))
+        .collect();
+        assert_eq!(toks, want);
+    }
+
+    #[test]
+    fn test_literate_mode() {
+        let src = "Prose line.\n> (def a 1)\nMore prose; \"not code\n\n>(x)\n>   y\n>";
+        let mut s = Scanner::init(src.as_bytes());
+        s.set_literate_prefix(Some("> "));
+
+        let mut toks = Vec::new();
+        loop {
+            let tok = s.scan();
+            if tok == EOF {
+                break;
+            }
+            toks.push((tok, s.token_text(), s.position.line, s.position.column));
+        }
+        let want: Vec<(Token, String, usize, usize)> = [
+            (PROSE, "Prose line.", 1, 1),
+            ('(' as i32, "(", 2, 3),
+            (IDENT, "def", 2, 4),
+            (IDENT, "a", 2, 8),
+            (INT, "1", 2, 10),
+            (')' as i32, ")", 2, 11),
+            (PROSE, "More prose; \"not code", 3, 1),
+            (PROSE, ">(x)", 5, 1),
+            (IDENT, "y", 6, 5),
+            (PROSE, ">", 7, 1),
+        ]
+        .iter()
+        .map(|&(tok, text, line, column)| (tok, text.to_string(), line, column))
+        .collect();
+        assert_eq!(toks, want);
+        assert_eq!(s.error_count(), 0);
+        assert_eq!(token_string(PROSE), "Prose");
+    }
+
+    #[test]
+    fn test_next_significant() {
+        let src = "(a ; one\n b) ; two\n";
+        let mut s = Scanner::init(src.as_bytes());
+        s.set_mode(LOSSLESS_TOKENS);
+
+        // A formatter scans everything, a parser only the significant tokens
+        assert_eq!(s.scan(), '(' as i32);
+        assert_eq!(s.next_significant(), IDENT);
+        assert_eq!(s.scan(), WHITESPACE);
+        assert_eq!(s.scan(), COMMENT);
+        assert_eq!(s.next_significant(), IDENT);
+        assert_eq!(s.token_text(), "b");
+        assert_eq!(s.next_significant(), ')' as i32);
+        assert_eq!(s.next_significant(), EOF);
+    }
+
+    #[test]
+    fn test_set_trivia() {
+        let src = "a ; c\nb";
+        let mut s = Scanner::init(src.as_bytes());
+        s.set_mode(LOSSLESS_TOKENS);
+        s.set_trivia(&[WHITESPACE, EOF, 'x' as i32]);
+
+        assert_eq!(s.next_significant(), IDENT);
+        assert_eq!(s.next_significant(), COMMENT);
+        assert_eq!(s.next_significant(), IDENT);
+        assert_eq!(s.next_significant(), EOF);
+
+        let mut s = Scanner::init(src.as_bytes());
+        s.set_mode(LOSSLESS_TOKENS);
+        s.set_trivia(&[]);
+        assert_eq!(s.next_significant(), IDENT);
+        assert_eq!(s.next_significant(), WHITESPACE);
+    }
+
+    #[test]
+    fn test_scan_with_mode_override() {
+        let mut s = Scanner::init(b":k :j");
+        let opts = ScanOpts { clear: SCAN_KEYWORDS, ..Default::default() };
+
+        assert_eq!(s.scan_with(&opts), ':' as i32);
+        assert_eq!(s.scan(), IDENT);
+        assert_eq!(s.scan(), KEYWORD);
+        assert_eq!(s.mode, LISP_TOKENS);
+
+        let mut s = Scanner::init(b"; c\nx");
+        let opts = ScanOpts { clear: SKIP_COMMENTS, ..Default::default() };
+        assert_eq!(s.scan_with(&opts), COMMENT);
+        assert_eq!(s.scan(), IDENT);
+    }
+
+    #[test]
+    fn test_scan_with_capture_line() {
+        let src = "#include  foo \"bar\r\n(x)\n#define\n  y";
+        let mut s = Scanner::init(src.as_bytes());
+        let capture = ScanOpts { capture_line: true, ..Default::default() };
+
+        assert_eq!(s.scan(), '#' as i32);
+        assert_eq!(s.scan(), IDENT);
+        assert_eq!(s.scan_with(&capture), CAPTURE);
+        assert_eq!(s.token_text(), "foo \"bar\r");
+        assert_eq!((s.position.line, s.position.column), (1, 11));
+        assert_eq!(s.error_count(), 0);
+        assert_eq!(s.scan(), '(' as i32);
+        assert_eq!(s.scan(), IDENT);
+        assert_eq!(s.scan(), ')' as i32);
+
+        // Nothing left on the line: the next token is scanned as usual
+        assert_eq!(s.scan(), '#' as i32);
+        assert_eq!(s.scan(), IDENT);
+        assert_eq!(s.scan_with(&capture), IDENT);
+        assert_eq!(s.token_text(), "y");
+        assert_eq!(s.scan_with(&capture), EOF);
+        assert_eq!(token_string(CAPTURE), "Capture");
+    }
+
+    #[test]
+    fn test_scan_with_after_lookahead() {
+        let mut s = Scanner::init(b"a b c");
+        let capture = ScanOpts { capture_line: true, ..Default::default() };
+        assert_eq!(s.scan(), IDENT);
+        assert!(s.eat(INT).is_none());
+        // b was already scanned ahead
+        assert_eq!(s.scan_with(&capture), IDENT);
+        assert_eq!(s.token_text(), "b");
+        assert_eq!(s.scan_with(&capture), CAPTURE);
+        assert_eq!(s.token_text(), "c");
+    }
+
+    #[test]
+    fn test_next_char_keeps_token() {
+        let src = format!("foo {}", "x".repeat(3000));
+        let mut s = Scanner::init(src.as_bytes());
+        assert_eq!(s.scan(), IDENT);
+        let span = s.token_span();
+
+        // Read past the token and across a buffer refill
+        for _ in 0..2000 {
+            s.next_char();
+        }
+        assert_eq!(s.token_text(), "foo");
+        assert_eq!(s.token_span(), span);
+        assert_eq!(s.token_byte_range(), 0..3);
+        assert_eq!((s.position.line, s.position.column), (1, 1));
+        assert_eq!(s.pos().offset, 2003);
+
+        assert_eq!(s.scan(), IDENT);
+        assert_eq!(s.token_len(), 1001);
+    }
+
+    #[test]
+    fn test_long_run_of_skipped_comments() {
+        // Skipped constructs don't use stack space
+        let src = format!("{}a", ";c\n".repeat(200_000));
+        let mut s = Scanner::init(src.as_bytes());
+        assert_eq!(s.scan(), IDENT);
+        assert_eq!(s.position.line, 200_001);
+
+        let src = format!("{}b", "#|c|# \"d\" ".repeat(200_000));
+        let mut s = Scanner::init(src.as_bytes());
+        s.set_mode((LISP_TOKENS | SCAN_BLOCK_COMMENTS) & !SCAN_STRINGS);
+        s.set_disabled_policy(DisabledPolicy::Skip);
+        assert_eq!(s.scan(), IDENT);
+        assert_eq!(s.token_text(), "b");
+        assert_eq!(s.scan(), EOF);
+    }
+
+    #[test]
+    fn test_next_char_after_lookahead() {
+        // The characters after the current token are read, not those after
+        // the tokens scanned ahead
+        let mut s = Scanner::init(b"a b c");
+        assert_eq!(s.scan(), IDENT);
+        assert!(s.eat(INT).is_none());
+        assert_eq!(s.next_char(), ' ' as i32);
+        assert_eq!(s.token_text(), "a");
+        assert_eq!(s.peek(), 'b' as i32);
+        assert_eq!(s.scan(), IDENT);
+        assert_eq!(s.token_text(), "b");
+        assert_eq!(s.token_span().start.offset, 2);
+
+        // Also after consuming some of them
+        let mut s = Scanner::init(b"(a \"b\" c) d");
+        assert_eq!(s.scan(), '(' as Token);
+        assert_eq!(s.lookahead(4).count(), 4);
+        assert_eq!(s.scan(), IDENT);
+        assert_eq!(s.peek_char(), Some(' '));
+        assert_eq!(s.next_char(), ' ' as i32);
+        assert_eq!(s.token_text(), "a");
+        assert_eq!(s.scan(), STRING);
+        assert_eq!(s.token_text(), "\"b\"");
+        assert_eq!(s.scan(), IDENT);
+        assert_eq!(s.token_text(), "c");
+        assert!(s.diagnostics().is_empty());
+    }
+
+    #[test]
+    fn test_token_str() {
+        let mut s = Scanner::init("¬a¬ \"b\"".as_bytes());
+        assert_eq!(s.token_str(), "");
+        s.scan();
+        assert!(matches!(s.token_str(), std::borrow::Cow::Borrowed("¬a¬")));
+        s.scan();
+        assert_eq!(s.token_str(), "\"b\"");
+        s.scan();
+        assert_eq!(s.token_str(), "");
+
+        let mut s = Scanner::init(b"a\xffb c");
+        s.set_base_position(&Position { offset: 10, line: 1, column: 1, ..Default::default() });
+        assert_eq!(s.scan(), IDENT);
+        assert_eq!(s.scan(), 0xFFFD);
+        assert!(matches!(s.token_str(), std::borrow::Cow::Owned(_)));
+        assert_eq!(s.token_str(), "\u{FFFD}");
+        s.scan();
+        s.scan();
+        assert_eq!(s.token_str(), "c");
+    }
+
+    #[test]
+    fn test_token_source_str() {
+        let src = "(Def x 1)".to_string();
+        let texts = {
+            let mut s = Scanner::init(src.as_bytes());
+            s.set_text_normalizer(IDENT, |text| text.to_lowercase());
+            let mut texts = Vec::new();
+            s.scan();
+            s.scan();
+            assert_eq!(s.token_str(), "def");
+            texts.push(s.token_source_str());
+            assert!(s.eat(IDENT).is_some());
+            texts.push(s.token_source_str());
+            s.peek();
+            texts.push(s.token_source_str());
+            s.scan();
+            s.next_char();
+            texts.push(s.token_source_str());
+            texts
+        };
+        assert_eq!(texts, ["Def", "x", "x", "1"]);
+    }
+
+    #[test]
+    fn test_token_source_str_small_buffer() {
+        // Tokens longer than the buffer are still borrowed from the source
+        let src = "(λ ¬raw\nstring¬ :kw \"é\")";
+        let mut s: Scanner<'_, 8> = Scanner::with_buffer(src.as_bytes());
+        let mut texts = Vec::new();
+        while s.scan() != EOF {
+            texts.push(s.token_source_str());
+        }
+        assert_eq!(texts, ["(", "λ", "¬raw\nstring¬", ":kw", "\"é\"", ")"]);
+        assert!(texts.iter().all(|t| matches!(t, std::borrow::Cow::Borrowed(_))));
+    }
+
+    #[test]
+    fn test_read_char() {
+        let mut s = Scanner::init("\u{FEFF}aé\n".as_bytes());
+        assert_eq!(s.peek_char(), Some('a'));
+        assert_eq!(s.read_char(), Some('a'));
+        assert_eq!(s.peek_char(), Some('é'));
+        assert_eq!(s.read_char(), Some('é'));
+        assert_eq!(s.read_char(), Some('\n'));
+        assert_eq!(s.peek_char(), None);
+        assert_eq!(s.read_char(), None);
+        assert_eq!(s.read_char(), None);
+    }
+
+    #[test]
+    fn test_read_char_bom() {
+        // Reading first or peeking first skips the BOM the same way
+        let mut s = Scanner::init("\u{FEFF}".as_bytes());
+        assert_eq!(s.read_char(), None);
+        let mut s = Scanner::init("\u{FEFF}".as_bytes());
+        assert_eq!(s.peek_char(), None);
+
+        let mut s = Scanner::init("\u{FEFF}x".as_bytes());
+        s.set_mode(LOSSLESS_TOKENS);
+        assert_eq!(s.read_char(), Some('\u{FEFF}'));
+        assert_eq!(s.peek_char(), Some('x'));
+    }
+
+    #[test]
+    fn test_comment_continuations() {
+        let src = "a ; one \\\n  two \\\r\n three\nb ; four \\ five\nc ; \\\n";
+        let mut s = Scanner::init(src.as_bytes());
+        s.set_mode(LISP_TOKENS & !SKIP_COMMENTS | SCAN_COMMENT_CONTINUATIONS);
+
+        let mut toks = Vec::new();
+        while s.scan() != EOF {
+            toks.push((s.token_text(), s.position.line, s.position.column, s.pos().line));
+        }
+        let want: Vec<(String, usize, usize, usize)> = [
+            ("a", 1, 1, 1),
+            ("; one \\\n  two \\\r\n three", 1, 3, 3),
+            ("b", 4, 1, 4),
+            ("; four \\ five", 4, 3, 4),
+            ("c", 5, 1, 5),
+            ("; \\\n", 5, 3, 6),
+        ]
+        .iter()
+        .map(|&(text, line, column, end_line)| (text.to_string(), line, column, end_line))
+        .collect();
+        assert_eq!(toks, want);
+
+        // Skipped comments are continued too
+        let mut s = Scanner::init(src.as_bytes());
+        s.set_mode(LISP_TOKENS | SCAN_COMMENT_CONTINUATIONS);
+        let idents: Vec<String> = s.tokens().spanned().map(|t| t.text).collect();
+        assert_eq!(idents, ["a", "b", "c"]);
+
+        // Without the mode, "two" is an identifier
+        let mut s = Scanner::init(src.as_bytes());
+        let idents: Vec<String> = s.tokens().spanned().map(|t| t.text).collect();
+        assert_eq!(idents, ["a", "two", "\\", "three", "b", "c"]);
+    }
+
+    #[test]
+    fn test_token_hook() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let mut s = Scanner::init(b"(a ; c\n 10)");
+        s.set_mode(LISP_TOKENS & !SKIP_COMMENTS);
+        let log = Rc::clone(&seen);
+        s.set_token_hook(move |tok, span| log.borrow_mut().push((tok, span.start.offset, span.end.offset)));
+
+        assert_eq!(s.scan(), '(' as i32);
+        assert_eq!(s.scan(), IDENT);
+        // Tokens scanned ahead are reported once, when scanned
+        assert!(s.eat(INT).is_none());
+        assert_eq!(seen.borrow().len(), 3);
+        while s.scan() != EOF {}
+        assert_eq!(s.scan(), EOF);
+
+        let want = [('(' as i32, 0, 1), (IDENT, 1, 2), (COMMENT, 3, 6), (INT, 8, 10), (')' as i32, 10, 11)];
+        assert_eq!(*seen.borrow(), want);
+    }
+
+    #[test]
+    fn test_lookahead_after_next_char() {
+        let mut s = Scanner::init(b"a b c");
+        assert_eq!(s.scan(), IDENT);
+        assert_eq!(s.next_char(), ' ' as i32);
+        assert!(s.eat(INT).is_none());
+        assert_eq!(s.token_text(), "a");
+        assert_eq!(s.scan(), IDENT);
+        assert_eq!(s.token_text(), "b");
+        assert_eq!(s.token_span().start.offset, 2);
+    }
+
+    fn spanned_tokens<const N: usize>(src: &str) -> (Vec<SpannedToken>, Vec<Diagnostic>) {
+        let mut s: Scanner<'_, N> = Scanner::with_buffer(src.as_bytes());
+        s.set_mode(LOSSLESS_TOKENS);
+        let tokens = s.tokens().spanned().collect();
+        (tokens, s.take_diagnostics())
+    }
+
+    #[test]
+    fn test_small_buffer() {
+        let src = "(def ¬raw ¬¬ string¬ \"a long string with ñ and 世界\" 0x1.fp+3 ; comment ☺\n:keyword \"abc\n";
+        let expected = spanned_tokens::<DEFAULT_BUFFER_SIZE>(src);
+        assert!(!expected.1.is_empty());
+        assert_eq!(spanned_tokens::<5>(src), expected);
+        assert_eq!(spanned_tokens::<6>(src), expected);
+        assert_eq!(spanned_tokens::<7>(src), expected);
+        assert_eq!(spanned_tokens::<64>(src), expected);
+
+        assert!(core::mem::size_of::<Scanner<'_, 16>>() + 1000 < core::mem::size_of::<Scanner<'_>>());
+    }
+
+    #[test]
+    fn test_track_positions_off() {
+        let src = "(a\n  \"b\nc\" ; d\n:e)";
+        let mut tracked = Scanner::init(src.as_bytes());
+        let mut s = Scanner::init(src.as_bytes());
+        s.set_track_positions(false);
+        loop {
+            let (t, u) = (tracked.scan_spanned(), s.scan_spanned());
+            assert_eq!((u.tok, s.token_text()), (t.tok, tracked.token_text()));
+            assert_eq!((u.span.start.offset, u.span.end.offset), (t.span.start.offset, t.span.end.offset));
+            assert_eq!((u.span.start.line, u.span.start.column, u.span.end.line), (0, 0, 0));
+            if t.tok == EOF {
+                break;
+            }
+        }
+    }
+
+    fn scan_disabled(src: &str, mode: u32, policy: DisabledPolicy) -> Vec<(Token, String)> {
+        let mut s = Scanner::init(src.as_bytes());
+        s.set_mode(mode);
+        s.set_disabled_policy(policy);
+        let mut toks = Vec::new();
+        loop {
+            match s.scan() {
+                EOF => break,
+                tok => toks.push((tok, s.token_text())),
+            }
+        }
+        assert_eq!(s.error_count(), 0);
+        toks
+    }
+
+    #[test]
+    fn test_disabled_policy() {
+        let src = "(a \"b \\\" c\" ¬d¬¬e¬ ; f \"g\nh)";
+        let mode = SCAN_IDENTS;
+
+        let toks = scan_disabled(src, mode, DisabledPolicy::Chars);
+        assert_eq!(toks[2], ('"' as Token, "\"".to_string()));
+        assert_eq!(toks[3], (IDENT, "b".to_string()));
+
+        let toks = scan_disabled(src, mode, DisabledPolicy::Skip);
+        let idents: Vec<&str> = toks.iter().map(|(_, text)| text.as_str()).collect();
+        assert_eq!(idents, ["(", "a", "h", ")"]);
+
+        let toks = scan_disabled(src, mode, DisabledPolicy::Other);
+        assert_eq!(
+            toks,
+            [
+                ('(' as Token, "(".to_string()),
+                (IDENT, "a".to_string()),
+                (OTHER, "\"b \\\" c\"".to_string()),
+                (OTHER, "¬d¬¬e¬".to_string()),
+                (OTHER, "; f \"g".to_string()),
+                (IDENT, "h".to_string()),
+                (')' as Token, ")".to_string()),
+            ]
+        );
+
+        // Unterminated constructs end at the line end or at EOF
+        let toks = scan_disabled("\"a\nb ¬c", mode, DisabledPolicy::Other);
+        assert_eq!(toks, [(OTHER, "\"a".to_string()), (IDENT, "b".to_string()), (OTHER, "¬c".to_string())]);
+
+        // Constructs recognized by the mode are unaffected
+        let toks = scan_disabled("\"a\" ; b", mode | SCAN_STRINGS, DisabledPolicy::Other);
+        assert_eq!(toks, [(STRING, "\"a\"".to_string()), (OTHER, "; b".to_string())]);
+        assert_eq!(token_string(OTHER), "Other");
+    }
+
+    #[test]
+    fn test_text_normalizer() {
+        let mut s = Scanner::init("(Foo 1_000 ¬a¬¬b¬ \"x\\ty\" :Key)".as_bytes());
+        s.set_text_normalizer(IDENT, |text| text.to_lowercase());
+        s.set_text_normalizer(IDENT, |text| text.to_uppercase());
+        s.set_text_normalizer(INT, |text| text.replace('_', ""));
+        s.set_text_normalizer(RAW_STRING, |text| text.replace("¬¬", "¬"));
+        s.set_text_normalizer(STRING, |_| String::from("?"));
+        s.set_text_normalizer(KEYWORD, |text| text.to_lowercase());
+
+        assert_eq!(s.scan(), '(' as Token);
+        assert_eq!(s.scan(), IDENT);
+        assert_eq!(s.token_text(), "FOO");
+        assert_eq!(s.token_bytes(), b"Foo");
+        assert_eq!(s.token_len(), 3);
+
+        // Tokens scanned ahead are normalized too
+        assert!(s.eat(STRING).is_none());
+        assert_eq!(s.token_text(), "FOO");
+        let t = s.scan_spanned();
+        assert_eq!((t.tok, t.text.as_str()), (INT, "1000"));
+        assert_eq!(s.token_bytes(), b"1_000");
+
+        assert_eq!(s.scan(), RAW_STRING);
+        assert_eq!(s.token_text(), "¬a¬b¬");
+        assert_eq!(s.raw_string_value().as_deref(), Some("a¬b"));
+
+        assert_eq!(s.scan(), STRING);
+        assert_eq!(s.token_str(), "?");
+        assert_eq!(s.string_value().as_deref(), Some("x\ty"));
+
+        assert_eq!(s.scan(), KEYWORD);
+        assert_eq!(s.token_text(), ":key");
+        assert_eq!(s.keyword_name().as_deref(), Some("Key"));
+
+        assert_eq!(s.scan(), ')' as Token);
+        assert_eq!(s.token_text(), ")");
+        assert_eq!(s.scan(), EOF);
+        assert_eq!(s.token_bytes(), b"");
+    }
+
+    // Kind, text and start line of the tokens of src
+    fn documents(src: &str, delimiter: &str, reset: bool, mode: u32) -> Vec<(Token, String, usize)> {
+        let mut s = Scanner::init(src.as_bytes());
+        s.set_mode(mode);
+        s.set_document_delimiter(Some(delimiter));
+        s.set_reset_lines_per_document(reset);
+        let mut toks = Vec::new();
+        loop {
+            match s.scan() {
+                EOF => return toks,
+                tok => toks.push((tok, s.token_text(), s.position.line)),
+            }
+        }
+    }
+
+    #[test]
+    fn test_document_boundaries() {
+        let src = "(a)\n---\nb\n\n c\n---x\n---\r\n---";
+        let toks = documents(src, "---", false, LISP_TOKENS);
+        let boundaries: Vec<(String, usize)> = toks
+            .iter()
+            .filter(|t| t.0 == DOCUMENT_BOUNDARY)
+            .map(|t| (t.1.clone(), t.2))
+            .collect();
+        assert_eq!(boundaries, [("---\n".to_string(), 2), ("---\r\n".to_string(), 7), ("---".to_string(), 8)]);
+        // "---x" isn't a delimiter line
+        assert!(toks.iter().any(|t| t.1 == "-x"));
+
+        let toks = documents(src, "---", true, LISP_TOKENS);
+        let lines: Vec<(String, usize)> = toks.iter().map(|t| (t.1.clone(), t.2)).collect();
+        assert_eq!(lines[4], ("b".to_string(), 1));
+        assert_eq!(lines[5], ("c".to_string(), 3));
+        assert_eq!(lines[8], ("-x".to_string(), 4));
+        assert_eq!(lines[9], ("---\r\n".to_string(), 5));
+        assert_eq!(lines[10], ("---".to_string(), 1));
+
+        // Form feed pages, losslessly
+        let src = "a\n\x0C\nb ; c\n\x0C";
+        let toks = documents(src, "\x0C", true, LOSSLESS_TOKENS);
+        let text: String = toks.iter().map(|t| t.1.as_str()).collect();
+        assert_eq!(text, src);
+        assert_eq!(toks.iter().filter(|t| t.0 == DOCUMENT_BOUNDARY).count(), 2);
+        assert!(toks.iter().any(|t| t.0 == IDENT && t.1 == "b" && t.2 == 1));
+        assert_eq!(token_string(DOCUMENT_BOUNDARY), "DocumentBoundary");
+    }
+
+    #[test]
+    fn test_comment_after_document_boundary() {
+        let mut s = Scanner::init("(a)\n---\n; c\n".as_bytes());
+        s.set_mode(LISP_TOKENS & !SKIP_COMMENTS);
+        s.set_document_delimiter(Some("---"));
+        s.set_reset_lines_per_document(true);
+        while s.scan() != COMMENT {}
+        assert_eq!(s.position.line, 1);
+        assert_eq!(s.comment_info().unwrap().placement, CommentPlacement::FullLine);
+    }
+
+    #[test]
+    fn test_page_whitespace() {
+        let src = "(a)\n\x0C\n(b\x0Bc)";
+        let mut s = Scanner::init(src.as_bytes());
+        assert_eq!(s.scan(), '(' as Token);
+        s.scan();
+        s.scan();
+        assert_eq!(s.scan(), 0x0C);
+
+        let mut s = Scanner::init(src.as_bytes());
+        s.set_whitespace(PAGE_WHITESPACE);
+        let toks: Vec<Token> = s.tokens().collect();
+        assert_eq!(toks, ['(' as Token, IDENT, ')' as Token, '(' as Token, IDENT, IDENT, ')' as Token]);
+    }
+
+    #[test]
+    fn test_whitespace_rune() {
+        let src = "(a\u{A0}b,\u{2028}c\u{3000})";
+        let mut s = Scanner::init(src.as_bytes());
+        assert!(s.tokens().any(|t| t == 0xA0));
+
+        // The predicate only applies beyond the bitmask: ',' stays a character
+        let mut s = Scanner::init(src.as_bytes());
+        s.set_is_whitespace_rune(|ch| ch == ',' || ch.is_whitespace());
+        let texts: Vec<String> = s.tokens().spanned().map(|t| t.text).collect();
+        assert_eq!(texts, ["(", "a", "b", ",", "c", ")"]);
+        assert!(s.capabilities().custom_whitespace_rune);
+
+        let mut s = Scanner::init(src.as_bytes());
+        s.set_mode(LOSSLESS_TOKENS);
+        s.set_is_whitespace_rune(char::is_whitespace);
+        let toks: Vec<(Token, String)> = s.tokens().spanned().map(|t| (t.tok, t.text)).collect();
+        assert_eq!(toks[2], (WHITESPACE, "\u{A0}".to_string()));
+        assert_eq!(toks[5], (WHITESPACE, "\u{2028}".to_string()));
+        assert_eq!(toks[7], (WHITESPACE, "\u{3000}".to_string()));
+    }
+
+    #[test]
+    fn test_max_ident_length() {
+        let src = "(défini :kéyword short)";
+        let mut s = Scanner::init(src.as_bytes());
+        s.set_max_ident_length(5, IdentLengthPolicy::Truncate);
+        s.set_text_normalizer(IDENT, |text| text.to_uppercase());
+        let toks: Vec<SpannedToken> = s.iter().collect();
+        assert_eq!((toks[1].tok, toks[1].text.as_str()), (IDENT, "DÉFIN"));
+        assert_eq!((toks[2].tok, toks[2].text.as_str()), (KEYWORD, ":kéyw"));
+        assert_eq!((toks[3].tok, toks[3].text.as_str()), (IDENT, "SHORT"));
+        assert_eq!(toks[1].span.end.column, 8);
+        assert_eq!(s.error_count(), 2);
+        assert_eq!(s.diagnostics()[1].message, "keyword is 8 characters long (maximum is 5)");
+        assert_eq!(s.diagnostics()[1].code, DiagnosticCode::IdentTooLong);
+
+        let mut s = Scanner::init(src.as_bytes());
+        s.set_max_ident_length(5, IdentLengthPolicy::Error);
+        let toks: Vec<(Token, String)> = s.iter().map(|t| (t.tok, t.text)).collect();
+        assert_eq!(toks[1], (ERROR, "défini".to_string()));
+        assert_eq!(toks[2], (ERROR, ":kéyword".to_string()));
+        assert_eq!(toks[3].0, IDENT);
+        assert_eq!(token_string(ERROR), "Error");
+    }
+
+    #[test]
+    fn test_leading_comment() {
+        let src = b";; doc\n;; more\n(def a 1) ; trailing\nb\n; other\n\n\nc";
+        for mode in [LISP_TOKENS, LISP_TOKENS & !SKIP_COMMENTS] {
+            let mut s = Scanner::init(src);
+            s.set_mode(mode);
+            let mut leading = Vec::new();
+            loop {
+                let tok = s.scan();
+                if tok == EOF {
+                    break;
+                }
+                if tok != COMMENT {
+                    let c = s.leading_comment().map(|c| (c.span.start.line, c.span.end.line, c.blank_lines));
+                    leading.push((s.token_text(), c));
+                }
+            }
+            assert_eq!(
+                leading,
+                [
+                    ("(".to_string(), Some((1, 2, 0))),
+                    ("def".to_string(), None),
+                    ("a".to_string(), None),
+                    ("1".to_string(), None),
+                    (")".to_string(), None),
+                    ("b".to_string(), None),
+                    ("c".to_string(), Some((5, 5, 2))),
+                ]
+            );
+        }
+
+        // lookahead doesn't change the comment of the current token
+        let mut s = Scanner::init(b"; a\nx\n; b\ny");
+        s.scan();
+        s.peek_token();
+        assert_eq!(s.leading_comment().unwrap().span.start.line, 1);
+        s.scan();
+        assert_eq!(s.leading_comment().unwrap().span.start.line, 3);
+    }
 }
@@ -0,0 +1,95 @@
+// Copyright 2022 Jordi Íñigo Griera. All rights reserved.
+
+#[cfg(test)]
+mod tests {
+    use scanner::*;
+
+    #[test]
+    fn test_find_tokens() {
+        let src = "(def foo 1) ; foo in a comment\n(print \"foo\" foo)";
+        let found: Vec<SpannedToken> = find_tokens(src.as_bytes(), |tok, text| tok == IDENT && text == "foo").collect();
+
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].text, "foo");
+        assert_eq!((found[0].span.start.line, found[0].span.start.column), (1, 6));
+        assert_eq!((found[0].span.end.line, found[0].span.end.column), (1, 9));
+        assert_eq!((found[1].span.start.offset, found[1].span.end.offset), (44, 47));
+        assert_eq!(&src[found[1].span.start.offset..found[1].span.end.offset], "foo");
+    }
+
+    #[test]
+    fn test_find_tokens_by_kind() {
+        let src = "(+ 1 2.5 \"3\" 0x4)";
+        let texts: Vec<String> = find_tokens(src.as_bytes(), |tok, _| tok == INT || tok == FLOAT)
+            .map(|t| t.text)
+            .collect();
+
+        assert_eq!(texts, ["1", "2.5", "0x4"]);
+    }
+
+    #[test]
+    fn test_scan_spanned() {
+        let src = "abc\n  :key";
+        let mut s = Scanner::init(src.as_bytes());
+
+        let t = s.scan_spanned();
+        assert_eq!(t.tok, IDENT);
+        assert_eq!((t.span.start.line, t.span.start.column), (1, 1));
+        assert_eq!((t.span.end.line, t.span.end.column), (1, 4));
+
+        let t = s.scan_spanned();
+        assert_eq!(t.tok, KEYWORD);
+        assert_eq!(t.text, ":key");
+        assert_eq!((t.span.start.line, t.span.start.column), (2, 3));
+        assert_eq!((t.span.end.line, t.span.end.column), (2, 7));
+        assert_eq!((t.span.start.offset, t.span.end.offset), (6, 10));
+
+        let t = s.scan_spanned();
+        assert_eq!(t.tok, EOF);
+        assert_eq!(t.text, "");
+    }
+
+    #[test]
+    fn test_reference_index() {
+        let src = "(def inc (fn [x] (+ x 1)))\n; x in a comment\n(inc \"x\" ~@x #{x})";
+        let index = ReferenceIndex::build(src.as_bytes());
+
+        let xs = index.references("x");
+        assert_eq!(xs.len(), 4);
+        assert_eq!((xs[0].start.line, xs[0].start.column), (1, 15));
+        assert_eq!((xs[3].start.line, xs[3].start.column), (3, 16));
+        assert_eq!(index.references("inc").len(), 2);
+        assert!(index.references("y").is_empty());
+        assert!(!index.contains("~@"));
+        assert!(!index.contains("#{"));
+        assert_eq!(index.names().collect::<Vec<_>>(), ["+", "def", "fn", "inc", "x"]);
+        assert_eq!(index.len(), 5);
+    }
+
+    #[test]
+    fn test_token_table() {
+        let src = "(def x\n  \"a b\") ; c";
+        let mut s = Scanner::init(src.as_bytes());
+        s.set_mode(LISP_TOKENS & !SKIP_COMMENTS);
+        let table = TokenTable::from_scanner(&mut s);
+        assert_eq!(table.len(), 6);
+        let texts: Vec<Option<&str>> = (0..=src.len()).map(|i| table.token_at(i).map(|t| t.text.as_str())).collect();
+        assert_eq!(texts[0], Some("("));
+        assert_eq!(texts[1..4], [Some("def"); 3]);
+        assert_eq!(texts[4], None);
+        assert_eq!(texts[5], Some("x"));
+        assert_eq!(texts[6..9], [None; 3]);
+        assert_eq!(texts[9..14], [Some("\"a b\""); 5]);
+        assert_eq!(texts[14], Some(")"));
+        assert_eq!(texts[15], None);
+        assert_eq!(texts[16..19], [Some("; c"); 3]);
+        assert_eq!(texts[19], None);
+
+        // From a stream of tokens, and one-off queries
+        let table = TokenTable::from_tokens(table.tokens().iter().filter(|t| t.tok != COMMENT).cloned());
+        assert_eq!(table.token_at(17), None);
+        assert_eq!(token_at(src.as_bytes(), 10).map(|t| t.tok), Some(STRING));
+        assert_eq!(token_at(src.as_bytes(), 17), None);
+        assert!(TokenTable::default().is_empty());
+    }
+}
@@ -0,0 +1,56 @@
+// Copyright 2022 Jordi Íñigo Griera. All rights reserved.
+
+#[cfg(test)]
+mod tests {
+    use scanner::*;
+
+    #[test]
+    fn test_registry_interns_sources() {
+        let mut registry = SourceRegistry::new();
+        assert!(registry.is_empty());
+        let a = registry.register(SourceKind::Path, "src/a.lisp");
+        let b = registry.register(SourceKind::Uri, "untitled:1");
+        let c = registry.register(SourceKind::Virtual, "src/a.lisp");
+        assert_eq!(registry.register(SourceKind::Path, "src/a.lisp"), a);
+        assert_eq!(registry.len(), 3);
+        assert_eq!((a.index(), b.index(), c.index()), (Some(0), Some(1), Some(2)));
+        assert_ne!(a, c);
+        assert_eq!(registry.get(1), Some(&b));
+        assert_eq!(registry.get(3), None);
+        let names: Vec<&str> = registry.iter().map(|id| id.name()).collect();
+        assert_eq!(names, ["src/a.lisp", "untitled:1", "src/a.lisp"]);
+        assert_eq!(b.kind(), Some(SourceKind::Uri));
+
+        for i in 0..1000 {
+            registry.register(SourceKind::Path, &format!("src/{i}.lisp"));
+        }
+        assert_eq!(registry.register(SourceKind::Path, "src/500.lisp").index(), Some(503));
+        assert_eq!(registry.register(SourceKind::Virtual, "src/a.lisp"), c);
+        assert_eq!(registry.len(), 1003);
+    }
+
+    #[test]
+    fn test_source_id() {
+        let anonymous = SourceId::default();
+        assert!(anonymous.is_anonymous());
+        assert_eq!((anonymous.name(), anonymous.kind(), anonymous.index()), ("", None, None));
+        assert_eq!(anonymous.to_string(), "<input>");
+        assert_eq!(SourceId::from(""), anonymous);
+
+        let path = SourceId::from("a.lisp");
+        assert_eq!(path, SourceId::new(SourceKind::Path, "a.lisp"));
+        assert_eq!(path.index(), None);
+        assert_eq!(path.to_string(), "a.lisp");
+    }
+
+    #[test]
+    fn test_positions_share_the_source() {
+        let mut registry = SourceRegistry::new();
+        let mut s = Scanner::init(b"(a\n\"b)");
+        s.position.source = registry.register(SourceKind::Virtual, "<repl>");
+        let tokens: Vec<SpannedToken> = s.tokens().spanned().collect();
+        assert!(tokens.iter().all(|t| t.span.start.source.index() == Some(0)));
+        assert_eq!(tokens[1].span.start.to_string(), "<repl>:1:2");
+        assert_eq!(s.diagnostics()[0].to_string(), "<repl>:2:1: literal not terminated");
+    }
+}
@@ -0,0 +1,161 @@
+// Copyright 2022 Jordi Íñigo Griera. All rights reserved.
+
+#[cfg(test)]
+mod tests {
+    use scanner::*;
+
+    // Collects the texts of the identifiers, as a parser would.
+    fn idents(src: &mut dyn TokenSource) -> Vec<String> {
+        let mut v = Vec::new();
+        loop {
+            match src.scan() {
+                EOF => return v,
+                IDENT => v.push(src.token_text()),
+                _ => {}
+            }
+        }
+    }
+
+    #[test]
+    fn test_scanner_source() {
+        let mut s = Scanner::init(b"(def a 10)");
+        assert_eq!(idents(&mut s), ["def", "a"]);
+    }
+
+    #[test]
+    fn test_backend_sources() {
+        let src = "(def a 10)\n(b \"s\" c)";
+        let expected = ["def", "a", "b", "c"];
+        assert_eq!(idents(&mut IterScanner::new(src.chars())), expected);
+        let mut s = PushScanner::new();
+        s.feed(src.as_bytes());
+        s.finish();
+        assert_eq!(idents(&mut s), expected);
+
+        let mut s = IterScanner::new(src.bytes());
+        let src: &mut dyn TokenSource = &mut s;
+        let t = (0..6).map(|_| src.scan_spanned()).last().unwrap();
+        assert_eq!((t.tok, t.text.as_str()), ('(' as Token, "("));
+        assert_eq!((t.span.start.line, t.span.start.offset), (2, 11));
+        assert_eq!(src.span(), t.span);
+        assert_eq!(src.token_text(), "(");
+        while src.scan() != EOF {}
+        assert_eq!(src.token_text(), "");
+        assert_eq!((src.span().start.offset, src.span().end.offset), (20, 20));
+    }
+
+    #[test]
+    fn test_push_source() {
+        let mut s = PushScanner::new();
+        s.feed(b"(a b");
+        let src: &mut dyn TokenSource = &mut s;
+        assert_eq!(src.scan(), '(' as Token);
+        assert_eq!(src.scan(), IDENT);
+        // "b" may continue
+        assert_eq!(src.scan(), EOF);
+        s.feed(b" \"c");
+        s.finish();
+        let src: &mut dyn TokenSource = &mut s;
+        assert_eq!(src.scan(), IDENT);
+        assert_eq!(src.token_text(), "b");
+        assert_eq!(src.scan(), STRING);
+        assert_eq!(src.scan(), EOF);
+        assert_eq!(src.diagnostics()[0].code, DiagnosticCode::Unterminated);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_std_backend_sources() {
+        let mut s = LineScanner::new("(def a\n 10)\n".as_bytes());
+        assert_eq!(idents(&mut s), ["def", "a"]);
+        assert_eq!(TokenSource::span(&s).end.line, 2);
+
+        let mut s = TokenPipeline::spawn(b"(a 0x) b".to_vec(), 2, |_| {});
+        assert_eq!(idents(&mut s), ["a", "b"]);
+        assert_eq!(TokenSource::diagnostics(&s)[0].code, DiagnosticCode::NoDigits);
+        assert_eq!(s.finish().len(), 1);
+    }
+
+    #[test]
+    fn test_span() {
+        let mut s = Scanner::init(b"(foo\n  bar)");
+        let src: &mut dyn TokenSource = &mut s;
+        src.scan();
+        src.scan();
+        let span = src.span();
+        assert_eq!((span.start.offset, span.end.offset), (1, 4));
+
+        let t = src.scan_spanned();
+        assert_eq!(t.tok, IDENT);
+        assert_eq!(t.text, "bar");
+        assert_eq!((t.span.start.line, t.span.start.column), (2, 3));
+        assert_eq!(src.span(), t.span);
+
+        src.scan();
+        assert_eq!(src.scan(), EOF);
+        assert_eq!(src.span().start.offset, 11);
+        assert_eq!(src.span().end.offset, 11);
+    }
+
+    #[test]
+    fn test_span_after_lookahead() {
+        let mut s = Scanner::init(b"a b");
+        assert_eq!(s.scan(), IDENT);
+        assert!(s.eat(INT).is_none());
+        assert_eq!(s.token_span().start.offset, 0);
+        assert_eq!(TokenSource::scan(&mut s), IDENT);
+        assert_eq!(s.token_span().start.offset, 2);
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let mut s = Scanner::init(b"\"abc");
+        let src: &mut dyn TokenSource = &mut s;
+        assert_eq!(src.scan(), STRING);
+        assert_eq!(src.diagnostics().len(), 1);
+        assert_eq!(src.diagnostics()[0].message, "literal not terminated");
+    }
+
+    #[test]
+    fn test_vec_source() {
+        let mut src = VecTokenSource::new(vec![('(' as i32, "("), (IDENT, "def"), (IDENT, "a"), (INT, "10"), (')' as i32, ")")]);
+        assert_eq!(idents(&mut src), ["def", "a"]);
+        assert_eq!(src.scan(), EOF);
+        assert_eq!(src.token_text(), "");
+    }
+
+    #[test]
+    fn test_vec_source_positions() {
+        let mut src = VecTokenSource::new(vec![
+            (IDENT, "foo".to_string()),
+            (COMMENT, "; über".to_string()),
+            (RAW_STRING, "¬a\nb¬".to_string()),
+            (INT, "1".to_string()),
+        ]);
+        src.set_filename("mock.lisp");
+
+        let spans: Vec<Span> = (0..4).map(|_| src.scan_spanned().span).collect();
+        let at = |s: &Position| (s.offset, s.line, s.column);
+        assert_eq!((at(&spans[0].start), at(&spans[0].end)), ((0, 1, 1), (3, 1, 4)));
+        assert_eq!((at(&spans[1].start), at(&spans[1].end)), ((4, 1, 5), (11, 1, 11)));
+        assert_eq!((at(&spans[2].start), at(&spans[2].end)), ((12, 2, 1), (19, 3, 3)));
+        assert_eq!(at(&spans[3].start), (20, 3, 4));
+        assert_eq!(spans[3].start.to_string(), "mock.lisp:3:4");
+
+        assert_eq!(src.scan(), EOF);
+        assert_eq!(at(&src.span().start), (22, 3, 6));
+        assert_eq!(src.scan(), EOF);
+    }
+
+    #[test]
+    fn test_vec_source_diagnostics() {
+        let mut src = VecTokenSource::new(Vec::<(Token, &str)>::new());
+        assert_eq!(src.span().start.line, 1);
+        assert_eq!(src.scan(), EOF);
+        assert!(src.diagnostics().is_empty());
+
+        let span = src.span();
+        src.push_diagnostic(Diagnostic { severity: Severity::Error, code: DiagnosticCode::Unterminated, message: "boom".into(), span, user_data: UserData::default() });
+        assert_eq!(src.diagnostics()[0].to_string(), "<input>:1:1: boom");
+    }
+}
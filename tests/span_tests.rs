@@ -0,0 +1,264 @@
+// Copyright 2022 Jordi Íñigo Griera. All rights reserved.
+
+#[cfg(test)]
+mod tests {
+    use scanner::*;
+
+    // Deterministic xorshift generator, so failures are reproducible.
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn pick<'a>(&mut self, items: &[&'a str]) -> &'a str {
+            items[(self.next() % items.len() as u64) as usize]
+        }
+    }
+
+    const ATOMS: &[&str] = &[
+        "a", "foo-bar", "*host*", "äöü", "本", "42", "-7", "0x1F", "3.14", "1e10", ".5", "\"str\"",
+        "\"a\\\"b\"", "\"本\\n\"", "¬raw¬", "¬multi\nline\nraw¬", "¬a¬¬b¬", ":kw", "~@", "#{", "(", ")", "[",
+        "]", "'", "`", "~", "@", "; comment", ";; 本 comment",
+    ];
+    const SEPARATORS: &[&str] = &[" ", "  ", "\t", "\n", "\r\n", "\n\n"];
+
+    fn corpus(seed: u64, n: usize) -> String {
+        let mut rng = Rng(seed);
+        let mut src = String::new();
+        for _ in 0..n {
+            let atom = rng.pick(ATOMS);
+            src.push_str(atom);
+            // Comments extend to the end of the line
+            src.push_str(if atom.starts_with(';') { "\n" } else { rng.pick(SEPARATORS) });
+        }
+        src
+    }
+
+    // Returns the (line, column) of a byte offset, counting columns in chars.
+    fn line_column(src: &str, offset: usize) -> (usize, usize) {
+        let before = &src[..offset];
+        let line = before.matches('\n').count() + 1;
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        (line, before[line_start..].chars().count() + 1)
+    }
+
+    fn check_spans(src: &str, mode: u32) -> usize {
+        let mut s = Scanner::init(src.as_bytes());
+        s.set_mode(mode);
+        let mut n = 0;
+        loop {
+            let t = s.scan_spanned();
+            if t.tok == EOF {
+                break;
+            }
+            let (start, end) = (t.span.start.offset, t.span.end.offset);
+            assert_eq!(end - start, t.text.len(), "span length of {:?}", t);
+            assert_eq!(&src[start..end], t.text, "span text of {:?}", t);
+            assert_eq!(line_column(src, start), (t.span.start.line, t.span.start.column), "start of {:?}", t);
+            assert_eq!(line_column(src, end), (t.span.end.line, t.span.end.column), "end of {:?}", t);
+            assert_eq!(t.line_count(), t.text.matches('\n').count() + 1, "line count of {:?}", t);
+            n += 1;
+        }
+        assert_eq!(s.error_count(), 0);
+        n
+    }
+
+    #[test]
+    fn test_multiline_raw_string_positions() {
+        let src = "(x ¬a\nbc\n¬ y)\n¬\n¬";
+        let mut s = Scanner::init(src.as_bytes());
+
+        assert_eq!(s.scan(), '(' as i32);
+        assert_eq!(s.scan(), IDENT);
+        assert_eq!(s.scan(), RAW_STRING);
+        assert_eq!((s.position.line, s.position.column), (1, 4));
+        assert_eq!(s.token_line_count(), 3);
+        assert_eq!((s.pos().line, s.pos().column), (3, 2));
+        assert_eq!(s.scan(), IDENT);
+        assert_eq!((s.position.line, s.position.column), (3, 3));
+        assert_eq!(s.token_line_count(), 1);
+        assert_eq!(s.scan(), ')' as i32);
+
+        let t = s.scan_spanned();
+        assert_eq!(t.tok, RAW_STRING);
+        assert_eq!(t.line_count(), 2);
+        assert_eq!((t.span.start.line, t.span.start.column), (4, 1));
+        assert_eq!((t.span.end.line, t.span.end.column), (5, 2));
+    }
+
+    #[test]
+    fn test_span_consistency_fuzzed() {
+        for seed in 1..=20u64 {
+            let src = corpus(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15), 400);
+            assert!(check_spans(&src, LISP_TOKENS) > 0);
+            check_spans(&src, LISP_TOKENS & !SKIP_COMMENTS);
+        }
+    }
+
+    #[test]
+    fn test_token_byte_range_across_refills() {
+        // The string crosses two buffer refills, the identifier one
+        let long = "x".repeat(2500);
+        let src = format!("{} \"{}\" {} end", "a".repeat(1000), long, "b".repeat(1500));
+        let mut s = Scanner::init(src.as_bytes());
+
+        let mut ranges = Vec::new();
+        while s.scan() != EOF {
+            let range = s.token_byte_range();
+            assert_eq!(&src[range.start as usize..range.end as usize], s.token_text());
+            ranges.push(range);
+        }
+        assert_eq!(ranges, [0..1000, 1001..3503, 3504..5004, 5005..5008]);
+        assert_eq!(s.token_byte_range(), 5008..5008);
+    }
+
+    #[test]
+    fn test_token_byte_range_with_base_and_lookahead() {
+        let mut s = Scanner::init("foo ¬bar¬".as_bytes());
+        s.set_base_position(&Position { offset: 100, line: 5, column: 1, ..Default::default() });
+
+        assert_eq!(s.scan(), IDENT);
+        assert_eq!(s.token_byte_range(), 100..103);
+        assert!(s.eat(INT).is_none());
+        assert_eq!(s.scan(), RAW_STRING);
+        assert_eq!(s.token_byte_range(), 104..111);
+    }
+
+    #[test]
+    fn test_spanned_token_display() {
+        let mut s = Scanner::init("(def x\n  \"a\\n\tb\")".as_bytes());
+        s.position.source = SourceId::from("ignored.lisp");
+        let lines: Vec<String> = s.tokens().spanned().map(|t| t.to_string()).collect();
+
+        assert_eq!(
+            lines,
+            [
+                r#""(" "(" @1:1..1:2"#,
+                r#"Ident "def" @1:2..1:5"#,
+                r#"Ident "x" @1:6..1:7"#,
+                r#"String "\"a\\n\tb\"" @2:3..2:10"#,
+                r#"")" ")" @2:10..2:11"#,
+            ]
+        );
+    }
+
+    // Scans src to EOF, checking the accessors on every token and at EOF.
+    fn scan_edge_case(src: &[u8]) -> Vec<SpannedToken> {
+        let mut s = Scanner::init(src);
+        s.set_lints(ALL_LINTS);
+        let mut toks = Vec::new();
+        loop {
+            let t = s.scan_spanned();
+            let range = s.token_byte_range();
+            assert!(range.start <= range.end && range.end <= src.len() as u64);
+            assert_eq!(s.token_span(), t.span);
+            assert!(t.span.start.offset <= t.span.end.offset);
+            assert!(t.span.start.line >= 1 && t.span.start.column >= 1);
+            assert!(s.pos().line >= 1);
+            if t.tok == EOF {
+                assert_eq!(t.text, "");
+                assert_eq!(s.scan(), EOF);
+                return toks;
+            }
+            toks.push(t);
+        }
+    }
+
+    #[test]
+    fn test_empty_input() {
+        assert!(scan_edge_case(b"").is_empty());
+        let mut s = Scanner::init(b"");
+        assert_eq!((s.pos().offset, s.pos().line, s.pos().column), (0, 1, 1));
+        assert_eq!(s.token_byte_range(), 0..0);
+        assert_eq!(s.scan(), EOF);
+        assert_eq!(s.token_byte_range(), 0..0);
+    }
+
+    #[test]
+    fn test_token_at_offset_zero() {
+        let toks = scan_edge_case(b"a");
+        assert_eq!(toks.len(), 1);
+        assert_eq!((toks[0].span.start.offset, toks[0].span.end.offset), (0, 1));
+        assert_eq!((toks[0].span.start.column, toks[0].span.end.column), (1, 2));
+
+        let toks = scan_edge_case(b"\n");
+        assert!(toks.is_empty());
+    }
+
+    #[test]
+    fn test_bom_only_input() {
+        assert!(scan_edge_case("\u{FEFF}".as_bytes()).is_empty());
+        assert_eq!(scan_edge_case("\u{FEFF}\n".as_bytes()).len(), 0);
+        let toks = scan_edge_case("\u{FEFF}x".as_bytes());
+        assert_eq!(toks[0].span.start.offset, 3);
+
+        let mut s = Scanner::init("\u{FEFF}".as_bytes());
+        s.set_mode(LOSSLESS_TOKENS);
+        assert_eq!(s.scan(), WHITESPACE);
+        assert_eq!(s.token_byte_range(), 0..3);
+        assert_eq!(s.scan(), EOF);
+    }
+
+    #[test]
+    fn test_eof_mid_escape() {
+        for src in ["\"\\", "\"\\x", "\"\\u12", "\"\\U0010", "\"\\0", "\"a\\\n"] {
+            let toks = scan_edge_case(src.as_bytes());
+            assert_eq!(toks[0].tok, STRING, "{:?}", src);
+            assert_eq!(toks[0].span.start.offset, 0);
+        }
+    }
+
+    #[test]
+    fn test_base_position_edge_cases() {
+        let mut s = Scanner::init(b"a\nb");
+        s.set_base_position(&Position { offset: 0, line: 0, column: 0, ..Default::default() });
+        assert_eq!(s.scan(), IDENT);
+        assert_eq!((s.position.line, s.position.column), (1, 1));
+        assert_eq!(s.scan(), IDENT);
+        assert_eq!((s.position.line, s.position.column), (2, 1));
+    }
+
+    #[test]
+    fn test_slice() {
+        let src = b"(a \"\xc3\xa9\" b\xff)";
+        let mut s = Scanner::init(src);
+        s.set_base_position(&Position { offset: 100, line: 5, column: 1, ..Default::default() });
+        let t: Vec<SpannedToken> = s.iter().collect();
+        assert_eq!(s.slice(&t[2].span), Some("\"é\""));
+        let span = Span { start: t[0].span.start.clone(), end: t[3].span.end.clone() };
+        assert_eq!(s.slice(&span), Some("(a \"é\" b"));
+        assert_eq!(s.slice(&t[4].span), None);
+        let mut before = t[0].span.clone();
+        before.start.offset = 99;
+        assert_eq!(s.slice(&before), None);
+    }
+
+    #[test]
+    fn test_token_len() {
+        let src = "(héllo \"本\" ¬a¬¬b¬) ".repeat(200);
+        let mut s = Scanner::init(src.as_bytes());
+        while s.scan() != EOF {
+            let text = s.token_text();
+            assert!(s.token_len() > 0);
+            assert_eq!(s.token_len(), text.len());
+            assert_eq!(s.token_char_len(), text.chars().count());
+        }
+        assert_eq!((s.token_len(), s.token_char_len()), (0, 0));
+
+        let mut s = Scanner::init(b"a \xffb");
+        assert_eq!(s.scan(), IDENT);
+        assert_eq!(s.scan(), 0xFFFD);
+        assert_eq!((s.token_len(), s.token_char_len()), (1, 1));
+
+        // Tokens replayed after a lookahead
+        let mut s = Scanner::init("é 1".as_bytes());
+        assert_eq!(s.scan(), IDENT);
+        assert!(s.eat(IDENT).is_none());
+        assert_eq!((s.token_len(), s.token_char_len()), (2, 1));
+    }
+}
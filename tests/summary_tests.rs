@@ -0,0 +1,58 @@
+// Copyright 2022 Jordi Íñigo Griera. All rights reserved.
+
+#[cfg(test)]
+mod tests {
+    use scanner::*;
+
+    fn summary(src: &str) -> ScanSummary {
+        let mut s = Scanner::init(src.as_bytes());
+        s.set_summary(true);
+        while s.scan() != EOF {}
+        s.eof_summary().unwrap()
+    }
+
+    #[test]
+    fn test_totals() {
+        let t = summary("");
+        assert_eq!((t.lines, t.bytes, t.token_count()), (0, 0, 0));
+        let t = summary("a\n\nb");
+        assert_eq!((t.lines, t.bytes, t.count(IDENT)), (3, 4, 2));
+        let t = summary("(a ;; é\n)\n");
+        assert_eq!((t.lines, t.bytes), (2, 11));
+        assert_eq!(t.tokens.iter().map(|(&k, &n)| (k, n)).collect::<Vec<_>>(), [(IDENT, 1), ('(' as Token, 1), (')' as Token, 1)]);
+        assert!(t.unclosed.is_empty() && t.unterminated.is_empty());
+    }
+
+    #[test]
+    fn test_unterminated_constructs() {
+        let t = summary("#{1 [2] {:a ¬b\n c");
+        let open: Vec<(&str, usize)> = t.unclosed.iter().map(|t| (t.text.as_str(), t.span.start.column)).collect();
+        assert_eq!(open, [("#{", 1), ("{", 9)]);
+        assert_eq!(t.unterminated.len(), 1);
+        assert_eq!((t.unterminated[0].start.column, t.unterminated[0].end.line), (13, 2));
+    }
+
+    #[test]
+    fn test_only_at_eof() {
+        let mut s = Scanner::init(b"(a)");
+        s.set_summary(true);
+        assert!(s.eof_summary().is_none());
+        s.scan();
+        assert!(s.eof_summary().is_none());
+        s.eat(IDENT);
+        while s.scan() != EOF {}
+        assert_eq!(s.eof_summary().unwrap().token_count(), 3);
+
+        // Errors sent to a handler are summarized too
+        let mut s = Scanner::init(b"\"a");
+        s.set_summary(true);
+        s.set_error_handler(|_, _| {});
+        while s.scan() != EOF {}
+        assert_eq!(s.eof_summary().unwrap().unterminated.len(), 1);
+
+        // The tally is off by default
+        let mut s = Scanner::init(b"(a)");
+        while s.scan() != EOF {}
+        assert!(s.eof_summary().is_none());
+    }
+}
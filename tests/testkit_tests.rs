@@ -0,0 +1,55 @@
+// Copyright 2022 Jordi Íñigo Griera. All rights reserved.
+
+#[cfg(test)]
+mod tests {
+    use scanner::testkit::{self, Failure, Suite};
+    use scanner::*;
+
+    scanner::dialect! {
+        mod plain {
+            mode = MINIMAL_TOKENS;
+            whitespace = LISP_WHITESPACE;
+        }
+    }
+
+    #[test]
+    fn test_presets_conform() {
+        for mode in [LISP_TOKENS, MINIMAL_TOKENS, LOSSLESS_TOKENS, LISP_TOKENS | SCAN_BLOCK_COMMENTS | SCAN_CHARS] {
+            assert_eq!(testkit::run_all(|s: &mut Scanner| s.set_mode(mode)), []);
+        }
+        assert_eq!(testkit::run_all(plain::configure), []);
+
+        let custom = |s: &mut Scanner| {
+            s.set_mode(LOSSLESS_TOKENS | SCAN_BLOCK_COMMENTS);
+            s.set_whitespace(PAGE_WHITESPACE);
+            s.set_line_comment_prefixes(&["//", "#"]);
+            s.set_raw_string_delimiters('«', '»');
+            s.set_eof_policy(EofPolicy::NeedMoreInput);
+        };
+        assert_eq!(testkit::run_all(custom), []);
+    }
+
+    #[test]
+    fn test_failures() {
+        // Identifiers starting with digits swallow numbers
+        let digits = |s: &mut Scanner| s.set_is_ident_rune(|ch, _| ch.is_alphanumeric() || ch == '.');
+        let failures = testkit::run(Suite::Numbers, digits);
+        assert!(!failures.is_empty());
+        assert!(failures.iter().all(|f| f.suite == Suite::Numbers));
+        assert_eq!(failures[0].to_string(), "numbers: \"0\": expected Int \"0\", found Ident");
+        assert_eq!(testkit::run(Suite::Positions, digits), []);
+        assert_eq!(testkit::run(Suite::ChunkBoundaries, digits), []);
+
+        // Raw strings delimited by parentheses swallow the delimited numbers
+        let failures = testkit::run(Suite::Numbers, |s: &mut Scanner| s.set_raw_string_delimiters('(', ')'));
+        assert_eq!(
+            failures.first(),
+            Some(&Failure {
+                suite: Suite::Numbers,
+                source: String::from("(0)"),
+                message: String::from("expected Int \"0\", found [RawString \"(0)\"]"),
+            })
+        );
+        assert_eq!(Suite::ALL.map(|s| s.to_string()), ["numbers", "strings", "comments", "positions", "chunk_boundaries"]);
+    }
+}
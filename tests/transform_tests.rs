@@ -0,0 +1,45 @@
+// Copyright 2022 Jordi Íñigo Griera. All rights reserved.
+
+#[cfg(test)]
+mod tests {
+    use scanner::*;
+
+    const SRC: &str = ";; header\n(def  a\n\t0XFF) ; trailing\n(print \"\\x41\\u000a\")\n";
+
+    #[test]
+    fn test_normalize_default_is_identity() {
+        assert_eq!(normalize(SRC.as_bytes(), &NormalizeOptions::default()), SRC);
+    }
+
+    #[test]
+    fn test_normalize_minify() {
+        let opts = NormalizeOptions {
+            collapse_whitespace: true,
+            drop_comments: true,
+            lowercase_numbers: true,
+            normalize_escapes: true,
+        };
+        assert_eq!(normalize(SRC.as_bytes(), &opts), "(def a 0xff) (print \"A\\n\")");
+    }
+
+    #[test]
+    fn test_normalize_keeps_comments_on_their_own_line() {
+        let opts = NormalizeOptions {
+            collapse_whitespace: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            normalize(SRC.as_bytes(), &opts),
+            ";; header\n(def a 0XFF) ; trailing\n(print \"\\x41\\u000a\")"
+        );
+    }
+
+    #[test]
+    fn test_normalize_drop_comments_only() {
+        let opts = NormalizeOptions {
+            drop_comments: true,
+            ..Default::default()
+        };
+        assert_eq!(normalize(b"(a) ; c\n(b)", &opts), "(a)\n(b)");
+    }
+}
@@ -0,0 +1,90 @@
+// Copyright 2022 Jordi Íñigo Griera. All rights reserved.
+
+#[cfg(test)]
+mod tests {
+    use scanner::*;
+
+    // Records each call as a line
+    #[derive(Default)]
+    struct Recorder(Vec<String>);
+
+    impl TokenVisitor for Recorder {
+        fn on_ident(&mut self, span: &Span, text: &str) {
+            self.0.push(format!("ident {} {}", text, span.start.column));
+        }
+        fn on_keyword(&mut self, _span: &Span, text: &str) {
+            self.0.push(format!("keyword {}", text));
+        }
+        fn on_number(&mut self, tok: Token, _span: &Span, text: &str) {
+            self.0.push(format!("number {} {}", token_string(tok), text));
+        }
+        fn on_string(&mut self, tok: Token, _span: &Span, text: &str) {
+            self.0.push(format!("string {} {}", token_string(tok), text));
+        }
+        fn on_comment(&mut self, _span: &Span, text: &str) {
+            self.0.push(format!("comment {}", text));
+        }
+        fn on_char(&mut self, ch: char, _span: &Span) {
+            self.0.push(format!("char {}", ch));
+        }
+        fn on_other(&mut self, tok: Token, _span: &Span, text: &str) {
+            self.0.push(format!("other {} {:?}", token_string(tok), text));
+        }
+        fn on_error(&mut self, d: &Diagnostic) {
+            self.0.push(format!("error {} {}", d.code, d.span.start.column));
+        }
+        fn on_eof(&mut self, pos: &Position) {
+            self.0.push(format!("eof {}:{}", pos.line, pos.column));
+        }
+    }
+
+    #[test]
+    fn test_drive() {
+        let mut s = Scanner::init("(f :k 1 2.5 \"s\" ¬r¬) ; c\n0x \"\\q\"".as_bytes());
+        s.set_mode(LISP_TOKENS & !SKIP_COMMENTS);
+        s.set_lints(LINT_TRAILING_WHITESPACE);
+        let mut r = Recorder::default();
+        s.drive(&mut r);
+        assert_eq!(
+            r.0,
+            [
+                "char (",
+                "ident f 2",
+                "keyword :k",
+                "number Int 1",
+                "number Float 2.5",
+                "string String \"s\"",
+                "string RawString ¬r¬",
+                "char )",
+                "comment ; c",
+                "error E004 1",
+                "number Int 0x",
+                "error E010 5",
+                "string String \"\\q\"",
+                "eof 2:8",
+            ]
+        );
+        assert_eq!(s.error_count(), 2);
+    }
+
+    #[test]
+    fn test_drive_defaults() {
+        struct Idents(usize);
+        impl TokenVisitor for Idents {
+            fn on_ident(&mut self, _span: &Span, _text: &str) {
+                self.0 += 1;
+            }
+        }
+        let mut s = Scanner::init(b"a b\n  c");
+        s.set_mode(LOSSLESS_TOKENS);
+        let mut v = Idents(0);
+        s.drive(&mut v);
+        assert_eq!(v.0, 3);
+
+        let mut r = Recorder::default();
+        let mut s = Scanner::init(b"a ");
+        s.set_mode(LOSSLESS_TOKENS);
+        s.drive(&mut r);
+        assert_eq!(r.0, ["ident a 1", "other Whitespace \" \"", "eof 1:3"]);
+    }
+}